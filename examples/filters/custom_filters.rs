@@ -127,7 +127,7 @@ pub async fn example_usage() -> Result<(), Box<dyn Error + Send + Sync>> {
             ("Subject".to_string(), "This is spam content".to_string()),
             ("Newsgroups".to_string(), "alt.test".to_string()),
         ],
-        body: "Article body".to_string(),
+        body: "Article body".into(),
     };
 
     // This should fail validation
@@ -147,7 +147,7 @@ pub async fn example_usage() -> Result<(), Box<dyn Error + Send + Sync>> {
             ("Newsgroups".to_string(), "alt.test".to_string()),
             ("X-Site-Policy".to_string(), "accepted".to_string()),
         ],
-        body: "Article body".to_string(),
+        body: "Article body".into(),
     };
 
     // This should pass validation (assuming the group exists)
@@ -210,7 +210,7 @@ mod tests {
 
         let article = Message {
             headers: vec![("Subject".to_string(), "This is spam".to_string())],
-            body: "Body".to_string(),
+            body: "Body".into(),
         };
 
         let result = filter
@@ -229,7 +229,7 @@ mod tests {
 
         let article_without_header = Message {
             headers: vec![("Subject".to_string(), "Test".to_string())],
-            body: "Body".to_string(),
+            body: "Body".into(),
         };
 
         let result = filter
@@ -242,7 +242,7 @@ mod tests {
                 ("Subject".to_string(), "Test".to_string()),
                 ("X-Custom".to_string(), "value".to_string()),
             ],
-            body: "Body".to_string(),
+            body: "Body".into(),
         };
 
         let result = filter
@@ -0,0 +1,193 @@
+//! Identity of a TLS client certificate, used by `AUTHINFO SASL EXTERNAL`.
+//!
+//! rustls hands us the peer chain as raw DER, so this module walks just
+//! enough of the X.509 structure to pull out the subject name.
+
+use anyhow::{Result, bail};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use tokio_rustls::rustls;
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_OID: u8 = 0x06;
+const TAG_VERSION: u8 = 0xa0;
+const TAG_BMP_STRING: u8 = 0x1e;
+
+/// The verified leaf certificate presented by a TLS client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name in RFC 4514 form, e.g. `CN=feeder,O=Example`.
+    pub subject: String,
+    /// Value of the subject's first common name attribute.
+    pub common_name: Option<String>,
+    /// Lowercase hex SHA-256 digest of the DER encoding.
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    /// Extract the identity from a DER encoded X.509 certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (cert, _) = read_tlv(der, TAG_SEQUENCE)?;
+        let (mut tbs, _) = read_tlv(cert, TAG_SEQUENCE)?;
+        if tbs.first() == Some(&TAG_VERSION) {
+            tbs = skip_tlv(tbs)?;
+        }
+        // serialNumber, signature, issuer and validity precede the subject
+        for _ in 0..4 {
+            tbs = skip_tlv(tbs)?;
+        }
+        let (subject, _) = read_tlv(tbs, TAG_SEQUENCE)?;
+
+        let mut attributes = Vec::new();
+        let mut rdns = subject;
+        while !rdns.is_empty() {
+            let (mut set, rest) = read_tlv(rdns, TAG_SET)?;
+            while !set.is_empty() {
+                let (pair, rest) = read_tlv(set, TAG_SEQUENCE)?;
+                let (oid, value) = read_tlv(pair, TAG_OID)?;
+                let (tag, value, _) = read_any(value)?;
+                attributes.push((oid.to_vec(), decode_string(tag, value)));
+                set = rest;
+            }
+            rdns = rest;
+        }
+
+        let common_name = attributes
+            .iter()
+            .find(|(oid, _)| oid.as_slice() == [0x55, 0x04, 0x03])
+            .map(|(_, value)| value.clone());
+        // RFC 4514 lists the most specific RDN first
+        let subject = attributes
+            .iter()
+            .rev()
+            .map(|(oid, value)| format!("{}={}", attribute_name(oid), escape_value(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let mut fingerprint = String::with_capacity(64);
+        for byte in Sha256::digest(der) {
+            let _ = write!(fingerprint, "{byte:02x}");
+        }
+
+        Ok(Self {
+            subject,
+            common_name,
+            fingerprint,
+        })
+    }
+
+    /// Identity of the leaf certificate a TLS client presented, if any.
+    pub fn from_peer(certificates: Option<&[rustls::Certificate]>) -> Option<Self> {
+        let leaf = certificates?.first()?;
+        Self::from_der(&leaf.0).ok()
+    }
+}
+
+/// Split a DER element into tag, contents and the bytes following it.
+fn read_any(input: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    let [tag, first, rest @ ..] = input else {
+        bail!("truncated certificate");
+    };
+    let (len, rest) = if first & 0x80 == 0 {
+        (usize::from(*first), rest)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > 4 || rest.len() < count {
+            bail!("unsupported certificate length encoding");
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        bail!("truncated certificate");
+    }
+    Ok((*tag, &rest[..len], &rest[len..]))
+}
+
+/// Read an element that must carry `expected` as its tag.
+fn read_tlv(input: &[u8], expected: u8) -> Result<(&[u8], &[u8])> {
+    let (tag, contents, rest) = read_any(input)?;
+    if tag != expected {
+        bail!("unexpected tag {tag:#04x} in certificate");
+    }
+    Ok((contents, rest))
+}
+
+fn skip_tlv(input: &[u8]) -> Result<&[u8]> {
+    Ok(read_any(input)?.2)
+}
+
+fn decode_string(tag: u8, value: &[u8]) -> String {
+    if tag == TAG_BMP_STRING {
+        let units: Vec<u16> = value
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(value).into_owned()
+    }
+}
+
+fn attribute_name(oid: &[u8]) -> String {
+    let name = match oid {
+        [0x55, 0x04, 0x03] => "CN",
+        [0x55, 0x04, 0x06] => "C",
+        [0x55, 0x04, 0x07] => "L",
+        [0x55, 0x04, 0x08] => "ST",
+        [0x55, 0x04, 0x09] => "STREET",
+        [0x55, 0x04, 0x0a] => "O",
+        [0x55, 0x04, 0x0b] => "OU",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01] => "UID",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC",
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+        _ => return dotted_oid(oid),
+    };
+    name.to_string()
+}
+
+fn dotted_oid(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value = 0u64;
+    for byte in oid {
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            arcs.push(value);
+            value = 0;
+        }
+    }
+    let Some(&first) = arcs.first() else {
+        return String::new();
+    };
+    let (a, b) = match first {
+        0..40 => (0, first),
+        40..80 => (1, first - 40),
+        _ => (2, first - 80),
+    };
+    std::iter::once(a)
+        .chain(std::iter::once(b))
+        .chain(arcs[1..].iter().copied())
+        .map(|arc| arc.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Escape an attribute value as described in RFC 4514 section 2.4.
+fn escape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        let edge_space = c == ' ' && (i == 0 || i == last);
+        if matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';')
+            || edge_space
+            || (i == 0 && c == '#')
+        {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -8,6 +8,34 @@ const CREATE_VERSION_TABLE_POSTGRES: &str = "CREATE TABLE IF NOT EXISTS schema_v
     version INTEGER PRIMARY KEY
 )";
 
+/// Schema version of a freshly initialized PostgreSQL auth database
+pub const LATEST_VERSION: u32 = 2;
+
+/// Adds the `scram_sha256` column used by `AUTHINFO SASL SCRAM-SHA-256`
+#[cfg(feature = "postgres")]
+struct AddScramCredentials {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddScramCredentials {
+    fn target_version(&self) -> u32 {
+        2
+    }
+
+    fn description(&self) -> &str {
+        "Add scram_sha256 column to users"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS scram_sha256 TEXT")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
 /// PostgreSQL auth migrator
 #[cfg(feature = "postgres")]
 pub struct PostgresAuthMigrator {
@@ -67,9 +95,9 @@ impl Migrator for PostgresAuthMigrator {
     }
 
     fn get_migrations(&self) -> Vec<Box<dyn Migration>> {
-        // Since this is pre-1.0, no migrations exist yet
-        // This will serve as a template for future migrations
-        vec![]
+        vec![Box::new(AddScramCredentials {
+            pool: self.pool.clone(),
+        })]
     }
 }
 
@@ -104,7 +132,7 @@ mod tests {
 
     #[cfg(feature = "postgres")]
     #[tokio::test]
-    async fn test_postgres_auth_migrator_no_migrations() {
+    async fn test_postgres_auth_migrator_adds_scram_column() {
         if std::env::var("POSTGRES_TEST_URL").is_err() {
             return;
         }
@@ -113,15 +141,19 @@ mod tests {
         let pool = sqlx::PgPool::connect(&db_url).await.unwrap();
         let migrator = PostgresAuthMigrator::new(pool.clone());
 
-        // Pre-1.0, so no migrations should exist
-        let migrations = migrator.get_migrations();
-        assert!(migrations.is_empty());
+        // Simulate a version 1 database created before SCRAM support
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS users (username TEXT PRIMARY KEY, password_hash TEXT NOT NULL, key TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        migrator.set_version(1).await.unwrap();
 
-        // Migration should be a no-op
-        migrator.set_version(1).await.unwrap(); // Simulate fresh initialization
+        assert_eq!(migrator.get_migrations().len(), 1);
         migrator.migrate_to_latest().await.unwrap();
 
         let version = migrator.get_current_version().await.unwrap();
-        assert_eq!(version, 1); // Should remain at 1
+        assert_eq!(version, LATEST_VERSION);
     }
 }
@@ -8,6 +8,39 @@ const CREATE_VERSION_TABLE_SQLITE: &str = "CREATE TABLE IF NOT EXISTS schema_ver
     version INTEGER PRIMARY KEY
 )";
 
+/// Schema version of a freshly initialized SQLite auth database
+pub const LATEST_VERSION: u32 = 2;
+
+/// Adds the `scram_sha256` column used by `AUTHINFO SASL SCRAM-SHA-256`
+struct AddScramCredentials {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddScramCredentials {
+    fn target_version(&self) -> u32 {
+        2
+    }
+
+    fn description(&self) -> &str {
+        "Add scram_sha256 column to users"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('users') WHERE name = 'scram_sha256'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        if !exists {
+            sqlx::query("ALTER TABLE users ADD COLUMN scram_sha256 TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
 /// SQLite auth migrator
 pub struct SqliteAuthMigrator {
     pool: SqlitePool,
@@ -64,9 +97,9 @@ impl Migrator for SqliteAuthMigrator {
     }
 
     fn get_migrations(&self) -> Vec<Box<dyn Migration>> {
-        // Since this is pre-1.0, no migrations exist yet
-        // This will serve as a template for future migrations
-        vec![]
+        vec![Box::new(AddScramCredentials {
+            pool: self.pool.clone(),
+        })]
     }
 }
 
@@ -116,22 +149,38 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_sqlite_auth_migrator_no_migrations() {
+    async fn test_sqlite_auth_migrator_adds_scram_column() {
         let temp_file = NamedTempFile::new().unwrap();
         let db_path = format!("sqlite://{}", temp_file.path().display());
 
         let pool = sqlx::SqlitePool::connect(&db_path).await.unwrap();
         let migrator = SqliteAuthMigrator::new(pool.clone());
 
-        // Pre-1.0, so no migrations should exist
-        let migrations = migrator.get_migrations();
-        assert!(migrations.is_empty());
+        // Simulate a version 1 database created before SCRAM support
+        sqlx::query(
+            "CREATE TABLE users (username TEXT PRIMARY KEY, password_hash TEXT NOT NULL, key TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        migrator.set_version(1).await.unwrap();
 
-        // Migration should be a no-op
-        migrator.set_version(1).await.unwrap(); // Simulate fresh initialization
+        assert_eq!(migrator.get_migrations().len(), 1);
         migrator.migrate_to_latest().await.unwrap();
-
-        let version = migrator.get_current_version().await.unwrap();
-        assert_eq!(version, 1); // Should remain at 1
+        assert_eq!(
+            migrator.get_current_version().await.unwrap(),
+            LATEST_VERSION
+        );
+
+        sqlx::query("UPDATE users SET scram_sha256 = 'x'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Re-applying is harmless
+        AddScramCredentials { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
     }
 }
@@ -1,4 +1,4 @@
-use super::{AuthProvider, async_trait};
+use super::{AuthProvider, ScramCredentials, async_trait};
 use crate::migrations::Migrator;
 use anyhow::Result;
 use argon2::password_hash::{SaltString, rand_core::OsRng};
@@ -13,7 +13,8 @@ use std::str::FromStr;
 const USERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS users (
         username TEXT PRIMARY KEY,
         password_hash TEXT NOT NULL,
-        key TEXT
+        key TEXT,
+        scram_sha256 TEXT
     )";
 
 const ADMINS_TABLE: &str = "CREATE TABLE IF NOT EXISTS admins (
@@ -106,8 +107,8 @@ Please verify:
                 anyhow::anyhow!("Failed to create moderators table in PostgreSQL authentication database '{}': {}", uri, e)
             })?;
 
-            // Set current version (since pre-1.0, we use version 1 as the baseline)
-            migrator.set_version(1).await.map_err(|e| {
+            // The schema above already includes every migration
+            migrator.set_version(super::migrations::postgres::LATEST_VERSION).await.map_err(|e| {
                 anyhow::anyhow!(
                     "Failed to set initial schema version for PostgreSQL auth database '{}': {}",
                     uri,
@@ -116,7 +117,8 @@ Please verify:
             })?;
 
             tracing::info!(
-                "Successfully initialized PostgreSQL authentication database at version 1"
+                "Successfully initialized PostgreSQL authentication database at version {}",
+                super::migrations::postgres::LATEST_VERSION
             );
         } else {
             // Existing database: apply any pending migrations
@@ -153,12 +155,13 @@ impl AuthProvider for PostgresAuth {
             .hash_password(password.as_bytes(), &salt)?
             .to_string();
         sqlx::query(
-            "INSERT INTO users (username, password_hash, key) VALUES ($1, $2, $3)\
-            ON CONFLICT (username) DO UPDATE SET password_hash = EXCLUDED.password_hash, key = EXCLUDED.key",
+            "INSERT INTO users (username, password_hash, key, scram_sha256) VALUES ($1, $2, $3, $4)\
+            ON CONFLICT (username) DO UPDATE SET password_hash = EXCLUDED.password_hash, key = EXCLUDED.key, scram_sha256 = EXCLUDED.scram_sha256",
         )
         .bind(username)
         .bind(hash)
         .bind(key)
+        .bind(ScramCredentials::derive(password).to_string())
         .execute(&self.pool)
         .await?;
         Ok(())
@@ -169,8 +172,9 @@ impl AuthProvider for PostgresAuth {
         let hash = Argon2::default()
             .hash_password(new_password.as_bytes(), &salt)?
             .to_string();
-        sqlx::query("UPDATE users SET password_hash = $1 WHERE username = $2")
+        sqlx::query("UPDATE users SET password_hash = $1, scram_sha256 = $2 WHERE username = $3")
             .bind(hash)
+            .bind(ScramCredentials::derive(new_password).to_string())
             .bind(username)
             .execute(&self.pool)
             .await?;
@@ -209,6 +213,23 @@ impl AuthProvider for PostgresAuth {
         }
     }
 
+    async fn user_exists(&self, username: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM users WHERE username = $1")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn get_scram_credentials(&self, username: &str) -> Result<Option<ScramCredentials>> {
+        let stored: Option<Option<String>> =
+            sqlx::query_scalar("SELECT scram_sha256 FROM users WHERE username = $1")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await?;
+        stored.flatten().map(|s| s.parse()).transpose()
+    }
+
     async fn is_admin(&self, username: &str) -> Result<bool> {
         let row = sqlx::query("SELECT 1 FROM admins WHERE username = $1")
             .bind(username)
@@ -0,0 +1,286 @@
+//! SCRAM-SHA-256 support (RFC 5802, RFC 7677) for `AUTHINFO SASL`.
+//!
+//! Only the derived keys are stored, so the server can verify a client
+//! proof without ever seeing the plaintext password.
+
+use anyhow::{Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// PBKDF2 iteration count used for newly derived credentials.
+pub const DEFAULT_ITERATIONS: u32 = 4096;
+
+const KEY_LEN: usize = 32;
+
+/// HMAC-SHA-256 of `data` under `key`.
+pub(crate) fn hmac(key: &[u8], data: &[u8]) -> [u8; KEY_LEN] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    let mut out = [0u8; KEY_LEN];
+    out.copy_from_slice(&mac.finalize().into_bytes());
+    out
+}
+
+fn sha256(data: &[u8]) -> [u8; KEY_LEN] {
+    let mut out = [0u8; KEY_LEN];
+    out.copy_from_slice(&Sha256::digest(data));
+    out
+}
+
+/// The `Hi()` function from RFC 5802, i.e. PBKDF2-HMAC-SHA-256 for one block.
+fn salted_password(password: &[u8], salt: &[u8], iterations: u32) -> [u8; KEY_LEN] {
+    let mut block = salt.to_vec();
+    block.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac(password, &block);
+    let mut result = u;
+    for _ in 1..iterations {
+        u = hmac(password, &u);
+        for (r, b) in result.iter_mut().zip(u) {
+            *r ^= b;
+        }
+    }
+    result
+}
+
+fn random_base64(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    STANDARD.encode(bytes)
+}
+
+/// Stored SCRAM-SHA-256 verifier for a user.
+///
+/// Serialized as `iterations:salt:stored_key:server_key` with base64 fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScramCredentials {
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+    pub stored_key: [u8; KEY_LEN],
+    pub server_key: [u8; KEY_LEN],
+}
+
+impl ScramCredentials {
+    /// Derive credentials for `password` using a fresh random salt.
+    #[must_use]
+    pub fn derive(password: &str) -> Self {
+        let mut salt = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self::with_salt(password, salt, DEFAULT_ITERATIONS)
+    }
+
+    /// Derive credentials for `password` from an explicit salt and iteration count.
+    #[must_use]
+    pub fn with_salt(password: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        let salted = salted_password(password.as_bytes(), &salt, iterations);
+        let client_key = hmac(&salted, b"Client Key");
+        Self {
+            stored_key: sha256(&client_key),
+            server_key: hmac(&salted, b"Server Key"),
+            salt,
+            iterations,
+        }
+    }
+}
+
+impl fmt::Display for ScramCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.iterations,
+            STANDARD.encode(&self.salt),
+            STANDARD.encode(self.stored_key),
+            STANDARD.encode(self.server_key)
+        )
+    }
+}
+
+impl FromStr for ScramCredentials {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let key = |field: &str| -> Result<[u8; KEY_LEN]> {
+            STANDARD
+                .decode(field)?
+                .try_into()
+                .map_err(|_| anyhow!("SCRAM key has the wrong length"))
+        };
+        let mut parts = s.split(':');
+        let (Some(iterations), Some(salt), Some(stored_key), Some(server_key), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            bail!("malformed SCRAM credentials");
+        };
+        Ok(Self {
+            iterations: iterations.parse()?,
+            salt: STANDARD.decode(salt)?,
+            stored_key: key(stored_key)?,
+            server_key: key(server_key)?,
+        })
+    }
+}
+
+/// Decode a SCRAM `saslname`, which escapes `,` and `=` as `=2C` and `=3D`.
+fn decode_saslname(name: &str) -> Result<String> {
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(idx) = rest.find('=') {
+        out.push_str(&rest[..idx]);
+        let escape = rest.get(idx..idx + 3);
+        match escape {
+            Some("=2C") => out.push(','),
+            Some("=3D") => out.push('='),
+            _ => bail!("invalid escape in SCRAM username"),
+        }
+        rest = &rest[idx + 3..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A parsed client-first message.
+#[derive(Debug)]
+pub struct ClientFirst {
+    pub username: String,
+    pub authzid: Option<String>,
+    gs2_header: String,
+    client_first_bare: String,
+    client_nonce: String,
+}
+
+impl ClientFirst {
+    /// Parse the client-first message. Channel binding is not supported.
+    pub fn parse(message: &str) -> Result<Self> {
+        let mut parts = message.splitn(3, ',');
+        let (Some(cbind), Some(raw_authzid), Some(bare)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("malformed SCRAM client-first message");
+        };
+        if cbind != "n" && cbind != "y" {
+            bail!("SCRAM channel binding is not supported");
+        }
+        let authzid = match raw_authzid {
+            "" => None,
+            a => Some(decode_saslname(
+                a.strip_prefix("a=")
+                    .ok_or_else(|| anyhow!("malformed SCRAM authzid"))?,
+            )?),
+        };
+
+        let mut username = None;
+        let mut client_nonce = None;
+        for attr in bare.split(',') {
+            if let Some(v) = attr.strip_prefix("n=") {
+                username = Some(decode_saslname(v)?);
+            } else if let Some(v) = attr.strip_prefix("r=") {
+                client_nonce = Some(v.to_string());
+            } else if attr.starts_with("m=") {
+                bail!("unsupported mandatory SCRAM extension");
+            }
+        }
+
+        Ok(Self {
+            username: username.ok_or_else(|| anyhow!("SCRAM username missing"))?,
+            authzid,
+            gs2_header: format!("{cbind},{raw_authzid},"),
+            client_first_bare: bare.to_string(),
+            client_nonce: client_nonce
+                .filter(|n| !n.is_empty())
+                .ok_or_else(|| anyhow!("SCRAM client nonce missing"))?,
+        })
+    }
+
+    /// Build the server-first message using a random server nonce.
+    #[must_use]
+    pub fn respond(self, credentials: ScramCredentials) -> (ScramServer, String) {
+        let server_nonce = random_base64(18);
+        self.respond_with_nonce(credentials, &server_nonce)
+    }
+
+    /// Build the server-first message using the given server nonce.
+    #[must_use]
+    pub fn respond_with_nonce(
+        self,
+        credentials: ScramCredentials,
+        server_nonce: &str,
+    ) -> (ScramServer, String) {
+        let nonce = format!("{}{server_nonce}", self.client_nonce);
+        let server_first = format!(
+            "r={nonce},s={},i={}",
+            STANDARD.encode(&credentials.salt),
+            credentials.iterations
+        );
+        let server = ScramServer {
+            gs2_header: self.gs2_header,
+            client_first_bare: self.client_first_bare,
+            server_first: server_first.clone(),
+            nonce,
+            credentials,
+        };
+        (server, server_first)
+    }
+}
+
+/// Server state after sending the server-first message.
+pub struct ScramServer {
+    gs2_header: String,
+    client_first_bare: String,
+    server_first: String,
+    nonce: String,
+    credentials: ScramCredentials,
+}
+
+impl ScramServer {
+    /// Verify the client-final message and return the server-final message.
+    pub fn finish(&self, client_final: &str) -> Result<String> {
+        let (without_proof, proof) = client_final
+            .rsplit_once(",p=")
+            .ok_or_else(|| anyhow!("SCRAM client proof missing"))?;
+
+        let mut channel = None;
+        let mut nonce = None;
+        for attr in without_proof.split(',') {
+            if let Some(v) = attr.strip_prefix("c=") {
+                channel = Some(STANDARD.decode(v)?);
+            } else if let Some(v) = attr.strip_prefix("r=") {
+                nonce = Some(v);
+            }
+        }
+        if channel.as_deref() != Some(self.gs2_header.as_bytes()) {
+            bail!("SCRAM channel binding mismatch");
+        }
+        if nonce != Some(self.nonce.as_str()) {
+            bail!("SCRAM nonce mismatch");
+        }
+
+        let proof = STANDARD.decode(proof)?;
+        if proof.len() != KEY_LEN {
+            bail!("SCRAM client proof has the wrong length");
+        }
+        let auth_message = format!(
+            "{},{},{without_proof}",
+            self.client_first_bare, self.server_first
+        );
+        let client_signature = hmac(&self.credentials.stored_key, auth_message.as_bytes());
+        let client_key: Vec<u8> = proof
+            .iter()
+            .zip(client_signature)
+            .map(|(p, s)| p ^ s)
+            .collect();
+        if sha256(&client_key) != self.credentials.stored_key {
+            bail!("SCRAM client proof rejected");
+        }
+
+        let server_signature = hmac(&self.credentials.server_key, auth_message.as_bytes());
+        Ok(format!("v={}", STANDARD.encode(server_signature)))
+    }
+}
@@ -1,4 +1,4 @@
-use super::{AuthProvider, async_trait};
+use super::{AuthProvider, ScramCredentials, async_trait};
 use crate::migrations::Migrator;
 use anyhow::Result;
 use argon2::password_hash::{SaltString, rand_core::OsRng};
@@ -13,7 +13,8 @@ use std::str::FromStr;
 const USERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS users (
         username TEXT PRIMARY KEY,
         password_hash TEXT NOT NULL,
-        key TEXT
+        key TEXT,
+        scram_sha256 TEXT
     )";
 
 const ADMINS_TABLE: &str = "CREATE TABLE IF NOT EXISTS admins (
@@ -92,14 +93,17 @@ Possible causes:
                 anyhow::anyhow!("Failed to create moderators table in SQLite authentication database '{path}': {e}")
             })?;
 
-            // Set current version (since pre-1.0, we use version 1 as the baseline)
-            migrator.set_version(1).await.map_err(|e| {
+            // The schema above already includes every migration
+            migrator.set_version(super::migrations::sqlite::LATEST_VERSION).await.map_err(|e| {
                 anyhow::anyhow!(
                     "Failed to set initial schema version for SQLite auth database '{path}': {e}"
                 )
             })?;
 
-            tracing::info!("Successfully initialized SQLite authentication database at version 1");
+            tracing::info!(
+                "Successfully initialized SQLite authentication database at version {}",
+                super::migrations::sqlite::LATEST_VERSION
+            );
         } else {
             // Existing database: apply any pending migrations
             tracing::info!(
@@ -130,12 +134,15 @@ impl AuthProvider for SqliteAuth {
         let hash = Argon2::default()
             .hash_password(password.as_bytes(), &salt)?
             .to_string();
-        sqlx::query("INSERT OR REPLACE INTO users (username, password_hash, key) VALUES (?, ?, ?)")
-            .bind(username)
-            .bind(hash)
-            .bind(key)
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO users (username, password_hash, key, scram_sha256) VALUES (?, ?, ?, ?)",
+        )
+        .bind(username)
+        .bind(hash)
+        .bind(key)
+        .bind(ScramCredentials::derive(password).to_string())
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
@@ -144,8 +151,9 @@ impl AuthProvider for SqliteAuth {
         let hash = Argon2::default()
             .hash_password(new_password.as_bytes(), &salt)?
             .to_string();
-        sqlx::query("UPDATE users SET password_hash = ? WHERE username = ?")
+        sqlx::query("UPDATE users SET password_hash = ?, scram_sha256 = ? WHERE username = ?")
             .bind(hash)
+            .bind(ScramCredentials::derive(new_password).to_string())
             .bind(username)
             .execute(&self.pool)
             .await?;
@@ -184,6 +192,23 @@ impl AuthProvider for SqliteAuth {
         }
     }
 
+    async fn user_exists(&self, username: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn get_scram_credentials(&self, username: &str) -> Result<Option<ScramCredentials>> {
+        let stored: Option<Option<String>> =
+            sqlx::query_scalar("SELECT scram_sha256 FROM users WHERE username = ?")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await?;
+        stored.flatten().map(|s| s.parse()).transpose()
+    }
+
     async fn is_admin(&self, username: &str) -> Result<bool> {
         let row = sqlx::query("SELECT 1 FROM admins WHERE username = ?")
             .bind(username)
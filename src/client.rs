@@ -0,0 +1,485 @@
+//! Async NNTP client.
+//!
+//! [`NntpClient`] speaks the client side of NNTP over plain TCP, implicit
+//! TLS or a connection upgraded with STARTTLS. Reader commands (GROUP, OVER,
+//! STAT, ARTICLE) return parsed values and articles come back as
+//! [`Message`]s from [`parse_message`]. The transit commands used by push
+//! feeds are offered as well. Subscriptions and peer feeds use it, and it
+//! can be used on its own by tests and tooling.
+
+use anyhow::{Result, anyhow};
+use rustls_native_certs::load_native_certs;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{self, RootCertStore},
+};
+
+use crate::handlers::utils::{send_body, send_headers, write_simple};
+use crate::overview::OverviewLine;
+use crate::peers::{PeerConnectionInfo, PeerCredentials};
+use crate::{Message, parse_message};
+
+/// Byte stream a client runs over: TCP, or TLS on top of it.
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Creates a TLS connector trusting the system's root certificates.
+fn create_tls_connector() -> Result<TlsConnector> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_native_certs()? {
+        roots.add(&rustls::Certificate(cert.0))?;
+    }
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Outcome of offering one article to a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offer {
+    /// The peer took the article.
+    Accepted,
+    /// The peer already has the article or refused it for good.
+    Refused,
+    /// The peer asked for the article to be offered again later.
+    Deferred,
+}
+
+/// Article count and water marks from a `211` GROUP reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupStatus {
+    pub count: u64,
+    pub low: u64,
+    pub high: u64,
+}
+
+impl GroupStatus {
+    /// Parse the numbers of a `211 count low high group` reply.
+    fn parse(reply: &str) -> Option<Self> {
+        let mut parts = reply.split_whitespace().skip(1);
+        let mut next = || parts.next()?.parse().ok();
+        Some(Self {
+            count: next()?,
+            low: next()?,
+            high: next()?,
+        })
+    }
+}
+
+/// Parse one line of an OVER reply. Servers may leave the metadata fields
+/// empty or add fields after them, so only the article number is required.
+fn parse_overview(line: &str) -> Option<OverviewLine> {
+    let mut fields = line.split('\t');
+    let number = fields.next()?.parse().ok()?;
+    let mut next = || fields.next().unwrap_or_default().to_string();
+    Some(OverviewLine {
+        number,
+        subject: next(),
+        from: next(),
+        date: next(),
+        message_id: next(),
+        references: next(),
+        bytes: next().parse().unwrap_or_default(),
+        lines: next().parse().unwrap_or_default(),
+    })
+}
+
+/// A connection to an NNTP server.
+pub struct NntpClient {
+    stream: BufReader<Box<dyn Stream>>,
+    host: String,
+    line_buffer: String,
+}
+
+impl NntpClient {
+    /// Connect over plain TCP and read the greeting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be reached or does not greet
+    /// with `200` or `201`.
+    pub async fn connect(host: &str, port: u16) -> Result<Self> {
+        let tcp = Self::tcp(host, port).await?;
+        Self::greet(Box::new(tcp), host).await
+    }
+
+    /// Connect with TLS from the start, as on port 563, and read the
+    /// greeting. The server certificate is checked against the system's
+    /// root certificates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot be reached, the TLS handshake
+    /// fails or the greeting is not `200` or `201`.
+    pub async fn connect_tls(host: &str, port: u16) -> Result<Self> {
+        let tcp = Self::tcp(host, port).await?;
+        let tls = Self::handshake(Box::new(tcp), host).await?;
+        Self::greet(tls, host).await
+    }
+
+    /// Connect as described by `info`: with TLS or STARTTLS if asked, then
+    /// logging in when credentials are given.
+    pub(crate) async fn open(info: &PeerConnectionInfo) -> Result<Self> {
+        let mut client = if info.tls {
+            Self::connect_tls(&info.host, info.port).await?
+        } else if info.starttls {
+            Self::connect(&info.host, info.port)
+                .await?
+                .starttls()
+                .await?
+        } else {
+            Self::connect(&info.host, info.port).await?
+        };
+
+        if let Some(PeerCredentials { username, password }) = &info.credentials {
+            client.authenticate(username, password).await.map_err(|e| {
+                anyhow!("Authentication failed for {}:{}: {e}", info.host, info.port)
+            })?;
+        }
+        Ok(client)
+    }
+
+    async fn tcp(host: &str, port: u16) -> Result<TcpStream> {
+        let addr = format!("{host}:{port}");
+        TcpStream::connect(&addr)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to {addr}: {e}"))
+    }
+
+    async fn handshake(stream: Box<dyn Stream>, host: &str) -> Result<Box<dyn Stream>> {
+        let connector =
+            create_tls_connector().map_err(|e| anyhow!("Failed to create TLS connector: {e}"))?;
+        let server_name = rustls::ServerName::try_from(host)
+            .map_err(|e| anyhow!("Invalid server name '{host}': {e}"))?;
+        let tls = connector
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| anyhow!("TLS handshake failed for {host}: {e}"))?;
+        Ok(Box::new(tls))
+    }
+
+    async fn greet(stream: Box<dyn Stream>, host: &str) -> Result<Self> {
+        let mut client = Self {
+            stream: BufReader::new(stream),
+            host: host.to_string(),
+            line_buffer: String::new(),
+        };
+        let greeting = client.read_response().await?;
+        if !greeting.starts_with("200") && !greeting.starts_with("201") {
+            return Err(anyhow!(
+                "Unexpected greeting from {host}: {}",
+                greeting.trim()
+            ));
+        }
+        Ok(client)
+    }
+
+    /// Upgrade the connection to TLS with STARTTLS (RFC 4642).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server refuses STARTTLS or the handshake
+    /// fails; the connection is unusable afterwards.
+    pub async fn starttls(mut self) -> Result<Self> {
+        let reply = self.command("STARTTLS\r\n").await?;
+        if !reply.starts_with("382") {
+            return Err(anyhow!("STARTTLS refused by {}: {reply}", self.host));
+        }
+        let stream = Self::handshake(self.stream.into_inner(), &self.host).await?;
+        Ok(Self {
+            stream: BufReader::new(stream),
+            host: self.host,
+            line_buffer: String::new(),
+        })
+    }
+
+    /// Log in with AUTHINFO USER and PASS.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server does not accept the credentials.
+    pub async fn authenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        let mut reply = self
+            .command(&format!("AUTHINFO USER {username}\r\n"))
+            .await?;
+        if reply.starts_with("381") {
+            reply = self
+                .command(&format!("AUTHINFO PASS {password}\r\n"))
+                .await?;
+        }
+        if !reply.starts_with("281") {
+            return Err(anyhow!("Authentication failed"));
+        }
+        Ok(())
+    }
+
+    /// Read a response line from the server.
+    async fn read_response(&mut self) -> Result<&str> {
+        self.line_buffer.clear();
+        if self.stream.read_line(&mut self.line_buffer).await? == 0 {
+            return Err(anyhow!("Connection closed by peer"));
+        }
+        Ok(&self.line_buffer)
+    }
+
+    /// Send a command and return the first line of the reply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn command(&mut self, command: &str) -> Result<String> {
+        self.send_command(command).await?;
+        Ok(self.read_response().await?.trim_end().to_string())
+    }
+
+    /// Read a dot-terminated multi-line block, undoing dot-stuffing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails before the block ends.
+    pub async fn read_multiline(&mut self) -> Result<String> {
+        let mut block = String::new();
+        loop {
+            let line = self.read_response().await?;
+            if line == ".\r\n" || line == ".\n" {
+                return Ok(block);
+            }
+            block.push_str(line.strip_prefix('.').unwrap_or(line));
+        }
+    }
+
+    /// Read a dot-terminated article, undoing dot-stuffing. Lines are kept
+    /// as raw octets, so 8-bit bodies arrive unchanged.
+    async fn read_article(&mut self) -> Result<Vec<u8>> {
+        let mut article = Vec::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.stream.read_until(b'\n', &mut line).await? == 0 {
+                return Err(anyhow!("Connection closed by peer"));
+            }
+            if line == b".\r\n" || line == b".\n" {
+                return Ok(article);
+            }
+            article.extend_from_slice(line.strip_prefix(b".").unwrap_or(&line));
+        }
+    }
+
+    /// Send a command to the server.
+    async fn send_command(&mut self, command: &str) -> Result<()> {
+        write_simple(&mut self.stream, command).await
+    }
+
+    /// Switch to reader mode, returning whether posting is allowed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server refuses reader mode.
+    pub async fn mode_reader(&mut self) -> Result<bool> {
+        let reply = self.command("MODE READER\r\n").await?;
+        match reply.get(..3) {
+            Some("200") => Ok(true),
+            Some("201") => Ok(false),
+            _ => Err(anyhow!("MODE READER refused by {}: {reply}", self.host)),
+        }
+    }
+
+    /// Select `group`, returning `None` if the server does not carry it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on any other failure or a malformed reply.
+    pub async fn group(&mut self, group: &str) -> Result<Option<GroupStatus>> {
+        let reply = self.command(&format!("GROUP {group}\r\n")).await?;
+        if reply.starts_with("411") {
+            return Ok(None);
+        }
+        if !reply.starts_with("211") {
+            return Err(anyhow!("GROUP {group} failed on {}: {reply}", self.host));
+        }
+        GroupStatus::parse(&reply)
+            .map(Some)
+            .ok_or_else(|| anyhow!("Malformed GROUP reply from {}: {reply}", self.host))
+    }
+
+    /// Overview lines of the articles numbered `low` to `high` in the
+    /// selected group. An empty range gives no lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server does not support OVER or no group is
+    /// selected.
+    pub async fn over(&mut self, low: u64, high: u64) -> Result<Vec<OverviewLine>> {
+        let reply = self.command(&format!("OVER {low}-{high}\r\n")).await?;
+        if reply.starts_with("423") {
+            return Ok(Vec::new());
+        }
+        if !reply.starts_with("224") {
+            return Err(anyhow!("OVER failed on {}: {reply}", self.host));
+        }
+        let block = self.read_multiline().await?;
+        Ok(block.lines().filter_map(parse_overview).collect())
+    }
+
+    /// Number and Message-ID of the article `spec` (a number or a
+    /// Message-ID), or `None` if the server does not have it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn stat(&mut self, spec: &str) -> Result<Option<(u64, String)>> {
+        let reply = self.command(&format!("STAT {spec}\r\n")).await?;
+        if !reply.starts_with("223") {
+            return Ok(None);
+        }
+        let mut parts = reply.split_whitespace().skip(1);
+        let number = parts.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        Ok(parts.next().map(|id| (number, id.to_string())))
+    }
+
+    /// Full text of the article `spec` (a number or a Message-ID) as sent
+    /// by the server, or `None` if the server does not have it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn article_text(&mut self, spec: &str) -> Result<Option<Vec<u8>>> {
+        let reply = self.command(&format!("ARTICLE {spec}\r\n")).await?;
+        if !reply.starts_with("220") {
+            return Ok(None);
+        }
+        self.read_article().await.map(Some)
+    }
+
+    /// The article `spec` (a number or a Message-ID), or `None` if the
+    /// server does not have it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails or the article cannot be
+    /// parsed.
+    pub async fn article(&mut self, spec: &str) -> Result<Option<Message>> {
+        let Some(text) = self.article_text(spec).await? else {
+            return Ok(None);
+        };
+        let (_, message) = parse_message(&text)
+            .map_err(|e| anyhow!("Unparsable article {spec} from {}: {e}", self.host))?;
+        Ok(Some(message))
+    }
+
+    /// Offer an article with IHAVE, returning an error if the peer accepted
+    /// the offer but then failed the transfer.
+    pub(crate) async fn transfer_article(&mut self, article: &Message, msg_id: &str) -> Result<()> {
+        self.send_command(&format!("IHAVE {msg_id}\r\n")).await?;
+        let response = self.read_response().await?;
+        if !response.starts_with("335") {
+            return Ok(()); // Article not wanted by peer
+        }
+
+        self.send_article_content(article).await?;
+        let response = self.read_response().await?;
+        if !response.starts_with('2') {
+            return Err(anyhow!("Transfer failed: {}", response.trim()));
+        }
+
+        Ok(())
+    }
+
+    /// Switch to streaming mode, returning whether the peer supports it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn mode_stream(&mut self) -> Result<bool> {
+        self.send_command("MODE STREAM\r\n").await?;
+        Ok(self.read_response().await?.starts_with("203"))
+    }
+
+    /// Offer an article with IHAVE.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn ihave(&mut self, article: &Message, msg_id: &str) -> Result<Offer> {
+        self.send_command(&format!("IHAVE {msg_id}\r\n")).await?;
+        let response = self.read_response().await?;
+        if response.starts_with("436") {
+            return Ok(Offer::Deferred);
+        }
+        if !response.starts_with("335") {
+            return Ok(Offer::Refused);
+        }
+
+        self.send_article_content(article).await?;
+        let response = self.read_response().await?;
+        Ok(match response.get(..3) {
+            Some("235") => Offer::Accepted,
+            Some("436") => Offer::Deferred,
+            _ => Offer::Refused,
+        })
+    }
+
+    /// Offer `articles`, each with its Message-ID, with CHECK and send the
+    /// wanted ones with TAKETHIS. Every CHECK goes out before the first
+    /// reply is read and every TAKETHIS before the first of theirs, so a
+    /// batch costs two round trips however many articles it holds; keep
+    /// batches small enough for the replies to fit in the socket buffers.
+    /// Returns the outcome of each offer, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn stream_offers(&mut self, articles: &[(String, Message)]) -> Result<Vec<Offer>> {
+        for (msg_id, _) in articles {
+            self.send_command(&format!("CHECK {msg_id}\r\n")).await?;
+        }
+        let mut offers = vec![Offer::Refused; articles.len()];
+        let mut wanted = Vec::new();
+        for (index, offer) in offers.iter_mut().enumerate() {
+            match self.read_response().await?.get(..3) {
+                Some("238") => wanted.push(index),
+                Some("431") => *offer = Offer::Deferred,
+                _ => {}
+            }
+        }
+
+        for &index in &wanted {
+            let (msg_id, article) = &articles[index];
+            self.send_command(&format!("TAKETHIS {msg_id}\r\n")).await?;
+            self.send_article_content(article).await?;
+        }
+        for &index in &wanted {
+            offers[index] = match self.read_response().await?.get(..3) {
+                Some("239") => Offer::Accepted,
+                Some("431") => Offer::Deferred,
+                _ => Offer::Refused,
+            };
+        }
+        Ok(offers)
+    }
+
+    /// Send the complete article content including headers and body.
+    async fn send_article_content(&mut self, article: &Message) -> Result<()> {
+        send_headers(&mut self.stream, article).await?;
+        self.send_command("\r\n").await?;
+        send_body(&mut self.stream, &article.body).await?;
+        self.send_command(".\r\n").await?;
+        Ok(())
+    }
+
+    /// Close the connection gracefully.
+    ///
+    /// # Errors
+    ///
+    /// Shutdown failures are ignored, so this currently always succeeds.
+    pub async fn close(mut self) -> Result<()> {
+        let _ = self.stream.get_mut().shutdown().await;
+        Ok(())
+    }
+}
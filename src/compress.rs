@@ -0,0 +1,251 @@
+//! DEFLATE stream layer for the NNTP `COMPRESS` extension (RFC 8054).
+//!
+//! [`InflateReader`] and [`DeflateWriter`] wrap the two halves of a
+//! connection. They pass bytes through untouched until compression is
+//! started, after which reads are inflated and writes are deflated using a
+//! raw (headerless) DEFLATE stream. Because they sit on top of whatever
+//! transport the connection uses, the same layer works for plain TCP and
+//! TLS sockets.
+
+use crate::handlers::{HandlerContext, HandlerResult};
+use crate::responses::*;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+
+/// Size of the buffer holding compressed input read from the transport.
+const INPUT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Amount of pending compressed output that triggers a write to the
+/// transport before more data is accepted.
+const OUTPUT_HIGH_WATER: usize = 64 * 1024;
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Read half that optionally inflates incoming data.
+pub struct InflateReader<R> {
+    inner: R,
+    decompress: Option<Decompress>,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    eof: bool,
+}
+
+impl<R> InflateReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decompress: None,
+            buf: vec![0; INPUT_BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            eof: false,
+        }
+    }
+
+    /// Inflate everything read from the transport from now on.
+    pub fn start_compression(&mut self) {
+        self.decompress = Some(Decompress::new(false));
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for InflateReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(decompress) = this.decompress.as_mut() else {
+            return Pin::new(&mut this.inner).poll_read(cx, out);
+        };
+        if out.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            if this.pos == this.len && !this.eof {
+                let mut input = ReadBuf::new(&mut this.buf[..]);
+                ready!(Pin::new(&mut this.inner).poll_read(cx, &mut input))?;
+                this.len = input.filled().len();
+                this.pos = 0;
+                this.eof = this.len == 0;
+            }
+
+            let before_in = decompress.total_in();
+            let before_out = decompress.total_out();
+            let status = decompress
+                .decompress(
+                    &this.buf[this.pos..this.len],
+                    out.initialize_unfilled(),
+                    FlushDecompress::Sync,
+                )
+                .map_err(invalid_data)?;
+            let consumed = (decompress.total_in() - before_in) as usize;
+            let produced = (decompress.total_out() - before_out) as usize;
+            this.pos += consumed;
+            out.advance(produced);
+
+            if produced > 0 || this.eof || status == Status::StreamEnd {
+                return Poll::Ready(Ok(()));
+            }
+            if consumed == 0 && this.pos < this.len {
+                return Poll::Ready(Err(invalid_data("deflate stream made no progress")));
+            }
+        }
+    }
+}
+
+/// Write half that optionally deflates outgoing data.
+///
+/// Compressed output is only guaranteed to reach the peer after
+/// [`AsyncWriteExt::flush`], which emits a DEFLATE sync flush.
+pub struct DeflateWriter<W> {
+    inner: W,
+    compress: Option<Compress>,
+    out: Vec<u8>,
+    pos: usize,
+    needs_sync: bool,
+}
+
+impl<W> DeflateWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            compress: None,
+            out: Vec::new(),
+            pos: 0,
+            needs_sync: false,
+        }
+    }
+
+    /// Deflate everything written to the transport from now on.
+    pub fn start_compression(&mut self) {
+        self.compress = Some(Compress::new(Compression::default(), false));
+    }
+}
+
+impl<W: AsyncWrite + Unpin> DeflateWriter<W> {
+    /// Write all pending compressed output to the transport.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pos < self.out.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.out[self.pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pos += n;
+        }
+        self.out.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for DeflateWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.compress.is_some() && this.out.len() - this.pos >= OUTPUT_HIGH_WATER {
+            ready!(this.poll_drain(cx))?;
+        }
+        let Some(compress) = this.compress.as_mut() else {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        };
+
+        loop {
+            if this.out.capacity() - this.out.len() < 64 {
+                this.out.reserve(buf.len().max(INPUT_BUFFER_SIZE));
+            }
+            let before = compress.total_in();
+            compress
+                .compress_vec(buf, &mut this.out, FlushCompress::None)
+                .map_err(invalid_data)?;
+            let consumed = (compress.total_in() - before) as usize;
+            if consumed > 0 || buf.is_empty() {
+                this.needs_sync = true;
+                return Poll::Ready(Ok(consumed));
+            }
+            this.out.reserve(INPUT_BUFFER_SIZE);
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(compress) = this.compress.as_mut() {
+            if this.needs_sync {
+                loop {
+                    if this.out.capacity() - this.out.len() < 64 {
+                        this.out.reserve(INPUT_BUFFER_SIZE);
+                    }
+                    compress
+                        .compress_vec(&[], &mut this.out, FlushCompress::Sync)
+                        .map_err(invalid_data)?;
+                    // The flush is complete once deflate stops filling the
+                    // space it was given.
+                    if this.out.len() < this.out.capacity() {
+                        break;
+                    }
+                }
+                this.needs_sync = false;
+            }
+            ready!(this.poll_drain(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Handle the `COMPRESS` command.
+///
+/// Replies 206 uncompressed and then switches both halves of the
+/// connection to DEFLATE. Compression can only be negotiated once per
+/// session; later attempts are refused with 502.
+pub async fn handle_compress<R, W>(
+    ctx: &mut HandlerContext<BufReader<InflateReader<R>>, DeflateWriter<W>>,
+    args: &[String],
+) -> HandlerResult
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if ctx.state.compressed {
+        ctx.writer
+            .write_all(RESP_502_COMPRESSION_ACTIVE.as_bytes())
+            .await?;
+        return Ok(());
+    }
+    let [algorithm] = args else {
+        ctx.writer.write_all(RESP_501_SYNTAX.as_bytes()).await?;
+        return Ok(());
+    };
+    if !algorithm.eq_ignore_ascii_case("DEFLATE") {
+        ctx.writer
+            .write_all(RESP_503_NOT_SUPPORTED.as_bytes())
+            .await?;
+        return Ok(());
+    }
+
+    ctx.writer
+        .write_all(RESP_206_COMPRESSION_ACTIVE.as_bytes())
+        .await?;
+    ctx.writer.flush().await?;
+    ctx.reader.get_mut().start_compression();
+    ctx.writer.start_compression();
+    ctx.state.compressed = true;
+    Ok(())
+}
@@ -1,6 +1,8 @@
-use crate::wildmat::wildmat;
+use crate::auth::ClientCertificate;
+use crate::wildmat::{wildmat, wildmat_list, wildmat_regex};
 use anyhow::Result;
 use chrono::Duration;
+use ipnet::IpNet;
 use regex::Regex;
 use serde::Deserialize;
 use serde::de::{self, Deserializer, Visitor};
@@ -26,14 +28,46 @@ fn default_idle_timeout_secs() -> u64 {
     600
 }
 
+fn default_article_line_timeout_secs() -> u64 {
+    60
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    60
+}
+
 fn default_article_queue_capacity() -> usize {
     1000
 }
 
+fn default_history_cache_size() -> usize {
+    100_000
+}
+
+fn default_posting_enabled() -> bool {
+    true
+}
+
+fn default_peer_tls() -> bool {
+    true
+}
+
 fn default_article_worker_count() -> usize {
     4
 }
 
+fn default_max_article_bytes() -> Option<u64> {
+    Some(1024 * 1024)
+}
+
+fn default_article_batch_size() -> usize {
+    32
+}
+
 fn default_runtime_threads() -> usize {
     1
 }
@@ -145,48 +179,593 @@ where
     deserializer.deserialize_any(SizeVisitor)
 }
 
+fn parse_duration(input: &str) -> Option<Duration> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let (digits, unit) = match trimmed.chars().last()? {
+        's' | 'S' => (&trimmed[..trimmed.len() - 1], 1i64),
+        'm' | 'M' => (&trimmed[..trimmed.len() - 1], 60),
+        'h' | 'H' => (&trimmed[..trimmed.len() - 1], 60 * 60),
+        'd' | 'D' => (&trimmed[..trimmed.len() - 1], 24 * 60 * 60),
+        'w' | 'W' => (&trimmed[..trimmed.len() - 1], 7 * 24 * 60 * 60),
+        '0'..='9' => (trimmed, 1),
+        _ => return None,
+    };
+    digits
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|n| *n >= 0)
+        .and_then(|n| n.checked_mul(unit))
+        .and_then(Duration::try_seconds)
+}
+
+/// `[[filters]]` tables, or a `[filters]` table whose `pipeline` lists
+/// built-in filters by name.
+fn deserialize_filters<'de, D>(deserializer: D) -> Result<Vec<FilterConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Filters {
+        Tables(Vec<FilterConfig>),
+        Pipeline { pipeline: Vec<String> },
+    }
+
+    Ok(match Filters::deserialize(deserializer)? {
+        Filters::Tables(filters) => filters,
+        Filters::Pipeline { pipeline } => pipeline
+            .into_iter()
+            .map(|name| FilterConfig {
+                name,
+                parameters: serde_json::Map::new(),
+            })
+            .collect(),
+    })
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DurationVisitor;
+
+    impl Visitor<'_> for DurationVisitor {
+        type Value = Option<Duration>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("seconds or a string with an s, m, h, d or w suffix")
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            i64::try_from(v)
+                .ok()
+                .and_then(Duration::try_seconds)
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("duration out of range: {v}")))
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if v < 0 {
+                Err(de::Error::custom("duration must be positive"))
+            } else {
+                Duration::try_seconds(v)
+                    .map(Some)
+                    .ok_or_else(|| de::Error::custom(format!("duration out of range: {v}")))
+            }
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            parse_duration(v)
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("invalid duration: {v}")))
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(DurationVisitor)
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Config {
     pub addr: String,
     #[serde(default = "default_site_name")]
     pub site_name: String,
+    /// Name this server adds to the Path of stored articles and looks for to
+    /// refuse looping offers; `site_name` when unset.
+    #[serde(default)]
+    pub path_host: Option<String>,
+    /// Contact address of the server administrator, recorded as the creator
+    /// of groups added locally.
+    #[serde(default)]
+    pub admin_address: Option<String>,
     #[serde(default = "default_db_path")]
     pub db_path: String,
     #[serde(default = "default_auth_db_path")]
     pub auth_db_path: String,
     #[serde(default = "default_peer_db_path")]
     pub peer_db_path: String,
+    #[serde(default)]
+    pub mirror_db_path: Option<String>,
+    #[serde(default)]
+    pub mirror_failure_policy: MirrorFailurePolicy,
 
     #[serde(default = "default_peer_sync_schedule")]
     pub peer_sync_schedule: String,
     #[serde(default = "default_idle_timeout_secs")]
     pub idle_timeout_secs: u64,
+    /// Most client connections open at once across every listener.
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// Most client connections open at once from a single address.
+    #[serde(default)]
+    pub max_connections_per_ip: Option<usize>,
+    /// Overrides `idle_timeout_secs` for the plain text listener.
+    #[serde(default)]
+    pub plain_idle_timeout_secs: Option<u64>,
+    /// Overrides `idle_timeout_secs` for the TLS listener.
+    #[serde(default)]
+    pub tls_idle_timeout_secs: Option<u64>,
+    /// Overrides `idle_timeout_secs` for the transit listener.
+    #[serde(default)]
+    pub transit_idle_timeout_secs: Option<u64>,
+    /// Seconds to wait for each line of an article once POST, IHAVE or
+    /// TAKETHIS is sending one.
+    #[serde(default = "default_article_line_timeout_secs")]
+    pub article_line_timeout_secs: u64,
+    /// Seconds busy sessions get to finish their current command on
+    /// shutdown before the article queue is drained.
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Seconds the queue workers get to store the articles still queued at
+    /// shutdown; whatever is left after that is abandoned.
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
     #[serde(default, alias = "peer")]
     pub peers: Vec<PeerRule>,
+    /// Upstream servers whose groups are fetched periodically.
+    #[serde(default, alias = "subscription")]
+    pub subscriptions: Vec<SubscriptionRule>,
     #[serde(default)]
     pub tls_addr: Option<String>,
     #[serde(default)]
     pub tls_cert: Option<String>,
     #[serde(default)]
     pub tls_key: Option<String>,
+    /// PEM bundle of CAs whose client certificates are accepted on the TLS
+    /// listener, enabling `AUTHINFO SASL EXTERNAL`.
+    #[serde(default)]
+    pub tls_client_ca: Option<String>,
     #[serde(default)]
     pub ws_addr: Option<String>,
-    #[serde(default = "default_article_queue_capacity")]
+    /// Listener for peer feeds. Connections here may only use IHAVE, CHECK
+    /// and TAKETHIS, and once it is set the reader listeners refuse them.
+    #[serde(default)]
+    pub transit_addr: Option<String>,
+    /// HTTP listener serving Prometheus metrics at `/metrics`; needs the
+    /// `metrics` feature.
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+    /// Articles the queue holds before submissions wait or are refused.
+    #[serde(default = "default_article_queue_capacity", alias = "queue_capacity")]
     pub article_queue_capacity: usize,
-    #[serde(default = "default_article_worker_count")]
+    /// Queue workers storing articles; one suits SQLite on small hosts.
+    #[serde(default = "default_article_worker_count", alias = "queue_workers")]
     pub article_worker_count: usize,
+    /// Most waiting articles a worker takes at once and stores in a single
+    /// batch; 1 stores each article on its own.
+    #[serde(default = "default_article_batch_size")]
+    pub article_batch_size: usize,
+    /// Directory where articles that fail in a queue worker are written
+    /// with the reason, for inspection and replay.
+    #[serde(default)]
+    pub dead_letter_dir: Option<String>,
+    /// Number of recently offered Message-IDs remembered to refuse
+    /// duplicates without a storage lookup; 0 disables the cache.
+    #[serde(default = "default_history_cache_size")]
+    pub history_cache_size: usize,
+    /// Headers appended to every overview line after the standard seven
+    /// fields, in order, and listed in `LIST OVERVIEW.FMT` as `Name:full`.
+    #[serde(default)]
+    pub overview_extra_headers: Vec<String>,
     #[serde(default = "default_runtime_threads")]
     pub runtime_threads: usize,
     #[serde(default, alias = "group")]
     pub group_settings: Vec<GroupRule>,
-    #[serde(default, alias = "filter")]
+    /// How often the expiry task applies retention; hourly when unset.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub retention_interval: Option<Duration>,
+    /// How long cancelled and expired Message-IDs are remembered; 30 days when unset.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub history_retention: Option<Duration>,
+    /// Article filters in the order they run; the default chain when empty.
+    #[serde(default, alias = "filter", deserialize_with = "deserialize_filters")]
     pub filters: Vec<FilterConfig>,
+    /// TLS client certificates that log in as a local user without AUTHINFO.
+    #[serde(default, alias = "client_cert")]
+    pub client_certs: Vec<ClientCertRule>,
+    /// Per-group read and post rights; groups no rule covers are open.
+    #[serde(default)]
+    pub access: AccessRules,
+    /// Show groups a client may not read in LIST and NEWGROUPS output
+    /// instead of hiding them.
+    #[serde(default)]
+    pub list_unreadable_groups: bool,
 
     #[serde(default = "default_pgp_key_servers")]
     pub pgp_key_servers: Vec<String>,
 
+    /// Keys trusted to sign newgroup, rmgroup and checkgroups control
+    /// messages, by hierarchy.
+    #[serde(default, alias = "control_key")]
+    pub control_keys: Vec<ControlKeyRule>,
+    /// How checkgroups control messages are applied.
+    #[serde(default)]
+    pub checkgroups: CheckgroupsConfig,
+
+    /// Whether readers may POST. Transit via IHAVE and TAKETHIS is not
+    /// affected.
+    #[serde(default = "default_posting_enabled")]
+    pub posting_enabled: bool,
+    /// Overrides `posting_enabled` for the plain text listener.
+    #[serde(default)]
+    pub plain_posting_enabled: Option<bool>,
+    /// Overrides `posting_enabled` for the TLS listener.
+    #[serde(default)]
+    pub tls_posting_enabled: Option<bool>,
+
     #[serde(default)]
     pub allow_posting_insecure_connections: bool,
+
+    /// Offer and accept `AUTHINFO SASL PLAIN` on connections without TLS.
+    #[serde(default)]
+    pub allow_sasl_plain_insecure: bool,
+
+    #[serde(default)]
+    pub moderated_crosspost_policy: ModeratedCrosspostPolicy,
+
+    /// Header checks made by the `HeaderRulesFilter`, in order.
+    #[serde(default, alias = "header_rule")]
+    pub header_rules: Vec<HeaderRule>,
+
+    /// Largest article accepted in groups without a `max_article_bytes`
+    /// rule of their own; `0` removes the limit.
+    #[serde(
+        default = "default_max_article_bytes",
+        deserialize_with = "deserialize_size"
+    )]
+    pub max_article_bytes: Option<u64>,
+
+    /// Most distinct groups an article may be crossposted to.
+    #[serde(default)]
+    pub max_crosspost_groups: Option<usize>,
+    /// Crossposts to more groups than this must carry a Followup-To naming
+    /// a single group.
+    #[serde(default)]
+    pub require_followup_to_above: Option<usize>,
+
+    /// Moderator address template for moderated groups without an address
+    /// of their own; `%s` stands for the group name with dots turned into
+    /// dashes, as in `%s@moderators.example.org`.
+    #[serde(default)]
+    pub moderators: Option<String>,
+
+    /// SMTP relay (`host:port`) through which unapproved posts are mailed to
+    /// moderators. Without one such posts are refused.
+    #[serde(default)]
+    pub smtp_relay: Option<String>,
+
+    #[serde(default)]
+    pub post_durability: PostDurability,
+
+    /// What POST does with an article that has no Message-ID.
+    #[serde(default)]
+    pub message_id_generation: MessageIdGeneration,
+
+    /// Limits on POST, IHAVE and TAKETHIS per user and per remote address.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+
+    /// Whether a full article queue makes submissions wait or be refused.
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+
+    /// What the Injection-Info header of posted articles reveals.
+    #[serde(default)]
+    pub injection: InjectionConfig,
+
+    /// Networks allowed to connect and to submit articles.
+    #[serde(default)]
+    pub ip_access: IpAccessConfig,
+
+    /// Detection of one body posted over and over.
+    #[serde(default)]
+    pub spam: SpamConfig,
+
+    /// Distributions listed by `LIST DISTRIBUTIONS` and `LIST DISTRIB.PATS`.
+    #[serde(default)]
+    pub distributions: DistributionsConfig,
+}
+
+fn default_duplicate_window() -> u64 {
+    60
+}
+
+/// Limits applied by the `DuplicateBodyFilter`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpamConfig {
+    /// Articles with the same body accepted within the window; further
+    /// copies are refused. Unset disables the check.
+    #[serde(default)]
+    pub max_duplicates: Option<u32>,
+    /// Minutes an accepted body counts against `max_duplicates`.
+    #[serde(default = "default_duplicate_window")]
+    pub duplicate_window: u64,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            max_duplicates: None,
+            duplicate_window: default_duplicate_window(),
+        }
+    }
+}
+
+impl SpamConfig {
+    /// How long an accepted body is remembered, at least a minute.
+    #[must_use]
+    pub fn window(&self) -> Duration {
+        // Capped well inside what a chrono duration can hold
+        Duration::minutes(self.duplicate_window.clamp(1, u64::from(u32::MAX)) as i64)
+    }
+}
+
+/// The `[distributions]` table: the distribution keywords of this site and
+/// the defaults newsreaders suggest for groups.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DistributionsConfig {
+    /// Refuse posts whose Distribution header names a keyword not listed.
+    #[serde(default)]
+    pub strict: bool,
+    /// Keywords returned by `LIST DISTRIBUTIONS`.
+    #[serde(default, alias = "keyword")]
+    pub keywords: Vec<Distribution>,
+    /// Rules returned by `LIST DISTRIB.PATS`.
+    #[serde(default, alias = "pattern")]
+    pub patterns: Vec<DistributionPattern>,
+}
+
+impl DistributionsConfig {
+    /// Whether `name` is a listed keyword, compared without regard to case.
+    #[must_use]
+    pub fn is_known(&self, name: &str) -> bool {
+        self.keywords
+            .iter()
+            .any(|d| d.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A distribution keyword and what it covers.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Distribution {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A default distribution for the groups matching a wildmat; where several
+/// match, newsreaders take the one with the highest weight.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DistributionPattern {
+    pub weight: u32,
+    /// Wildmat of the groups covered, such as `local.*`.
+    pub groups: String,
+    pub distribution: String,
+}
+
+/// Deserialize a list of networks in CIDR notation, where a bare address
+/// stands for that address alone.
+fn deserialize_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| {
+            s.parse::<IpNet>()
+                .or_else(|_| s.parse::<std::net::IpAddr>().map(IpNet::from))
+                .map_err(|_| de::Error::custom(format!("invalid network: {s}")))
+        })
+        .collect()
+}
+
+/// Whether `ip` is in any of `networks`. A linear scan, which is quick
+/// enough for the few hundred entries these lists hold.
+fn in_networks(networks: &[IpNet], ip: std::net::IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    networks.iter().any(|net| net.contains(&ip))
+}
+
+/// Coarse access control by client address. Empty lists allow every
+/// address.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IpAccessConfig {
+    /// Networks allowed to connect.
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub allow: Vec<IpNet>,
+    /// Networks refused at connect time, even when also allowed.
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub deny: Vec<IpNet>,
+    /// Networks allowed to submit articles with POST, IHAVE, CHECK and
+    /// TAKETHIS.
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub posting_allow: Vec<IpNet>,
+}
+
+impl IpAccessConfig {
+    /// Whether a client at `ip` may connect at all.
+    #[must_use]
+    pub fn connection_allowed(&self, ip: std::net::IpAddr) -> bool {
+        (self.allow.is_empty() || in_networks(&self.allow, ip)) && !in_networks(&self.deny, ip)
+    }
+
+    /// Whether a client at `ip` may submit articles.
+    #[must_use]
+    pub fn posting_allowed(&self, ip: std::net::IpAddr) -> bool {
+        self.posting_allow.is_empty() || in_networks(&self.posting_allow, ip)
+    }
+}
+
+/// Token bucket limits applied to article submission. Unset rates are not
+/// enforced.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub articles_per_minute: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub bytes_per_minute: Option<u64>,
+    /// Articles per hour, for throttling posters over a longer window than
+    /// the per-minute rates.
+    #[serde(default)]
+    pub posts_per_hour: Option<u32>,
+}
+
+impl RateLimitConfig {
+    /// Whether any limit is configured.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.articles_per_minute.is_some()
+            || self.bytes_per_minute.is_some()
+            || self.posts_per_hour.is_some()
+    }
+}
+
+/// What each class of command does when the article queue is full.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BackpressureConfig {
+    /// POST from readers.
+    #[serde(default)]
+    pub post: QueueFullPolicy,
+    /// IHAVE and TAKETHIS from peers.
+    #[serde(default)]
+    pub transit: QueueFullPolicy,
+    /// Longest a `wait` submission holds its command before it is refused
+    /// as under `reject`. Unset waits for as long as it takes.
+    #[serde(default)]
+    pub wait_timeout_ms: Option<u64>,
+}
+
+impl BackpressureConfig {
+    /// How long a `wait` submission may wait for room, if bounded.
+    #[must_use]
+    pub fn wait_timeout(&self) -> Option<std::time::Duration> {
+        self.wait_timeout_ms.map(std::time::Duration::from_millis)
+    }
+}
+
+/// Whether a submission waits for room in a full article queue.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueFullPolicy {
+    /// Refuse the article at once with a try-again-later response.
+    #[default]
+    Reject,
+    /// Hold the command until a worker makes room.
+    Wait,
+}
+
+/// Settings for the Injection-Info header added to posted articles.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct InjectionConfig {
+    #[serde(default)]
+    pub posting_host: PostingHost,
+    /// Key for hashing the posting host and user; a random key chosen at
+    /// startup is used when unset.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// How the poster's address and user name appear in Injection-Info.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostingHost {
+    /// A keyed hash that only the operator can match against logs.
+    #[default]
+    Hashed,
+    /// The address and user name as they are.
+    Plain,
+    /// Neither is included.
+    Omit,
+}
+
+/// How to treat an article crossposted to moderated and unmoderated groups
+/// when it lacks approval for one or more of the moderated groups.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeratedCrosspostPolicy {
+    /// Hold the whole article until every moderated group has approved it.
+    #[default]
+    HoldAll,
+    /// Let the article go live in the unmoderated groups and send a copy for
+    /// each moderated group that has not approved it to that group's
+    /// moderator.
+    Split,
+}
+
+/// When POST acknowledges an article with `240`.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostDurability {
+    /// Reply as soon as the article is queued.
+    #[default]
+    AckOnQueue,
+    /// Wait until a worker has stored the article.
+    AckOnStore,
+}
+
+/// How POST supplies a Message-ID the client left out.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageIdGeneration {
+    /// Derive the id from a hash of the body.
+    #[default]
+    Hash,
+    /// Use a timestamp and random part unused by any stored article, and
+    /// name it in the `240` reply.
+    Random,
+    /// Refuse the article.
+    Refuse,
+}
+
+/// What to do when a write to the mirror (secondary) storage fails.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorFailurePolicy {
+    /// Log the failure and keep the primary write.
+    #[default]
+    Warn,
+    /// Report the failure to the caller after the primary write.
+    Fail,
 }
 
 #[derive(Deserialize, Clone)]
@@ -197,8 +776,37 @@ pub struct GroupRule {
     pub pattern: Option<String>,
     #[serde(default)]
     pub retention_days: Option<i64>,
+    /// Retention window such as `"36h"` or `"2w"`; takes precedence over
+    /// `retention_days` when both are set on the same rule.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub retention: Option<Duration>,
     #[serde(default, deserialize_with = "deserialize_size")]
     pub max_article_bytes: Option<u64>,
+    /// Store only the first N bytes of larger bodies instead of the full text.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub body_truncate_bytes: Option<u64>,
+    /// Keep at most this many of the newest articles in the group.
+    #[serde(default)]
+    pub max_articles: Option<u64>,
+    /// Most distinct groups an article posted here may name.
+    #[serde(default)]
+    pub max_crosspost_groups: Option<usize>,
+    /// Refuse base64 parts, yEnc data and non-text media types in articles
+    /// posted here.
+    #[serde(default)]
+    pub no_binaries: Option<bool>,
+}
+
+impl GroupRule {
+    /// Whether this rule names `group` exactly or by pattern.
+    #[must_use]
+    pub fn matches(&self, group: &str) -> bool {
+        match (&self.group, &self.pattern) {
+            (Some(name), _) => name == group,
+            (None, Some(pattern)) => wildmat(pattern, group),
+            (None, None) => false,
+        }
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -208,6 +816,292 @@ pub struct PeerRule {
     pub patterns: Vec<String>,
     #[serde(default)]
     pub sync_schedule: Option<String>,
+    /// Host to push accepted articles to as they arrive. Peers without a
+    /// host are fed by the scheduled sync instead.
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_peer_tls")]
+    pub tls: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Most articles kept queued for a push peer; the oldest are dropped
+    /// beyond it. 100000 when unset.
+    #[serde(default)]
+    pub max_backlog: Option<usize>,
+}
+
+impl PeerRule {
+    /// Whether this peer receives a real-time push feed.
+    #[must_use]
+    pub fn pushes(&self) -> bool {
+        self.host.is_some()
+    }
+
+    /// Whether an article posted to `group` is wanted by this peer.
+    #[must_use]
+    pub fn wants(&self, group: &str) -> bool {
+        wildmat_list(&self.patterns.join(","), group)
+    }
+
+    /// Most articles kept queued for this peer.
+    #[must_use]
+    pub fn max_backlog_or_default(&self) -> usize {
+        self.max_backlog.unwrap_or(100_000)
+    }
+}
+
+/// An upstream server to fetch ("suck") articles from.
+#[derive(Deserialize, Clone)]
+pub struct SubscriptionRule {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_peer_tls")]
+    pub tls: bool,
+    /// Upgrade a plain connection with STARTTLS before logging in; only
+    /// used with `tls = false`.
+    #[serde(default)]
+    pub starttls: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Local groups to fetch, as wildmat patterns.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Time between fetches; 15 minutes when unset.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub poll_interval: Option<Duration>,
+}
+
+impl SubscriptionRule {
+    /// Whether the local group `group` is fetched from this server.
+    #[must_use]
+    pub fn wants(&self, group: &str) -> bool {
+        wildmat_list(&self.patterns.join(","), group)
+    }
+
+    /// Name under which the high-water marks for this server are kept.
+    #[must_use]
+    pub fn server(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{port}", self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Time between fetches.
+    #[must_use]
+    pub fn poll_interval_or_default(&self) -> std::time::Duration {
+        self.poll_interval
+            .and_then(|d| d.to_std().ok())
+            .filter(|d| !d.is_zero())
+            .unwrap_or(std::time::Duration::from_secs(15 * 60))
+    }
+}
+
+/// Maps a TLS client certificate to a local user. A rule matches on the
+/// certificate's subject DN, its SHA-256 fingerprint, or both.
+#[derive(Deserialize, Clone)]
+pub struct ClientCertRule {
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// Hex SHA-256 fingerprint; colons and case are ignored.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// The session is authenticated as this user, with that user's
+    /// admin and moderator rights.
+    pub username: String,
+}
+
+impl ClientCertRule {
+    fn matches(&self, cert: &ClientCertificate) -> bool {
+        if self.subject.is_none() && self.fingerprint.is_none() {
+            return false;
+        }
+        let subject_ok = self.subject.as_ref().is_none_or(|s| *s == cert.subject);
+        let fingerprint_ok = self.fingerprint.as_ref().is_none_or(|f| {
+            f.chars()
+                .filter(|c| *c != ':')
+                .map(|c| c.to_ascii_lowercase())
+                .eq(cert.fingerprint.chars())
+        });
+        subject_ok && fingerprint_ok
+    }
+}
+
+/// Restricts who may read or post in the groups matching `groups`.
+/// Where several rules cover a group, the last one setting a right decides it.
+#[derive(Deserialize, Clone)]
+pub struct AccessRule {
+    /// Wildmat list of the groups the rule applies to.
+    pub groups: String,
+    /// Users allowed to read; `*` admits every client, including anonymous ones.
+    #[serde(default)]
+    pub read: Option<Vec<String>>,
+    /// Users allowed to post, with the same `*` convention as `read`.
+    #[serde(default)]
+    pub post: Option<Vec<String>>,
+}
+
+/// Handling of checkgroups control messages.
+#[derive(Deserialize, Clone, Copy, Default)]
+pub struct CheckgroupsConfig {
+    /// Remove carried groups that a checkgroups message no longer lists.
+    #[serde(default)]
+    pub remove_unlisted: bool,
+    /// Only log the changes a checkgroups message would make.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What a header rule does with an article whose header matches it.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderAction {
+    /// Refuse articles with a matching header.
+    #[default]
+    Reject,
+    /// Refuse articles without a matching header.
+    Require,
+}
+
+/// A `[[header_rules]]` entry. The pattern is a wildmat matched without
+/// regard to case, or a regular expression when `regex` is set; either is
+/// compiled when the configuration is loaded.
+#[derive(Clone, Debug)]
+pub struct HeaderRule {
+    /// Named in the rejection; `header: pattern` unless configured.
+    pub name: String,
+    /// Header whose values are checked, in any case.
+    pub header: String,
+    pub action: HeaderAction,
+    matcher: Regex,
+}
+
+impl HeaderRule {
+    /// Whether the header value `value` matches the rule's pattern.
+    #[must_use]
+    pub fn matches(&self, value: &str) -> bool {
+        self.matcher.is_match(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderRule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(default)]
+            name: Option<String>,
+            header: String,
+            pattern: String,
+            #[serde(default)]
+            regex: bool,
+            #[serde(default)]
+            action: HeaderAction,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        let matcher = if raw.regex {
+            Regex::new(&raw.pattern)
+        } else {
+            wildmat_regex(&raw.pattern, true)
+        }
+        .map_err(|e| {
+            de::Error::custom(format!(
+                "invalid pattern {:?} in header rule for {}: {e}",
+                raw.pattern, raw.header
+            ))
+        })?;
+        Ok(Self {
+            name: raw
+                .name
+                .unwrap_or_else(|| format!("{}: {}", raw.header, raw.pattern)),
+            header: raw.header,
+            action: raw.action,
+            matcher,
+        })
+    }
+}
+
+/// PGP keys allowed to create and remove groups in a hierarchy.
+#[derive(Deserialize, Clone)]
+pub struct ControlKeyRule {
+    /// Wildmat list of the groups covered, such as `comp.*`.
+    pub hierarchy: String,
+    /// Fingerprints of the acceptable signing keys; spaces, colons and
+    /// case are ignored.
+    pub fingerprints: Vec<String>,
+}
+
+/// The `[[access]]` rules of a configuration. Cheap to clone, so handlers
+/// can take a copy instead of holding the configuration lock while writing.
+#[derive(Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct AccessRules(pub Vec<AccessRule>);
+
+impl AccessRules {
+    /// Whether `user` (`None` before authentication) may read `group`.
+    #[must_use]
+    pub fn can_read_group(&self, group: &str, user: Option<&str>) -> bool {
+        self.access_allows(group, user, |rule| rule.read.as_deref())
+    }
+
+    /// Whether `user` (`None` before authentication) may post to `group`.
+    #[must_use]
+    pub fn can_post_group(&self, group: &str, user: Option<&str>) -> bool {
+        self.access_allows(group, user, |rule| rule.post.as_deref())
+    }
+
+    /// Whether `user` may post to some group: false only when no rule lets
+    /// them post and a rule covering every group (`*`) does not either.
+    #[must_use]
+    pub fn can_post_somewhere(&self, user: Option<&str>) -> bool {
+        let admits = |users: &[String]| users.iter().any(|u| u == "*" || Some(u.as_str()) == user);
+        for rule in self.0.iter().rev() {
+            let Some(users) = rule.post.as_deref() else {
+                continue;
+            };
+            if admits(users) {
+                return true;
+            }
+            if rule.groups.trim() == "*" {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether `user` may read at least one of the groups `article` is in.
+    #[must_use]
+    pub fn can_read_article(&self, article: &crate::Message, user: Option<&str>) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        let groups = crate::storage::common::parse_newsgroups_from_message(article);
+        groups.is_empty() || groups.iter().any(|g| self.can_read_group(g, user))
+    }
+
+    fn access_allows(
+        &self,
+        group: &str,
+        user: Option<&str>,
+        right: impl Fn(&AccessRule) -> Option<&[String]>,
+    ) -> bool {
+        self.0
+            .iter()
+            .rev()
+            .filter(|rule| wildmat_list(&rule.groups, group))
+            .find_map(right)
+            .is_none_or(|users| users.iter().any(|u| u == "*" || Some(u.as_str()) == user))
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -277,95 +1171,226 @@ See 'examples/config.toml' for a valid configuration example."
         // Enforce minimum values for queue configuration
         cfg.article_queue_capacity = cfg.article_queue_capacity.max(1);
         cfg.article_worker_count = cfg.article_worker_count.max(1);
+        cfg.article_batch_size = cfg.article_batch_size.max(1);
+
+        crate::filters::factory::create_filter_chain(&cfg.filters)
+            .map_err(|e| anyhow::anyhow!("Invalid filter pipeline in '{path}': {e}"))?;
 
         Ok(cfg)
     }
 
+    /// Access rules deciding which groups LIST and NEWGROUPS show a client:
+    /// none at all when `list_unreadable_groups` is set.
     #[must_use]
-    pub fn retention_for_group(&self, group: &str) -> Option<Duration> {
-        // First check for exact group matches
-        if let Some(rule) = self
+    pub fn listing_access(&self) -> AccessRules {
+        if self.list_unreadable_groups {
+            AccessRules::default()
+        } else {
+            self.access.clone()
+        }
+    }
+
+    /// Whether reader posting is enabled on the TLS or plain text listener.
+    #[must_use]
+    pub fn posting_enabled_for(&self, is_tls: bool) -> bool {
+        let listener = if is_tls {
+            self.tls_posting_enabled
+        } else {
+            self.plain_posting_enabled
+        };
+        listener.unwrap_or(self.posting_enabled)
+    }
+
+    /// Whether a session logged in as `user` from `ip` is one of the
+    /// configured peers: the user is a peer's sitename or the address is a
+    /// peer's host.
+    #[must_use]
+    pub fn is_peer(&self, user: Option<&str>, ip: Option<std::net::IpAddr>) -> bool {
+        self.peers.iter().any(|peer| {
+            user == Some(peer.sitename.as_str())
+                || (ip.is_some() && peer.host.as_deref().and_then(|h| h.parse().ok()) == ip)
+        })
+    }
+
+    /// How long to wait for each line of an article being transferred.
+    #[must_use]
+    pub fn article_line_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.article_line_timeout_secs)
+    }
+
+    /// Fingerprints of the keys that may sign group changes for `group`, if
+    /// its hierarchy has any configured. The last matching rule wins.
+    #[must_use]
+    pub fn control_fingerprints_for(&self, group: &str) -> Option<&[String]> {
+        self.control_keys
+            .iter()
+            .rev()
+            .find(|rule| wildmat_list(&rule.hierarchy, group))
+            .map(|rule| rule.fingerprints.as_slice())
+    }
+
+    /// The local user a TLS client certificate is mapped to, if any rule matches.
+    #[must_use]
+    pub fn user_for_certificate(&self, cert: &ClientCertificate) -> Option<String> {
+        self.client_certs
+            .iter()
+            .find(|rule| rule.matches(cert))
+            .map(|rule| rule.username.clone())
+    }
+
+    /// The configured `path_host`, or `site_name` if none is set.
+    #[must_use]
+    pub fn path_identity(&self) -> &str {
+        self.path_host.as_deref().unwrap_or(&self.site_name)
+    }
+
+    /// The configured admin address, or `usenet@<site_name>` if none is set.
+    #[must_use]
+    pub fn effective_admin_address(&self) -> String {
+        self.admin_address
+            .clone()
+            .unwrap_or_else(|| format!("usenet@{}", self.site_name))
+    }
+
+    /// The value `setting` takes for `group`: from an exact `group` rule
+    /// setting it, else from the most specific matching pattern that does,
+    /// the one with the fewest wildcards and then the longest.
+    #[must_use]
+    fn group_setting<T>(
+        &self,
+        group: &str,
+        setting: impl Fn(&GroupRule) -> Option<T>,
+    ) -> Option<T> {
+        if let Some(value) = self
             .group_settings
             .iter()
-            .find(|r| r.group.as_deref() == Some(group))
+            .filter(|r| r.group.as_deref() == Some(group))
+            .find_map(&setting)
         {
-            if let Some(days) = rule.retention_days {
-                if days > 0 {
-                    return Some(Duration::days(days));
-                }
-                return None;
-            }
+            return Some(value);
         }
-
-        // Then check for pattern matches, looking for the most specific pattern that has retention_days
-        let mut matches: Vec<_> = self
-            .group_settings
+        self.group_settings
             .iter()
             .filter(|r| r.group.is_none())
-            .filter(|r| r.pattern.as_deref().is_some_and(|p| wildmat(p, group)))
-            .filter(|r| r.retention_days.is_some())
-            .collect();
+            .filter_map(|r| {
+                let pattern = r.pattern.as_deref().filter(|p| wildmat(p, group))?;
+                Some((pattern, setting(r)?))
+            })
+            .min_by_key(|(pattern, _)| {
+                let wildcards = pattern.chars().filter(|c| matches!(c, '*' | '?')).count();
+                (wildcards, std::cmp::Reverse(pattern.len()))
+            })
+            .map(|(_, value)| value)
+    }
 
-        if matches.is_empty() {
-            return None;
-        }
+    #[must_use]
+    pub fn retention_for_group(&self, group: &str) -> Option<Duration> {
+        self.group_setting(group, |rule| {
+            rule.retention.or(rule.retention_days.map(Duration::days))
+        })
+        .filter(|retention| *retention > Duration::zero())
+    }
 
-        // Sort by pattern specificity (fewer wildcards = more specific)
-        matches.sort_by_key(|r| {
-            let pattern = r.pattern.as_ref().unwrap();
-            // Count wildcards - fewer wildcards means more specific
-            let wildcard_count = pattern.chars().filter(|c| *c == '*' || *c == '?').count();
-            // Also consider pattern length - longer patterns with same wildcard count are more specific
-            (wildcard_count, -(pattern.len() as i32))
-        });
+    /// The global `max_article_bytes`, or `None` when it is unset or `0`.
+    #[must_use]
+    pub fn global_max_size(&self) -> Option<u64> {
+        self.max_article_bytes.filter(|max| *max > 0)
+    }
 
-        if let Some(rule) = matches.first() {
-            if let Some(days) = rule.retention_days {
-                if days > 0 {
-                    return Some(Duration::days(days));
-                }
-            }
-        }
+    /// Size limit for articles in `group`: its own rule's, else the global
+    /// `max_article_bytes`.
+    #[must_use]
+    pub fn size_limit_for_group(&self, group: &str) -> Option<u64> {
+        self.max_size_for_group(group)
+            .or_else(|| self.global_max_size())
+    }
 
-        None
+    /// The largest size any article may have, whatever its groups: the
+    /// biggest of the global limit and every rule's. With the global limit
+    /// off the rules still bound it; `None` only when there is no limit at
+    /// all.
+    #[must_use]
+    pub fn largest_size_limit(&self) -> Option<u64> {
+        self.group_settings
+            .iter()
+            .filter_map(|r| r.max_article_bytes)
+            .chain(self.global_max_size())
+            .max()
+    }
+
+    /// The smallest size limit among `groups`, which an article posted to
+    /// all of them must keep to. Without groups the global limit applies.
+    #[must_use]
+    pub fn max_size_for_groups<S: AsRef<str>>(&self, groups: &[S]) -> Option<u64> {
+        if groups.is_empty() {
+            return self.global_max_size();
+        }
+        groups
+            .iter()
+            .filter_map(|group| self.size_limit_for_group(group.as_ref()))
+            .min()
     }
 
     #[must_use]
     pub fn max_size_for_group(&self, group: &str) -> Option<u64> {
-        // First check for exact group matches
-        if let Some(rule) = self
-            .group_settings
+        self.group_setting(group, |rule| rule.max_article_bytes)
+    }
+
+    /// Article count limit for `group`. Unlike the other settings every
+    /// matching rule applies, so the smallest limit wins.
+    #[must_use]
+    pub fn max_articles_for_group(&self, group: &str) -> Option<u64> {
+        self.group_settings
             .iter()
-            .find(|r| r.group.as_deref() == Some(group))
-        {
-            if rule.max_article_bytes.is_some() {
-                return rule.max_article_bytes;
-            }
-        }
+            .filter(|r| r.matches(group))
+            .filter_map(|r| r.max_articles)
+            .min()
+    }
 
-        // Then check for pattern matches, looking for the most specific pattern that has max_article_bytes
-        let mut matches: Vec<_> = self
-            .group_settings
+    /// Crosspost limit for `group`, the smallest among the global setting
+    /// and every matching rule.
+    #[must_use]
+    pub fn max_crosspost_for_group(&self, group: &str) -> Option<usize> {
+        self.group_settings
             .iter()
-            .filter(|r| r.group.is_none())
-            .filter(|r| r.pattern.as_deref().is_some_and(|p| wildmat(p, group)))
-            .filter(|r| r.max_article_bytes.is_some())
-            .collect();
+            .filter(|r| r.matches(group))
+            .filter_map(|r| r.max_crosspost_groups)
+            .chain(self.max_crosspost_groups)
+            .min()
+    }
 
-        if matches.is_empty() {
-            return None;
-        }
+    /// Time between runs of the expiry task.
+    #[must_use]
+    pub fn retention_interval_or_default(&self) -> std::time::Duration {
+        self.retention_interval
+            .and_then(|d| d.to_std().ok())
+            .filter(|d| !d.is_zero())
+            .unwrap_or(std::time::Duration::from_secs(3600))
+    }
 
-        // Sort by pattern specificity (fewer wildcards = more specific)
-        matches.sort_by_key(|r| {
-            let pattern = r.pattern.as_ref().unwrap();
-            // Count wildcards - fewer wildcards means more specific
-            let wildcard_count = pattern.chars().filter(|c| *c == '*' || *c == '?').count();
-            // Also consider pattern length - longer patterns with same wildcard count are more specific
-            (wildcard_count, -(pattern.len() as i32))
-        });
+    /// How long the Message-ID history keeps an entry.
+    #[must_use]
+    pub fn history_retention_or_default(&self) -> Duration {
+        self.history_retention
+            .filter(|d| *d > Duration::zero())
+            .unwrap_or_else(|| Duration::days(30))
+    }
+
+    /// Body truncation threshold for `group`, if archive-mode trimming is configured.
+    #[must_use]
+    pub fn body_truncate_for_group(&self, group: &str) -> Option<u64> {
+        self.group_setting(group, |rule| rule.body_truncate_bytes)
+    }
 
-        matches.first().and_then(|r| r.max_article_bytes)
+    /// Whether articles posted to `group` must be free of binaries. The most
+    /// specific rule setting `no_binaries` decides, and groups with a
+    /// `binaries` component in their name are never restricted.
+    #[must_use]
+    pub fn no_binaries_for_group(&self, group: &str) -> bool {
+        if group.split('.').any(|part| part == "binaries") {
+            return false;
+        }
+        self.group_setting(group, |rule| rule.no_binaries) == Some(true)
     }
 
     /// Get the actual number of runtime threads, handling the special case where 0 means "use all cores".
@@ -387,17 +1412,54 @@ See 'examples/config.toml' for a valid configuration example."
     /// Only retention, group, filter pipeline, and TLS settings are changed.
     pub fn update_runtime(&mut self, other: Config) {
         self.group_settings = other.group_settings;
+        self.retention_interval = other.retention_interval;
+        self.history_retention = other.history_retention;
         self.filters = other.filters;
+        self.client_certs = other.client_certs;
+        self.access = other.access;
+        self.list_unreadable_groups = other.list_unreadable_groups;
+        self.overview_extra_headers = other.overview_extra_headers;
 
         self.peer_sync_schedule = other.peer_sync_schedule;
         self.idle_timeout_secs = other.idle_timeout_secs;
+        self.plain_idle_timeout_secs = other.plain_idle_timeout_secs;
+        self.tls_idle_timeout_secs = other.tls_idle_timeout_secs;
+        self.transit_idle_timeout_secs = other.transit_idle_timeout_secs;
+        self.article_line_timeout_secs = other.article_line_timeout_secs;
+        self.shutdown_grace_secs = other.shutdown_grace_secs;
+        self.drain_timeout_secs = other.drain_timeout_secs;
         self.peers = other.peers;
         self.tls_cert = other.tls_cert;
         self.tls_key = other.tls_key;
+        self.tls_client_ca = other.tls_client_ca;
         self.ws_addr = other.ws_addr;
         self.runtime_threads = other.runtime_threads;
         self.pgp_key_servers = other.pgp_key_servers;
+        self.control_keys = other.control_keys;
+        self.checkgroups = other.checkgroups;
+        self.posting_enabled = other.posting_enabled;
+        self.plain_posting_enabled = other.plain_posting_enabled;
+        self.tls_posting_enabled = other.tls_posting_enabled;
         self.allow_posting_insecure_connections = other.allow_posting_insecure_connections;
+        self.allow_sasl_plain_insecure = other.allow_sasl_plain_insecure;
+        self.admin_address = other.admin_address;
+        self.moderated_crosspost_policy = other.moderated_crosspost_policy;
+        self.header_rules = other.header_rules;
+        self.max_article_bytes = other.max_article_bytes;
+        self.max_crosspost_groups = other.max_crosspost_groups;
+        self.require_followup_to_above = other.require_followup_to_above;
+        self.moderators = other.moderators;
+        self.smtp_relay = other.smtp_relay;
+        self.post_durability = other.post_durability;
+        self.message_id_generation = other.message_id_generation;
+        self.rate_limit = other.rate_limit;
+        self.ip_access = other.ip_access;
+        self.spam = other.spam;
+        self.distributions = other.distributions;
+        self.backpressure = other.backpressure;
+        self.injection = other.injection;
+        self.dead_letter_dir = other.dead_letter_dir;
+        self.article_batch_size = other.article_batch_size;
     }
 }
 
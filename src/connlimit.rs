@@ -0,0 +1,126 @@
+//! Caps on simultaneous client connections.
+//!
+//! Every listener shares one [`ConnectionLimiter`]. An accepted connection
+//! holds a [`ConnectionPermit`] for as long as it is open; when none is left,
+//! overall or for the remote address, the client is told
+//! `400 too many connections` and the socket is closed at once. Clients from
+//! addresses `[ip_access]` does not allow get `502` instead.
+
+use crate::config::Config;
+use crate::responses::{RESP_400_TOO_MANY_CONNECTIONS, RESP_502_NOT_PERMITTED};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+type AddressCounts = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+fn lock(counts: &AddressCounts) -> std::sync::MutexGuard<'_, HashMap<IpAddr, usize>> {
+    counts.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Connection slots shared by every listener. Unset limits are not
+/// enforced.
+pub struct ConnectionLimiter {
+    total: Option<Arc<Semaphore>>,
+    max_per_ip: Option<usize>,
+    per_ip: AddressCounts,
+}
+
+/// One admitted connection; its slots are given back when it is dropped.
+pub struct ConnectionPermit {
+    _total: Option<OwnedSemaphorePermit>,
+    ip: IpAddr,
+    per_ip: Option<AddressCounts>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        if let Some(per_ip) = &self.per_ip {
+            let mut counts = lock(per_ip);
+            if let Some(n) = counts.get_mut(&self.ip) {
+                *n -= 1;
+                if *n == 0 {
+                    counts.remove(&self.ip);
+                }
+            }
+        }
+    }
+}
+
+impl ConnectionLimiter {
+    #[must_use]
+    pub fn new(max: Option<usize>, max_per_ip: Option<usize>) -> Self {
+        Self {
+            total: max.map(|n| Arc::new(Semaphore::new(n))),
+            max_per_ip,
+            per_ip: AddressCounts::default(),
+        }
+    }
+
+    /// Limits from `max_connections` and `max_connections_per_ip`.
+    #[must_use]
+    pub fn from_config(cfg: &Config) -> Self {
+        Self::new(cfg.max_connections, cfg.max_connections_per_ip)
+    }
+
+    /// Take a slot for a connection from `ip`, or `None` if the server or
+    /// that address is already at its limit.
+    #[must_use]
+    pub fn try_admit(&self, ip: IpAddr) -> Option<ConnectionPermit> {
+        let total = match &self.total {
+            Some(semaphore) => Some(semaphore.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+        let per_ip = match self.max_per_ip {
+            Some(max) => {
+                let mut counts = lock(&self.per_ip);
+                let open = counts.get(&ip).copied().unwrap_or(0);
+                if open >= max {
+                    return None;
+                }
+                counts.insert(ip, open + 1);
+                Some(self.per_ip.clone())
+            }
+            None => None,
+        };
+        Some(ConnectionPermit {
+            _total: total,
+            ip,
+            per_ip,
+        })
+    }
+
+    /// Connections from `ip` currently holding a permit, when a per-address
+    /// limit is set.
+    #[must_use]
+    pub fn open_from(&self, ip: IpAddr) -> usize {
+        lock(&self.per_ip).get(&ip).copied().unwrap_or(0)
+    }
+}
+
+/// Tell a client over the limit why its connection is closed, then close it.
+pub async fn refuse<S>(socket: S)
+where
+    S: AsyncWrite + Unpin,
+{
+    close_with(socket, RESP_400_TOO_MANY_CONNECTIONS).await;
+}
+
+/// Greet a client from a denied address with `502` and close the connection.
+pub async fn deny<S>(socket: S)
+where
+    S: AsyncWrite + Unpin,
+{
+    close_with(socket, RESP_502_NOT_PERMITTED).await;
+}
+
+async fn close_with<S>(mut socket: S, response: &str)
+where
+    S: AsyncWrite + Unpin,
+{
+    crate::metrics::global().connection_refused();
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
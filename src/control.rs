@@ -8,6 +8,8 @@ use crate::{
 };
 use anyhow::Result;
 use base64::{Engine as _, engine::general_purpose::STANDARD};
+use futures_util::StreamExt;
+use pgp::native::types::KeyTrait;
 use pgp::native::{Deserializable, SignedPublicKey, StandaloneSignature};
 use sha2::{Digest, Sha256, Sha512};
 use std::io::Cursor;
@@ -15,8 +17,16 @@ use std::io::Cursor;
 #[derive(Debug, PartialEq, Eq)]
 pub enum ControlCommand {
     Cancel(String),
-    NewGroup { group: String, moderated: bool },
+    NewGroup {
+        group: String,
+        moderated: bool,
+    },
     RmGroup(String),
+    /// Authoritative group list for the hierarchies in `scope`, or for the
+    /// top-level hierarchies of the listed groups when empty.
+    CheckGroups {
+        scope: Vec<String>,
+    },
 }
 
 fn parse_command(val: &str) -> Option<ControlCommand> {
@@ -36,10 +46,193 @@ fn parse_command(val: &str) -> Option<ControlCommand> {
             })
         }
         "rmgroup" => parts.next().map(|g| ControlCommand::RmGroup(g.to_string())),
+        "checkgroups" => Some(ControlCommand::CheckGroups {
+            // A trailing `#serial` only orders successive lists
+            scope: parts
+                .filter(|p| !p.starts_with('#'))
+                .map(|p| p.trim_end_matches(".*").to_string())
+                .collect(),
+        }),
         _ => None,
     }
 }
 
+/// Extract the description for `group` from the "For your newsgroups file:"
+/// section of a newgroup control message body.
+pub fn newsgroups_file_description(body: &str, group: &str) -> Option<String> {
+    let mut lines = body.lines().skip_while(|l| {
+        !l.trim_start()
+            .to_ascii_lowercase()
+            .starts_with("for your newsgroups file")
+    });
+    lines.next()?;
+    lines
+        .map(str::trim)
+        .skip_while(|l| l.is_empty())
+        .take_while(|l| !l.is_empty())
+        .find_map(|l| {
+            let (name, desc) = l.split_once(char::is_whitespace)?;
+            (name == group).then(|| desc.trim().to_string())
+        })
+        .filter(|d| !d.is_empty())
+}
+
+/// A group listed in a checkgroups body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckedGroup {
+    pub name: String,
+    pub description: String,
+    pub moderated: bool,
+}
+
+/// Parse the `group<TAB>description` lines of a checkgroups body. A
+/// description ending in ` (Moderated)` marks the group moderated.
+#[must_use]
+pub fn parse_checkgroups(body: &str) -> Vec<CheckedGroup> {
+    body.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (name, description) = line
+                .split_once(char::is_whitespace)
+                .map_or((line, ""), |(n, d)| (n, d.trim()));
+            if !name.contains('.') {
+                return None;
+            }
+            Some(CheckedGroup {
+                name: name.to_string(),
+                description: description.to_string(),
+                moderated: description.ends_with("(Moderated)"),
+            })
+        })
+        .collect()
+}
+
+/// Changes a checkgroups message makes to the local group list.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CheckgroupsDiff {
+    /// Listed groups that do not exist yet.
+    pub add: Vec<CheckedGroup>,
+    /// Existing groups whose description or moderation differs.
+    pub update: Vec<CheckedGroup>,
+    /// Existing groups in scope that are no longer listed.
+    pub remove: Vec<String>,
+}
+
+/// The hierarchies a checkgroups message covers: its scope, or without
+/// one the top level of every listed group.
+#[must_use]
+pub fn checkgroups_hierarchies(scope: &[String], listed: &[CheckedGroup]) -> Vec<String> {
+    let mut hierarchies: Vec<String> = if scope.is_empty() {
+        listed
+            .iter()
+            .filter_map(|g| g.name.split('.').next())
+            .map(str::to_string)
+            .collect()
+    } else {
+        scope.to_vec()
+    };
+    hierarchies.sort();
+    hierarchies.dedup();
+    hierarchies
+}
+
+/// Compare a checkgroups listing with the groups carried in its scope.
+///
+/// # Errors
+///
+/// Returns an error if the group list cannot be read from storage.
+pub async fn checkgroups_diff(
+    storage: &DynStorage,
+    scope: &[String],
+    listed: &[CheckedGroup],
+) -> Result<CheckgroupsDiff> {
+    let patterns: Vec<String> = checkgroups_hierarchies(scope, listed)
+        .iter()
+        .map(|h| format!("{h}.*"))
+        .collect();
+    let in_scope = |group: &str| patterns.iter().any(|p| crate::wildmat::wildmat(p, group));
+
+    let mut diff = CheckgroupsDiff::default();
+    for group in listed.iter().filter(|g| in_scope(&g.name)) {
+        if !storage.group_exists(&group.name).await? {
+            diff.add.push(group.clone());
+            continue;
+        }
+        let description = storage
+            .get_group_description(&group.name)
+            .await?
+            .unwrap_or_default();
+        let moderated = storage.is_group_moderated(&group.name).await?;
+        if description != group.description || moderated != group.moderated {
+            diff.update.push(group.clone());
+        }
+    }
+    let mut carried = storage.list_groups();
+    while let Some(name) = carried.next().await {
+        let name = name?;
+        if in_scope(&name) && !listed.iter().any(|g| g.name == name) {
+            diff.remove.push(name);
+        }
+    }
+    Ok(diff)
+}
+
+/// Bring the local group list in line with a checkgroups message from
+/// `from`, or only log the changes when configured for a dry run.
+async fn apply_checkgroups(
+    scope: &[String],
+    msg: &Message,
+    storage: &DynStorage,
+    from: &str,
+    config: &crate::config::Config,
+) -> Result<()> {
+    let diff = checkgroups_diff(storage, scope, &parse_checkgroups(&msg.body_text())).await?;
+    let settings = config.checkgroups;
+    if settings.dry_run {
+        for group in &diff.add {
+            tracing::info!("checkgroups (dry run): would add {}", group.name);
+        }
+        for group in &diff.update {
+            tracing::info!("checkgroups (dry run): would update {}", group.name);
+        }
+        for name in &diff.remove {
+            if settings.remove_unlisted {
+                tracing::info!("checkgroups (dry run): would remove {name}");
+            } else {
+                tracing::info!("checkgroups (dry run): {name} is not listed");
+            }
+        }
+        return Ok(());
+    }
+    for group in &diff.add {
+        storage
+            .add_group_with_creator(&group.name, group.moderated, Some(from))
+            .await?;
+        storage
+            .set_group_description(&group.name, &group.description)
+            .await?;
+    }
+    for group in &diff.update {
+        storage
+            .set_group_description(&group.name, &group.description)
+            .await?;
+        storage
+            .set_group_moderated(&group.name, group.moderated)
+            .await?;
+    }
+    for name in &diff.remove {
+        if settings.remove_unlisted {
+            storage.remove_group(name).await?;
+        } else {
+            tracing::info!("checkgroups: {name} is carried but not listed");
+        }
+    }
+    Ok(())
+}
+
 fn parse_elements(val: &str) -> Vec<(String, String)> {
     val.split_whitespace()
         .filter_map(|p| {
@@ -74,6 +267,128 @@ fn verify_cancel(keys: &[(String, String)], locks: &[(String, String)]) -> bool
     false
 }
 
+/// Whether a `Cancel-Key` header in `msg` matches a `Cancel-Lock` of the
+/// stored article `target`. False when either header or the article is missing.
+///
+/// # Errors
+///
+/// Returns an error if the target article cannot be loaded.
+pub async fn cancel_key_authorizes(
+    msg: &Message,
+    storage: &DynStorage,
+    target: &str,
+) -> Result<bool> {
+    let keys = header_elements(msg, "Cancel-Key");
+    if keys.is_empty() {
+        return Ok(false);
+    }
+    let Some(orig) = storage.get_article_by_id(target).await? else {
+        return Ok(false);
+    };
+    Ok(verify_cancel(&keys, &header_elements(&orig, "Cancel-Lock")))
+}
+
+/// Every `scheme:value` element of every `name` header in `msg`, as locks
+/// and keys may be split across repeated headers.
+fn header_elements(msg: &Message, name: &str) -> Vec<(String, String)> {
+    msg.headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+        .flat_map(|(_, v)| parse_elements(v))
+        .collect()
+}
+
+/// The Message-ID named by a `Supersedes` header, if it holds one.
+#[must_use]
+pub fn superseded_id(msg: &Message) -> Option<String> {
+    let (_, val) = msg
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Supersedes"))?;
+    let id = val.split_whitespace().next()?;
+    (id.starts_with('<') && id.ends_with('>') && id.contains('@')).then(|| id.to_string())
+}
+
+/// The address part of a `From` header, lower-cased, so `Name <a@b>` and
+/// `a@b (Name)` compare equal.
+fn mailbox(from: &str) -> Option<String> {
+    let addr = match (from.find('<'), from.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &from[start + 1..end],
+        _ => from.split_whitespace().find(|w| w.contains('@'))?,
+    };
+    (!addr.is_empty()).then(|| addr.trim().to_ascii_lowercase())
+}
+
+fn from_header(msg: &Message) -> Option<&str> {
+    msg.headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("From"))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Whether `article` may replace the stored article `old`. An original with
+/// a `Cancel-Lock` needs a matching `Cancel-Key`; one without a lock may be
+/// replaced by an article from the same `From` address. False when `old`
+/// is not stored.
+///
+/// # Errors
+///
+/// Returns an error if the old article cannot be loaded.
+pub async fn supersede_authorized(
+    article: &Message,
+    storage: &DynStorage,
+    old: &str,
+) -> Result<bool> {
+    let Some(orig) = storage.get_article_by_id(old).await? else {
+        return Ok(false);
+    };
+    let locks = header_elements(&orig, "Cancel-Lock");
+    if !locks.is_empty() {
+        return Ok(verify_cancel(
+            &header_elements(article, "Cancel-Key"),
+            &locks,
+        ));
+    }
+    let new_from = from_header(article).and_then(mailbox);
+    Ok(new_from.is_some() && new_from == from_header(&orig).and_then(mailbox))
+}
+
+/// Store `article`, replacing the article named by its `Supersedes` header
+/// when [`supersede_authorized`] allows it. Otherwise, or when the old
+/// article is unknown, the new article is simply stored. Once stored, the
+/// article gets an Xref header naming `host` and its article numbers.
+///
+/// # Errors
+///
+/// Returns an error if storage fails.
+pub async fn store_superseding(article: &Message, storage: &DynStorage, host: &str) -> Result<()> {
+    match superseded_id(article) {
+        Some(old) if supersede_authorized(article, storage, &old).await? => {
+            storage.replace_article(article, &old).await?;
+        }
+        Some(old) => {
+            tracing::info!("Supersedes {old} not authorized or unknown; keeping the original");
+            storage.store_article(article).await?;
+        }
+        None => storage.store_article(article).await?,
+    }
+    record_stored(article, storage, host).await
+}
+
+/// Finish storing `article` once it is in `storage`: give it an Xref header
+/// naming `host` and its article numbers and count it as stored.
+///
+/// # Errors
+///
+/// Returns an error if the Xref header cannot be recorded.
+pub async fn record_stored(article: &Message, storage: &DynStorage, host: &str) -> Result<()> {
+    if let Some(id) = crate::storage::common::extract_message_id(article) {
+        storage.record_xref(&id, host).await?;
+    }
+    crate::metrics::global().article_stored();
+    Ok(())
+}
+
 /// Build the canonical text that was signed according to the pgpcontrol format.
 #[must_use]
 pub fn canonical_text(msg: &Message, signed_headers: &str) -> String {
@@ -93,13 +408,14 @@ pub fn canonical_text(msg: &Message, signed_headers: &str) -> String {
         out.push('\n');
     }
     out.push('\n');
-    for line in msg.body.replace("\r\n", "\n").split_inclusive('\n') {
+    let body = msg.body_text().replace("\r\n", "\n");
+    for line in body.split_inclusive('\n') {
         if line.starts_with('-') {
             out.push_str("- ");
         }
         out.push_str(line);
     }
-    if !msg.body.ends_with('\n') {
+    if !body.ends_with('\n') {
         out.push('\n');
     }
     out
@@ -138,15 +454,13 @@ pub async fn verify_pgp(
     // First try with existing stored key
     let stored_key = auth.get_pgp_key(user).await?;
 
-    if let Some(key_text) = &stored_key {
-        if let Ok(verification_result) =
+    // If verification fails with the stored key, try discovery
+    if let Some(key_text) = &stored_key
+        && let Ok(verification_result) =
             try_verify_with_key(msg, key_text, version, signed_headers, sig_data).await
-        {
-            if verification_result.is_ok() {
-                return Ok(());
-            }
-            // If verification failed with stored key, try discovery
-        }
+        && verification_result.is_ok()
+    {
+        return Ok(());
     }
 
     // Attempt key discovery if no key exists or verification failed
@@ -233,44 +547,127 @@ pub async fn handle_control(
 
     if let ControlCommand::Cancel(ref id) = cmd {
         // try Cancel-Key authentication first
-        if let Some((_, key_val)) = msg
+        if msg
             .headers
             .iter()
-            .find(|(k, _)| k.eq_ignore_ascii_case("Cancel-Key"))
+            .any(|(k, _)| k.eq_ignore_ascii_case("Cancel-Key"))
         {
-            if let Some(orig) = storage.get_article_by_id(id).await? {
-                if let Some((_, lock_val)) = orig
-                    .headers
-                    .iter()
-                    .find(|(k, _)| k.eq_ignore_ascii_case("Cancel-Lock"))
-                {
-                    let keys = parse_elements(key_val);
-                    let locks = parse_elements(lock_val);
-                    if verify_cancel(&keys, &locks) {
-                        storage.delete_article_by_id(id).await?;
-                    }
-                    return Ok(true);
-                }
+            if cancel_key_authorizes(msg, storage, id).await? {
+                storage.delete_article_by_id(id).await?;
             }
             return Ok(true);
         }
     }
 
-    // fall back to admin-signed control message
     let from = msg
         .headers
         .iter()
         .find(|(k, _)| k.eq_ignore_ascii_case("From"))
         .map_or("", |(_, v)| v.as_str());
+
+    // Group changes in a hierarchy with configured signing keys must be
+    // signed by one of those keys; anything else is logged and dropped
+    let governed = match &cmd {
+        ControlCommand::NewGroup { group, .. } | ControlCommand::RmGroup(group) => {
+            Some(group.clone())
+        }
+        ControlCommand::CheckGroups { scope } => {
+            let hierarchies = checkgroups_hierarchies(scope, &parse_checkgroups(&msg.body_text()));
+            // The keys of one hierarchy cannot vouch for another, so a
+            // checkgroups reaching into a pinned hierarchy must cover it alone
+            let pinned = hierarchies
+                .iter()
+                .any(|h| config.control_fingerprints_for(&format!("{h}.*")).is_some());
+            if pinned && hierarchies.len() > 1 {
+                tracing::warn!(
+                    "Ignoring checkgroups spanning {} from {from}",
+                    hierarchies.join(", ")
+                );
+                return Ok(true);
+            }
+            hierarchies.first().map(|h| format!("{h}.*"))
+        }
+        ControlCommand::Cancel(_) => None,
+    };
+    if let Some(group) = governed
+        && let Some(fingerprints) = config.control_fingerprints_for(&group)
+    {
+        match verify_with_pinned_keys(msg, auth, from, fingerprints, &config.pgp_key_servers).await
+        {
+            Ok(()) => apply_command(cmd, msg, storage, from, config).await?,
+            Err(e) => tracing::warn!("Ignoring control message for {group}: {e}"),
+        }
+        return Ok(true);
+    }
+
+    // fall back to admin-signed control message
+    let verified = async {
+        let (version, signed, sig_rest) = pgp_signature(msg)?;
+        if !auth.is_admin(from).await? {
+            return Err(anyhow::anyhow!("not admin"));
+        }
+        verify_pgp(
+            msg,
+            auth,
+            from,
+            &version,
+            &signed,
+            &sig_rest,
+            &config.pgp_key_servers,
+        )
+        .await
+    }
+    .await;
+    match verified {
+        Ok(()) => apply_command(cmd, msg, storage, from, config).await?,
+        // Group changes are dropped quietly so the sending peer is not cut off
+        Err(e) if !matches!(cmd, ControlCommand::Cancel(_)) => {
+            tracing::warn!("Ignoring unverified control message from {from}: {e}");
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(true)
+}
+
+/// Carry out a verified control command sent by `from`.
+async fn apply_command(
+    cmd: ControlCommand,
+    msg: &Message,
+    storage: &DynStorage,
+    from: &str,
+    config: &crate::config::Config,
+) -> Result<()> {
+    match cmd {
+        ControlCommand::Cancel(id) => {
+            storage.delete_article_by_id(&id).await?;
+        }
+        ControlCommand::NewGroup { group, moderated } => {
+            storage
+                .add_group_with_creator(&group, moderated, Some(from))
+                .await?;
+            if let Some(desc) = newsgroups_file_description(&msg.body_text(), &group) {
+                storage.set_group_description(&group, &desc).await?;
+            }
+        }
+        ControlCommand::RmGroup(group) => {
+            storage.remove_group(&group).await?;
+        }
+        ControlCommand::CheckGroups { scope } => {
+            apply_checkgroups(&scope, msg, storage, from, config).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Split the `X-PGP-Sig` header into its version, signed header list and
+/// armored signature lines.
+fn pgp_signature(msg: &Message) -> Result<(String, String, String)> {
     let sig_header = msg
         .headers
         .iter()
         .find(|(k, _)| k.eq_ignore_ascii_case("X-PGP-Sig"))
-        .map(|(_, v)| v.clone())
+        .map(|(_, v)| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("missing signature"))?;
-    if !auth.is_admin(from).await? {
-        return Err(anyhow::anyhow!("not admin"));
-    }
     let mut words = sig_header.split_whitespace();
     let version = words
         .next()
@@ -279,26 +676,55 @@ pub async fn handle_control(
         .next()
         .ok_or_else(|| anyhow::anyhow!("bad signature"))?;
     let sig_rest = words.collect::<Vec<_>>().join("\n");
-    verify_pgp(
-        msg,
-        auth,
-        from,
-        version,
-        signed,
-        &sig_rest,
-        &config.pgp_key_servers,
-    )
-    .await?;
-    match cmd {
-        ControlCommand::Cancel(id) => {
-            storage.delete_article_by_id(&id).await?;
-        }
-        ControlCommand::NewGroup { group, moderated } => {
-            storage.add_group(&group, moderated).await?;
-        }
-        ControlCommand::RmGroup(group) => {
-            storage.remove_group(&group).await?;
-        }
+    Ok((version.to_string(), signed.to_string(), sig_rest))
+}
+
+/// Uppercase hex fingerprint of the primary key in an armored public key.
+///
+/// # Errors
+///
+/// Returns an error if the key cannot be parsed.
+pub fn key_fingerprint(key_text: &str) -> Result<String> {
+    let (key, _) = SignedPublicKey::from_string(key_text)?;
+    Ok(key
+        .fingerprint()
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect())
+}
+
+/// Verify the signature of `msg` with the key of its sender `from` and
+/// require that key to be one of `fingerprints`. The stored key is used when
+/// there is one; otherwise the key servers are asked.
+async fn verify_with_pinned_keys(
+    msg: &Message,
+    auth: &DynAuth,
+    from: &str,
+    fingerprints: &[String],
+    key_servers: &[String],
+) -> Result<()> {
+    let (version, signed, sig_rest) = pgp_signature(msg)?;
+    let key_text = match auth.get_pgp_key(from).await? {
+        Some(key) => key,
+        None => DefaultPgpKeyDiscovery::with_key_servers(key_servers.to_vec())
+            .discover_key(from)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no PGP key found for {from}"))?,
+    };
+    let fingerprint = key_fingerprint(&key_text)?;
+    if !fingerprints
+        .iter()
+        .any(|f| normalize_fingerprint(f) == fingerprint)
+    {
+        anyhow::bail!("key {fingerprint} of {from} is not trusted for this hierarchy");
     }
-    Ok(true)
+    try_verify_with_key(msg, &key_text, &version, &signed, &sig_rest).await?
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
 }
@@ -0,0 +1,100 @@
+//! Dead-letter sink for articles that fail in a queue worker.
+//!
+//! Each failed article is written to the configured directory as two files
+//! sharing a name: `<name>.msg` holds the article in wire format and
+//! `<name>.json` holds the failure reason together with the queue flags, so
+//! a replay can resubmit the article exactly as it was queued.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::parse_message;
+use crate::queue::QueuedArticle;
+use crate::storage::common::extract_message_id;
+
+/// Sidecar metadata written next to each dead-lettered article.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetterMeta {
+    pub message_id: Option<String>,
+    pub reason: String,
+    /// RFC 3339 time of the failure
+    pub failed_at: String,
+    pub size: u64,
+    pub is_control: bool,
+    pub already_validated: bool,
+}
+
+/// Keep only characters that are safe in a file name.
+fn file_stem(now: DateTime<Utc>, message_id: Option<&str>) -> String {
+    let id: String = message_id
+        .unwrap_or("no-id")
+        .chars()
+        .filter(|c| !matches!(c, '<' | '>'))
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(128)
+        .collect();
+    format!("{}-{id}", now.format("%Y%m%dT%H%M%S%.9f"))
+}
+
+/// Write `article` and the reason it failed to `dir`, returning the path of
+/// the article file.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created or a file cannot be
+/// written.
+pub async fn write(dir: &Path, article: &QueuedArticle, reason: &str) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    let now = Utc::now();
+    let message_id = extract_message_id(&article.message);
+    let meta = DeadLetterMeta {
+        message_id: message_id.clone(),
+        reason: reason.to_string(),
+        failed_at: now.to_rfc3339(),
+        size: article.size,
+        is_control: article.is_control,
+        already_validated: article.already_validated,
+    };
+
+    let stem = file_stem(now, message_id.as_deref());
+    let article_path = dir.join(format!("{stem}.msg"));
+    tokio::fs::write(&article_path, article.message.to_wire()).await?;
+    // The sidecar goes last so a complete pair marks a finished write
+    tokio::fs::write(
+        dir.join(format!("{stem}.json")),
+        serde_json::to_vec_pretty(&meta)?,
+    )
+    .await?;
+    Ok(article_path)
+}
+
+/// Read a dead-lettered article back as it was queued, given the path of
+/// its `.msg` file.
+///
+/// # Errors
+///
+/// Returns an error if either file is missing or cannot be parsed.
+pub async fn read(article_path: &Path) -> Result<(QueuedArticle, DeadLetterMeta)> {
+    let text = tokio::fs::read(article_path).await?;
+    let meta: DeadLetterMeta =
+        serde_json::from_slice(&tokio::fs::read(article_path.with_extension("json")).await?)?;
+    let (_, message) = parse_message(&text)
+        .map_err(|e| anyhow!("Failed to parse {}: {e}", article_path.display()))?;
+    let article = QueuedArticle {
+        message,
+        size: meta.size,
+        is_control: meta.is_control,
+        already_validated: meta.already_validated,
+        ack: None,
+    };
+    Ok((article, meta))
+}
@@ -0,0 +1,312 @@
+//! Real-time push feeds to peer servers.
+//!
+//! Every article accepted locally is queued in storage for each peer that
+//! has a `host` and whose patterns match one of the article's newsgroups.
+//! One task per peer drains that queue with IHAVE, or with pipelined CHECK
+//! and TAKETHIS when the peer accepts `MODE STREAM`, and backs off
+//! exponentially while the peer or the queue is unreachable. The connection stays open between articles
+//! and is closed once the feed has been idle for a while. Because the queue
+//! lives in storage, articles waiting for a peer survive a restart; while a
+//! peer stays unreachable its queue is capped by dropping the oldest entries.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::Message;
+use crate::client::{NntpClient, Offer};
+use crate::config::PeerRule;
+use crate::peers::{PeerConnectionInfo, create_peer_article, should_skip_article};
+use crate::storage::DynStorage;
+use crate::storage::common::{extract_message_id, parse_newsgroups_from_message};
+
+/// Message-IDs read from the queue per storage round trip.
+const BATCH_SIZE: usize = 100;
+/// Articles offered to a streaming peer before waiting for its replies.
+const STREAM_WINDOW: usize = 16;
+/// Delay before the first reconnection attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay between reconnection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// How often an idle feed looks at its queue without being woken; an open
+/// connection is closed after this long without work.
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+struct FeedPeer {
+    rule: PeerRule,
+    wake: Notify,
+}
+
+impl FeedPeer {
+    fn connection_info(&self) -> PeerConnectionInfo {
+        PeerConnectionInfo::from_parts(
+            self.rule.host.as_deref().unwrap_or_default(),
+            self.rule.port,
+            self.rule.tls,
+            self.rule.username.as_deref(),
+            self.rule.password.as_deref(),
+        )
+    }
+}
+
+/// An open connection to a peer.
+struct Session {
+    connection: NntpClient,
+    streaming: bool,
+}
+
+impl Session {
+    async fn open(peer: &FeedPeer) -> Result<Self> {
+        let mut connection = NntpClient::open(&peer.connection_info()).await?;
+        let streaming = connection.mode_stream().await?;
+        Ok(Self {
+            connection,
+            streaming,
+        })
+    }
+
+    async fn close(self, name: &str) {
+        if let Err(e) = self.connection.close().await {
+            tracing::debug!("Failed to close connection to {}: {}", name, e);
+        }
+    }
+}
+
+/// The push feeds of this server, one per peer with a `host`.
+pub struct Feeds {
+    peers: Vec<FeedPeer>,
+    storage: DynStorage,
+    site_name: String,
+}
+
+impl Feeds {
+    /// Build the feeds for every pushing peer among `peers`. Outgoing
+    /// articles have `site_name` prepended to their Path.
+    #[must_use]
+    pub fn new(peers: &[PeerRule], storage: DynStorage, site_name: &str) -> Arc<Self> {
+        Arc::new(Self {
+            peers: peers
+                .iter()
+                .filter(|p| p.pushes())
+                .map(|rule| FeedPeer {
+                    rule: rule.clone(),
+                    wake: Notify::new(),
+                })
+                .collect(),
+            storage,
+            site_name: site_name.to_string(),
+        })
+    }
+
+    /// Whether no peer is fed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Queue a newly stored `article` for every peer that wants one of its
+    /// groups and is not already on its Path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feed queue cannot be written.
+    pub async fn article_accepted(&self, article: &Message) -> Result<()> {
+        let Some(id) = extract_message_id(article) else {
+            return Ok(());
+        };
+        let groups = parse_newsgroups_from_message(article);
+        for peer in &self.peers {
+            if !groups.iter().any(|g| peer.rule.wants(g))
+                || should_skip_article(article, &peer.rule.sitename)
+            {
+                continue;
+            }
+            self.storage.enqueue_feed(&peer.rule.sitename, &id).await?;
+            crate::metrics::global().feed_queued(&peer.rule.sitename);
+            peer.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Spawn one delivery task per peer.
+    pub fn start(self: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        (0..self.peers.len())
+            .map(|index| {
+                let feeds = Arc::clone(self);
+                tokio::spawn(async move { feeds.run(index).await })
+            })
+            .collect()
+    }
+
+    /// Deliver to the peer at `index` until the task is dropped.
+    async fn run(&self, index: usize) {
+        let peer = &self.peers[index];
+        let name = &peer.rule.sitename;
+        let mut backoff = INITIAL_BACKOFF;
+        let mut session: Option<Session> = None;
+        tracing::info!("Starting push feed to {}", name);
+        self.trim(peer).await;
+        loop {
+            match self.storage.peek_feed(name, 1).await {
+                Ok(pending) if pending.is_empty() => {
+                    backoff = INITIAL_BACKOFF;
+                    crate::metrics::global().set_feed_backlog(name, 0);
+                    let woken = tokio::time::timeout(IDLE_POLL, peer.wake.notified()).await;
+                    match (woken, session.take()) {
+                        (Err(_), Some(idle)) => idle.close(name).await,
+                        (_, open) => session = open,
+                    }
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to read feed queue for {}: {}", name, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+            }
+
+            let reused = session.is_some();
+            let outcome = match session.take() {
+                Some(open) => Ok(open),
+                None => Session::open(peer).await,
+            };
+            let outcome = match outcome {
+                Ok(mut open) => {
+                    let delivered = self.deliver(peer, &mut open).await;
+                    if delivered.is_ok() {
+                        session = Some(open);
+                    }
+                    delivered
+                }
+                Err(e) => Err(e),
+            };
+            match outcome {
+                Ok(true) => {
+                    backoff = INITIAL_BACKOFF;
+                    continue;
+                }
+                Ok(false) => tracing::debug!("{} asked us to retry later", name),
+                Err(e) if reused => {
+                    // The peer may have timed out the idle connection; reconnect at once
+                    tracing::debug!("Connection to {} went away: {}", name, e);
+                    continue;
+                }
+                Err(e) => tracing::warn!("Feeding {} failed: {}", name, e),
+            }
+            // The queue only grows while the peer is not taking articles
+            self.trim(peer).await;
+            tracing::debug!("Retrying feed to {} in {:?}", name, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Drop the oldest articles queued for `peer` beyond its backlog limit.
+    async fn trim(&self, peer: &FeedPeer) {
+        let name = &peer.rule.sitename;
+        let limit = peer.rule.max_backlog_or_default();
+        match self.storage.trim_feed(name, limit).await {
+            Ok(0) => {}
+            Ok(dropped) => tracing::warn!(
+                "Dropped {} oldest articles queued for {} to stay within its backlog of {}",
+                dropped,
+                name,
+                limit
+            ),
+            Err(e) => tracing::warn!("Failed to trim feed queue for {}: {}", name, e),
+        }
+        self.record_backlog(name).await;
+    }
+
+    /// Report how many articles wait for the peer called `name`.
+    async fn record_backlog(&self, name: &str) {
+        match self.storage.feed_len(name).await {
+            Ok(len) => crate::metrics::global().set_feed_backlog(name, len),
+            Err(e) => tracing::debug!("Failed to count feed queue for {}: {}", name, e),
+        }
+    }
+
+    /// The article queued for the peer called `name` as it is sent to it,
+    /// or `None` after dropping it from the queue if it is gone.
+    async fn outgoing(&self, name: &str, id: &str) -> Result<Option<Message>> {
+        let Some(article) = self.storage.get_article_by_id(id).await? else {
+            // Expired or cancelled while waiting
+            self.storage.dequeue_feed(name, id).await?;
+            return Ok(None);
+        };
+        Ok(Some(create_peer_article(&article, &self.site_name)?))
+    }
+
+    /// Send everything queued for `peer` over `session`. Returns `false` if
+    /// the peer deferred an article, leaving it at the head of the queue.
+    async fn deliver(&self, peer: &FeedPeer, session: &mut Session) -> Result<bool> {
+        let name = &peer.rule.sitename;
+        let mut sent = 0usize;
+
+        let drained = loop {
+            let ids = self.storage.peek_feed(name, BATCH_SIZE).await?;
+            if ids.is_empty() {
+                break true;
+            }
+            let mut deferred = false;
+            if session.streaming {
+                for window in ids.chunks(STREAM_WINDOW) {
+                    let mut batch = Vec::with_capacity(window.len());
+                    for id in window {
+                        if let Some(article) = self.outgoing(name, id).await? {
+                            batch.push((id.clone(), article));
+                        }
+                    }
+                    let offers = session.connection.stream_offers(&batch).await?;
+                    // Deferred articles stay queued; the rest of the window
+                    // was answered and is done with
+                    for ((id, _), offer) in batch.iter().zip(offers) {
+                        match offer {
+                            Offer::Deferred => {
+                                deferred = true;
+                                continue;
+                            }
+                            Offer::Accepted => sent += 1,
+                            Offer::Refused => {}
+                        }
+                        self.storage.dequeue_feed(name, id).await?;
+                    }
+                    if deferred {
+                        break;
+                    }
+                }
+            } else {
+                for id in ids {
+                    let Some(outgoing) = self.outgoing(name, &id).await? else {
+                        continue;
+                    };
+                    match session.connection.ihave(&outgoing, &id).await? {
+                        Offer::Deferred => {
+                            deferred = true;
+                            break;
+                        }
+                        Offer::Accepted => sent += 1,
+                        Offer::Refused => {}
+                    }
+                    self.storage.dequeue_feed(name, &id).await?;
+                }
+            }
+            if deferred {
+                break false;
+            }
+        };
+
+        if sent > 0 {
+            tracing::info!("Fed {} articles to {}", sent, name);
+        }
+        if !drained {
+            self.record_backlog(name).await;
+        }
+        Ok(drained)
+    }
+}
@@ -0,0 +1,106 @@
+//! Crosspost limit filter
+//!
+//! Refuses articles posted to more groups than the configured limit and,
+//! above a softer threshold, crossposts that do not direct followups to a
+//! single group.
+
+use super::{ArticleFilter, Rejection};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::handlers::utils::get_header_values;
+use crate::storage::DynStorage;
+use anyhow::Result;
+
+/// Why [`CrosspostFilter`] refused an article; the text is sent to the
+/// poster in the 441 response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrosspostRejection {
+    /// More distinct groups than the smallest applicable limit.
+    TooManyGroups { groups: usize, limit: usize },
+    /// Above the Followup-To threshold without a single followup group.
+    FollowupToRequired { groups: usize, threshold: usize },
+}
+
+impl std::fmt::Display for CrosspostRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyGroups { groups, limit } => {
+                write!(f, "crossposted to {groups} groups; the limit is {limit}")
+            }
+            Self::FollowupToRequired { groups, threshold } => write!(
+                f,
+                "crossposted to {groups} groups; more than {threshold} needs a Followup-To naming one group"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CrosspostRejection {}
+
+/// Distinct, trimmed group names in a comma separated header value.
+fn distinct_groups(values: &[String]) -> Vec<&str> {
+    let mut groups: Vec<&str> = Vec::new();
+    for group in values.iter().flat_map(|v| v.split(',')).map(str::trim) {
+        if !group.is_empty() && !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+/// Filter that enforces `max_crosspost_groups` and
+/// `require_followup_to_above`. Control messages are exempt.
+pub struct CrosspostFilter;
+
+#[async_trait::async_trait]
+impl ArticleFilter for CrosspostFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        if crate::control::is_control_message(article) {
+            return Ok(());
+        }
+
+        let newsgroups = get_header_values(article, "Newsgroups");
+        let groups = distinct_groups(&newsgroups);
+        let count = groups.len();
+
+        let limit = groups
+            .iter()
+            .filter_map(|g| cfg.max_crosspost_for_group(g))
+            .chain(cfg.max_crosspost_groups)
+            .min();
+        if let Some(limit) = limit.filter(|limit| count > *limit) {
+            return Err(Rejection::Crosspost(CrosspostRejection::TooManyGroups {
+                groups: count,
+                limit,
+            })
+            .into());
+        }
+
+        if let Some(threshold) = cfg.require_followup_to_above.filter(|t| count > *t) {
+            let followup_to = get_header_values(article, "Followup-To");
+            if distinct_groups(&followup_to).len() != 1 {
+                return Err(
+                    Rejection::Crosspost(CrosspostRejection::FollowupToRequired {
+                        groups: count,
+                        threshold,
+                    })
+                    .into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "CrosspostFilter"
+    }
+}
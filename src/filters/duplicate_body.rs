@@ -0,0 +1,206 @@
+//! Duplicate body filter
+//!
+//! Catches one body posted over and over, whether under fresh Message-IDs
+//! or group after group, the pattern of an EMP flood. Bodies are compared by
+//! a hash of their text with quoted lines dropped and whitespace collapsed,
+//! so trivial reformatting does not make a copy look new. Sightings are kept
+//! in the `recent_bodies` table, with a bounded map in front of it for the
+//! bodies seen most recently. A crosspost is one article and counts once.
+
+use super::{ArticleFilter, Rejection};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::storage::DynStorage;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Bodies remembered in memory before the stalest are dropped.
+const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Why [`DuplicateBodyFilter`] refused an article; the text is sent to the
+/// poster in the 441 response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateBodyRejection {
+    /// Copies accepted within the window.
+    pub copies: usize,
+    /// Length of the window in minutes.
+    pub window_minutes: u64,
+}
+
+impl std::fmt::Display for DuplicateBodyRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the same text was already posted {} times in the last {} minutes",
+            self.copies, self.window_minutes
+        )
+    }
+}
+
+impl std::error::Error for DuplicateBodyRejection {}
+
+/// Hash of `body` with quoted lines left out and runs of whitespace
+/// collapsed, or `None` when nothing is left to compare.
+#[must_use]
+pub fn body_hash(body: &str) -> Option<String> {
+    let mut words = body
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .flat_map(str::split_whitespace)
+        .peekable();
+    words.peek()?;
+    let mut hasher = Sha256::new();
+    for (i, word) in words.enumerate() {
+        if i > 0 {
+            hasher.update(b" ");
+        }
+        hasher.update(word.as_bytes());
+    }
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+    )
+}
+
+/// Recent sightings of the bodies seen last, shared by every connection so
+/// a flood is counted without a storage lookup per article.
+#[derive(Debug)]
+pub struct RecentBodies {
+    capacity: usize,
+    inner: Mutex<HashMap<String, Vec<DateTime<Utc>>>>,
+}
+
+impl Default for RecentBodies {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RecentBodies {
+    /// Create a map remembering at most `capacity` bodies.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remembered sightings of `hash`, if it is in the map.
+    pub fn get(&self, hash: &str) -> Option<Vec<DateTime<Utc>>> {
+        let map = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        map.get(hash).cloned()
+    }
+
+    /// Remember `times` as the sightings of `hash`. When the map is full,
+    /// bodies not seen since `since` go first, then the longest unseen.
+    pub fn put(&self, hash: &str, times: Vec<DateTime<Utc>>, since: DateTime<Utc>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut map = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        map.insert(hash.to_string(), times);
+        if map.len() > self.capacity {
+            map.retain(|_, times| times.last().is_some_and(|t| *t >= since));
+        }
+        while map.len() > self.capacity {
+            let Some(stalest) = map
+                .iter()
+                .min_by_key(|(_, times)| times.last().copied())
+                .map(|(hash, _)| hash.clone())
+            else {
+                break;
+            };
+            map.remove(&stalest);
+        }
+    }
+
+    /// Number of remembered bodies.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Whether no body is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Filter that enforces `[spam] max_duplicates`. Control messages are
+/// exempt. Without a shared [`RecentBodies`] every check goes to storage.
+#[derive(Default)]
+pub struct DuplicateBodyFilter {
+    recent: Option<Arc<RecentBodies>>,
+}
+
+impl DuplicateBodyFilter {
+    /// A filter checking `recent` before storage.
+    #[must_use]
+    pub fn with_recent(recent: Arc<RecentBodies>) -> Self {
+        Self {
+            recent: Some(recent),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ArticleFilter for DuplicateBodyFilter {
+    async fn validate(
+        &self,
+        storage: &DynStorage,
+        _auth: &DynAuth,
+        cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        let Some(max) = cfg.spam.max_duplicates else {
+            return Ok(());
+        };
+        if crate::control::is_control_message(article) {
+            return Ok(());
+        }
+        let Some(hash) = body_hash(&article.body_text()) else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let since = now - cfg.spam.window();
+        let mut times = match self.recent.as_ref().and_then(|r| r.get(&hash)) {
+            Some(times) => times,
+            None => storage.recent_body_times(&hash, since).await?,
+        };
+        times.retain(|t| *t >= since);
+
+        // Refused copies are not recorded, so a flood does not keep the
+        // window open by itself
+        if times.len() >= max as usize {
+            let copies = times.len();
+            if let Some(recent) = &self.recent {
+                recent.put(&hash, times, since);
+            }
+            return Err(Rejection::DuplicateBody(DuplicateBodyRejection {
+                copies,
+                window_minutes: cfg.spam.duplicate_window,
+            })
+            .into());
+        }
+
+        storage.record_body(&hash, now).await?;
+        times.push(now);
+        if let Some(recent) = &self.recent {
+            recent.put(&hash, times, since);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "DuplicateBodyFilter"
+    }
+}
@@ -0,0 +1,197 @@
+//! External content scanner filter
+//!
+//! Pipes each article to a local command such as `spamc -c` or `rspamc` and
+//! rejects the article when the score the command prints exceeds a threshold.
+//! The process runner and failure policy here are shared with the external
+//! filter hook.
+
+use super::ArticleFilter;
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::storage::DynStorage;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tracing::warn;
+
+/// Most bytes of scanner output looked at for a score
+const MAX_SCANNER_OUTPUT: usize = 4096;
+
+/// What to do with an article when an external checker cannot be run, does
+/// not answer in time or answers something that cannot be understood.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Accept the article and log the failure.
+    #[default]
+    Open,
+    /// Reject the article.
+    Closed,
+}
+
+impl FailurePolicy {
+    /// Settle an article `checker` gave no verdict on because of `error`.
+    pub(crate) fn settle(self, checker: &str, error: anyhow::Error) -> Result<()> {
+        match self {
+            Self::Open => {
+                warn!("{checker} failed, accepting article: {error}");
+                Ok(())
+            }
+            Self::Closed => Err(error.context(format!("{checker} failed"))),
+        }
+    }
+}
+
+/// Run `command` with `input` on its stdin and return its exit status with
+/// at most `max_output` bytes of what it printed. The command is killed if
+/// it has not finished within `timeout`.
+pub(crate) async fn run_checker(
+    mut command: Command,
+    input: &[u8],
+    max_output: usize,
+    timeout: Duration,
+) -> Result<(ExitStatus, Vec<u8>)> {
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to start {program}"))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("{program} stdin unavailable"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("{program} stdout unavailable"))?;
+
+    let run = async move {
+        let feed = async move {
+            // The checker may decide before reading everything; its
+            // verdict still counts
+            let _ = stdin.write_all(input).await;
+        };
+        let (_, output) = tokio::join!(feed, read_capped(stdout, max_output));
+        let status = child.wait().await?;
+        Ok::<_, std::io::Error>((status, output?))
+    };
+    tokio::time::timeout(timeout, run)
+        .await
+        .map_err(|_| anyhow!("{program} timed out after {}s", timeout.as_secs()))?
+        .map_err(Into::into)
+}
+
+/// Read at most `limit` bytes from `reader`, then discard the rest so the
+/// writer is not left blocked on a full pipe.
+async fn read_capped<R>(mut reader: R, limit: usize) -> std::io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut kept = Vec::new();
+    (&mut reader)
+        .take(limit as u64)
+        .read_to_end(&mut kept)
+        .await?;
+    tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok(kept)
+}
+
+/// Configuration for the external scanner filter
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExternalCommandConfig {
+    /// Path of the scanner executable
+    pub command: String,
+    /// Arguments passed to the scanner
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Articles scoring above this value are rejected
+    pub threshold: f64,
+    /// Seconds to wait for the scanner before giving up on it
+    #[serde(default = "default_scanner_timeout")]
+    pub timeout_secs: u64,
+    /// Behavior when the scanner fails
+    #[serde(default)]
+    pub on_error: FailurePolicy,
+}
+
+fn default_scanner_timeout() -> u64 {
+    10
+}
+
+/// Filter that scores articles with an external command
+pub struct ExternalCommandFilter {
+    config: ExternalCommandConfig,
+}
+
+impl ExternalCommandFilter {
+    /// Create a new scanner filter with the given configuration
+    pub fn new(config: ExternalCommandConfig) -> Self {
+        Self { config }
+    }
+
+    /// Run the scanner over `article` and return the score it reports.
+    async fn score(&self, article: &Message) -> Result<f64> {
+        let mut command = Command::new(&self.config.command);
+        command.args(&self.config.args);
+        let (status, output) = run_checker(
+            command,
+            &article.to_wire(),
+            MAX_SCANNER_OUTPUT,
+            Duration::from_secs(self.config.timeout_secs),
+        )
+        .await?;
+
+        // Scanners such as `spamc -c` exit non-zero for spam, so only the
+        // printed score matters
+        parse_score(&String::from_utf8_lossy(&output))
+            .ok_or_else(|| anyhow!("scanner exited with {status} without printing a score"))
+    }
+}
+
+/// Extract the score from scanner output: the first number on the first
+/// non-empty line, so both `7.5` and spamc's `7.5/5.0` are understood.
+#[must_use]
+pub fn parse_score(output: &str) -> Option<f64> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let end = line
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+')))
+        .unwrap_or(line.len());
+    line[..end].parse().ok()
+}
+
+#[async_trait::async_trait]
+impl ArticleFilter for ExternalCommandFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        _cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        match self.score(article).await {
+            Ok(score) if score > self.config.threshold => bail!(
+                "article scored {score} with {}, above threshold {}",
+                self.config.command,
+                self.config.threshold
+            ),
+            Ok(_) => Ok(()),
+            Err(e) => self.config.on_error.settle("content scanner", e),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ExternalCommandFilter"
+    }
+}
@@ -20,7 +20,12 @@ impl std::fmt::Display for FilterFactoryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FilterFactoryError::UnknownFilter(name) => {
-                write!(f, "Unknown filter: {name}")
+                let valid: Vec<&str> = BUILTIN_FILTERS.iter().map(|(_, name)| *name).collect();
+                write!(
+                    f,
+                    "Unknown filter: {name} (valid filters: {})",
+                    valid.join(", ")
+                )
             }
             FilterFactoryError::InvalidParameters(msg) => {
                 write!(f, "Invalid filter parameters: {msg}")
@@ -31,13 +36,52 @@ impl std::fmt::Display for FilterFactoryError {
 
 impl Error for FilterFactoryError {}
 
+/// Built-in filters in default order, by the short name a `[filters]`
+/// pipeline may use and the name the filter reports.
+const BUILTIN_FILTERS: &[(&str, &str)] = &[
+    ("headers", "HeaderFilter"),
+    ("syntax", "HeaderSyntaxFilter"),
+    ("header_rules", "HeaderRulesFilter"),
+    ("path", "PathLoopFilter"),
+    ("size", "SizeFilter"),
+    ("crosspost", "CrosspostFilter"),
+    ("mime", "MimeFilter"),
+    ("groups", "GroupExistenceFilter"),
+    ("moderation", "ModerationFilter"),
+    ("duplicates", "DuplicateBodyFilter"),
+    ("milter", "MilterFilter"),
+    ("external", "ExternalCommandFilter"),
+    ("hook", "ExternalFilter"),
+];
+
+/// The name a filter reports for `name`, which may be either that name or
+/// its short pipeline name.
+#[must_use]
+pub fn filter_name(name: &str) -> Option<&'static str> {
+    BUILTIN_FILTERS
+        .iter()
+        .find(|(short, full)| name == *short || name == *full)
+        .map(|(_, full)| *full)
+}
+
 /// Create a filter instance from configuration
 pub fn create_filter(config: &FilterConfig) -> Result<Box<dyn ArticleFilter>, FilterFactoryError> {
-    match config.name.as_str() {
+    let Some(name) = filter_name(&config.name) else {
+        return Err(FilterFactoryError::UnknownFilter(config.name.clone()));
+    };
+    match name {
         "HeaderFilter" => Ok(Box::new(super::header::HeaderFilter)),
+        "HeaderSyntaxFilter" => Ok(Box::new(super::header_syntax::HeaderSyntaxFilter)),
+        "HeaderRulesFilter" => Ok(Box::new(super::header_rules::HeaderRulesFilter)),
+        "PathLoopFilter" => Ok(Box::new(super::path::PathLoopFilter)),
         "SizeFilter" => Ok(Box::new(super::size::SizeFilter)),
+        "CrosspostFilter" => Ok(Box::new(super::crosspost::CrosspostFilter)),
+        "MimeFilter" => Ok(Box::new(super::mime::MimeFilter)),
         "GroupExistenceFilter" => Ok(Box::new(super::groups::GroupExistenceFilter)),
-        "ModerationFilter" => Ok(Box::new(super::moderation::ModerationFilter)),
+        "ModerationFilter" => Ok(Box::new(super::moderation::ModerationFilter::default())),
+        "DuplicateBodyFilter" => Ok(Box::new(
+            super::duplicate_body::DuplicateBodyFilter::default(),
+        )),
         "MilterFilter" => {
             // Extract Milter configuration from parameters
             let milter_config: super::milter::MilterConfig =
@@ -49,6 +93,30 @@ pub fn create_filter(config: &FilterConfig) -> Result<Box<dyn ArticleFilter>, Fi
                     })?;
             Ok(Box::new(super::milter::MilterFilter::new(milter_config)))
         }
+        "ExternalCommandFilter" => {
+            let scanner_config: super::external::ExternalCommandConfig =
+                serde_json::from_value(serde_json::Value::Object(config.parameters.clone()))
+                    .map_err(|e| {
+                        FilterFactoryError::InvalidParameters(format!(
+                            "ExternalCommandFilter configuration error: {e}"
+                        ))
+                    })?;
+            Ok(Box::new(super::external::ExternalCommandFilter::new(
+                scanner_config,
+            )))
+        }
+        "ExternalFilter" => {
+            let invalid = |e: &dyn std::fmt::Display| {
+                FilterFactoryError::InvalidParameters(format!(
+                    "ExternalFilter configuration error: {e}"
+                ))
+            };
+            let hook_config: super::hook::ExternalHookConfig =
+                serde_json::from_value(serde_json::Value::Object(config.parameters.clone()))
+                    .map_err(|e| invalid(&e))?;
+            let hook = super::hook::ExternalFilter::new(hook_config).map_err(|e| invalid(&e))?;
+            Ok(Box::new(hook))
+        }
         _ => Err(FilterFactoryError::UnknownFilter(config.name.clone())),
     }
 }
@@ -109,6 +177,17 @@ mod tests {
         assert_eq!(filter.name(), "GroupExistenceFilter");
     }
 
+    #[test]
+    fn test_create_path_loop_filter() {
+        let config = FilterConfig {
+            name: "PathLoopFilter".to_string(),
+            parameters: serde_json::Map::new(),
+        };
+
+        let filter = create_filter(&config).unwrap();
+        assert_eq!(filter.name(), "PathLoopFilter");
+    }
+
     #[test]
     fn test_create_moderation_filter() {
         let config = FilterConfig {
@@ -120,6 +199,28 @@ mod tests {
         assert_eq!(filter.name(), "ModerationFilter");
     }
 
+    #[test]
+    fn test_create_header_syntax_filter() {
+        let config = FilterConfig {
+            name: "syntax".to_string(),
+            parameters: serde_json::Map::new(),
+        };
+
+        let filter = create_filter(&config).unwrap();
+        assert_eq!(filter.name(), "HeaderSyntaxFilter");
+    }
+
+    #[test]
+    fn test_create_duplicate_body_filter() {
+        let config = FilterConfig {
+            name: "duplicates".to_string(),
+            parameters: serde_json::Map::new(),
+        };
+
+        let filter = create_filter(&config).unwrap();
+        assert_eq!(filter.name(), "DuplicateBodyFilter");
+    }
+
     #[test]
     fn test_create_milter_filter() {
         let mut parameters = serde_json::Map::new();
@@ -135,6 +236,60 @@ mod tests {
         assert_eq!(filter.name(), "MilterFilter");
     }
 
+    #[test]
+    fn test_create_external_command_filter() {
+        let mut parameters = serde_json::Map::new();
+        parameters.insert("command".to_string(), json!("/usr/bin/spamc"));
+        parameters.insert("args".to_string(), json!(["-c"]));
+        parameters.insert("threshold".to_string(), json!(5.0));
+        parameters.insert("on_error".to_string(), json!("closed"));
+
+        let config = FilterConfig {
+            name: "ExternalCommandFilter".to_string(),
+            parameters,
+        };
+
+        let filter = create_filter(&config).unwrap();
+        assert_eq!(filter.name(), "ExternalCommandFilter");
+
+        let mut missing_threshold = config.clone();
+        missing_threshold.parameters.remove("threshold");
+        assert!(matches!(
+            create_filter(&missing_threshold),
+            Err(FilterFactoryError::InvalidParameters(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_external_hook_filter() {
+        let mut parameters = serde_json::Map::new();
+        parameters.insert("url".to_string(), json!("http://[::1]:8080/check"));
+        parameters.insert("on_timeout".to_string(), json!("closed"));
+        let config = FilterConfig {
+            name: "hook".to_string(),
+            parameters,
+        };
+        assert_eq!(create_filter(&config).unwrap().name(), "ExternalFilter");
+
+        // Exactly one of command and url
+        let mut both = config.clone();
+        both.parameters
+            .insert("command".to_string(), json!("/usr/local/bin/checkpost"));
+        assert!(matches!(
+            create_filter(&both),
+            Err(FilterFactoryError::InvalidParameters(_))
+        ));
+
+        let mut bad_scheme = config.clone();
+        bad_scheme
+            .parameters
+            .insert("url".to_string(), json!("https://filter.example/check"));
+        assert!(matches!(
+            create_filter(&bad_scheme),
+            Err(FilterFactoryError::InvalidParameters(_))
+        ));
+    }
+
     #[test]
     fn test_unknown_filter() {
         let config = FilterConfig {
@@ -155,8 +310,8 @@ mod tests {
     fn test_create_empty_filter_chain() {
         let configs = vec![];
         let chain = create_filter_chain(&configs).unwrap();
-        // Default chain should have 4 filters
-        assert_eq!(chain.filter_names().len(), 4);
+        // Default chain should have 10 filters
+        assert_eq!(chain.filter_names().len(), 10);
     }
 
     #[test]
@@ -195,4 +350,26 @@ mod tests {
         let result = create_filter_chain(&configs);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_short_names_resolve_to_filter_names() {
+        assert_eq!(filter_name("size"), Some("SizeFilter"));
+        assert_eq!(filter_name("SizeFilter"), Some("SizeFilter"));
+        assert_eq!(filter_name("sizes"), None);
+
+        let configs = vec![FilterConfig {
+            name: "crosspost".to_string(),
+            parameters: serde_json::Map::new(),
+        }];
+        let chain = create_filter_chain(&configs).unwrap();
+        assert_eq!(chain.filter_names(), vec!["CrosspostFilter"]);
+    }
+
+    #[test]
+    fn test_unknown_filter_error_lists_valid_names() {
+        let message = FilterFactoryError::UnknownFilter("Bogus".to_string()).to_string();
+        assert!(message.starts_with("Unknown filter: Bogus"), "{message}");
+        assert!(message.contains("SizeFilter"), "{message}");
+        assert!(message.contains("CrosspostFilter"), "{message}");
+    }
 }
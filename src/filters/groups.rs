@@ -9,7 +9,6 @@ use crate::config::Config;
 use crate::handlers::utils::extract_newsgroups;
 use crate::storage::DynStorage;
 use anyhow::Result;
-use futures_util::TryStreamExt;
 
 /// Filter that validates newsgroups exist in the server
 pub struct GroupExistenceFilter;
@@ -24,15 +23,10 @@ impl ArticleFilter for GroupExistenceFilter {
         article: &Message,
         _size: u64,
     ) -> Result<()> {
-        // Get newsgroups from the article
-        let newsgroups = extract_newsgroups(article);
-
-        // Check that all groups exist
-        let stream = storage.list_groups();
-        let all_groups = stream.try_collect::<Vec<String>>().await?;
-        for group in &newsgroups {
-            if !all_groups.contains(group) {
-                return Err(anyhow::anyhow!("group does not exist"));
+        // Look each group up rather than listing every group on the server
+        for group in extract_newsgroups(article) {
+            if !storage.group_exists(&group).await? {
+                return Err(anyhow::anyhow!("group does not exist: {group}"));
             }
         }
 
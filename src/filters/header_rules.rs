@@ -0,0 +1,72 @@
+//! Configurable header rules
+//!
+//! Applies the `[[header_rules]]` of the configuration in order: a `reject`
+//! rule refuses articles with a header value matching its pattern and a
+//! `require` rule refuses articles without one. The first rule an article
+//! breaks decides.
+
+use super::{ArticleFilter, Rejection};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::{Config, HeaderAction};
+use crate::handlers::utils::get_header_values;
+use crate::storage::DynStorage;
+use anyhow::Result;
+
+/// Why [`HeaderRulesFilter`] refused an article; the text names the rule and
+/// is sent to the poster in the 441 response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderRuleRejection {
+    /// Name of the rule the article broke.
+    pub rule: String,
+    pub action: HeaderAction,
+}
+
+impl std::fmt::Display for HeaderRuleRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.action {
+            HeaderAction::Reject => write!(f, "rejected by header rule {}", self.rule),
+            HeaderAction::Require => {
+                write!(f, "missing a header required by rule {}", self.rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeaderRuleRejection {}
+
+/// Filter that enforces `header_rules`.
+pub struct HeaderRulesFilter;
+
+#[async_trait::async_trait]
+impl ArticleFilter for HeaderRulesFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        for rule in &cfg.header_rules {
+            let values = get_header_values(article, &rule.header);
+            let matched = values.iter().any(|v| rule.matches(v));
+            let broken = match rule.action {
+                HeaderAction::Reject => matched,
+                HeaderAction::Require => !matched,
+            };
+            if broken {
+                return Err(Rejection::HeaderRule(HeaderRuleRejection {
+                    rule: rule.name.clone(),
+                    action: rule.action,
+                })
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "HeaderRulesFilter"
+    }
+}
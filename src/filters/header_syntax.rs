@@ -0,0 +1,170 @@
+//! Header syntax filter
+//!
+//! Checks the headers an article carries against the grammar of RFC 5536:
+//! a bracketed Message-ID, a Date that parses, an address in From, legal
+//! newsgroup names and header text limited to printable US-ASCII, so 8-bit
+//! text has to arrive as RFC 2047 encoded words. Headers that are absent are
+//! left to [`super::header::HeaderFilter`].
+
+use super::{ArticleFilter, Rejection};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::handlers::utils::get_header_values;
+use crate::storage::DynStorage;
+use anyhow::Result;
+
+/// Longest Message-ID RFC 5536 allows, brackets included.
+const MAX_MESSAGE_ID_LEN: usize = 250;
+
+/// The header that made [`HeaderSyntaxFilter`] refuse an article, with the
+/// offending text. Each variant reads differently so logs tell them apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderSyntaxError {
+    /// A header name or value with 8-bit or control characters.
+    NonAscii(String),
+    /// A Message-ID that is not `<id-left@id-right>`.
+    MessageId(String),
+    /// A Date that is not an RFC 5322 date.
+    Date(String),
+    /// A From without a usable mailbox.
+    From(String),
+    /// A Newsgroups header without any group.
+    NoNewsgroups,
+    /// A group name outside the RFC 5536 grammar.
+    Newsgroup(String),
+}
+
+impl std::fmt::Display for HeaderSyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NonAscii(name) => write!(f, "8-bit or control characters in {name} header"),
+            Self::MessageId(id) => write!(f, "malformed Message-ID {id}"),
+            Self::Date(date) => write!(f, "unparseable Date {date:?}"),
+            Self::From(from) => write!(f, "no valid address in From {from:?}"),
+            Self::NoNewsgroups => f.write_str("empty Newsgroups header"),
+            Self::Newsgroup(group) => write!(f, "invalid newsgroup name {group:?}"),
+        }
+    }
+}
+
+impl std::error::Error for HeaderSyntaxError {}
+
+/// Whether `c` may appear in a header value: printable US-ASCII, space
+/// or tab.
+fn header_char(c: char) -> bool {
+    c.is_ascii_graphic() || c == ' ' || c == '\t'
+}
+
+/// Whether `id` is `<id-left@id-right>` in printable US-ASCII without
+/// whitespace or further angle brackets, as RFC 5536 section 3.1.3 asks.
+#[must_use]
+pub fn valid_message_id(id: &str) -> bool {
+    let Some(inner) = id.strip_prefix('<').and_then(|id| id.strip_suffix('>')) else {
+        return false;
+    };
+    let Some((left, right)) = inner.rsplit_once('@') else {
+        return false;
+    };
+    id.len() <= MAX_MESSAGE_ID_LEN
+        && !left.is_empty()
+        && !right.is_empty()
+        && inner
+            .chars()
+            .all(|c| c.is_ascii_graphic() && c != '<' && c != '>')
+}
+
+/// The mailbox in a From value, which may be a bare address, a display name
+/// with the address in angle brackets, or an address followed by a comment.
+fn from_address(from: &str) -> Option<&str> {
+    let address = match from.rfind('<') {
+        Some(start) => from[start + 1..].split_once('>')?.0,
+        None => from.split('(').next()?,
+    }
+    .trim();
+    let (local, domain) = address.rsplit_once('@')?;
+    let valid = !local.is_empty()
+        && !domain.is_empty()
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && address
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, '<' | '>' | '(' | ')' | ','));
+    valid.then_some(address)
+}
+
+/// Whether `name` follows RFC 5536 section 3.1.4: dot separated components
+/// of lowercase letters, digits, `+`, `-` and `_`, none of them empty or
+/// starting with a digit or an underscore.
+#[must_use]
+pub fn valid_newsgroup_name(name: &str) -> bool {
+    name.split('.').all(|component| {
+        !component.is_empty()
+            && !component.starts_with(|c: char| c == '_' || c.is_ascii_digit())
+            && component.chars().all(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '-' | '_')
+            })
+    })
+}
+
+/// Check the headers of `article`, returning the first problem found.
+///
+/// # Errors
+///
+/// Returns the [`HeaderSyntaxError`] describing the first malformed header.
+pub fn check_headers(article: &Message) -> Result<(), HeaderSyntaxError> {
+    for (name, value) in &article.headers {
+        if !name.chars().all(|c| c.is_ascii_graphic()) || !value.chars().all(header_char) {
+            return Err(HeaderSyntaxError::NonAscii(name.clone()));
+        }
+    }
+    for id in get_header_values(article, "Message-ID") {
+        if !valid_message_id(id.trim()) {
+            return Err(HeaderSyntaxError::MessageId(id));
+        }
+    }
+    for date in get_header_values(article, "Date") {
+        if chrono::DateTime::parse_from_rfc2822(date.trim()).is_err() {
+            return Err(HeaderSyntaxError::Date(date));
+        }
+    }
+    for from in get_header_values(article, "From") {
+        if from_address(&from).is_none() {
+            return Err(HeaderSyntaxError::From(from));
+        }
+    }
+    for newsgroups in get_header_values(article, "Newsgroups") {
+        if newsgroups.trim().is_empty() {
+            return Err(HeaderSyntaxError::NoNewsgroups);
+        }
+        if let Some(group) = newsgroups
+            .split(',')
+            .map(str::trim)
+            .find(|g| !valid_newsgroup_name(g))
+        {
+            return Err(HeaderSyntaxError::Newsgroup(group.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Filter that refuses articles with malformed headers.
+pub struct HeaderSyntaxFilter;
+
+#[async_trait::async_trait]
+impl ArticleFilter for HeaderSyntaxFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        _cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        check_headers(article).map_err(|e| Rejection::HeaderSyntax(e).into())
+    }
+
+    fn name(&self) -> &'static str {
+        "HeaderSyntaxFilter"
+    }
+}
@@ -0,0 +1,131 @@
+//! Duplicate Message-ID filter
+//!
+//! Remembers the Message-IDs of recently offered articles, much like INN's
+//! history file, so that re-offers during a feed storm are refused without
+//! a storage lookup. IDs not in the cache are checked against storage.
+
+use super::ArticleFilter;
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::handlers::utils::extract_message_id;
+use crate::storage::DynStorage;
+use anyhow::{Result, bail};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Bounded least-recently-used set of Message-IDs shared by all connections.
+#[derive(Debug)]
+pub struct HistoryCache {
+    capacity: usize,
+    inner: Mutex<LruSet>,
+}
+
+#[derive(Debug, Default)]
+struct LruSet {
+    tick: u64,
+    entries: HashMap<String, u64>,
+    order: BTreeMap<u64, String>,
+}
+
+impl HistoryCache {
+    /// Create a cache remembering at most `capacity` Message-IDs.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(LruSet::default()),
+        }
+    }
+
+    /// Whether `id` was seen recently. A hit counts as a use of the entry.
+    pub fn contains(&self, id: &str) -> bool {
+        let mut lru = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        lru.tick += 1;
+        let tick = lru.tick;
+        let Some(stamp) = lru.entries.get_mut(id) else {
+            return false;
+        };
+        let old = std::mem::replace(stamp, tick);
+        if let Some(key) = lru.order.remove(&old) {
+            lru.order.insert(tick, key);
+        }
+        true
+    }
+
+    /// Remember `id`, evicting the least recently used entry when full.
+    pub fn insert(&self, id: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lru = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some(old) = lru.entries.insert(id.to_string(), tick) {
+            lru.order.remove(&old);
+        }
+        lru.order.insert(tick, id.to_string());
+        while lru.entries.len() > self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+    }
+
+    /// Number of remembered Message-IDs.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    /// Whether the cache holds no Message-IDs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Filter that rejects articles whose Message-ID has already been offered
+pub struct HistoryFilter {
+    cache: Arc<HistoryCache>,
+}
+
+impl HistoryFilter {
+    /// Create a filter backed by the shared `cache`
+    pub fn new(cache: Arc<HistoryCache>) -> Self {
+        Self { cache }
+    }
+}
+
+#[async_trait::async_trait]
+impl ArticleFilter for HistoryFilter {
+    async fn validate(
+        &self,
+        storage: &DynStorage,
+        _auth: &DynAuth,
+        _cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        let Some(id) = extract_message_id(article) else {
+            return Ok(());
+        };
+        if self.cache.contains(&id) {
+            bail!("duplicate article {id} (history)");
+        }
+        let exists = storage.message_seen(&id).await?;
+        // Every offer is remembered whether or not it ends up stored, as
+        // INN's history does, so a peer re-sending it is turned away cheaply
+        self.cache.insert(&id);
+        if exists {
+            bail!("duplicate article {id}");
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "HistoryFilter"
+    }
+}
@@ -0,0 +1,326 @@
+//! External article filter hook
+//!
+//! Hands each article to a site-specific checker, either a local command or
+//! an HTTP webhook, and accepts it only when the checker does: a command
+//! must exit with status 0 and a webhook must answer `200`. Whatever else
+//! the checker prints or answers becomes the reason for the rejection.
+//!
+//! A command reads the article from stdin and finds its size, the
+//! authenticated user and the client address in the `RENEWS_ARTICLE_SIZE`,
+//! `RENEWS_USER` and `RENEWS_CLIENT_IP` environment variables. A webhook is
+//! sent the same as a JSON object with `size`, `user`, `client_ip`,
+//! `headers` (name and value pairs) and `body` fields. Webhooks are plain
+//! `http://` and must answer with a `Content-Length` body.
+
+use super::external::{FailurePolicy, run_checker};
+use super::{ArticleFilter, Rejection, Submitter};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::storage::DynStorage;
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+
+/// Most bytes of status line and headers read from a webhook
+const MAX_RESPONSE_HEAD: u64 = 8192;
+
+/// Configuration for the external filter hook; exactly one of `command` and
+/// `url` is set.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExternalHookConfig {
+    /// Path of the checker executable
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Arguments passed to the checker executable
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// `http://` URL articles are posted to
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Seconds to wait for a verdict
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_secs: u64,
+    /// Behavior when the checker times out or cannot be reached
+    #[serde(default)]
+    pub on_timeout: FailurePolicy,
+    /// Most bytes of checker output kept as the rejection reason
+    #[serde(default = "default_max_output")]
+    pub max_output: usize,
+}
+
+fn default_hook_timeout() -> u64 {
+    5
+}
+
+fn default_max_output() -> usize {
+    1024
+}
+
+/// The checker refused an article; the text is its reason, sent to the
+/// poster in the 441 response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookRejection {
+    pub reason: String,
+}
+
+impl std::fmt::Display for HookRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Only the first line, so the reason fits in a response line
+        match self.reason.lines().map(str::trim).find(|l| !l.is_empty()) {
+            Some(line) => write!(f, "rejected by external filter: {line}"),
+            None => f.write_str("rejected by external filter"),
+        }
+    }
+}
+
+impl std::error::Error for HookRejection {}
+
+/// Where a webhook lives, split out of its URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            bail!("unsupported URL {url}: use http://");
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        // IPv6 addresses are bracketed so their colons are not a port
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => bracketed
+                .split_once(']')
+                .map(|(host, rest)| (host, rest.strip_prefix(':')))
+                .ok_or_else(|| anyhow!("unclosed bracket in URL {url}"))?,
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port
+                .parse()
+                .map_err(|_| anyhow!("invalid port in URL {url}"))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            bail!("missing host in URL {url}");
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Filter that lets an external command or webhook accept or reject
+/// articles.
+pub struct ExternalFilter {
+    config: Arc<ExternalHookConfig>,
+    endpoint: Option<Endpoint>,
+    submitter: Submitter,
+}
+
+impl ExternalFilter {
+    /// Create the hook, checking that exactly one of `command` and `url` is
+    /// given and that the URL is usable.
+    pub fn new(config: ExternalHookConfig) -> Result<Self> {
+        let endpoint = match (&config.command, &config.url) {
+            (Some(_), None) => None,
+            (None, Some(url)) => Some(Endpoint::parse(url)?),
+            _ => bail!("set exactly one of command and url"),
+        };
+        Ok(Self {
+            config: Arc::new(config),
+            endpoint,
+            submitter: Submitter::default(),
+        })
+    }
+
+    /// Ask the checker about `article`: `Ok(None)` accepts it,
+    /// `Ok(Some(reason))` rejects it and an error means there was no answer.
+    async fn verdict(&self, article: &Message, size: u64) -> Result<Option<String>> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        match &self.endpoint {
+            Some(endpoint) => {
+                tokio::time::timeout(timeout, self.ask_webhook(endpoint, article, size))
+                    .await
+                    .map_err(|_| anyhow!("no verdict after {}s", timeout.as_secs()))?
+            }
+            None => self.ask_command(article, size, timeout).await,
+        }
+    }
+
+    async fn ask_command(
+        &self,
+        article: &Message,
+        size: u64,
+        timeout: Duration,
+    ) -> Result<Option<String>> {
+        let mut command = Command::new(self.config.command.as_deref().unwrap_or_default());
+        command
+            .args(&self.config.args)
+            .env("RENEWS_ARTICLE_SIZE", size.to_string())
+            .env_remove("RENEWS_USER")
+            .env_remove("RENEWS_CLIENT_IP");
+        if let Some(user) = &self.submitter.user {
+            command.env("RENEWS_USER", user);
+        }
+        if let Some(ip) = self.submitter.ip {
+            command.env("RENEWS_CLIENT_IP", ip.to_string());
+        }
+        let (status, output) =
+            run_checker(command, &article.to_wire(), self.config.max_output, timeout).await?;
+        if status.success() {
+            return Ok(None);
+        }
+        let reason = String::from_utf8_lossy(&output).trim().to_string();
+        Ok(Some(if reason.is_empty() {
+            format!("checker exited with {status}")
+        } else {
+            reason
+        }))
+    }
+
+    async fn ask_webhook(
+        &self,
+        endpoint: &Endpoint,
+        article: &Message,
+        size: u64,
+    ) -> Result<Option<String>> {
+        let headers: Vec<[&String; 2]> = article.headers.iter().map(|(k, v)| [k, v]).collect();
+        let document = serde_json::json!({
+            "size": size,
+            "user": self.submitter.user,
+            "client_ip": self.submitter.ip.map(|ip| ip.to_string()),
+            "headers": headers,
+            "body": STANDARD.encode(&article.body),
+        });
+        let body = serde_json::to_vec(&document)?;
+        let host = if endpoint.host.contains(':') {
+            format!("[{}]", endpoint.host)
+        } else {
+            endpoint.host.clone()
+        };
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            endpoint.path,
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+
+        let stream = TcpStream::connect((endpoint.host.as_str(), endpoint.port))
+            .await
+            .with_context(|| format!("failed to connect to {}", endpoint.host))?;
+        let (status, reason) = exchange(stream, &request, self.config.max_output).await?;
+        if status == 200 {
+            return Ok(None);
+        }
+        let reason = String::from_utf8_lossy(&reason).trim().to_string();
+        Ok(Some(if reason.is_empty() {
+            format!("checker answered {status}")
+        } else {
+            reason
+        }))
+    }
+}
+
+/// Send `request` and read back the status code and at most `max_body`
+/// bytes of the body. Only responses that give their length with
+/// `Content-Length` are understood.
+async fn exchange(
+    mut stream: TcpStream,
+    request: &[u8],
+    max_body: usize,
+) -> Result<(u16, Vec<u8>)> {
+    stream.write_all(request).await?;
+    stream.flush().await?;
+    let mut reader = BufReader::new(stream);
+
+    let mut head = (&mut reader).take(MAX_RESPONSE_HEAD);
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("malformed status line from checker"))?;
+    let mut length = None;
+    loop {
+        line.clear();
+        if head.read_line(&mut line).await? == 0 {
+            bail!("incomplete response from checker");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("Content-Length") {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("invalid Content-Length from checker"))?,
+            );
+        } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+            bail!("checker answered with Transfer-Encoding {}", value.trim());
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("checker answered without Content-Length"))?;
+
+    let wanted = length.min(max_body as u64);
+    let mut body = Vec::new();
+    reader.take(wanted).read_to_end(&mut body).await?;
+    if (body.len() as u64) < wanted {
+        bail!("response from checker ended early");
+    }
+    Ok((status, body))
+}
+
+#[async_trait::async_trait]
+impl ArticleFilter for ExternalFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        _cfg: &Config,
+        article: &Message,
+        size: u64,
+    ) -> Result<()> {
+        match self.verdict(article, size).await {
+            Ok(None) => Ok(()),
+            Ok(Some(reason)) => Err(Rejection::Hook(HookRejection { reason }).into()),
+            Err(e) => self.config.on_timeout.settle("external filter", e),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ExternalFilter"
+    }
+
+    fn for_submitter(&self, submitter: &Submitter) -> Option<Box<dyn ArticleFilter>> {
+        Some(Box::new(Self {
+            config: self.config.clone(),
+            endpoint: self.endpoint.clone(),
+            submitter: submitter.clone(),
+        }))
+    }
+}
@@ -233,8 +233,7 @@ impl MilterFilter {
         }
 
         // Send body
-        conn.send_command(MILTER_BODY, article.body.as_bytes())
-            .await?;
+        conn.send_command(MILTER_BODY, &article.body).await?;
         let response = conn.read_response().await?;
         if response != MILTER_CONTINUE {
             return self.handle_response(response);
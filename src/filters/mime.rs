@@ -0,0 +1,216 @@
+//! MIME content filter
+//!
+//! Keeps binaries out of groups marked `no_binaries` in `[[group_settings]]`.
+//! The article and, when it is multipart, each of its parts are checked
+//! for base64 transfer encoding and for media types other than text. Bodies
+//! are also scanned for yEnc data, which travels outside MIME.
+
+use super::{ArticleFilter, Rejection};
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::handlers::utils::{extract_newsgroups, get_header_values};
+use crate::storage::DynStorage;
+use anyhow::Result;
+
+/// Deepest nesting of multipart and message parts that is looked into; an
+/// article nested deeper is refused rather than passed unchecked.
+const MAX_DEPTH: usize = 8;
+
+/// Media types outside `text/*`, `multipart/*` and `message/*` that text
+/// groups still accept.
+const ALLOWED_TYPES: &[&str] = &["application/pgp-signature"];
+
+/// The content that made [`MimeFilter`] refuse an article.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MimeContent {
+    /// A part of this media type sent in base64.
+    Base64(String),
+    /// A part of a media type text groups do not take.
+    MediaType(String),
+    /// A `=ybegin` line of yEnc data.
+    Yenc,
+}
+
+/// Why [`MimeFilter`] refused an article; the text is sent to the poster in
+/// the 441 response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeRejection {
+    /// The restricted group the article was posted to.
+    pub group: String,
+    pub content: MimeContent,
+}
+
+impl std::fmt::Display for MimeRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.content {
+            MimeContent::Base64(media_type) => write!(
+                f,
+                "base64 encoded {media_type} part not allowed in {}",
+                self.group
+            ),
+            MimeContent::MediaType(media_type) => {
+                write!(f, "{media_type} part not allowed in {}", self.group)
+            }
+            MimeContent::Yenc => write!(f, "yEnc data not allowed in {}", self.group),
+        }
+    }
+}
+
+impl std::error::Error for MimeRejection {}
+
+/// The lowercased media type of a `Content-Type` value and its `boundary`
+/// parameter, if any. An empty value means `text/plain`.
+fn parse_content_type(value: Option<&str>) -> (String, Option<String>) {
+    let mut params = value.unwrap_or_default().split(';');
+    let media_type = params
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let boundary = params
+        .filter_map(|p| p.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string());
+    if media_type.is_empty() {
+        ("text/plain".to_string(), boundary)
+    } else {
+        (media_type, boundary)
+    }
+}
+
+/// `Content-Type` and `Content-Transfer-Encoding` from the header lines of
+/// a part, with folded lines joined.
+fn part_headers(lines: &[&str]) -> (Option<String>, Option<String>) {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in lines {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push_str(line);
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let find = |wanted: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.clone())
+    };
+    (find("Content-Type"), find("Content-Transfer-Encoding"))
+}
+
+/// The parts between the `boundary` delimiter lines of a multipart body.
+fn split_parts<'a>(lines: &[&'a str], boundary: &str) -> Vec<Vec<&'a str>> {
+    let delimiter = format!("--{boundary}");
+    let close = format!("{delimiter}--");
+    let mut parts = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for &line in lines {
+        let marker = line.trim_end();
+        if marker == close {
+            parts.extend(current.take());
+            break;
+        } else if marker == delimiter {
+            parts.extend(current.take());
+            current = Some(Vec::new());
+        } else if let Some(part) = current.as_mut() {
+            part.push(line);
+        }
+    }
+    parts.extend(current);
+    parts
+}
+
+/// Check one MIME entity, descending into multipart and message parts.
+fn check_entity(
+    content_type: Option<&str>,
+    encoding: Option<&str>,
+    body: &[&str],
+    depth: usize,
+) -> Result<(), MimeContent> {
+    let (media_type, boundary) = parse_content_type(content_type);
+    if encoding.is_some_and(|e| e.trim().eq_ignore_ascii_case("base64")) {
+        return Err(MimeContent::Base64(media_type));
+    }
+
+    let nested = media_type.starts_with("multipart/") || media_type == "message/rfc822";
+    if nested && depth >= MAX_DEPTH {
+        return Err(MimeContent::MediaType(media_type));
+    }
+    if let Some(boundary) = boundary.filter(|_| media_type.starts_with("multipart/")) {
+        for part in split_parts(body, &boundary) {
+            check_part(&part, depth + 1)?;
+        }
+        return Ok(());
+    }
+    if media_type == "message/rfc822" {
+        return check_part(body, depth + 1);
+    }
+
+    let allowed = media_type.starts_with("text/")
+        || media_type.starts_with("multipart/")
+        || media_type.starts_with("message/")
+        || ALLOWED_TYPES.contains(&media_type.as_str());
+    if !allowed {
+        return Err(MimeContent::MediaType(media_type));
+    }
+    if body.iter().any(|line| line.starts_with("=ybegin ")) {
+        return Err(MimeContent::Yenc);
+    }
+    Ok(())
+}
+
+/// Check a part given as its header lines, a blank line and its body.
+fn check_part(lines: &[&str], depth: usize) -> Result<(), MimeContent> {
+    let split = lines
+        .iter()
+        .position(|line| line.trim().is_empty())
+        .unwrap_or(lines.len());
+    let (content_type, encoding) = part_headers(&lines[..split]);
+    let body = lines.get(split + 1..).unwrap_or_default();
+    check_entity(content_type.as_deref(), encoding.as_deref(), body, depth)
+}
+
+/// Filter that refuses binaries in groups with `no_binaries` set. Control
+/// messages are exempt.
+pub struct MimeFilter;
+
+#[async_trait::async_trait]
+impl ArticleFilter for MimeFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        if crate::control::is_control_message(article) {
+            return Ok(());
+        }
+        let Some(group) = extract_newsgroups(article)
+            .into_iter()
+            .find(|g| cfg.no_binaries_for_group(g))
+        else {
+            return Ok(());
+        };
+
+        let content_type = get_header_values(article, "Content-Type");
+        let encoding = get_header_values(article, "Content-Transfer-Encoding");
+        let text = article.body_text();
+        let body: Vec<&str> = text.lines().collect();
+        check_entity(
+            content_type.first().map(String::as_str),
+            encoding.first().map(String::as_str),
+            &body,
+            0,
+        )
+        .map_err(|content| Rejection::Mime(MimeRejection { group, content }).into())
+    }
+
+    fn name(&self) -> &'static str {
+        "MimeFilter"
+    }
+}
@@ -9,12 +9,23 @@ use crate::auth::DynAuth;
 use crate::config::Config;
 use crate::storage::DynStorage;
 use anyhow::Result;
+use std::net::IpAddr;
+use std::sync::Arc;
 
+pub mod crosspost;
+pub mod duplicate_body;
+pub mod external;
 pub mod factory;
 pub mod groups;
 pub mod header;
+pub mod header_rules;
+pub mod header_syntax;
+pub mod history;
+pub mod hook;
 pub mod milter;
+pub mod mime;
 pub mod moderation;
+pub mod path;
 pub mod size;
 
 /// Trait for article validation filters
@@ -34,8 +45,50 @@ pub trait ArticleFilter: Send + Sync {
 
     /// Get a descriptive name for this filter (for logging/debugging)
     fn name(&self) -> &'static str;
+
+    /// A copy of this filter that knows who submitted the articles it
+    /// checks, for filters that pass that on; `None` keeps the filter as is
+    fn for_submitter(&self, _submitter: &Submitter) -> Option<Box<dyn ArticleFilter>> {
+        None
+    }
+}
+
+/// Who submitted an article, as far as the session knows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Submitter {
+    /// The authenticated user, if any.
+    pub user: Option<String>,
+    /// The remote address of the connection.
+    pub ip: Option<IpAddr>,
+}
+
+/// A refusal the poster can act on, so POST spells out its reason instead
+/// of a bare 441.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    Crosspost(crosspost::CrosspostRejection),
+    DuplicateBody(duplicate_body::DuplicateBodyRejection),
+    HeaderRule(header_rules::HeaderRuleRejection),
+    HeaderSyntax(header_syntax::HeaderSyntaxError),
+    Hook(hook::HookRejection),
+    Mime(mime::MimeRejection),
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Crosspost(rejection) => rejection.fmt(f),
+            Self::DuplicateBody(rejection) => rejection.fmt(f),
+            Self::HeaderRule(rejection) => rejection.fmt(f),
+            Self::HeaderSyntax(rejection) => rejection.fmt(f),
+            Self::Hook(rejection) => rejection.fmt(f),
+            Self::Mime(rejection) => rejection.fmt(f),
+        }
+    }
 }
 
+impl std::error::Error for Rejection {}
+
 /// A chain of filters that all must pass for validation to succeed
 pub struct FilterChain {
     filters: Vec<Box<dyn ArticleFilter>>,
@@ -55,6 +108,65 @@ impl FilterChain {
         self
     }
 
+    /// The chain configured under `filters`, or the default chain when none
+    /// is configured or the configuration names an unknown filter.
+    #[must_use]
+    pub fn from_config(cfg: &Config) -> Self {
+        factory::create_filter_chain(&cfg.filters).unwrap_or_else(|e| {
+            tracing::error!("Failed to create filter chain: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Put a [`history::HistoryFilter`] backed by `cache` at the front of
+    /// the chain so duplicates are refused before any other work is done
+    pub fn with_history(mut self, cache: Arc<history::HistoryCache>) -> Self {
+        self.filters
+            .insert(0, Box::new(history::HistoryFilter::new(cache)));
+        self
+    }
+
+    /// Let `user`, the authenticated poster, approve articles for the groups
+    /// they moderate without a signed `Approved` header
+    pub fn with_approver(mut self, user: &str) -> Self {
+        for filter in &mut self.filters {
+            if filter.name() == "ModerationFilter" {
+                *filter = Box::new(moderation::ModerationFilter::approved_by(user));
+            }
+        }
+        self
+    }
+
+    /// Tell the filters that pass it on who submitted the articles this
+    /// chain checks
+    pub fn with_submitter(mut self, submitter: &Submitter) -> Self {
+        for filter in &mut self.filters {
+            if let Some(specialised) = filter.for_submitter(submitter) {
+                *filter = specialised;
+            }
+        }
+        self
+    }
+
+    /// Have the [`duplicate_body::DuplicateBodyFilter`] in the chain count
+    /// sightings in `recent` before going to storage
+    pub fn with_recent_bodies(mut self, recent: Arc<duplicate_body::RecentBodies>) -> Self {
+        for filter in &mut self.filters {
+            if filter.name() == "DuplicateBodyFilter" {
+                *filter = Box::new(duplicate_body::DuplicateBodyFilter::with_recent(
+                    recent.clone(),
+                ));
+            }
+        }
+        self
+    }
+
+    /// Drop the filters named `name` from the chain
+    pub fn without(mut self, name: &str) -> Self {
+        self.filters.retain(|f| f.name() != name);
+        self
+    }
+
     /// Run all filters in the chain, returning on first failure
     pub async fn validate(
         &self,
@@ -65,7 +177,10 @@ impl FilterChain {
         size: u64,
     ) -> Result<()> {
         for filter in &self.filters {
-            filter.validate(storage, auth, cfg, article, size).await?;
+            if let Err(e) = filter.validate(storage, auth, cfg, article, size).await {
+                crate::metrics::global().filter_rejected(filter.name());
+                return Err(e);
+            }
         }
         Ok(())
     }
@@ -81,8 +196,14 @@ impl Default for FilterChain {
     fn default() -> Self {
         Self::new()
             .add_filter(Box::new(header::HeaderFilter))
+            .add_filter(Box::new(header_syntax::HeaderSyntaxFilter))
+            .add_filter(Box::new(header_rules::HeaderRulesFilter))
+            .add_filter(Box::new(path::PathLoopFilter))
             .add_filter(Box::new(size::SizeFilter))
+            .add_filter(Box::new(crosspost::CrosspostFilter))
+            .add_filter(Box::new(mime::MimeFilter))
             .add_filter(Box::new(groups::GroupExistenceFilter))
-            .add_filter(Box::new(moderation::ModerationFilter))
+            .add_filter(Box::new(moderation::ModerationFilter::default()))
+            .add_filter(Box::new(duplicate_body::DuplicateBodyFilter::default()))
     }
 }
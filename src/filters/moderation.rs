@@ -5,14 +5,28 @@
 use super::ArticleFilter;
 use crate::Message;
 use crate::auth::DynAuth;
-use crate::config::Config;
+use crate::config::{Config, ModeratedCrosspostPolicy};
 use crate::handlers::utils::{extract_newsgroups, get_header_values};
 use crate::storage::DynStorage;
 use anyhow::Result;
 use smallvec::SmallVec;
 
 /// Filter that validates moderated group requirements
-pub struct ModerationFilter;
+#[derive(Default)]
+pub struct ModerationFilter {
+    approver: Option<String>,
+}
+
+impl ModerationFilter {
+    /// Also accept an `Approved` header without a signature when `user`,
+    /// the authenticated poster, moderates the group.
+    #[must_use]
+    pub fn approved_by(user: &str) -> Self {
+        Self {
+            approver: Some(user.to_string()),
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl ArticleFilter for ModerationFilter {
@@ -20,12 +34,13 @@ impl ArticleFilter for ModerationFilter {
         &self,
         storage: &DynStorage,
         auth: &DynAuth,
-        _cfg: &Config,
+        cfg: &Config,
         article: &Message,
         _size: u64,
     ) -> Result<()> {
         // Get newsgroups from the article
         let newsgroups = extract_newsgroups(article);
+        let mut held = 0;
 
         // Get all approved values and signatures
         let approved_values = get_header_values(article, "Approved");
@@ -34,6 +49,10 @@ impl ArticleFilter for ModerationFilter {
         // Check each newsgroup for moderation requirements
         for group in &newsgroups {
             if storage.is_group_moderated(group).await? {
+                if poster_approves(auth, self.approver.as_deref(), &approved_values, group).await? {
+                    continue;
+                }
+
                 // Find moderators for this specific group
                 let mut group_moderators = SmallVec::<[String; 2]>::new();
                 let mut group_signatures = SmallVec::<[String; 2]>::new();
@@ -48,7 +67,8 @@ impl ArticleFilter for ModerationFilter {
                 }
 
                 if group_moderators.is_empty() {
-                    return Err(anyhow::anyhow!("missing approval for moderated group"));
+                    held += 1;
+                    continue;
                 }
 
                 if group_signatures.len() < group_moderators.len() {
@@ -90,13 +110,19 @@ impl ArticleFilter for ModerationFilter {
                         version,
                         signed,
                         &sig_rest,
-                        &_cfg.pgp_key_servers,
+                        &cfg.pgp_key_servers,
                     )
                     .await?;
                 }
             }
         }
 
+        // Under the split policy an unapproved crosspost may still go live in
+        // its unmoderated groups; `strip_held_groups` removes the rest.
+        if held > 0 && !may_split(cfg, held, newsgroups.len()) {
+            return Err(anyhow::anyhow!("missing approval for moderated group"));
+        }
+
         Ok(())
     }
 
@@ -104,3 +130,169 @@ impl ArticleFilter for ModerationFilter {
         "ModerationFilter"
     }
 }
+
+/// Whether an article with the given `Approved` values was posted by
+/// `approver`, an authenticated moderator of `group`.
+async fn poster_approves(
+    auth: &DynAuth,
+    approver: Option<&str>,
+    approved_values: &[String],
+    group: &str,
+) -> Result<bool> {
+    match approver {
+        Some(user) if !approved_values.is_empty() => auth.is_moderator(user, group).await,
+        _ => Ok(false),
+    }
+}
+
+/// Return the moderated groups in `article` that neither its `Approved`
+/// values nor the authenticated `approver` is allowed to approve.
+pub async fn unapproved_moderated_groups(
+    storage: &DynStorage,
+    auth: &DynAuth,
+    article: &Message,
+    approver: Option<&str>,
+) -> Result<SmallVec<[String; 4]>> {
+    let approved_values = get_header_values(article, "Approved");
+    let mut held = SmallVec::new();
+
+    for group in extract_newsgroups(article) {
+        if !storage.is_group_moderated(&group).await? {
+            continue;
+        }
+        let mut approved = poster_approves(auth, approver, &approved_values, &group).await?;
+        for value in &approved_values {
+            if approved {
+                break;
+            }
+            if auth.is_moderator(value, &group).await? {
+                approved = true;
+                break;
+            }
+        }
+        if !approved {
+            held.push(group);
+        }
+    }
+
+    Ok(held)
+}
+
+/// Whether an unapproved crosspost with `held` of its `total` groups waiting
+/// for approval may go live in the others. Only the `split` policy allows
+/// it, and only when some group does not need approval.
+#[must_use]
+pub fn may_split(cfg: &Config, held: usize, total: usize) -> bool {
+    cfg.moderated_crosspost_policy == ModeratedCrosspostPolicy::Split && held < total
+}
+
+/// The copies of an unapproved post to send for approval, each with the
+/// moderator address it goes to. When the post is split every `held` group
+/// gets its own copy naming only that group; otherwise the whole article goes
+/// to the moderator of the first held group. `None` when a copy has no
+/// moderator address to go to.
+pub async fn approval_copies(
+    storage: &DynStorage,
+    cfg: &Config,
+    article: &Message,
+    held: &[String],
+) -> Result<Option<Vec<(String, Message)>>> {
+    let Some(first) = held.first() else {
+        return Ok(Some(Vec::new()));
+    };
+    if !may_split(cfg, held.len(), extract_newsgroups(article).len()) {
+        let address = moderator_address(storage, cfg, first).await?;
+        return Ok(address.map(|address| vec![(address, article.clone())]));
+    }
+
+    let mut copies = Vec::with_capacity(held.len());
+    for group in held {
+        let Some(address) = moderator_address(storage, cfg, group).await? else {
+            return Ok(None);
+        };
+        let mut copy = article.clone();
+        for (name, value) in &mut copy.headers {
+            if name.eq_ignore_ascii_case("Newsgroups") {
+                value.clone_from(group);
+            }
+        }
+        copies.push((address, copy));
+    }
+    Ok(Some(copies))
+}
+
+/// Apply the `split` crosspost policy by removing moderated groups that have
+/// not approved the article from its Newsgroups header, so that only the
+/// unmoderated copies are stored. Does nothing under `hold_all`.
+pub async fn strip_held_groups(
+    storage: &DynStorage,
+    auth: &DynAuth,
+    cfg: &Config,
+    article: &mut Message,
+    approver: Option<&str>,
+) -> Result<()> {
+    if cfg.moderated_crosspost_policy != ModeratedCrosspostPolicy::Split {
+        return Ok(());
+    }
+
+    let held = unapproved_moderated_groups(storage, auth, article, approver).await?;
+    if held.is_empty() || !may_split(cfg, held.len(), extract_newsgroups(article).len()) {
+        return Ok(());
+    }
+
+    let remaining = extract_newsgroups(article)
+        .into_iter()
+        .filter(|g| !held.contains(g))
+        .collect::<Vec<_>>()
+        .join(",");
+    for (name, value) in &mut article.headers {
+        if name.eq_ignore_ascii_case("Newsgroups") {
+            *value = remaining.clone();
+        }
+    }
+
+    Ok(())
+}
+
+/// The address unapproved posts to `group` are mailed to: the address
+/// stored for the group, else the configured `moderators` template. `None`
+/// when there is no address or no SMTP relay to send through.
+pub async fn moderator_address(
+    storage: &DynStorage,
+    cfg: &Config,
+    group: &str,
+) -> Result<Option<String>> {
+    if cfg.smtp_relay.is_none() {
+        return Ok(None);
+    }
+    if let Some(address) = storage.get_group_moderator(group).await? {
+        return Ok(Some(address));
+    }
+    Ok(cfg
+        .moderators
+        .as_ref()
+        .map(|template| template.replace("%s", &group.replace('.', "-"))))
+}
+
+/// Mail `article` to the moderator at `address` through the SMTP `relay`,
+/// as `usenet@` the site. Takes the settings rather than the configuration
+/// so no lock on it is held while the mail goes out.
+pub async fn mail_to_moderator(
+    relay: Option<&str>,
+    site_name: &str,
+    address: &str,
+    article: &Message,
+) -> Result<()> {
+    let relay = relay.ok_or_else(|| anyhow::anyhow!("no SMTP relay configured"))?;
+    let mut mail = format!("To: {address}\r\n");
+    for (name, value) in &article.headers {
+        mail.push_str(name);
+        mail.push_str(": ");
+        mail.push_str(value);
+        mail.push_str("\r\n");
+    }
+    mail.push_str("\r\n");
+    mail.push_str(&article.body_text());
+    let sender = format!("usenet@{site_name}");
+    crate::smtp::send_mail(relay, site_name, &sender, address, &mail).await
+}
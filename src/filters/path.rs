@@ -0,0 +1,39 @@
+//! Path loop filter
+//!
+//! Refuses articles whose Path already names this server, so an article
+//! that comes back around a feed mesh is not accepted twice.
+
+use super::ArticleFilter;
+use crate::Message;
+use crate::auth::DynAuth;
+use crate::config::Config;
+use crate::storage::DynStorage;
+use crate::storage::common::path_contains;
+use anyhow::Result;
+
+/// Filter that refuses articles which have already passed through this site
+pub struct PathLoopFilter;
+
+#[async_trait::async_trait]
+impl ArticleFilter for PathLoopFilter {
+    async fn validate(
+        &self,
+        _storage: &DynStorage,
+        _auth: &DynAuth,
+        cfg: &Config,
+        article: &Message,
+        _size: u64,
+    ) -> Result<()> {
+        if path_contains(article, cfg.path_identity()) {
+            return Err(anyhow::anyhow!(
+                "article has already passed through {}",
+                cfg.path_identity()
+            ));
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "PathLoopFilter"
+    }
+}
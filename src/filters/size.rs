@@ -1,6 +1,7 @@
 //! Size validation filter
 //!
-//! Validates that articles are within configured size limits.
+//! Validates that articles are within configured size limits: the group's
+//! own `max_article_bytes` rule, else the global `max_article_bytes`.
 
 use super::ArticleFilter;
 use crate::Message;
@@ -28,13 +29,20 @@ impl ArticleFilter for SizeFilter {
 
         // Check size limit for each newsgroup
         for group in &newsgroups {
-            if let Some(max_size) = cfg.max_size_for_group(group) {
-                if size > max_size {
-                    return Err(anyhow::anyhow!("article too large for group {group}"));
-                }
+            if let Some(max_size) = cfg.size_limit_for_group(group)
+                && size > max_size
+            {
+                return Err(anyhow::anyhow!("article too large for group {group}"));
             }
         }
 
+        if newsgroups.is_empty()
+            && let Some(max_size) = cfg.global_max_size()
+            && size > max_size
+        {
+            return Err(anyhow::anyhow!("article too large"));
+        }
+
         Ok(())
     }
 
@@ -1,12 +1,17 @@
 //! Article retrieval command handlers.
 
 use super::utils::{
-    ArticleOperation, get_header_value, handle_article_operation, metadata_value, resolve_articles,
-    write_response_with_values, write_simple,
+    ArticleOperation, ArticleQueryError, get_header_value, handle_article_error,
+    handle_article_operation, metadata_value, resolve_articles, write_response_with_values,
+    write_simple,
 };
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::config::AccessRules;
+use crate::overview::{extra_field, extra_field_name};
 use crate::responses::*;
 use anyhow::Result;
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
 use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 
 /// Macro to create simple article command handlers.
@@ -20,10 +25,12 @@ macro_rules! article_handler {
                 R: AsyncBufRead + Unpin,
                 W: AsyncWrite + Unpin,
             {
+                let access = ctx.config.read().await.access.clone();
                 handle_article_operation(
                     &mut ctx.writer,
                     &ctx.storage,
                     &mut ctx.state,
+                    &access,
                     args,
                     $operation,
                 )
@@ -48,31 +55,67 @@ impl CommandHandler for HdrHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        if args.is_empty() {
-            return write_simple(&mut ctx.writer, RESP_501_NOT_ENOUGH).await;
-        }
+        handle_hdr(ctx, args, RESP_225_HEADERS).await
+    }
+}
 
-        let field = &args[0];
+/// Handler for the legacy XHDR command (RFC 2980), which answers with 221.
+pub struct XHdrHandler;
 
-        // Handle special case for all headers
-        if field == ":" {
-            return handle_all_headers(ctx, args).await;
-        }
+impl CommandHandler for XHdrHandler {
+    async fn handle<R, W>(ctx: &mut HandlerContext<R, W>, args: &[String]) -> HandlerResult
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        handle_hdr(ctx, args, RESP_221_HEADER_FOLLOWS).await
+    }
+}
 
-        // Collect header values for the specified field
-        let values = collect_header_values(
-            &ctx.storage,
-            &ctx.state,
-            field,
-            args.get(1).map(|s| s.as_str()),
-        )
-        .await?;
-
-        // Send response
-        write_response_with_values(&mut ctx.writer, RESP_225_HEADERS, &values).await
+/// Shared implementation of HDR and XHDR.
+async fn handle_hdr<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    args: &[String],
+    initial_response: &str,
+) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if args.is_empty() {
+        return write_simple(&mut ctx.writer, RESP_501_NOT_ENOUGH).await;
     }
+
+    let field = &args[0];
+
+    // Handle special case for all headers
+    if field == ":" {
+        return handle_all_headers(ctx, args).await;
+    }
+
+    // Collect header values for the specified field
+    let access = ctx.config.read().await.access.clone();
+    let values = match collect_header_values(
+        &ctx.storage,
+        &ctx.state,
+        &access,
+        field,
+        args.get(1).map(|s| s.as_str()),
+    )
+    .await
+    {
+        Ok(values) => values,
+        Err(error) => return handle_article_error(&mut ctx.writer, error).await,
+    };
+
+    // Send response
+    write_response_with_values(&mut ctx.writer, initial_response, &values).await
 }
 
+/// Article numbers read per storage query when XPAT walks a range, so wide
+/// ranges are streamed to the client instead of buffered whole.
+const XPAT_CHUNK: u64 = 1000;
+
 /// Handler for the XPAT command.
 pub struct XPatHandler;
 
@@ -89,22 +132,70 @@ impl CommandHandler for XPatHandler {
         let field = &args[0];
         let range_or_msgid = &args[1];
         let patterns: Vec<&str> = args[2..].iter().map(String::as_str).collect();
+        let matches = |v: &str| patterns.iter().any(|pat| crate::wildmat::wildmat(pat, v));
 
-        let values =
-            collect_header_values(&ctx.storage, &ctx.state, field, Some(range_or_msgid)).await?;
-
-        write_simple(&mut ctx.writer, RESP_221_HEADER_FOLLOWS).await?;
+        if range_or_msgid.starts_with('<') && range_or_msgid.ends_with('>') {
+            let access = ctx.config.read().await.access.clone();
+            let values = match collect_header_values(
+                &ctx.storage,
+                &ctx.state,
+                &access,
+                field,
+                Some(range_or_msgid),
+            )
+            .await
+            {
+                Ok(values) => values,
+                Err(error) => return handle_article_error(&mut ctx.writer, error).await,
+            };
 
-        for (n, val) in values {
-            if let Some(v) = val {
-                if patterns.iter().any(|pat| crate::wildmat::wildmat(pat, &v)) {
+            write_simple(&mut ctx.writer, RESP_221_HEADER_FOLLOWS).await?;
+            for (n, v) in values.into_iter().filter_map(|(n, v)| Some((n, v?))) {
+                if matches(&v) {
                     ctx.writer
                         .write_all(format!("{n} {v}\r\n").as_bytes())
                         .await?;
                 }
             }
+            ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+            return Ok(());
         }
 
+        let Some(group) = ctx.state.current_group.clone() else {
+            return handle_article_error(&mut ctx.writer, ArticleQueryError::NoGroup).await;
+        };
+        let Ok(range) = crate::parse::parse_range_bounds(range_or_msgid) else {
+            return write_simple(&mut ctx.writer, RESP_501_SYNTAX).await;
+        };
+        // Open-ended ranges stop at the high water mark
+        let (_, low, high) = ctx.storage.group_counts(&group).await?;
+        let (mut start, end) = range.into_inner();
+        start = start.max(low);
+        let end = end.min(high);
+
+        write_simple(&mut ctx.writer, RESP_221_HEADER_FOLLOWS).await?;
+        while start <= end {
+            let chunk_end = end.min(start.saturating_add(XPAT_CHUNK - 1));
+            let values = ctx
+                .storage
+                .get_header_range(&group, field, start..=chunk_end)
+                .await?;
+            for (n, v) in values {
+                if v.is_empty() {
+                    continue;
+                }
+                let v = crate::overview::sanitize_field(&v);
+                if matches(&v) {
+                    ctx.writer
+                        .write_all(format!("{n} {v}\r\n").as_bytes())
+                        .await?;
+                }
+            }
+            let Some(next) = chunk_end.checked_add(1) else {
+                break;
+            };
+            start = next;
+        }
         ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
         Ok(())
     }
@@ -119,33 +210,98 @@ impl CommandHandler for OverHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        match resolve_articles(
-            &ctx.storage,
-            &mut ctx.state,
-            args.first().map(String::as_str),
-        )
-        .await
-        {
-            Ok(articles) => {
-                ctx.writer.write_all(RESP_224_OVERVIEW.as_bytes()).await?;
-                for (num, article) in articles {
-                    let overview_line = crate::overview::generate_overview_line(
-                        ctx.storage.as_ref(),
-                        num,
-                        &article,
-                    )
-                    .await?;
-                    ctx.writer
-                        .write_all(format!("{overview_line}\r\n").as_bytes())
-                        .await?;
-                }
-                ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+        let arg = args.first().map(String::as_str);
+
+        // Message-ID lookups are not group-relative, so build the line directly
+        if let Some(id) = arg.filter(|a| a.starts_with('<') && a.ends_with('>')) {
+            let access = ctx.config.read().await.access.clone();
+            let Some(article) = ctx
+                .storage
+                .get_article_by_id(id)
+                .await?
+                .filter(|article| access.can_read_article(article, ctx.state.user()))
+            else {
+                return write_simple(&mut ctx.writer, RESP_430_NO_ARTICLE).await;
+            };
+            let mut overview_line =
+                crate::overview::generate_overview_line(ctx.storage.as_ref(), 0, &article).await?;
+            let extra = ctx.config.read().await.overview_extra_headers.clone();
+            for entry in &extra {
+                let name = extra_field_name(entry);
+                let value = get_header_value(&article, name);
+                overview_line.push('\t');
+                overview_line.push_str(&extra_field(name, value.as_deref()));
             }
-            Err(error) => {
-                use super::utils::handle_article_error;
-                handle_article_error(&mut ctx.writer, error).await?;
+            ctx.writer.write_all(RESP_224_OVERVIEW.as_bytes()).await?;
+            ctx.writer
+                .write_all(format!("{overview_line}\r\n").as_bytes())
+                .await?;
+            ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+            return Ok(());
+        }
+
+        let Some(group) = ctx.state.current_group.clone() else {
+            return write_simple(&mut ctx.writer, RESP_412_NO_GROUP).await;
+        };
+
+        let range = match arg {
+            Some(spec) => match crate::parse::parse_range_bounds(spec) {
+                Ok(range) if !range.is_empty() => range,
+                Ok(_) => return write_simple(&mut ctx.writer, RESP_423_RANGE_EMPTY).await,
+                Err(_) => return write_simple(&mut ctx.writer, RESP_501_SYNTAX).await,
+            },
+            None => match ctx.state.current_article {
+                Some(n) => n..=n,
+                None => return write_simple(&mut ctx.writer, RESP_420_NO_CURRENT).await,
+            },
+        };
+
+        // Extra headers are not in the overview table, so read them from
+        // the stored headers up front
+        let extra = ctx.config.read().await.overview_extra_headers.clone();
+        let mut extra_values = Vec::with_capacity(extra.len());
+        for entry in &extra {
+            let name = extra_field_name(entry);
+            let values: HashMap<u64, String> = ctx
+                .storage
+                .get_header_for_range(&group, name, *range.start(), *range.end())
+                .try_filter_map(|(n, v)| std::future::ready(Ok(v.map(|v| (n, v)))))
+                .try_collect()
+                .await?;
+            extra_values.push((name, values));
+        }
+        let render = |line: &crate::overview::OverviewLine| {
+            let mut text = line.to_string();
+            for (name, values) in &extra_values {
+                text.push('\t');
+                text.push_str(&extra_field(
+                    name,
+                    values.get(&line.number).map(String::as_str),
+                ));
             }
+            text.push_str("\r\n");
+            text
+        };
+
+        // Stream a single ranged query against the precomputed overview table
+        let mut lines = ctx
+            .storage
+            .get_overview_range(&group, *range.start(), *range.end());
+        let Some(first) = lines.try_next().await? else {
+            let resp = if arg.is_some() {
+                RESP_423_NO_ARTICLE_NUM
+            } else {
+                RESP_420_NO_CURRENT
+            };
+            return write_simple(&mut ctx.writer, resp).await;
+        };
+
+        ctx.writer.write_all(RESP_224_OVERVIEW.as_bytes()).await?;
+        ctx.writer.write_all(render(&first).as_bytes()).await?;
+        while let Some(line) = lines.try_next().await? {
+            ctx.writer.write_all(render(&line).as_bytes()).await?;
         }
+        ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
         Ok(())
     }
 }
@@ -157,16 +313,17 @@ where
     W: AsyncWrite + Unpin,
 {
     // Use the existing resolve_articles function to handle the complex logic
+    let access = ctx.config.read().await.access.clone();
     let articles = match resolve_articles(
         &ctx.storage,
         &mut ctx.state,
+        &access,
         args.get(1).map(String::as_str),
     )
     .await
     {
         Ok(articles) => articles,
         Err(error) => {
-            use super::utils::handle_article_error;
             handle_article_error(&mut ctx.writer, error).await?;
             return Ok(());
         }
@@ -175,7 +332,7 @@ where
     ctx.writer.write_all(RESP_225_HEADERS.as_bytes()).await?;
     for (n, article) in articles {
         for (name, val) in &article.headers {
-            let sanitized_val = sanitize_header_value(val);
+            let sanitized_val = crate::overview::sanitize_field(val);
             ctx.writer
                 .write_all(format!("{n} {name}: {sanitized_val}\r\n").as_bytes())
                 .await?;
@@ -185,13 +342,6 @@ where
     Ok(())
 }
 
-/// Sanitize header values by removing tabs and line breaks.
-fn sanitize_header_value(val: &str) -> String {
-    let mut v = val.replace('\t', " ");
-    v.retain(|c| c != '\r' && c != '\n');
-    v
-}
-
 /// Extract header value for a field (handles both standard headers and metadata).
 async fn get_field_value(
     storage: &crate::storage::DynStorage,
@@ -209,36 +359,62 @@ async fn get_field_value(
 async fn collect_header_values(
     storage: &crate::storage::DynStorage,
     state: &crate::ConnectionState,
+    access: &AccessRules,
     field: &str,
     range_or_msgid: Option<&str>,
-) -> Result<Vec<(u64, Option<String>)>> {
-    let mut values = Vec::new();
-
-    if let Some(arg) = range_or_msgid {
-        if arg.starts_with('<') && arg.ends_with('>') {
+) -> Result<Vec<(u64, Option<String>)>, ArticleQueryError> {
+    let (group, range) = match range_or_msgid {
+        Some(arg) if arg.starts_with('<') && arg.ends_with('>') => {
             // Message-ID lookup
-            if let Some(article) = storage.get_article_by_id(arg).await? {
-                let val = get_field_value(storage, &article, field).await;
-                values.push((0, val));
-            }
-        } else if let Some(group) = state.current_group.as_deref() {
-            // Range lookup
-            let nums = crate::parse_range(storage, group, arg).await?;
-            for n in nums {
-                if let Some(article) = storage.get_article_by_number(group, n).await? {
-                    let val = get_field_value(storage, &article, field).await;
-                    values.push((n, val));
-                }
-            }
-        }
-    } else if let (Some(group), Some(num)) = (state.current_group.as_deref(), state.current_article)
-    {
-        // Current article lookup
-        if let Some(article) = storage.get_article_by_number(group, num).await? {
+            let article = storage
+                .get_article_by_id(arg)
+                .await
+                .map_err(|_| ArticleQueryError::MessageIdNotFound)?
+                .filter(|article| access.can_read_article(article, state.user()))
+                .ok_or(ArticleQueryError::MessageIdNotFound)?;
             let val = get_field_value(storage, &article, field).await;
-            values.push((num, val));
+            return Ok(vec![(0, val)]);
+        }
+        Some(arg) => {
+            let group = state
+                .current_group
+                .as_deref()
+                .ok_or(ArticleQueryError::NoGroup)?;
+            let range =
+                crate::parse::parse_range_bounds(arg).map_err(|_| ArticleQueryError::RangeEmpty)?;
+            (group, range)
+        }
+        None => {
+            let group = state
+                .current_group
+                .as_deref()
+                .ok_or(ArticleQueryError::NoGroup)?;
+            let num = state
+                .current_article
+                .ok_or(ArticleQueryError::NoCurrentArticle)?;
+            (group, num..=num)
         }
+    };
+
+    // Range lookup reads the overview or stored headers, never the article bodies
+    let values = storage
+        .get_header_range(group, field, range)
+        .await
+        .map_err(|_| ArticleQueryError::RangeEmpty)?;
+
+    if values.is_empty() {
+        return Err(if range_or_msgid.is_some() {
+            ArticleQueryError::RangeEmpty
+        } else {
+            ArticleQueryError::NoCurrentArticle
+        });
     }
 
-    Ok(values)
+    Ok(values
+        .into_iter()
+        .map(|(n, v)| {
+            let value = (!v.is_empty()).then(|| crate::overview::sanitize_field(&v));
+            (n, value)
+        })
+        .collect())
 }
@@ -1,9 +1,12 @@
 //! Authentication and mode command handlers.
 
-use super::utils::write_simple;
+use super::utils::{ReadTimeout, write_and_flush, write_simple};
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::SessionMode;
+use crate::auth::scram::ClientFirst;
 use crate::responses::*;
-use tokio::io::{AsyncBufRead, AsyncWrite};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite};
 
 /// Handler for the AUTHINFO command.
 pub struct AuthInfoHandler;
@@ -45,6 +48,7 @@ impl CommandHandler for AuthInfoHandler {
                     write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await?;
                 }
             }
+            "SASL" => authinfo_sasl(ctx, &args[1..]).await?,
             _ => {
                 write_simple(&mut ctx.writer, RESP_501_SYNTAX).await?;
             }
@@ -53,6 +57,242 @@ impl CommandHandler for AuthInfoHandler {
     }
 }
 
+/// Longest SASL response line accepted, matching the 12288-octet command
+/// line limit of RFC 4643.
+const MAX_SASL_LINE: u64 = 12 * 1024;
+
+/// Outcome of decoding a SASL message from the client.
+enum SaslInput {
+    Data(Vec<u8>),
+    Cancelled,
+    Invalid,
+}
+
+/// Decode a base64 SASL message, where `=` stands for an empty response
+/// and `*` cancels the exchange (RFC 4643).
+fn decode_sasl(text: &str) -> SaslInput {
+    match text {
+        "*" => SaslInput::Cancelled,
+        "=" => SaslInput::Data(Vec::new()),
+        _ => STANDARD
+            .decode(text)
+            .map_or(SaslInput::Invalid, SaslInput::Data),
+    }
+}
+
+/// Send a 383 challenge and read the client's response line.
+async fn sasl_challenge<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    challenge: &[u8],
+) -> anyhow::Result<SaslInput>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let encoded = if challenge.is_empty() {
+        "=".to_string()
+    } else {
+        STANDARD.encode(challenge)
+    };
+    write_and_flush(
+        &mut ctx.writer,
+        &format!("{RESP_383_SASL_CHALLENGE} {encoded}\r\n"),
+    )
+    .await?;
+    let timeout = ctx.state.idle_timeout(&*ctx.config.read().await);
+    let mut line = Vec::new();
+    let n = read_piece(&mut ctx.reader, timeout, &mut line).await?;
+    if n == 0 {
+        return Ok(SaslInput::Cancelled);
+    }
+    if !line.ends_with(b"\n") {
+        // Too long to be a response; drop the rest of it and refuse
+        loop {
+            line.clear();
+            if read_piece(&mut ctx.reader, timeout, &mut line).await? == 0 || line.ends_with(b"\n")
+            {
+                return Ok(SaslInput::Cancelled);
+            }
+        }
+    }
+    match std::str::from_utf8(&line) {
+        Ok(text) => Ok(decode_sasl(text.trim_end_matches(['\r', '\n']))),
+        Err(_) => Ok(SaslInput::Invalid),
+    }
+}
+
+/// Read up to [`MAX_SASL_LINE`] bytes of a line, waiting at most `timeout`.
+async fn read_piece<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    timeout: std::time::Duration,
+    buf: &mut Vec<u8>,
+) -> anyhow::Result<usize> {
+    Ok(tokio::time::timeout(
+        timeout,
+        (&mut *reader).take(MAX_SASL_LINE).read_until(b'\n', buf),
+    )
+    .await
+    .map_err(|_| ReadTimeout)??)
+}
+
+/// Use the initial response if the client sent one, otherwise ask for it
+/// with an empty challenge.
+async fn sasl_first_response<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    initial: Option<&String>,
+) -> anyhow::Result<SaslInput>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    match initial {
+        Some(text) => Ok(decode_sasl(text)),
+        None => sasl_challenge(ctx, &[]).await,
+    }
+}
+
+/// Handle `AUTHINFO SASL <mechanism> [initial-response]`.
+async fn authinfo_sasl<R, W>(ctx: &mut HandlerContext<R, W>, args: &[String]) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if ctx.state.authenticated {
+        return write_simple(&mut ctx.writer, RESP_502_NOT_PERMITTED).await;
+    }
+    let Some(mechanism) = args.first() else {
+        return write_simple(&mut ctx.writer, RESP_501_NOT_ENOUGH).await;
+    };
+
+    match mechanism.to_ascii_uppercase().as_str() {
+        "PLAIN" => {
+            let allowed = ctx.state.is_tls || ctx.config.read().await.allow_sasl_plain_insecure;
+            if !allowed {
+                return write_simple(&mut ctx.writer, RESP_483_SECURE_REQ).await;
+            }
+            let response = match sasl_first_response(ctx, args.get(1)).await? {
+                SaslInput::Data(data) => data,
+                SaslInput::Cancelled => {
+                    return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+                }
+                SaslInput::Invalid => return write_simple(&mut ctx.writer, RESP_504_BASE64).await,
+            };
+            sasl_plain(ctx, &response).await
+        }
+        "SCRAM-SHA-256" => sasl_scram(ctx, args.get(1)).await,
+        "EXTERNAL" => sasl_external(ctx, args.get(1)).await,
+        _ => write_simple(&mut ctx.writer, RESP_503_SASL_MECHANISM).await,
+    }
+}
+
+/// Verify a PLAIN response of the form `authzid NUL authcid NUL password`.
+async fn sasl_plain<R, W>(ctx: &mut HandlerContext<R, W>, response: &[u8]) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let fields: Vec<&str> = match std::str::from_utf8(response) {
+        Ok(text) => text.split('\0').collect(),
+        Err(_) => Vec::new(),
+    };
+    let [authzid, authcid, password] = fields.as_slice() else {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    };
+    // Acting on behalf of another user is not supported
+    if !authzid.is_empty() && authzid != authcid {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    }
+    if ctx.auth.verify_user(authcid, password).await? {
+        ctx.state.username = Some((*authcid).to_string());
+        ctx.state.authenticated = true;
+        write_simple(&mut ctx.writer, RESP_281_AUTH_OK).await
+    } else {
+        write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await
+    }
+}
+
+/// Authenticate as the user a `[[client_cert]]` rule maps the TLS client
+/// certificate to. The client may send an authorization identity, which must
+/// match that user.
+async fn sasl_external<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    initial: Option<&String>,
+) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mapped = match ctx.state.client_cert.as_ref() {
+        Some(cert) => ctx.config.read().await.user_for_certificate(cert),
+        None => None,
+    };
+    let identity = match mapped {
+        Some(user) if ctx.auth.user_exists(&user).await? => user,
+        _ => return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await,
+    };
+    let authzid = match sasl_first_response(ctx, initial).await? {
+        SaslInput::Data(data) => data,
+        SaslInput::Cancelled => return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await,
+        SaslInput::Invalid => return write_simple(&mut ctx.writer, RESP_504_BASE64).await,
+    };
+    if !authzid.is_empty() && authzid != identity.as_bytes() {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    }
+    ctx.state.username = Some(identity);
+    ctx.state.authenticated = true;
+    write_simple(&mut ctx.writer, RESP_281_AUTH_OK).await
+}
+
+/// Run a SCRAM-SHA-256 exchange against the stored verifier.
+async fn sasl_scram<R, W>(ctx: &mut HandlerContext<R, W>, initial: Option<&String>) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let client_first = match sasl_first_response(ctx, initial).await? {
+        SaslInput::Data(data) => data,
+        SaslInput::Cancelled => return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await,
+        SaslInput::Invalid => return write_simple(&mut ctx.writer, RESP_504_BASE64).await,
+    };
+    let Some(first) = std::str::from_utf8(&client_first)
+        .ok()
+        .and_then(|text| ClientFirst::parse(text).ok())
+    else {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    };
+    if first.authzid.as_ref().is_some_and(|a| *a != first.username) {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    }
+    let Some(credentials) = ctx.auth.get_scram_credentials(&first.username).await? else {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    };
+
+    let username = first.username.clone();
+    let (server, server_first) = first.respond(credentials);
+    let client_final = match sasl_challenge(ctx, server_first.as_bytes()).await? {
+        SaslInput::Data(data) => data,
+        SaslInput::Cancelled => return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await,
+        SaslInput::Invalid => return write_simple(&mut ctx.writer, RESP_504_BASE64).await,
+    };
+    let verified = std::str::from_utf8(&client_final)
+        .ok()
+        .and_then(|text| server.finish(text).ok());
+    let Some(server_final) = verified else {
+        return write_simple(&mut ctx.writer, RESP_481_AUTH_REJECTED).await;
+    };
+
+    ctx.state.username = Some(username);
+    ctx.state.authenticated = true;
+    write_simple(
+        &mut ctx.writer,
+        &format!(
+            "{RESP_283_SASL_SUCCESS} {}\r\n",
+            STANDARD.encode(server_final)
+        ),
+    )
+    .await
+}
+
 /// Handler for the MODE command.
 pub struct ModeHandler;
 
@@ -68,8 +308,16 @@ impl CommandHandler for ModeHandler {
         }
 
         match args[0].to_ascii_uppercase().as_str() {
+            "READER" if ctx.state.mode == SessionMode::Transit => {
+                write_simple(&mut ctx.writer, RESP_502_WRONG_MODE).await?;
+            }
+            "STREAM" if ctx.state.mode == SessionMode::Reader => {
+                write_simple(&mut ctx.writer, RESP_502_WRONG_MODE).await?;
+            }
             "READER" => {
-                if ctx.state.is_tls || ctx.state.allow_posting_insecure {
+                ctx.state.mode = SessionMode::Reader;
+                let may_post = ctx.state.may_post(&*ctx.config.read().await);
+                if may_post {
                     write_simple(&mut ctx.writer, RESP_200_POSTING_ALLOWED).await?;
                 } else {
                     write_simple(&mut ctx.writer, RESP_201_POSTING_PROHIBITED).await?;
@@ -1,11 +1,16 @@
 //! Group and listing command handlers.
 
-use super::utils::{write_lines, write_simple};
+use super::utils::{read_denied, write_simple};
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::config::{AccessRules, Config};
 use crate::responses::*;
-use crate::{parse_datetime, wildmat};
-use futures_util::{StreamExt, TryStreamExt};
+use crate::storage::{Direction, DynStorage};
+use crate::{ConnectionState, parse_datetime, wildmat};
+use anyhow::Result;
+use futures_util::StreamExt;
+use std::collections::HashSet;
 use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::RwLock;
 
 /// Handler for the GROUP command.
 pub struct GroupHandler;
@@ -17,20 +22,25 @@ impl CommandHandler for GroupHandler {
         W: AsyncWrite + Unpin,
     {
         if let Some(group_name) = args.first() {
+            let readable = ctx
+                .config
+                .read()
+                .await
+                .access
+                .can_read_group(group_name, ctx.state.user());
+            if !readable {
+                return write_simple(&mut ctx.writer, read_denied(&ctx.state)).await;
+            }
             // Check if the group exists using the storage interface
             if !ctx.storage.group_exists(group_name).await? {
                 write_simple(&mut ctx.writer, RESP_411_NO_SUCH_GROUP).await?;
                 return Ok(());
             }
 
-            let stream = ctx.storage.list_article_numbers(group_name);
-            let nums = stream.try_collect::<Vec<u64>>().await?;
-            let count = nums.len();
-            let high = nums.last().copied().unwrap_or(0);
-            let low = nums.first().copied().unwrap_or(0);
+            let (count, low, high) = ctx.storage.group_counts(group_name).await?;
 
             ctx.state.current_group = Some(group_name.clone());
-            ctx.state.current_article = nums.first().copied();
+            ctx.state.current_article = (count > 0).then_some(low);
 
             write_simple(
                 &mut ctx.writer,
@@ -56,22 +66,28 @@ impl CommandHandler for ListHandler {
         if let Some(keyword) = args.first() {
             match keyword.as_str() {
                 "ACTIVE" => {
-                    handle_list_active(ctx, args.get(1)).await?;
+                    handle_list_active(ctx, args.get(1), false).await?;
+                }
+                "COUNTS" => {
+                    handle_list_active(ctx, args.get(1), true).await?;
                 }
                 "NEWSGROUPS" => {
-                    handle_list_newsgroups(ctx).await?;
+                    handle_list_newsgroups(ctx, args.get(1)).await?;
                 }
                 "ACTIVE.TIMES" => {
-                    handle_list_active_times(ctx).await?;
+                    handle_list_active_times(ctx, args.get(1)).await?;
                 }
                 "OVERVIEW.FMT" => {
                     handle_list_overview_fmt(ctx).await?;
                 }
                 "HEADERS" => {
-                    handle_list_headers(ctx).await?;
+                    handle_list_headers(ctx, args.get(1)).await?;
+                }
+                "DISTRIBUTIONS" => {
+                    handle_list_distributions(ctx).await?;
                 }
                 "DISTRIB.PATS" => {
-                    write_simple(&mut ctx.writer, RESP_503_NOT_SUPPORTED).await?;
+                    handle_list_distrib_pats(ctx).await?;
                 }
                 _ => {
                     write_simple(&mut ctx.writer, RESP_501_UNKNOWN_KEYWORD).await?;
@@ -79,7 +95,7 @@ impl CommandHandler for ListHandler {
             }
         } else {
             // Default LIST without keyword behaves like LIST ACTIVE
-            handle_list_active(ctx, None).await?;
+            handle_list_active(ctx, None, false).await?;
         }
         Ok(())
     }
@@ -94,23 +110,61 @@ impl CommandHandler for ListGroupHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        let group_name = if let Some(name) = args.first() {
-            name.clone()
+        let (group_name, range_arg) = if let Some(name) = args.first() {
+            (name.clone(), args.get(1))
         } else if let Some(ref current) = ctx.state.current_group {
-            current.clone()
+            (current.clone(), None)
         } else {
             write_simple(&mut ctx.writer, RESP_412_NO_GROUP).await?;
             return Ok(());
         };
 
-        write_simple(&mut ctx.writer, RESP_211_LISTGROUP).await?;
-        let mut stream = ctx.storage.list_article_numbers(&group_name);
+        let readable = ctx
+            .config
+            .read()
+            .await
+            .access
+            .can_read_group(&group_name, ctx.state.user());
+        if !readable {
+            return write_simple(&mut ctx.writer, read_denied(&ctx.state)).await;
+        }
+        if !ctx.storage.group_exists(&group_name).await? {
+            write_simple(&mut ctx.writer, RESP_411_NO_SUCH_GROUP).await?;
+            return Ok(());
+        }
+
+        let range = match range_arg {
+            Some(spec) => match crate::parse::parse_range_bounds(spec) {
+                Ok(range) => range,
+                Err(_) => {
+                    write_simple(&mut ctx.writer, RESP_501_SYNTAX).await?;
+                    return Ok(());
+                }
+            },
+            None => 0..=u64::MAX,
+        };
+
+        let (count, low, high) = ctx.storage.group_counts(&group_name).await?;
+        write_simple(
+            &mut ctx.writer,
+            &format!("211 {count} {low} {high} {group_name} list follows\r\n"),
+        )
+        .await?;
+
+        let mut first = None;
+        let mut stream =
+            ctx.storage
+                .list_article_numbers_in_range(&group_name, *range.start(), *range.end());
         while let Some(result) = stream.next().await {
             let num = result?;
+            first.get_or_insert(num);
             ctx.writer.write_all(num.to_string().as_bytes()).await?;
             ctx.writer.write_all(b"\r\n").await?;
         }
         ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+
+        ctx.state.current_group = Some(group_name);
+        ctx.state.current_article = first.or((count > 0).then_some(low));
         Ok(())
     }
 }
@@ -124,7 +178,7 @@ impl CommandHandler for NextHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        navigate_article(ctx, NavigationDirection::Next).await
+        navigate_article(ctx, Direction::Next).await
     }
 }
 
@@ -137,7 +191,7 @@ impl CommandHandler for LastHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        navigate_article(ctx, NavigationDirection::Previous).await
+        navigate_article(ctx, Direction::Previous).await
     }
 }
 
@@ -173,11 +227,18 @@ impl CommandHandler for NewGroupsHandler {
         };
 
         write_simple(&mut ctx.writer, RESP_231_NEWGROUPS).await?;
+        let (access, posting) = posting_access(&ctx.config, &ctx.state).await;
         let mut stream = ctx.storage.list_groups_since(since);
         while let Some(result) = stream.next().await {
             let group = result?;
-            ctx.writer.write_all(group.as_bytes()).await?;
-            ctx.writer.write_all(b"\r\n").await?;
+            if !access.can_read_group(&group, ctx.state.user()) {
+                continue;
+            }
+            let may_post = posting
+                .as_ref()
+                .is_some_and(|rules| rules.can_post_group(&group, ctx.state.user()));
+            let line = active_line(&ctx.storage, &group, false, may_post).await?;
+            ctx.writer.write_all(line.as_bytes()).await?;
         }
         ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
         Ok(())
@@ -217,13 +278,21 @@ impl CommandHandler for NewNewsHandler {
         };
 
         write_simple(&mut ctx.writer, RESP_230_NEWNEWS).await?;
+        // Crossposted articles show up once per group; report each only once
+        let mut seen = HashSet::new();
+        let access = ctx.config.read().await.access.clone();
         let mut groups_stream = ctx.storage.list_groups();
         while let Some(result) = groups_stream.next().await {
             let group = result?;
-            if wildmat::wildmat(&group, wildmat_pattern) {
+            if wildmat::wildmat_list(wildmat_pattern, &group)
+                && access.can_read_group(&group, ctx.state.user())
+            {
                 let mut articles_stream = ctx.storage.list_article_ids_since(&group, since);
                 while let Some(article_result) = articles_stream.next().await {
                     let article_id = article_result?;
+                    if !seen.insert(article_id.clone()) {
+                        continue;
+                    }
                     ctx.writer.write_all(article_id.as_bytes()).await?;
                     ctx.writer.write_all(b"\r\n").await?;
                 }
@@ -237,79 +306,129 @@ impl CommandHandler for NewNewsHandler {
 
 // Helper functions for LIST subcommands
 
+/// Write `LIST ACTIVE`, or with `with_count` the `LIST COUNTS` variant that
+/// adds the number of articles after the low water mark as INN does.
 async fn handle_list_active<R, W>(
     ctx: &mut HandlerContext<R, W>,
     pattern: Option<&String>,
+    with_count: bool,
 ) -> HandlerResult
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
     write_simple(&mut ctx.writer, RESP_215_LIST_FOLLOWS).await?;
+    let (access, posting) = posting_access(&ctx.config, &ctx.state).await;
     let mut groups_stream = ctx.storage.list_groups();
     while let Some(result) = groups_stream.next().await {
         let group = result?;
-        if let Some(pat) = pattern {
-            if !wildmat::wildmat(&group, pat) {
-                continue;
-            }
+        if pattern.is_some_and(|pat| !wildmat::wildmat_list(pat, &group))
+            || !access.can_read_group(&group, ctx.state.user())
+        {
+            continue;
         }
 
-        let mut nums_stream = ctx.storage.list_article_numbers(&group);
-        let mut low = None;
-        let mut high = None;
-
-        while let Some(result) = nums_stream.next().await {
-            let num = result?;
-            if low.is_none() {
-                low = Some(num);
-            }
-            high = Some(num);
-        }
-
-        let low = low.unwrap_or(0);
-        let high = high.unwrap_or(0);
-
-        ctx.writer.write_all(group.as_bytes()).await?;
-        ctx.writer.write_all(b" ").await?;
-        ctx.writer.write_all(high.to_string().as_bytes()).await?;
-        ctx.writer.write_all(b" ").await?;
-        ctx.writer.write_all(low.to_string().as_bytes()).await?;
-        ctx.writer.write_all(b" y\r\n").await?;
+        let may_post = posting
+            .as_ref()
+            .is_some_and(|rules| rules.can_post_group(&group, ctx.state.user()));
+        let line = active_line(&ctx.storage, &group, with_count, may_post).await?;
+        ctx.writer.write_all(line.as_bytes()).await?;
     }
 
     ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
     Ok(())
 }
 
-async fn handle_list_newsgroups<R, W>(ctx: &mut HandlerContext<R, W>) -> HandlerResult
+/// The rules deciding which groups a listing shows, and the access rules
+/// for posting, or `None` when this session may not post at all.
+async fn posting_access(
+    config: &RwLock<Config>,
+    state: &ConnectionState,
+) -> (AccessRules, Option<AccessRules>) {
+    let cfg = config.read().await;
+    let posting = state.may_post(&cfg).then(|| cfg.access.clone());
+    (cfg.listing_access(), posting)
+}
+
+/// One line of the active file for `group`: name, high and low water marks
+/// and posting status, with the article count before the status for
+/// `LIST COUNTS`. The status is `n` when `may_post` is false, as for a
+/// session that cannot post or a group its access rules close to posting.
+async fn active_line(
+    storage: &DynStorage,
+    group: &str,
+    with_count: bool,
+    may_post: bool,
+) -> Result<String> {
+    let (count, low, high) = storage.group_counts(group).await?;
+    let status = if !may_post {
+        'n'
+    } else if storage.is_group_moderated(group).await? {
+        'm'
+    } else {
+        'y'
+    };
+    Ok(if with_count {
+        format!("{group} {high} {low} {count} {status}\r\n")
+    } else {
+        format!("{group} {high} {low} {status}\r\n")
+    })
+}
+
+async fn handle_list_newsgroups<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    pattern: Option<&String>,
+) -> HandlerResult
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
     write_simple(&mut ctx.writer, RESP_215_DESCRIPTIONS).await?;
-    let mut groups_stream = ctx.storage.list_groups();
-    while let Some(result) = groups_stream.next().await {
-        let group = result?;
+    let access = ctx.config.read().await.listing_access();
+    let mut descriptions = ctx
+        .storage
+        .list_group_descriptions(pattern.map(String::as_str));
+    while let Some(result) = descriptions.next().await {
+        let (group, description) = result?;
+        if !access.can_read_group(&group, ctx.state.user()) {
+            continue;
+        }
         ctx.writer
-            .write_all(format!("{group} \r\n").as_bytes())
+            .write_all(format!("{group}\t{description}\r\n").as_bytes())
             .await?;
     }
     ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
     Ok(())
 }
 
-async fn handle_list_active_times<R, W>(ctx: &mut HandlerContext<R, W>) -> HandlerResult
+async fn handle_list_active_times<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    pattern: Option<&String>,
+) -> HandlerResult
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
+    let (access, default_creator) = {
+        let cfg = ctx.config.read().await;
+        (cfg.listing_access(), cfg.effective_admin_address())
+    };
     write_simple(&mut ctx.writer, RESP_215_INFO_FOLLOWS).await?;
     let mut stream = ctx.storage.list_groups_with_times();
     while let Some(result) = stream.next().await {
         let (group, time) = result?;
+        if pattern.is_some_and(|pat| !wildmat::wildmat_list(pat, &group))
+            || !access.can_read_group(&group, ctx.state.user())
+        {
+            continue;
+        }
+        let creator = ctx
+            .storage
+            .get_group_creator(&group)
+            .await?
+            .unwrap_or_else(|| default_creator.clone());
         ctx.writer
-            .write_all(format!("{group} {time} -\r\n").as_bytes())
+            .write_all(format!("{group} {time} {creator}\r\n").as_bytes())
             .await?;
     }
 
@@ -328,7 +447,8 @@ where
         .write_all(RESP_215_OVERVIEW_FMT.as_bytes())
         .await?;
 
-    let format_lines = get_overview_format_lines();
+    let extra = ctx.config.read().await.overview_extra_headers.clone();
+    let format_lines = get_overview_format_lines(&extra);
     for line in format_lines {
         ctx.writer.write_all(line.as_bytes()).await?;
     }
@@ -337,89 +457,114 @@ where
     Ok(())
 }
 
-async fn handle_list_headers<R, W>(ctx: &mut HandlerContext<R, W>) -> HandlerResult
+async fn handle_list_headers<R, W>(
+    ctx: &mut HandlerContext<R, W>,
+    variant: Option<&String>,
+) -> HandlerResult
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
-    write_lines(
-        &mut ctx.writer,
-        &[
-            RESP_215_METADATA,
-            RESP_COLON,
-            RESP_LINES,
-            RESP_BYTES,
-            RESP_DOT_CRLF,
-        ],
-    )
-    .await
+    use crate::overview::get_header_list_lines;
+
+    // HDR serves the same fields for message-ids and ranges
+    if variant.is_some_and(|v| !v.eq_ignore_ascii_case("MSGID") && !v.eq_ignore_ascii_case("RANGE"))
+    {
+        return write_simple(&mut ctx.writer, RESP_501_SYNTAX).await;
+    }
+
+    ctx.writer.write_all(RESP_215_METADATA.as_bytes()).await?;
+    for line in get_header_list_lines() {
+        ctx.writer.write_all(line.as_bytes()).await?;
+    }
+    ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+    Ok(())
+}
+
+/// LIST DISTRIBUTIONS, answered with 503 when no distributions are
+/// configured as RFC 6048 allows.
+async fn handle_list_distributions<R, W>(ctx: &mut HandlerContext<R, W>) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let keywords = ctx.config.read().await.distributions.keywords.clone();
+    if keywords.is_empty() {
+        return write_simple(&mut ctx.writer, RESP_503_NOT_SUPPORTED).await;
+    }
+    ctx.writer
+        .write_all(RESP_215_DISTRIBUTIONS.as_bytes())
+        .await?;
+    for keyword in keywords {
+        ctx.writer
+            .write_all(format!("{}\t{}\r\n", keyword.name, keyword.description).as_bytes())
+            .await?;
+    }
+    ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+    Ok(())
+}
+
+/// LIST DISTRIB.PATS, one `weight:wildmat:distribution` line per rule.
+async fn handle_list_distrib_pats<R, W>(ctx: &mut HandlerContext<R, W>) -> HandlerResult
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let patterns = ctx.config.read().await.distributions.patterns.clone();
+    if patterns.is_empty() {
+        return write_simple(&mut ctx.writer, RESP_503_NOT_SUPPORTED).await;
+    }
+    ctx.writer
+        .write_all(RESP_215_DISTRIB_PATS.as_bytes())
+        .await?;
+    for rule in patterns {
+        ctx.writer
+            .write_all(
+                format!("{}:{}:{}\r\n", rule.weight, rule.groups, rule.distribution).as_bytes(),
+            )
+            .await?;
+    }
+    ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
+    Ok(())
 }
 
 /// Navigate to the next or previous article in the current group.
 async fn navigate_article<R, W>(
     ctx: &mut HandlerContext<R, W>,
-    direction: NavigationDirection,
+    direction: Direction,
 ) -> HandlerResult
 where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
-    let (group, current) = match (&ctx.state.current_group, ctx.state.current_article) {
-        (Some(group), Some(current)) => (group.as_str(), current),
-        _ => {
-            write_simple(&mut ctx.writer, RESP_412_NO_GROUP).await?;
-            return Ok(());
-        }
+    let Some(group) = ctx.state.current_group.clone() else {
+        return write_simple(&mut ctx.writer, RESP_412_NO_GROUP).await;
     };
-
-    let stream = ctx.storage.list_article_numbers(group);
-    let nums = stream.try_collect::<Vec<u64>>().await?;
-    let pos = match nums.iter().position(|&n| n == current) {
-        Some(pos) => pos,
-        None => {
-            let response = match direction {
-                NavigationDirection::Next => RESP_421_NO_NEXT,
-                NavigationDirection::Previous => RESP_422_NO_PREV,
-            };
-            write_simple(&mut ctx.writer, response).await?;
-            return Ok(());
-        }
+    let Some(current) = ctx.state.current_article else {
+        return write_simple(&mut ctx.writer, RESP_420_NO_CURRENT).await;
     };
-
-    let new_pos = match direction {
-        NavigationDirection::Next => pos + 1,
-        NavigationDirection::Previous => pos.saturating_sub(1),
+    let boundary = match direction {
+        Direction::Next => RESP_421_NO_NEXT,
+        Direction::Previous => RESP_422_NO_PREV,
     };
 
-    if let Some(&new_num) = nums.get(new_pos) {
-        if let Some(article) = ctx.storage.get_article_by_number(group, new_num).await? {
-            ctx.state.current_article = Some(new_num);
+    let mut from = current;
+    // Step over numbers whose article vanished between the lookups
+    while let Some(number) = ctx
+        .storage
+        .next_article_number(&group, from, direction)
+        .await?
+    {
+        if let Some(article) = ctx.storage.get_article_by_number(&group, number).await? {
+            ctx.state.current_article = Some(number);
             let id = super::utils::extract_message_id(&article).unwrap_or_default();
-            write_simple(
+            return write_simple(
                 &mut ctx.writer,
-                &format!("223 {new_num} {id} article exists\r\n"),
+                &format!("223 {number} {id} article exists\r\n"),
             )
-            .await?;
-        } else {
-            let response = match direction {
-                NavigationDirection::Next => RESP_421_NO_NEXT,
-                NavigationDirection::Previous => RESP_422_NO_PREV,
-            };
-            write_simple(&mut ctx.writer, response).await?;
+            .await;
         }
-    } else {
-        let response = match direction {
-            NavigationDirection::Next => RESP_421_NO_NEXT,
-            NavigationDirection::Previous => RESP_422_NO_PREV,
-        };
-        write_simple(&mut ctx.writer, response).await?;
+        from = number;
     }
-
-    Ok(())
-}
-
-#[derive(Debug, Clone, Copy)]
-enum NavigationDirection {
-    Next,
-    Previous,
+    write_simple(&mut ctx.writer, boundary).await
 }
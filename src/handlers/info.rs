@@ -1,8 +1,11 @@
-//! Information command handlers (DATE, HELP, CAPABILITIES, QUIT).
+//! Information command handlers (DATE, HELP, CAPABILITIES, XBACKEND, QUIT).
 
-use super::utils::write_simple;
+use super::utils::{write_lines, write_simple};
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::config::Config;
 use crate::responses::*;
+use crate::{ConnectionState, SessionMode};
+use std::borrow::Cow;
 use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
 
 /// Handler for the DATE command.
@@ -48,27 +51,125 @@ impl CommandHandler for CapabilitiesHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
-        ctx.writer
-            .write_all(RESP_101_CAPABILITIES.as_bytes())
-            .await?;
-        ctx.writer.write_all(RESP_CAP_VERSION.as_bytes()).await?;
-        ctx.writer
-            .write_all(RESP_CAP_IMPLEMENTATION.as_bytes())
-            .await?;
-        ctx.writer.write_all(RESP_CAP_READER.as_bytes()).await?;
+        let lines = {
+            let cfg = ctx.config.read().await;
+            capability_lines(&ctx.state, &cfg)
+        };
+        let lines: Vec<&str> = lines.iter().map(AsRef::as_ref).collect();
+        write_lines(&mut ctx.writer, &lines).await
+    }
+}
 
-        if ctx.state.is_tls {
-            ctx.writer.write_all(RESP_CAP_POST.as_bytes()).await?;
+/// Build the CAPABILITIES response for the current session.
+///
+/// The list follows the session state: POST only appears when this
+/// connection may post and the access rules let the user post somewhere,
+/// reader sessions drop MODE-READER, IHAVE and STREAMING, transit sessions
+/// offer only IHAVE and STREAMING, and the AUTHINFO and SASL lines disappear
+/// after authentication. LIST names DISTRIBUTIONS and DISTRIB.PATS only when
+/// they are configured. STARTTLS is never offered, since TLS is served on its
+/// own listener.
+fn capability_lines(state: &ConnectionState, cfg: &Config) -> Vec<Cow<'static, str>> {
+    let reader = state.mode != SessionMode::Transit;
+    let transit = state.mode != SessionMode::Reader;
+    let mut lines: Vec<Cow<'static, str>> = vec![
+        RESP_101_CAPABILITIES.into(),
+        RESP_CAP_VERSION.into(),
+        RESP_CAP_IMPLEMENTATION.into(),
+    ];
+    if reader {
+        lines.push(RESP_CAP_READER.into());
+    }
+    if state.mode == SessionMode::Mixed {
+        lines.push(RESP_CAP_MODE_READER.into());
+    }
+    if state.may_post(cfg) && cfg.access.can_post_somewhere(state.user()) {
+        lines.push(RESP_CAP_POST.into());
+    }
+    if reader {
+        lines.push(RESP_CAP_NEWNEWS.into());
+    }
+    if transit {
+        lines.extend([RESP_CAP_IHAVE.into(), RESP_CAP_STREAMING.into()]);
+    }
+    if !state.compressed {
+        lines.push(RESP_CAP_COMPRESS.into());
+    }
+    if reader {
+        lines.extend([
+            RESP_CAP_OVER.into(),
+            RESP_CAP_HDR.into(),
+            list_capability(cfg),
+        ]);
+    }
+    if !state.authenticated {
+        lines.push(RESP_CAP_AUTHINFO.into());
+        lines.push(
+            if state
+                .client_cert
+                .as_ref()
+                .is_some_and(|cert| cfg.user_for_certificate(cert).is_some())
+            {
+                RESP_CAP_SASL_WITH_EXTERNAL
+            } else if state.is_tls || cfg.allow_sasl_plain_insecure {
+                RESP_CAP_SASL_WITH_PLAIN
+            } else {
+                RESP_CAP_SASL
+            }
+            .into(),
+        );
+    }
+    lines.push(RESP_DOT_CRLF.into());
+    lines
+}
+
+/// The LIST capability line, naming the distribution lists when they have
+/// entries to return.
+fn list_capability(cfg: &Config) -> Cow<'static, str> {
+    let distributions = &cfg.distributions;
+    if distributions.keywords.is_empty() && distributions.patterns.is_empty() {
+        return RESP_CAP_LIST.into();
+    }
+    let mut line = RESP_CAP_LIST.trim_end().to_string();
+    if !distributions.keywords.is_empty() {
+        line.push_str(" DISTRIBUTIONS");
+    }
+    if !distributions.patterns.is_empty() {
+        line.push_str(" DISTRIB.PATS");
+    }
+    line.push_str("\r\n");
+    line.into()
+}
+
+/// Handler for the XBACKEND command, which reports the active storage
+/// backend and its schema version to administrators.
+pub struct BackendHandler;
+
+impl CommandHandler for BackendHandler {
+    async fn handle<R, W>(ctx: &mut HandlerContext<R, W>, _args: &[String]) -> HandlerResult
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let Some(username) = ctx
+            .state
+            .username
+            .as_deref()
+            .filter(|_| ctx.state.authenticated)
+        else {
+            return write_simple(&mut ctx.writer, RESP_480_AUTH_REQUIRED).await;
+        };
+        if !ctx.auth.is_admin(username).await? {
+            return write_simple(&mut ctx.writer, RESP_502_NOT_PERMITTED).await;
         }
 
-        ctx.writer.write_all(RESP_CAP_NEWNEWS.as_bytes()).await?;
-        ctx.writer.write_all(RESP_CAP_IHAVE.as_bytes()).await?;
-        ctx.writer.write_all(RESP_CAP_STREAMING.as_bytes()).await?;
-        ctx.writer.write_all(RESP_CAP_OVER.as_bytes()).await?;
-        ctx.writer.write_all(RESP_CAP_HDR.as_bytes()).await?;
-        ctx.writer.write_all(RESP_CAP_LIST.as_bytes()).await?;
-        ctx.writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
-        Ok(())
+        let name = ctx.storage.backend_name();
+        let version = ctx.storage.schema_version().await?;
+        write_simple(
+            &mut ctx.writer,
+            &format!("{RESP_290_BACKEND} {name} schema {version}\r\n"),
+        )
+        .await
     }
 }
 
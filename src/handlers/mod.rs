@@ -49,7 +49,12 @@ where
     R: AsyncBufRead + Unpin,
     W: AsyncWrite + Unpin,
 {
-    match cmd.name.to_ascii_uppercase().as_str() {
+    let name = cmd.name.to_ascii_uppercase();
+    if !ctx.state.mode.allows(&name) {
+        return utils::write_simple(&mut ctx.writer, crate::responses::RESP_502_WRONG_MODE).await;
+    }
+
+    let result = match name.as_str() {
         // Article retrieval commands
         "ARTICLE" => article::ArticleHandler::handle(ctx, &cmd.args).await,
         "HEAD" => article::HeadHandler::handle(ctx, &cmd.args).await,
@@ -67,6 +72,7 @@ where
 
         // Header and metadata commands
         "HDR" => article::HdrHandler::handle(ctx, &cmd.args).await,
+        "XHDR" => article::XHdrHandler::handle(ctx, &cmd.args).await,
         "XPAT" => article::XPatHandler::handle(ctx, &cmd.args).await,
         "OVER" => article::OverHandler::handle(ctx, &cmd.args).await,
         "XOVER" => article::OverHandler::handle(ctx, &cmd.args).await,
@@ -85,16 +91,20 @@ where
         "CAPABILITIES" => info::CapabilitiesHandler::handle(ctx, &cmd.args).await,
         "DATE" => info::DateHandler::handle(ctx, &cmd.args).await,
         "HELP" => info::HelpHandler::handle(ctx, &cmd.args).await,
+        "XBACKEND" => info::BackendHandler::handle(ctx, &cmd.args).await,
         "QUIT" => info::QuitHandler::handle(ctx, &cmd.args).await,
 
         // Unknown command
         _ => {
             use crate::responses::RESP_500_UNKNOWN_CMD;
             use tokio::io::AsyncWriteExt;
+            crate::metrics::global().command("unknown");
             ctx.writer
                 .write_all(RESP_500_UNKNOWN_CMD.as_bytes())
                 .await?;
-            Ok(())
+            return Ok(());
         }
-    }
+    };
+    crate::metrics::global().command(&name);
+    result
 }
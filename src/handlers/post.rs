@@ -1,13 +1,143 @@
 //! Posting command handlers.
 
-use super::utils::{comprehensive_validate_article, read_message, write_simple};
+use super::utils::{
+    ArticleTooLarge, comprehensive_validate_article, extract_newsgroups, get_header_values,
+    rate_limit_keys, read_message, validate_for_moderation, validate_submission, write_and_flush,
+    write_simple,
+};
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::config::{
+    Config, DistributionsConfig, InjectionConfig, MessageIdGeneration, PostDurability, PostingHost,
+};
+use crate::filters::{Rejection, moderation};
 use crate::prelude::*;
-use crate::queue::QueuedArticle;
+use crate::queue::{Outcome, QueueFull, QueuedArticle};
 use crate::responses::*;
-use crate::{control, ensure_message_id, parse, parse_message};
+use crate::{Message, control, ensure_message_id, parse, parse_message};
+use rand::RngCore;
+use std::net::IpAddr;
+use std::sync::OnceLock;
 use tokio::io::{AsyncBufRead, AsyncWrite};
 
+/// Headers only an injecting agent adds; relays pass them on unchanged.
+const INJECTION_HEADERS: [&str; 2] = ["Injection-Date", "Injection-Info"];
+
+/// Key for hashed Injection-Info values when no secret is configured.
+fn startup_secret() -> &'static [u8] {
+    static SECRET: OnceLock<[u8; 32]> = OnceLock::new();
+    SECRET.get_or_init(|| {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        key
+    })
+}
+
+/// A keyed hash of `value` that can be matched against logs but not reversed.
+fn obscure(value: &str, cfg: &InjectionConfig) -> String {
+    let key = cfg
+        .secret
+        .as_deref()
+        .map_or(startup_secret(), str::as_bytes);
+    crate::auth::scram::hmac(key, value.as_bytes())
+        .iter()
+        .take(12)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Add the Injection-Date and Injection-Info headers of RFC 5537 section 3.5.
+fn add_injection_headers(
+    message: &mut Message,
+    cfg: &Config,
+    peer_ip: Option<IpAddr>,
+    user: Option<&str>,
+) {
+    let reveal = |value: &str| match cfg.injection.posting_host {
+        PostingHost::Hashed => Some(obscure(value, &cfg.injection)),
+        PostingHost::Plain => Some(value.to_string()),
+        PostingHost::Omit => None,
+    };
+    let mut info = cfg.site_name.clone();
+    if let Some(host) = peer_ip.and_then(|ip| reveal(&ip.to_string())) {
+        info.push_str(&format!("; posting-host=\"{host}\""));
+    }
+    if let Some(data) = user.and_then(reveal) {
+        info.push_str(&format!("; logging-data=\"{data}\""));
+    }
+    message
+        .headers
+        .push(("Injection-Date".into(), chrono::Utc::now().to_rfc2822()));
+    message.headers.push(("Injection-Info".into(), info));
+}
+
+/// Give `message` a Message-ID if it has none, as `message_id_generation`
+/// says. Returns the id when a random one was assigned, or an error when
+/// posts without one are refused.
+async fn supply_message_id(
+    storage: &crate::storage::DynStorage,
+    cfg: &Config,
+    message: &mut Message,
+) -> Result<Option<String>> {
+    if !get_header_values(message, "Message-ID").is_empty() {
+        return Ok(None);
+    }
+    match cfg.message_id_generation {
+        MessageIdGeneration::Hash => {
+            ensure_message_id(message, &cfg.site_name);
+            Ok(None)
+        }
+        MessageIdGeneration::Random => {
+            // A clash needs the same millisecond and 64 random bits, but
+            // make sure rather than overwrite a stored article
+            for _ in 0..3 {
+                let id = parse::generate_message_id(&cfg.site_name);
+                if !storage.article_exists(&id).await? {
+                    message.headers.push(("Message-ID".into(), id.clone()));
+                    return Ok(Some(id));
+                }
+            }
+            Err(anyhow::anyhow!("could not generate an unused Message-ID"))
+        }
+        MessageIdGeneration::Refuse => Err(anyhow::anyhow!("article has no Message-ID")),
+    }
+}
+
+/// The first keyword in the Distribution header of `message` that is not
+/// configured, when `[distributions]` is strict.
+fn unknown_distribution(message: &Message, distributions: &DistributionsConfig) -> Option<String> {
+    if !distributions.strict {
+        return None;
+    }
+    get_header_values(message, "Distribution")
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .find(|name| !name.is_empty() && !distributions.is_known(name))
+        .map(str::to_string)
+}
+
+/// The 441 reply for an article the filters refused. Crosspost limits,
+/// header rules, malformed headers and external checkers' reasons are
+/// spelled out so the poster knows what to change.
+fn refusal(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<Rejection>() {
+        Some(rejection) => format!("441 {rejection}\r\n"),
+        None => RESP_441_POSTING_FAILED.to_string(),
+    }
+}
+
+/// The 441 reply for a poster over the rate limit, with a retry hint.
+fn rate_refusal(
+    limiter: &crate::ratelimit::RateLimiter,
+    keys: &[crate::ratelimit::RateLimitKey],
+    cfg: &Config,
+) -> String {
+    // Whole seconds, so the hint is never shorter than the real wait
+    let wait = limiter.retry_after(keys, &cfg.rate_limit);
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    format!("441 posting rate exceeded; retry after {secs} seconds\r\n")
+}
+
 /// Handler for the POST command.
 pub struct PostHandler;
 
@@ -17,6 +147,15 @@ impl CommandHandler for PostHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
+        let permitted = {
+            let cfg = ctx.config.read().await;
+            cfg.posting_enabled_for(ctx.state.is_tls) && ctx.state.may_submit(&cfg)
+        };
+        if !permitted {
+            write_simple(&mut ctx.writer, RESP_440_POSTING_NOT_PERMITTED).await?;
+            return Ok(());
+        }
+
         if !ctx.state.is_tls && !ctx.state.allow_posting_insecure {
             write_simple(&mut ctx.writer, RESP_483_SECURE_REQ).await?;
             return Ok(());
@@ -27,48 +166,216 @@ impl CommandHandler for PostHandler {
             return Ok(());
         }
 
-        write_simple(&mut ctx.writer, RESP_340_SEND_ARTICLE).await?;
+        let line_timeout = ctx.config.read().await.article_line_timeout();
+        write_and_flush(&mut ctx.writer, RESP_340_SEND_ARTICLE).await?;
 
-        let msg = read_message(&mut ctx.reader).await?;
+        let msg = match read_message(&mut ctx.reader, line_timeout, &ctx.config).await {
+            Ok(msg) => msg,
+            Err(e) if e.is::<ArticleTooLarge>() => {
+                crate::metrics::global().article_rejected("size");
+                write_simple(&mut ctx.writer, &format!("441 {e}\r\n")).await?;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
         let Ok((_, mut message)) = parse_message(&msg) else {
             write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
             return Ok(());
         };
 
+        // Only relays may pass on injection headers
+        if let Some(name) = INJECTION_HEADERS
+            .iter()
+            .find(|name| !get_header_values(&message, name).is_empty())
+        {
+            tracing::debug!("Refusing POST with client-supplied {name} header");
+            let reply = format!("441 {name} header not allowed in posts\r\n");
+            write_simple(&mut ctx.writer, &reply).await?;
+            return Ok(());
+        }
+
+        let distribution = unknown_distribution(&message, &ctx.config.read().await.distributions);
+        if let Some(name) = distribution {
+            let reply = format!("441 unknown distribution {name}\r\n");
+            write_simple(&mut ctx.writer, &reply).await?;
+            return Ok(());
+        }
+
         // Check if this is a control message first
         let is_control = control::is_control_message(&message);
 
         // Ensure required headers
-        let cfg_guard = ctx.config.read().await;
-        ensure_message_id(&mut message, &cfg_guard.site_name);
+        let mut cfg_guard = ctx.config.read().await;
+        let size = msg.len() as u64;
+        // The post is only charged once it has passed the filters, so
+        // refused articles do not use up the poster's quota
+        let keys = rate_limit_keys(&ctx.state);
+        let limiter = ctx.queue.rate_limiter();
+        if limiter.is_throttled(&keys, &cfg_guard.rate_limit) {
+            let reply = rate_refusal(limiter, &keys, &cfg_guard);
+            write_simple(&mut ctx.writer, &reply).await?;
+            return Ok(());
+        }
+        let received = match supply_message_id(&ctx.storage, &cfg_guard, &mut message).await {
+            Ok(Some(id)) => format!("240 article received {id}\r\n"),
+            Ok(None) => RESP_240_ARTICLE_RECEIVED.to_string(),
+            Err(e) => {
+                tracing::debug!("Refusing POST: {e}");
+                write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
+                return Ok(());
+            }
+        };
         parse::ensure_date(&mut message);
         parse::escape_message_id_header(&mut message);
+        // Injected here, so the Path starts with just our name when stored;
+        // Xref is ours to write once the article has numbers
+        message
+            .headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case("Path") && !k.eq_ignore_ascii_case("Xref"));
+        let user = ctx.state.user();
+        add_injection_headers(&mut message, &cfg_guard, ctx.state.peer_ip, user);
+
+        // An unapproved post to a moderated group is mailed to its moderator
+        // instead of being stored; under the split policy each unapproved
+        // group's moderator gets a copy and the other groups still get theirs
+        let held = if get_header_values(&message, "Approved").is_empty() {
+            moderation::unapproved_moderated_groups(&ctx.storage, &ctx.auth, &message, user).await?
+        } else {
+            Default::default()
+        };
+        let copies = moderation::approval_copies(&ctx.storage, &cfg_guard, &message, &held).await?;
+        let split =
+            moderation::may_split(&cfg_guard, held.len(), extract_newsgroups(&message).len());
+        let copies = match copies {
+            Some(copies) => copies,
+            // A split must not quietly drop the copies nobody can approve
+            None if split => {
+                write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
+                return Ok(());
+            }
+            None => Vec::new(),
+        };
+        let mailed = !copies.is_empty();
+        if mailed {
+            if let Err(e) = validate_for_moderation(
+                &ctx.storage,
+                &ctx.auth,
+                &cfg_guard,
+                &message,
+                size,
+                &ctx.state.submitter(),
+            )
+            .await
+            {
+                write_simple(&mut ctx.writer, &refusal(&e)).await?;
+                return Ok(());
+            }
+            if !limiter.check(&keys, size, &cfg_guard.rate_limit) {
+                let reply = rate_refusal(limiter, &keys, &cfg_guard);
+                write_simple(&mut ctx.writer, &reply).await?;
+                return Ok(());
+            }
+            // Not under the configuration lock while talking to the relay
+            let relay = cfg_guard.smtp_relay.clone();
+            let site_name = cfg_guard.site_name.clone();
+            drop(cfg_guard);
+            for (address, copy) in &copies {
+                if let Err(e) =
+                    moderation::mail_to_moderator(relay.as_deref(), &site_name, address, copy).await
+                {
+                    tracing::warn!("Failed to mail article to moderator {address}: {e}");
+                    write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
+                    return Ok(());
+                }
+            }
+            if !split {
+                write_simple(&mut ctx.writer, &received).await?;
+                return Ok(());
+            }
+            cfg_guard = ctx.config.read().await;
+        }
 
         // Comprehensive validation before queuing for POST (to maintain expected behavior)
-        let size = msg.len() as u64;
-        if comprehensive_validate_article(&ctx.storage, &ctx.auth, &cfg_guard, &message, size)
+        if let Err(e) = validate_submission(
+            &ctx.storage,
+            &ctx.auth,
+            &cfg_guard,
+            &message,
+            size,
+            &ctx.state.submitter(),
+            &ctx.queue,
+        )
+        .await
+        {
+            write_simple(&mut ctx.writer, &refusal(&e)).await?;
+            return Ok(());
+        }
+        // A copy already mailed to a moderator has paid for this post
+        if !mailed && !limiter.check(&keys, size, &cfg_guard.rate_limit) {
+            let reply = rate_refusal(limiter, &keys, &cfg_guard);
+            write_simple(&mut ctx.writer, &reply).await?;
+            return Ok(());
+        }
+
+        // Hold unapproved moderated groups of a crosspost when the policy allows splitting
+        if moderation::strip_held_groups(&ctx.storage, &ctx.auth, &cfg_guard, &mut message, user)
             .await
             .is_err()
         {
             write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
             return Ok(());
         }
+        let durability = cfg_guard.post_durability;
+        let (policy, wait_timeout) = (
+            cfg_guard.backpressure.post,
+            cfg_guard.backpressure.wait_timeout(),
+        );
         drop(cfg_guard);
 
+        // Under ack_on_store the 240 waits for the worker to confirm the store
+        let (ack, stored) = match durability {
+            PostDurability::AckOnQueue => (None, None),
+            PostDurability::AckOnStore => {
+                let (tx, rx) = flume::bounded(1);
+                (Some(tx), Some(rx))
+            }
+        };
+
         // Submit to queue for background processing
         let queued_article = QueuedArticle {
             message,
             size,
             is_control,
             already_validated: true, // POST uses comprehensive validation and queues for storage only
+            ack,
         };
 
-        if ctx.queue.submit(queued_article).await.is_err() {
+        let submitted = ctx
+            .queue
+            .submit_with(queued_article, policy, wait_timeout)
+            .await
+            .map_err(|e| {
+                if e.is::<QueueFull>() {
+                    RESP_441_QUEUE_FULL
+                } else {
+                    RESP_441_POSTING_FAILED
+                }
+            });
+        if let Err(response) = submitted {
+            write_simple(&mut ctx.writer, response).await?;
+            return Ok(());
+        }
+
+        let confirmed = match stored {
+            Some(rx) => matches!(rx.recv_async().await, Ok(Outcome::Stored)),
+            None => true,
+        };
+        if !confirmed {
             write_simple(&mut ctx.writer, RESP_441_POSTING_FAILED).await?;
             return Ok(());
         }
 
-        write_simple(&mut ctx.writer, RESP_240_ARTICLE_RECEIVED).await?;
+        write_simple(&mut ctx.writer, &received).await?;
         Ok(())
     }
 }
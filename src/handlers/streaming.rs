@@ -1,11 +1,36 @@
 //! Streaming command handlers (IHAVE, CHECK, TAKETHIS).
 
-use super::utils::{comprehensive_validate_article, read_message, write_simple};
+use super::utils::{
+    ArticleTooLarge, read_message, transit_rate_limit_keys, validate_submission, write_and_flush,
+    write_simple,
+};
 use super::{CommandHandler, HandlerContext, HandlerResult};
+use crate::config::QueueFullPolicy;
+use crate::queue::ArticleQueue;
 use crate::responses::*;
+use crate::storage::DynStorage;
+use crate::storage::common::prepare_for_storage;
 use crate::{control, ensure_message_id, parse, parse_message};
 use tokio::io::{AsyncBufRead, AsyncWrite};
 
+/// Whether `id` was offered before, answered from the shared Message-ID
+/// history when possible so repeated offers skip the storage lookup.
+async fn already_have(
+    queue: &ArticleQueue,
+    storage: &DynStorage,
+    id: &str,
+) -> anyhow::Result<bool> {
+    let history = queue.history();
+    if history.is_some_and(|h| h.contains(id)) {
+        return Ok(true);
+    }
+    let exists = storage.message_seen(id).await?;
+    if let Some(history) = history.filter(|_| exists) {
+        history.insert(id);
+    }
+    Ok(exists)
+}
+
 /// Handler for the IHAVE command.
 pub struct IHaveHandler;
 
@@ -16,14 +41,48 @@ impl CommandHandler for IHaveHandler {
         W: AsyncWrite + Unpin,
     {
         if let Some(id) = args.first() {
-            if ctx.storage.get_article_by_id(id).await?.is_some() {
+            if !ctx.state.may_submit(&*ctx.config.read().await) {
+                write_simple(&mut ctx.writer, RESP_502_NOT_PERMITTED).await?;
+                return Ok(());
+            }
+
+            if already_have(&ctx.queue, &ctx.storage, id).await? {
+                crate::metrics::global().article_rejected("duplicate");
                 write_simple(&mut ctx.writer, RESP_435_NOT_WANTED).await?;
                 return Ok(());
             }
 
-            write_simple(&mut ctx.writer, RESP_335_SEND_IT).await?;
-            let msg = read_message(&mut ctx.reader).await?;
+            // Turn the offer down before the transfer if the peer is already
+            // over its limit or the workers are behind
+            let (keys, limits, backpressure, line_timeout) = {
+                let cfg = ctx.config.read().await;
+                (
+                    transit_rate_limit_keys(&ctx.state, &cfg),
+                    cfg.rate_limit,
+                    cfg.backpressure,
+                    cfg.article_line_timeout(),
+                )
+            };
+            let (policy, wait_timeout) = (backpressure.transit, backpressure.wait_timeout());
+            if ctx.queue.rate_limiter().is_throttled(&keys, &limits)
+                || (policy == QueueFullPolicy::Reject && ctx.queue.is_full())
+            {
+                write_simple(&mut ctx.writer, RESP_436_TRY_LATER).await?;
+                return Ok(());
+            }
+
+            write_and_flush(&mut ctx.writer, RESP_335_SEND_IT).await?;
+            let msg = match read_message(&mut ctx.reader, line_timeout, &ctx.config).await {
+                Ok(msg) => msg,
+                Err(e) if e.is::<ArticleTooLarge>() => {
+                    crate::metrics::global().article_rejected("size");
+                    write_simple(&mut ctx.writer, RESP_437_REJECTED).await?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
             let Ok((_, mut article)) = parse_message(&msg) else {
+                crate::metrics::global().article_rejected("malformed");
                 write_simple(&mut ctx.writer, RESP_437_REJECTED).await?;
                 return Ok(());
             };
@@ -32,12 +91,20 @@ impl CommandHandler for IHaveHandler {
             let is_control = control::is_control_message(&article);
 
             let cfg_guard = ctx.config.read().await;
+            let size = msg.len() as u64;
             ensure_message_id(&mut article, &cfg_guard.site_name);
-            parse::ensure_date(&mut article);
             parse::escape_message_id_header(&mut article);
 
             // Handle control messages immediately without comprehensive validation
             if is_control {
+                if !ctx
+                    .queue
+                    .rate_limiter()
+                    .check(&keys, size, &cfg_guard.rate_limit)
+                {
+                    write_simple(&mut ctx.writer, RESP_436_TRY_LATER).await?;
+                    return Ok(());
+                }
                 if control::handle_control(&article, &ctx.storage, &ctx.auth, &cfg_guard).await? {
                     write_simple(&mut ctx.writer, RESP_235_TRANSFER_OK).await?;
                     return Ok(());
@@ -48,14 +115,32 @@ impl CommandHandler for IHaveHandler {
             }
 
             // Comprehensive validation before queuing for IHAVE (non-control messages)
-            let size = msg.len() as u64;
-            if comprehensive_validate_article(&ctx.storage, &ctx.auth, &cfg_guard, &article, size)
-                .await
-                .is_err()
+            if validate_submission(
+                &ctx.storage,
+                &ctx.auth,
+                &cfg_guard,
+                &article,
+                size,
+                &ctx.state.submitter(),
+                &ctx.queue,
+            )
+            .await
+            .is_err()
             {
                 write_simple(&mut ctx.writer, RESP_437_REJECTED).await?;
                 return Ok(());
             }
+            // Only articles that passed the filters are charged, as for POST
+            if !ctx
+                .queue
+                .rate_limiter()
+                .check(&keys, size, &cfg_guard.rate_limit)
+            {
+                write_simple(&mut ctx.writer, RESP_436_TRY_LATER).await?;
+                return Ok(());
+            }
+            let stored = prepare_for_storage(&cfg_guard, &article);
+            let host = cfg_guard.site_name.clone();
             drop(cfg_guard);
 
             // Submit to queue for background storage and immediate storage for protocol compliance
@@ -64,16 +149,25 @@ impl CommandHandler for IHaveHandler {
                 size,
                 is_control: false, // Control messages are handled above, so this is always false
                 already_validated: true, // IHAVE does comprehensive validation before queuing
+                ack: None,
             };
 
             // Store immediately for protocol compliance (second IHAVE should know article exists)
-            if ctx.storage.store_article(&article).await.is_err() {
+            if control::store_superseding(&stored, &ctx.storage, &host)
+                .await
+                .is_err()
+            {
                 write_simple(&mut ctx.writer, RESP_437_REJECTED).await?;
                 return Ok(());
             }
 
+            ctx.queue.article_stored(&stored).await;
+
             // Also queue for background processing consistency
-            let _ = ctx.queue.submit(queued_article).await; // Don't fail if queue is full since we already stored
+            let _ = ctx
+                .queue
+                .submit_with(queued_article, policy, wait_timeout)
+                .await; // Don't fail if queue is full since we already stored
             write_simple(&mut ctx.writer, RESP_235_TRANSFER_OK).await?;
         } else {
             write_simple(&mut ctx.writer, RESP_501_MSGID_REQUIRED).await?;
@@ -92,11 +186,28 @@ impl CommandHandler for CheckHandler {
         W: AsyncWrite + Unpin,
     {
         if let Some(id) = args.first() {
-            if ctx.storage.get_article_by_id(id).await?.is_some() {
-                write_simple(&mut ctx.writer, &format!("438 {id}\r\n")).await?;
+            // Ask the peer to retry later rather than accept work we cannot
+            // queue or that would exceed its rate limit
+            let (keys, limits, permitted) = {
+                let cfg = ctx.config.read().await;
+                (
+                    transit_rate_limit_keys(&ctx.state, &cfg),
+                    cfg.rate_limit,
+                    ctx.state.may_submit(&cfg),
+                )
+            };
+            let throttled = ctx.queue.rate_limiter().is_throttled(&keys, &limits);
+            // Addresses not allowed to submit are told not to send anything
+            let code = if permitted {
+                match already_have(&ctx.queue, &ctx.storage, id).await {
+                    Ok(true) => RESP_438_CHECK_REJECT,
+                    Ok(false) if !ctx.queue.is_full() && !throttled => RESP_238_CHECK_OK,
+                    Ok(false) | Err(_) => RESP_431_CHECK_LATER,
+                }
             } else {
-                write_simple(&mut ctx.writer, &format!("238 {id}\r\n")).await?;
-            }
+                RESP_438_CHECK_REJECT
+            };
+            write_simple(&mut ctx.writer, &format!("{code} {id}\r\n")).await?;
         } else {
             write_simple(&mut ctx.writer, RESP_501_MSGID_REQUIRED).await?;
         }
@@ -113,14 +224,36 @@ impl CommandHandler for TakeThisHandler {
         R: AsyncBufRead + Unpin,
         W: AsyncWrite + Unpin,
     {
+        // The article always follows TAKETHIS, so consume it before deciding
+        // anything or the command stream falls out of sync
+        let line_timeout = ctx.config.read().await.article_line_timeout();
+        let msg = match read_message(&mut ctx.reader, line_timeout, &ctx.config).await {
+            Ok(msg) => msg,
+            Err(e) if e.is::<ArticleTooLarge>() => {
+                crate::metrics::global().article_rejected("size");
+                let reply = match args.first() {
+                    Some(id) => format!("439 {id}\r\n"),
+                    None => RESP_501_MSGID_REQUIRED.to_string(),
+                };
+                write_simple(&mut ctx.writer, &reply).await?;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
         if let Some(id) = args.first() {
-            let msg = read_message(&mut ctx.reader).await?;
             let Ok((_, mut article)) = parse_message(&msg) else {
+                crate::metrics::global().article_rejected("malformed");
                 write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
                 return Ok(());
             };
 
-            if ctx.storage.get_article_by_id(id).await?.is_some() {
+            if !ctx.state.may_submit(&*ctx.config.read().await) {
+                write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
+                return Ok(());
+            }
+
+            if already_have(&ctx.queue, &ctx.storage, id).await? {
+                crate::metrics::global().article_rejected("duplicate");
                 write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
                 return Ok(());
             }
@@ -129,48 +262,109 @@ impl CommandHandler for TakeThisHandler {
             let is_control = control::is_control_message(&article);
 
             let cfg_guard = ctx.config.read().await;
+            let size = msg.len() as u64;
+            let policy = cfg_guard.backpressure.transit;
+            let wait_timeout = cfg_guard.backpressure.wait_timeout();
+            let keys = transit_rate_limit_keys(&ctx.state, &cfg_guard);
+            // Control messages have a lane of their own, so a backlog of
+            // ordinary articles does not hold them up
+            if (policy == QueueFullPolicy::Reject && !is_control && ctx.queue.is_full())
+                || ctx
+                    .queue
+                    .rate_limiter()
+                    .is_throttled(&keys, &cfg_guard.rate_limit)
+            {
+                write_simple(&mut ctx.writer, &format!("{RESP_431_CHECK_LATER} {id}\r\n")).await?;
+                return Ok(());
+            }
             ensure_message_id(&mut article, &cfg_guard.site_name);
-            parse::ensure_date(&mut article);
             parse::escape_message_id_header(&mut article);
 
-            // Handle control messages immediately without comprehensive validation
+            // Control messages are verified and applied by the queue workers
             if is_control {
-                if control::handle_control(&article, &ctx.storage, &ctx.auth, &cfg_guard).await? {
-                    write_simple(&mut ctx.writer, &format!("239 {id}\r\n")).await?;
-                    return Ok(());
-                } else {
-                    write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
+                if !ctx
+                    .queue
+                    .rate_limiter()
+                    .check(&keys, size, &cfg_guard.rate_limit)
+                {
+                    write_simple(&mut ctx.writer, &format!("{RESP_431_CHECK_LATER} {id}\r\n"))
+                        .await?;
                     return Ok(());
                 }
+                drop(cfg_guard);
+                let queued_article = crate::queue::QueuedArticle {
+                    message: article,
+                    size,
+                    is_control,
+                    already_validated: false,
+                    ack: None,
+                };
+                let code = match ctx
+                    .queue
+                    .submit_with(queued_article, policy, wait_timeout)
+                    .await
+                {
+                    Ok(()) => RESP_239_TAKETHIS_OK,
+                    Err(e) if e.is::<crate::queue::QueueFull>() => RESP_431_CHECK_LATER,
+                    Err(_) => RESP_439_TAKETHIS_REJECT,
+                };
+                write_simple(&mut ctx.writer, &format!("{code} {id}\r\n")).await?;
+                return Ok(());
             }
 
             // Comprehensive validation before queuing for TAKETHIS (non-control messages)
-            let size = msg.len() as u64;
-            if comprehensive_validate_article(&ctx.storage, &ctx.auth, &cfg_guard, &article, size)
-                .await
-                .is_err()
+            if validate_submission(
+                &ctx.storage,
+                &ctx.auth,
+                &cfg_guard,
+                &article,
+                size,
+                &ctx.state.submitter(),
+                &ctx.queue,
+            )
+            .await
+            .is_err()
             {
                 write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
                 return Ok(());
             }
+            if !ctx
+                .queue
+                .rate_limiter()
+                .check(&keys, size, &cfg_guard.rate_limit)
+            {
+                write_simple(&mut ctx.writer, &format!("{RESP_431_CHECK_LATER} {id}\r\n")).await?;
+                return Ok(());
+            }
+            let stored = prepare_for_storage(&cfg_guard, &article);
+            let host = cfg_guard.site_name.clone();
             drop(cfg_guard);
 
             // Submit to queue for background storage and immediate storage for protocol compliance
             let queued_article = crate::queue::QueuedArticle {
                 message: article.clone(),
                 size,
-                is_control: false, // Control messages are handled above, so this is always false
+                is_control,
                 already_validated: true, // TAKETHIS does comprehensive validation before queuing
+                ack: None,
             };
 
             // Store immediately for protocol compliance (duplicate TAKETHIS should be detected)
-            if ctx.storage.store_article(&article).await.is_err() {
+            if control::store_superseding(&stored, &ctx.storage, &host)
+                .await
+                .is_err()
+            {
                 write_simple(&mut ctx.writer, &format!("439 {id}\r\n")).await?;
                 return Ok(());
             }
 
+            ctx.queue.article_stored(&stored).await;
+
             // Also queue for background processing consistency
-            let _ = ctx.queue.submit(queued_article).await; // Don't fail if queue is full since we already stored
+            let _ = ctx
+                .queue
+                .submit_with(queued_article, policy, wait_timeout)
+                .await; // Don't fail if queue is full since we already stored
             write_simple(&mut ctx.writer, &format!("239 {id}\r\n")).await?;
         } else {
             write_simple(&mut ctx.writer, RESP_501_MSGID_REQUIRED).await?;
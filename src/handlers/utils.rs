@@ -1,12 +1,15 @@
 //! Utility functions for command handlers.
 
+use crate::config::AccessRules;
+use crate::filters::Submitter;
+use crate::ratelimit::RateLimitKey;
 use crate::storage::DynStorage;
 use crate::{ConnectionState, Message};
 use anyhow::Result;
 use smallvec::SmallVec;
 use std::error::Error;
 use std::fmt;
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 /// Extract newsgroups from message headers.
 /// Returns a collection of newsgroup names parsed from the Newsgroups header.
@@ -14,6 +17,32 @@ pub fn extract_newsgroups(article: &Message) -> SmallVec<[String; 4]> {
     crate::storage::common::parse_newsgroups_from_message(article)
 }
 
+/// Rate limiter buckets charged for a submission on this session: the
+/// logged-in user, if any, and the remote address.
+pub fn rate_limit_keys(state: &ConnectionState) -> Vec<RateLimitKey> {
+    let user = state
+        .authenticated
+        .then(|| state.username.clone().map(RateLimitKey::User))
+        .flatten();
+    user.into_iter()
+        .chain(state.peer_ip.map(RateLimitKey::Ip))
+        .collect()
+}
+
+/// Rate limiter buckets charged for a transfer on this session: none for a
+/// configured peer, which is never throttled, else those of
+/// [`rate_limit_keys`].
+pub fn transit_rate_limit_keys(
+    state: &ConnectionState,
+    cfg: &crate::config::Config,
+) -> Vec<RateLimitKey> {
+    if cfg.is_peer(state.user(), state.peer_ip) {
+        Vec::new()
+    } else {
+        rate_limit_keys(state)
+    }
+}
+
 /// Check if message has required header (case-insensitive).
 pub fn has_header(article: &Message, header_name: &str) -> bool {
     article
@@ -84,6 +113,16 @@ pub async fn write_simple<W: AsyncWrite + Unpin>(writer: &mut W, response: &str)
     Ok(())
 }
 
+/// Write an intermediate response and flush it to the client.
+///
+/// Used before a handler blocks waiting for more input, so the response is
+/// not left sitting in the DEFLATE buffer of a compressed connection.
+pub async fn write_and_flush<W: AsyncWrite + Unpin>(writer: &mut W, response: &str) -> Result<()> {
+    writer.write_all(response.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
 /// Send article headers to the writer.
 pub async fn send_headers<W: AsyncWrite + Unpin>(writer: &mut W, article: &Message) -> Result<()> {
     for (name, val) in &article.headers {
@@ -96,12 +135,12 @@ pub async fn send_headers<W: AsyncWrite + Unpin>(writer: &mut W, article: &Messa
 }
 
 /// Send article body to the writer with proper dot-stuffing.
-pub async fn send_body<W: AsyncWrite + Unpin>(writer: &mut W, body: &str) -> Result<()> {
-    for line in body.lines() {
-        if line.starts_with('.') {
+pub async fn send_body<W: AsyncWrite + Unpin>(writer: &mut W, body: &[u8]) -> Result<()> {
+    for line in crate::parse::body_lines(body) {
+        if line.starts_with(b".") {
             writer.write_all(b".").await?;
         }
-        writer.write_all(line.as_bytes()).await?;
+        writer.write_all(line).await?;
         writer.write_all(b"\r\n").await?;
     }
     Ok(())
@@ -123,7 +162,7 @@ pub async fn metadata_value(storage: &DynStorage, msg: &Message, name: &str) ->
                 Some((msg.body.len() as u64).to_string())
             }
         }
-        ":lines" => Some(msg.body.lines().count().to_string()),
+        ":lines" => Some(crate::parse::body_lines(&msg.body).count().to_string()),
         _ => None,
     }
 }
@@ -132,17 +171,19 @@ pub async fn metadata_value(storage: &DynStorage, msg: &Message, name: &str) ->
 pub async fn resolve_articles(
     storage: &DynStorage,
     state: &mut ConnectionState,
+    access: &AccessRules,
     arg: Option<&str>,
 ) -> Result<Vec<(u64, Message)>, ArticleQueryError> {
     let mut articles = Vec::new();
 
     if let Some(arg) = arg {
         if arg.starts_with('<') && arg.ends_with('>') {
-            // Message-ID
+            // Message-ID; articles only in unreadable groups are not found
             if let Some(article) = storage
                 .get_article_by_id(arg)
                 .await
                 .map_err(|_| ArticleQueryError::MessageIdNotFound)?
+                .filter(|article| access.can_read_article(article, state.user()))
             {
                 articles.push((0, article));
             } else {
@@ -232,12 +273,13 @@ pub async fn handle_article_operation<W: AsyncWrite + Unpin>(
     writer: &mut W,
     storage: &DynStorage,
     state: &mut ConnectionState,
+    access: &AccessRules,
     args: &[String],
     operation: ArticleOperation,
 ) -> Result<()> {
     use crate::responses::*;
 
-    match resolve_articles(storage, state, args.first().map(String::as_str)).await {
+    match resolve_articles(storage, state, access, args.first().map(String::as_str)).await {
         Ok(articles) => {
             for (num, article) in articles {
                 let id = extract_message_id(&article).unwrap_or_default();
@@ -321,17 +363,11 @@ pub async fn write_response_with_values<W: AsyncWrite + Unpin>(
 ) -> Result<()> {
     writer.write_all(response.as_bytes()).await?;
     for (n, val) in values {
-        // Use efficient direct writing instead of format!
-        use std::io::Write;
-        let mut buf = [0u8; 64];
-        let mut cursor = std::io::Cursor::new(&mut buf[..]);
-        if let Some(v) = val {
-            write!(&mut cursor, "{n} {v}\r\n").unwrap();
-        } else {
-            write!(&mut cursor, "{n}\r\n").unwrap();
-        }
-        let len = cursor.position() as usize;
-        writer.write_all(&buf[..len]).await?;
+        let line = match val {
+            Some(v) => format!("{n} {v}\r\n"),
+            None => format!("{n}\r\n"),
+        };
+        writer.write_all(line.as_bytes()).await?;
     }
     use crate::responses::RESP_DOT_CRLF;
     writer.write_all(RESP_DOT_CRLF.as_bytes()).await?;
@@ -346,26 +382,151 @@ pub async fn write_lines<W: AsyncWrite + Unpin>(writer: &mut W, lines: &[&str])
     Ok(())
 }
 
-/// Read a message from the reader until dot termination.
-pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<String> {
-    let mut msg = String::new();
-    let mut line = String::new();
+/// Returned by [`read_message`] when the client sends no line of the
+/// article for the whole line timeout, and by AUTHINFO SASL when a response
+/// does not arrive within the idle timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadTimeout;
+
+impl fmt::Display for ReadTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting for the client")
+    }
+}
+
+impl Error for ReadTimeout {}
+
+/// Returned by [`read_message`] when the article grows past `limit` bytes,
+/// the size limit that applies to it. The rest of the article has been read
+/// and thrown away, so the session is still in step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArticleTooLarge {
+    pub limit: u64,
+}
+
+impl fmt::Display for ArticleTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "article larger than {} bytes", self.limit)
+    }
+}
+
+impl Error for ArticleTooLarge {}
+
+/// Groups named by the `Newsgroups` header in `headers`, the raw header
+/// lines of an article.
+fn newsgroups_in(headers: &str) -> Vec<String> {
+    let mut value = String::new();
+    let mut in_newsgroups = false;
+    for line in headers.lines() {
+        if line.starts_with([' ', '\t']) {
+            if in_newsgroups {
+                value.push_str(line);
+            }
+            continue;
+        }
+        in_newsgroups = line
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("Newsgroups"));
+        if in_newsgroups {
+            value.push_str(line.split_once(':').map_or("", |(_, v)| v));
+            value.push(',');
+        }
+    }
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read a message from the reader until dot termination, waiting at most
+/// `line_timeout` for each line.
+///
+/// The size limits in `config` bound the read, the largest of them while
+/// the headers arrive and then the one for the groups they name: past the
+/// limit the article is discarded line by line as it arrives and
+/// [`ArticleTooLarge`] returned, so an oversized article is never held in
+/// memory. The limits count raw octets; nothing is decoded, so 8-bit
+/// articles are read and measured like any others.
+pub async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line_timeout: std::time::Duration,
+    config: &tokio::sync::RwLock<crate::config::Config>,
+) -> Result<Vec<u8>> {
+    let mut msg = Vec::new();
+    let mut line = Vec::new();
+    // Until the groups are known only the largest limit of any group holds
+    let mut limit = config.read().await.largest_size_limit();
+    let mut in_headers = true;
 
     loop {
         line.clear();
-        reader.read_line(&mut line).await?;
-        if line == ".\r\n" || line == ".\n" {
+        // Take no more than can still fit, allowing for a stuffed dot, so an
+        // endless line is cut off too
+        let cap = limit.map_or(u64::MAX, |limit| {
+            limit.saturating_sub(msg.len() as u64).saturating_add(2)
+        });
+        let n = tokio::time::timeout(
+            line_timeout,
+            (&mut *reader).take(cap).read_until(b'\n', &mut line),
+        )
+        .await
+        .map_err(|_| ReadTimeout)??;
+        if n == 0 {
+            return Err(anyhow::anyhow!("connection closed while reading article"));
+        }
+        if line == b".\r\n" || line == b".\n" {
             break;
         }
-        if line.starts_with("..") {
-            msg.push_str(&line[1..]);
-        } else {
-            msg.push_str(&line);
+        let stuffed = line.starts_with(b"..");
+        let kept = line.len() - usize::from(stuffed);
+        if let Some(limit) = limit.filter(|limit| (msg.len() + kept) as u64 > *limit) {
+            discard_message(reader, line_timeout, line.ends_with(b"\n")).await?;
+            return Err(ArticleTooLarge { limit }.into());
+        }
+        msg.extend_from_slice(&line[usize::from(stuffed)..]);
+        if in_headers && (line == b"\r\n" || line == b"\n") {
+            in_headers = false;
+            let groups = newsgroups_in(&String::from_utf8_lossy(&msg));
+            limit = config.read().await.max_size_for_groups(&groups);
+            if let Some(limit) = limit.filter(|limit| msg.len() as u64 > *limit) {
+                discard_message(reader, line_timeout, true).await?;
+                return Err(ArticleTooLarge { limit }.into());
+            }
         }
     }
     Ok(msg)
 }
 
+/// Read and drop the rest of an article, through its terminating dot line.
+/// `at_line_start` tells whether the last line read was complete. Lines are
+/// taken in bounded pieces, so a single endless line costs no memory.
+async fn discard_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line_timeout: std::time::Duration,
+    mut at_line_start: bool,
+) -> Result<()> {
+    const PIECE: u64 = 8192;
+    let mut piece = Vec::new();
+    loop {
+        piece.clear();
+        let n = tokio::time::timeout(
+            line_timeout,
+            (&mut *reader).take(PIECE).read_until(b'\n', &mut piece),
+        )
+        .await
+        .map_err(|_| ReadTimeout)??;
+        if n == 0 {
+            return Err(anyhow::anyhow!("connection closed while reading article"));
+        }
+        if at_line_start && (piece == b".\r\n" || piece == b".\n") {
+            return Ok(());
+        }
+        at_line_start = piece.ends_with(b"\n");
+    }
+}
+
 /// Perform basic validation on an article before queuing
 ///
 /// This checks only what can be validated without database access:
@@ -411,6 +572,65 @@ pub async fn comprehensive_validate_article(
     .await
 }
 
+/// Refuse an article crossposted to any group `user` may not post to.
+fn check_post_access(
+    cfg: &crate::config::Config,
+    article: &crate::Message,
+    user: Option<&str>,
+) -> Result<()> {
+    if let Some(group) = crate::storage::common::parse_newsgroups_from_message(article)
+        .into_iter()
+        .find(|group| !cfg.access.can_post_group(group, user))
+    {
+        anyhow::bail!("posting to {group} is not permitted");
+    }
+    Ok(())
+}
+
+/// Validate an article from `submitter` with the configured filter chain.
+/// Groups the submitting user may not post to are refused first, then
+/// Message-IDs found in the history of `queue`. A user who moderates a group
+/// may approve articles for it without signing them.
+pub async fn validate_submission(
+    storage: &crate::storage::DynStorage,
+    auth: &crate::auth::DynAuth,
+    cfg: &crate::config::Config,
+    article: &crate::Message,
+    size: u64,
+    submitter: &Submitter,
+    queue: &crate::queue::ArticleQueue,
+) -> Result<()> {
+    let user = submitter.user.as_deref();
+    check_post_access(cfg, article, user)?;
+    let mut chain = crate::filters::FilterChain::from_config(cfg)
+        .with_submitter(submitter)
+        .with_recent_bodies(queue.recent_bodies().clone());
+    if let Some(history) = queue.history() {
+        chain = chain.with_history(history.clone());
+    }
+    if let Some(user) = user {
+        chain = chain.with_approver(user);
+    }
+    validate_article_with_filters(storage, auth, cfg, article, size, &chain).await
+}
+
+/// Validate an unapproved article from `submitter` that is bound for a
+/// moderator: everything `validate_submission` checks except the approval.
+pub async fn validate_for_moderation(
+    storage: &crate::storage::DynStorage,
+    auth: &crate::auth::DynAuth,
+    cfg: &crate::config::Config,
+    article: &crate::Message,
+    size: u64,
+    submitter: &Submitter,
+) -> Result<()> {
+    check_post_access(cfg, article, submitter.user.as_deref())?;
+    let chain = crate::filters::FilterChain::from_config(cfg)
+        .with_submitter(submitter)
+        .without("ModerationFilter");
+    validate_article_with_filters(storage, auth, cfg, article, size, &chain).await
+}
+
 /// Validate an article using a custom filter chain.
 /// This allows for customizable validation beyond the default chain.
 pub async fn validate_article_with_filters(
@@ -426,6 +646,18 @@ pub async fn validate_article_with_filters(
         .await
 }
 
+/// Response refusing a group the session may not read: anonymous clients
+/// are asked to log in, everyone else is told the group does not exist so
+/// that restricted groups stay hidden.
+#[must_use]
+pub fn read_denied(state: &ConnectionState) -> &'static str {
+    if state.authenticated {
+        crate::responses::RESP_411_NO_SUCH_GROUP
+    } else {
+        crate::responses::RESP_480_AUTH_REQUIRED
+    }
+}
+
 /// Write a formatted response line efficiently, avoiding format! allocations where possible
 pub async fn write_response_with_args<W: AsyncWrite + Unpin>(
     writer: &mut W,
@@ -1,27 +1,83 @@
 pub mod parse;
 pub use parse::{
     Command, Message, Response, ensure_date, ensure_message_id, parse_command, parse_datetime,
-    parse_message, parse_range, parse_response,
+    parse_datetime_at, parse_message, parse_range, parse_response,
 };
 
 pub mod auth;
+pub mod client;
+pub mod compress;
 pub mod config;
+pub mod connlimit;
 pub mod control;
+pub mod dead_letter;
+pub mod feed;
 pub mod filters;
 pub mod handlers;
+pub mod metrics;
 mod migrations;
 pub mod overview;
 pub mod peers;
 pub mod prelude;
 pub mod queue;
+pub mod ratelimit;
 pub mod responses;
 pub mod retention;
 pub mod server;
+pub mod shutdown;
+pub mod smtp;
 pub mod storage;
+pub mod subscriptions;
 pub mod wildmat;
 #[cfg(feature = "websocket")]
 pub mod ws;
 
+/// Which family of commands a session accepts.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionMode {
+    /// Mode-switching session before MODE READER: reader and transit
+    /// commands are both accepted.
+    #[default]
+    Mixed,
+    /// Reader session: IHAVE, CHECK and TAKETHIS are refused.
+    Reader,
+    /// Transit session on the feed listener: only IHAVE, CHECK and TAKETHIS
+    /// are offered besides the session commands.
+    Transit,
+}
+
+impl SessionMode {
+    /// Whether `command` (upper case) may be used in this mode.
+    #[must_use]
+    pub fn allows(self, command: &str) -> bool {
+        const TRANSIT: &[&str] = &["IHAVE", "CHECK", "TAKETHIS"];
+        const READER: &[&str] = &[
+            "ARTICLE",
+            "HEAD",
+            "BODY",
+            "STAT",
+            "GROUP",
+            "LIST",
+            "LISTGROUP",
+            "NEXT",
+            "LAST",
+            "NEWGROUPS",
+            "NEWNEWS",
+            "HDR",
+            "XHDR",
+            "XPAT",
+            "OVER",
+            "XOVER",
+            "POST",
+        ];
+        match self {
+            Self::Mixed => true,
+            Self::Reader => !TRANSIT.contains(&command),
+            Self::Transit => !READER.contains(&command),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ConnectionState {
     pub current_group: Option<String>,
@@ -30,15 +86,73 @@ pub struct ConnectionState {
     pub username: Option<String>,
     pub is_tls: bool,
     pub in_stream_mode: bool,
+    pub mode: SessionMode,
+    pub compressed: bool,
     pub allow_posting_insecure: bool,
+    /// Certificate the client presented during the TLS handshake
+    pub client_cert: Option<ClientCertificate>,
+    /// Remote address of the client, when known
+    pub peer_ip: Option<IpAddr>,
+}
+
+impl ConnectionState {
+    /// The user this session acts as, once authenticated.
+    #[must_use]
+    pub fn user(&self) -> Option<&str> {
+        self.username.as_deref().filter(|_| self.authenticated)
+    }
+
+    /// Who is submitting articles on this connection, for the filters.
+    #[must_use]
+    pub fn submitter(&self) -> filters::Submitter {
+        filters::Submitter {
+            user: self.user().map(str::to_string),
+            ip: self.peer_ip,
+        }
+    }
+
+    /// Whether this connection may POST under `cfg`: reader posting must be
+    /// enabled for the listener, the session not a transit one, the
+    /// connection secure or exempted and its address allowed to submit.
+    #[must_use]
+    pub fn may_post(&self, cfg: &Config) -> bool {
+        self.mode != SessionMode::Transit
+            && cfg.posting_enabled_for(self.is_tls)
+            && (self.is_tls || self.allow_posting_insecure)
+            && self.may_submit(cfg)
+    }
+
+    /// Whether the remote address may submit articles at all under
+    /// `ip_access.posting_allow`.
+    #[must_use]
+    pub fn may_submit(&self, cfg: &Config) -> bool {
+        self.peer_ip
+            .is_none_or(|ip| cfg.ip_access.posting_allowed(ip))
+    }
+
+    /// How long this connection may wait between commands under `cfg`: the
+    /// override for its listener, else `idle_timeout_secs`.
+    #[must_use]
+    pub fn idle_timeout(&self, cfg: &Config) -> Duration {
+        let listener = if self.mode == SessionMode::Transit {
+            cfg.transit_idle_timeout_secs
+        } else if self.is_tls {
+            cfg.tls_idle_timeout_secs
+        } else {
+            cfg.plain_idle_timeout_secs
+        };
+        Duration::from_secs(listener.unwrap_or(cfg.idle_timeout_secs))
+    }
 }
 
-use crate::auth::DynAuth;
+use crate::auth::{ClientCertificate, DynAuth};
+use crate::compress::{DeflateWriter, InflateReader};
 use crate::config::Config;
 use crate::handlers::{HandlerContext, dispatch_command};
 use crate::queue::ArticleQueue;
 use crate::storage::DynStorage;
 use anyhow::Result;
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::{self, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
@@ -51,7 +165,6 @@ use tracing::debug;
 ///
 /// Returns an error if there's a problem handling the client connection,
 /// such as network I/O errors or protocol violations.
-#[tracing::instrument(skip(socket, storage, auth, cfg, queue))]
 pub async fn handle_client<S>(
     socket: S,
     storage: DynStorage,
@@ -60,36 +173,122 @@ pub async fn handle_client<S>(
     is_tls: bool,
     queue: ArticleQueue,
 ) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    handle_client_with_cert(socket, storage, auth, cfg, is_tls, None, None, queue).await
+}
+
+/// Handle a client connection from `peer_ip` that presented `client_cert`
+/// during the TLS handshake.
+///
+/// # Errors
+///
+/// Returns an error if there's a problem handling the client connection,
+/// such as network I/O errors or protocol violations.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(socket, storage, auth, cfg, client_cert, queue))]
+pub async fn handle_client_with_cert<S>(
+    socket: S,
+    storage: DynStorage,
+    auth: DynAuth,
+    cfg: Arc<RwLock<Config>>,
+    is_tls: bool,
+    client_cert: Option<ClientCertificate>,
+    peer_ip: Option<IpAddr>,
+    queue: ArticleQueue,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let state = ConnectionState {
+        is_tls,
+        client_cert,
+        peer_ip,
+        ..Default::default()
+    };
+    serve_client(socket, storage, auth, cfg, state, queue).await
+}
+
+/// Handle a connection from `peer_ip` on the transit listener, which only
+/// accepts feeding commands.
+///
+/// # Errors
+///
+/// Returns an error if there's a problem handling the client connection,
+/// such as network I/O errors or protocol violations.
+#[tracing::instrument(skip(socket, storage, auth, cfg, queue))]
+pub async fn handle_transit_client<S>(
+    socket: S,
+    storage: DynStorage,
+    auth: DynAuth,
+    cfg: Arc<RwLock<Config>>,
+    peer_ip: Option<IpAddr>,
+    queue: ArticleQueue,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let state = ConnectionState {
+        peer_ip,
+        mode: SessionMode::Transit,
+        ..Default::default()
+    };
+    serve_client(socket, storage, auth, cfg, state, queue).await
+}
+
+/// Run the command loop for a connection starting in `state`.
+async fn serve_client<S>(
+    socket: S,
+    storage: DynStorage,
+    auth: DynAuth,
+    cfg: Arc<RwLock<Config>>,
+    mut state: ConnectionState,
+    queue: ArticleQueue,
+) -> Result<()>
 where
     S: AsyncRead + AsyncWrite + Unpin,
 {
     use crate::responses::*;
 
+    let _connection = metrics::global().connection_opened();
+    let shutdown = queue.shutdown().clone();
+    let _session = shutdown.session();
     let (read_half, write_half) = io::split(socket);
-    let reader = BufReader::new(read_half);
+    let reader = BufReader::new(InflateReader::new(read_half));
 
-    // Read the config to get the allow_posting_insecure_connections flag
-    let allow_posting_insecure = {
+    // Read the config to decide whether the greeting offers posting and
+    // which user a mapped client certificate logs in as
+    let posting_enabled = {
         let cfg_guard = cfg.read().await;
-        cfg_guard.allow_posting_insecure_connections
+        state.allow_posting_insecure = cfg_guard.allow_posting_insecure_connections;
+        state.username = state
+            .client_cert
+            .as_ref()
+            .and_then(|cert| cfg_guard.user_for_certificate(cert));
+        state.authenticated = state.username.is_some();
+        // Feeds have a listener of their own, so reader listeners only read
+        if state.mode == SessionMode::Mixed && cfg_guard.transit_addr.is_some() {
+            state.mode = SessionMode::Reader;
+        }
+        cfg_guard.posting_enabled_for(state.is_tls) && state.may_submit(&cfg_guard)
     };
 
     let mut ctx = HandlerContext {
         reader,
-        writer: write_half,
+        writer: DeflateWriter::new(write_half),
         storage,
         auth,
         config: cfg,
-        state: ConnectionState {
-            is_tls,
-            allow_posting_insecure,
-            ..Default::default()
-        },
+        state,
         queue,
     };
 
-    // Send greeting
-    if is_tls || allow_posting_insecure {
+    // Send greeting; POST is never offered on the transit listener
+    if posting_enabled
+        && ctx.state.mode != SessionMode::Transit
+        && (ctx.state.is_tls || ctx.state.allow_posting_insecure)
+    {
         ctx.writer.write_all(RESP_200_READY.as_bytes()).await?;
     } else {
         ctx.writer
@@ -101,15 +300,28 @@ where
     loop {
         line.clear();
 
-        // Get the current idle timeout from config
+        // Push any buffered (possibly compressed) output to the client
+        // before waiting for the next command.
+        ctx.writer.flush().await?;
+
+        // Get the current idle timeout for this listener from config
         let timeout_duration = {
             let cfg_guard = ctx.config.read().await;
-            Duration::from_secs(cfg_guard.idle_timeout_secs)
+            ctx.state.idle_timeout(&cfg_guard)
         };
 
-        // Apply timeout to the read operation
-        let read_result =
-            tokio::time::timeout(timeout_duration, ctx.reader.read_line(&mut line)).await;
+        // Apply timeout to the read operation; an idle session is told the
+        // service is going away once shutdown starts
+        let read_result = tokio::select! {
+            // Commands already sent are still answered
+            biased;
+            result = tokio::time::timeout(timeout_duration, ctx.reader.read_line(&mut line)) => result,
+            () = shutdown.triggered() => {
+                ctx.writer.write_all(RESP_400_DISCONTINUED.as_bytes()).await?;
+                ctx.writer.flush().await?;
+                break;
+            }
+        };
 
         let n = match read_result {
             Ok(Ok(n)) => n,
@@ -120,6 +332,8 @@ where
                     "Connection timed out after {} seconds",
                     timeout_duration.as_secs()
                 );
+                ctx.writer.write_all(RESP_400_TIMEOUT.as_bytes()).await?;
+                ctx.writer.flush().await?;
                 break;
             }
         };
@@ -139,10 +353,37 @@ where
         // Handle QUIT specially since it needs to break the loop
         if cmd.name.as_str() == "QUIT" {
             ctx.writer.write_all(RESP_205_CLOSING.as_bytes()).await?;
+            ctx.writer.flush().await?;
+            break;
+        }
+
+        // Nothing new is taken while the queue drains for shutdown
+        if shutdown.is_triggered()
+            && matches!(cmd.name.as_str(), "POST" | "IHAVE" | "CHECK" | "TAKETHIS")
+        {
+            ctx.writer
+                .write_all(RESP_400_DISCONTINUED.as_bytes())
+                .await?;
+            ctx.writer.flush().await?;
             break;
         }
 
+        // COMPRESS replaces the connection's stream layer, so it is handled
+        // here rather than by a generic command handler.
+        if cmd.name.eq_ignore_ascii_case("COMPRESS") {
+            compress::handle_compress(&mut ctx, &cmd.args).await?;
+            continue;
+        }
+
         if let Err(e) = dispatch_command(&mut ctx, &cmd).await {
+            // A client that stalls while sending an article or SASL
+            // response is cut off
+            if e.is::<handlers::utils::ReadTimeout>() {
+                debug!("Command {} timed out reading from the client", cmd.name);
+                ctx.writer.write_all(RESP_400_TIMEOUT.as_bytes()).await?;
+                ctx.writer.flush().await?;
+                break;
+            }
             // Log the error but continue processing other commands
             debug!("Command {} failed: {}", cmd.name, e);
         }
@@ -1,12 +1,17 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 
 use clap::{Parser, Subcommand};
 use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
 
 use renews::auth;
 use renews::config::Config;
+use renews::queue::{ArticleQueue, WorkerPool};
 use renews::server;
 use renews::storage;
+use renews::subscriptions;
 
 #[derive(Parser)]
 struct Args {
@@ -19,6 +24,9 @@ struct Args {
     /// Allow posting without TLS for development
     #[arg(long)]
     allow_posting_insecure_connections: bool,
+    /// Fetch once from every configured subscription and exit
+    #[arg(long)]
+    sync_once: bool,
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -61,6 +69,10 @@ enum AdminCommand {
     UpdateKey { user: String, pgp_key: String },
     /// Set moderation status for a group
     SetModerated { group: String, moderated: String },
+    /// Set the description shown by LIST NEWSGROUPS
+    SetDescription { group: String, description: String },
+    /// Set the address unapproved posts to a moderated group are mailed to
+    SetModerator { group: String, address: String },
     /// Grant admin privileges to a user
     AddAdmin { user: String },
     /// Revoke admin privileges from a user
@@ -72,15 +84,20 @@ enum AdminCommand {
 }
 
 async fn run_admin(cmd: AdminCommand, cfg: &Config) -> Result<()> {
-    let storage = storage::open(&cfg.db_path).await?;
+    let storage = storage::open_configured(cfg).await?;
     let auth = auth::open(&cfg.auth_db_path).await?;
     match cmd {
         AdminCommand::AddGroup { group, groups } => {
+            let creator = cfg.effective_admin_address();
             // Add the first group
-            storage.add_group(&group, false).await?;
+            storage
+                .add_group_with_creator(&group, false, Some(&creator))
+                .await?;
             // Add any additional groups
             for g in groups {
-                storage.add_group(&g, false).await?;
+                storage
+                    .add_group_with_creator(&g, false, Some(&creator))
+                    .await?;
             }
         }
         AdminCommand::RemoveGroup { wildmat } => {
@@ -115,6 +132,12 @@ async fn run_admin(cmd: AdminCommand, cfg: &Config) -> Result<()> {
             };
             storage.set_group_moderated(&group, is_moderated).await?;
         }
+        AdminCommand::SetDescription { group, description } => {
+            storage.set_group_description(&group, &description).await?;
+        }
+        AdminCommand::SetModerator { group, address } => {
+            storage.set_group_moderator(&group, &address).await?;
+        }
         AdminCommand::AddAdmin { user } => {
             auth.add_admin_without_key(&user).await?;
         }
@@ -132,7 +155,7 @@ async fn run_admin(cmd: AdminCommand, cfg: &Config) -> Result<()> {
 }
 
 async fn run_init(cfg: &Config) -> Result<()> {
-    storage::open(&cfg.db_path).await?;
+    storage::open_configured(cfg).await?;
     auth::open(&cfg.auth_db_path).await?;
     let peer_db = renews::peers::PeerDb::new(&cfg.peer_db_path).await?;
     let names: Vec<String> = cfg.peers.iter().map(|p| p.sitename.clone()).collect();
@@ -140,6 +163,25 @@ async fn run_init(cfg: &Config) -> Result<()> {
     Ok(())
 }
 
+async fn run_sync_once(cfg: Config) -> Result<()> {
+    let storage = storage::open_configured(&cfg).await?;
+    let auth = auth::open(&cfg.auth_db_path).await?;
+    let queue = ArticleQueue::new(cfg.article_queue_capacity);
+    let workers = cfg.article_worker_count;
+    let subscriptions = cfg.subscriptions.clone();
+    let pool = WorkerPool::new(
+        queue.clone(),
+        storage.clone(),
+        auth,
+        Arc::new(RwLock::new(cfg)),
+        workers,
+    );
+    let _workers = pool.start().await;
+    let fetched = subscriptions::sync_all(&subscriptions, &storage, &queue).await?;
+    println!("Fetched {fetched} articles");
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
 fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
@@ -192,6 +234,14 @@ fn main() -> Result<()> {
             return Ok(());
         }
 
+        if args.sync_once {
+            if let Err(e) = run_sync_once(cfg_initial).await {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+
         if let Some(cmd) = args.command {
             match cmd {
                 Command::Admin(c) => {
@@ -0,0 +1,363 @@
+//! Process-wide counters exported in the Prometheus text format.
+//!
+//! Counting is always on and costs a few atomic operations; the HTTP
+//! listener that serves them is only built with the `metrics` feature.
+
+use crate::queue::ArticleQueue;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters shared by every connection and worker.
+pub struct Metrics {
+    articles_stored: AtomicU64,
+    connections: AtomicU64,
+    connections_total: AtomicU64,
+    connections_refused: AtomicU64,
+    worker_busy_micros: AtomicU64,
+    worker_failures: AtomicU64,
+    filter_rejections: Mutex<BTreeMap<&'static str, u64>>,
+    rejections: Mutex<BTreeMap<&'static str, u64>>,
+    commands: Mutex<BTreeMap<String, u64>>,
+    /// Call count and total microseconds per storage operation
+    storage_ops: Mutex<BTreeMap<&'static str, (u64, u64)>>,
+    feed_backlog: Mutex<BTreeMap<String, u64>>,
+}
+
+static METRICS: Metrics = Metrics {
+    articles_stored: AtomicU64::new(0),
+    connections: AtomicU64::new(0),
+    connections_total: AtomicU64::new(0),
+    connections_refused: AtomicU64::new(0),
+    worker_busy_micros: AtomicU64::new(0),
+    worker_failures: AtomicU64::new(0),
+    filter_rejections: Mutex::new(BTreeMap::new()),
+    rejections: Mutex::new(BTreeMap::new()),
+    commands: Mutex::new(BTreeMap::new()),
+    storage_ops: Mutex::new(BTreeMap::new()),
+    feed_backlog: Mutex::new(BTreeMap::new()),
+};
+
+/// Rejection reason reported for articles refused by the filter `filter`.
+fn rejection_reason(filter: &str) -> &'static str {
+    match filter {
+        "HeaderFilter" => "malformed",
+        "SizeFilter" => "size",
+        "GroupExistenceFilter" => "group",
+        "HistoryFilter" => "duplicate",
+        "PathLoopFilter" => "loop",
+        "ModerationFilter" => "moderation",
+        _ => "policy",
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The counters of this process.
+#[must_use]
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// Decrements the active connection gauge when the session ends.
+pub struct ConnectionGuard(());
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        METRICS.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    /// Count an article written to storage.
+    pub fn article_stored(&self) {
+        self.articles_stored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count an article refused by the filter called `filter`.
+    pub fn filter_rejected(&self, filter: &'static str) {
+        *lock(&self.filter_rejections).entry(filter).or_default() += 1;
+        self.article_rejected(rejection_reason(filter));
+    }
+
+    /// Count an incoming article refused for `reason`, such as `duplicate`
+    /// or `malformed`.
+    pub fn article_rejected(&self, reason: &'static str) {
+        *lock(&self.rejections).entry(reason).or_default() += 1;
+    }
+
+    /// Add the time a queue worker spent on one article.
+    pub fn worker_busy(&self, elapsed: Duration) {
+        self.worker_busy_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Count an article a queue worker failed on, by error or panic.
+    pub fn worker_failed(&self) {
+        self.worker_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record one call of the storage operation `op` taking `elapsed`.
+    pub fn storage_operation(&self, op: &'static str, elapsed: Duration) {
+        let mut map = lock(&self.storage_ops);
+        let (count, micros) = map.entry(op).or_default();
+        *count += 1;
+        *micros += elapsed.as_micros() as u64;
+    }
+
+    /// Set the number of articles queued for the push peer `peer`.
+    pub fn set_feed_backlog(&self, peer: &str, len: u64) {
+        lock(&self.feed_backlog).insert(peer.to_string(), len);
+    }
+
+    /// Count one more article queued for the push peer `peer`.
+    pub fn feed_queued(&self, peer: &str) {
+        *lock(&self.feed_backlog)
+            .entry(peer.to_string())
+            .or_default() += 1;
+    }
+
+    /// Count a dispatched command. Only names the server knows are passed
+    /// in, which keeps the label set small.
+    pub fn command(&self, name: &str) {
+        let mut map = lock(&self.commands);
+        match map.get_mut(name) {
+            Some(count) => *count += 1,
+            None => {
+                map.insert(name.to_string(), 1);
+            }
+        }
+    }
+
+    /// Mark a client connection as open until the guard is dropped.
+    #[must_use]
+    pub fn connection_opened(&self) -> ConnectionGuard {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(())
+    }
+
+    /// Count a connection turned away by the connection limits.
+    pub fn connection_refused(&self) {
+        self.connections_refused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render every metric, plus the state of `queue`, in the Prometheus
+    /// text exposition format.
+    #[must_use]
+    pub fn render(&self, queue: &ArticleQueue) -> String {
+        let mut out = String::new();
+        family(
+            &mut out,
+            "renews_articles_stored_total",
+            "counter",
+            "Articles written to storage.",
+            [(String::new(), self.articles_stored.load(Ordering::Relaxed))],
+        );
+        family(
+            &mut out,
+            "renews_connections_active",
+            "gauge",
+            "Open client connections.",
+            [(String::new(), self.connections.load(Ordering::Relaxed))],
+        );
+        family(
+            &mut out,
+            "renews_connections_total",
+            "counter",
+            "Client connections accepted.",
+            [(
+                String::new(),
+                self.connections_total.load(Ordering::Relaxed),
+            )],
+        );
+        family(
+            &mut out,
+            "renews_connections_refused_total",
+            "counter",
+            "Client connections refused by the connection limits.",
+            [(
+                String::new(),
+                self.connections_refused.load(Ordering::Relaxed),
+            )],
+        );
+        family(
+            &mut out,
+            "renews_queue_depth",
+            "gauge",
+            "Articles waiting for a worker.",
+            [(String::new(), queue.len() as u64)],
+        );
+        family(
+            &mut out,
+            "renews_queue_capacity",
+            "gauge",
+            "Articles the queue holds before submissions wait.",
+            [(String::new(), queue.capacity().unwrap_or(0) as u64)],
+        );
+        family(
+            &mut out,
+            "renews_queue_worker_busy_seconds_total",
+            "counter",
+            "Time queue workers spent processing articles.",
+            [(
+                String::new(),
+                seconds(self.worker_busy_micros.load(Ordering::Relaxed)),
+            )],
+        );
+        family(
+            &mut out,
+            "renews_queue_worker_failures_total",
+            "counter",
+            "Articles a queue worker failed to process.",
+            [(String::new(), self.worker_failures.load(Ordering::Relaxed))],
+        );
+        family(
+            &mut out,
+            "renews_articles_rejected_total",
+            "counter",
+            "Incoming articles refused, by reason.",
+            labelled("reason", &self.rejections),
+        );
+        family(
+            &mut out,
+            "renews_filter_rejections_total",
+            "counter",
+            "Articles refused, by filter.",
+            labelled("filter", &self.filter_rejections),
+        );
+        family(
+            &mut out,
+            "renews_commands_total",
+            "counter",
+            "Commands received, by command.",
+            labelled("command", &self.commands),
+        );
+        family(
+            &mut out,
+            "renews_rate_limit_throttled_total",
+            "counter",
+            "Submissions refused by the rate limiter.",
+            [(String::new(), queue.rate_limiter().stats().throttled)],
+        );
+        family(
+            &mut out,
+            "renews_feed_backlog",
+            "gauge",
+            "Articles queued for each push peer.",
+            labelled("peer", &self.feed_backlog),
+        );
+
+        // Sum and count make a summary without quantiles
+        let name = "renews_storage_operation_seconds";
+        let _ = writeln!(out, "# HELP {name} Time spent in storage, by operation.");
+        let _ = writeln!(out, "# TYPE {name} summary");
+        for (op, (count, micros)) in lock(&self.storage_ops).iter() {
+            let _ = writeln!(out, "{name}_sum{{operation=\"{op}\"}} {}", seconds(*micros));
+            let _ = writeln!(out, "{name}_count{{operation=\"{op}\"}} {count}");
+        }
+        out
+    }
+}
+
+fn seconds(micros: u64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+fn labelled<K: std::fmt::Display>(
+    label: &str,
+    map: &Mutex<BTreeMap<K, u64>>,
+) -> Vec<(String, u64)> {
+    lock(map)
+        .iter()
+        .map(|(value, count)| {
+            let value = value.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            (format!("{{{label}=\"{value}\"}}"), *count)
+        })
+        .collect()
+}
+
+/// Write one metric family: its help and type followed by every sample.
+fn family<V: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, V)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{name}{labels} {value}");
+    }
+}
+
+/// Answer `GET /metrics` on `listener` until the task is dropped.
+#[cfg(feature = "metrics")]
+pub async fn serve(listener: tokio::net::TcpListener, queue: ArticleQueue) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, _)) => {
+                let queue = queue.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = answer(socket, &queue).await {
+                        tracing::debug!("metrics request failed: {e}");
+                    }
+                });
+            }
+            Err(e) => tracing::error!("failed to accept metrics connection: {e}"),
+        }
+    }
+}
+
+/// How long a metrics client has to send its request.
+#[cfg(feature = "metrics")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most bytes of request line and headers read from a metrics client.
+#[cfg(feature = "metrics")]
+const MAX_REQUEST_HEAD: u64 = 8192;
+
+#[cfg(feature = "metrics")]
+async fn answer(socket: tokio::net::TcpStream, queue: &ArticleQueue) -> anyhow::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let (read, mut write) = socket.into_split();
+    let mut reader = BufReader::new(read).take(MAX_REQUEST_HEAD);
+    let mut request = String::new();
+    let head = async {
+        reader.read_line(&mut request).await?;
+        // Skip the headers; the request has no body we care about
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+                return Ok::<_, std::io::Error>(());
+            }
+        }
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, head)
+        .await
+        .map_err(|_| anyhow::anyhow!("no request after {}s", REQUEST_TIMEOUT.as_secs()))??;
+    let mut parts = request.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        // The cap ran out before the headers did
+        _ if reader.limit() == 0 => {
+            "HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        }
+        (Some("GET"), Some("/metrics")) => {
+            let body = global().render(queue);
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    write.write_all(response.as_bytes()).await?;
+    write.shutdown().await?;
+    Ok(())
+}
@@ -6,6 +6,7 @@
 use crate::Message;
 use crate::handlers::utils::{extract_message_id, get_header_value};
 use anyhow::Result;
+use std::fmt;
 
 /// Standard overview format fields as defined in RFC2980.
 /// This determines the order and content of fields returned by OVER/XOVER commands
@@ -20,6 +21,125 @@ pub const OVERVIEW_FORMAT: &[&str] = &[
     ":lines",
 ];
 
+/// Sanitize a header value for use as an overview field per RFC 3977
+/// Section 8.3: each TAB or NUL becomes a space and CR/LF characters are
+/// removed. PostgreSQL text cannot hold a NUL at all.
+pub fn sanitize_field(val: &str) -> String {
+    let mut v = val.replace(['\t', '\0'], " ");
+    v.retain(|c| c != '\r' && c != '\n');
+    v
+}
+
+/// Precomputed overview fields for a single article in a group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverviewLine {
+    pub number: u64,
+    pub subject: String,
+    pub from: String,
+    pub date: String,
+    pub message_id: String,
+    pub references: String,
+    pub bytes: u64,
+    pub lines: u64,
+}
+
+impl OverviewLine {
+    /// Build the overview fields for `article` stored as `number`.
+    pub async fn from_article(
+        storage: &dyn crate::storage::Storage,
+        number: u64,
+        article: &Message,
+    ) -> Result<Self> {
+        let bytes = if let Some(id) = extract_message_id(article) {
+            storage
+                .get_message_size(&id)
+                .await?
+                .unwrap_or(article.body.len() as u64)
+        } else {
+            article.body.len() as u64
+        };
+        Ok(Self::with_size(number, article, bytes))
+    }
+
+    /// Build the overview fields for `article` stored as `number` when its
+    /// stored size is already known, without asking the storage backend.
+    pub fn with_size(number: u64, article: &Message, bytes: u64) -> Self {
+        // Missing headers become empty fields
+        let field = |name: &str| {
+            get_header_value(article, name)
+                .map(|v| sanitize_field(&v))
+                .unwrap_or_default()
+        };
+
+        Self {
+            number,
+            subject: field("Subject"),
+            from: field("From"),
+            date: field("Date"),
+            message_id: field("Message-ID"),
+            references: field("References"),
+            bytes,
+            lines: crate::parse::body_lines(&article.body).count() as u64,
+        }
+    }
+
+    /// Whether `field` can be answered from the overview table alone.
+    pub fn has_field(field: &str) -> bool {
+        Self::default().field(field).is_some()
+    }
+
+    /// Value of an HDR field that the overview line already carries, or
+    /// `None` if `field` has to be read from the full headers. Entries of
+    /// [`OVERVIEW_FORMAT`] are accepted with their trailing colon.
+    pub fn field(&self, field: &str) -> Option<String> {
+        let field = field.strip_suffix(':').unwrap_or(field);
+        let value = match field.to_ascii_lowercase().as_str() {
+            "subject" => self.subject.clone(),
+            "from" => self.from.clone(),
+            "date" => self.date.clone(),
+            "message-id" => self.message_id.clone(),
+            "references" => self.references.clone(),
+            ":bytes" => self.bytes.to_string(),
+            ":lines" => self.lines.to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Parse a stored tab-separated overview line.
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut fields = line.split('\t');
+        let mut next = || {
+            fields
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("malformed overview line"))
+        };
+        Ok(Self {
+            number: next()?.parse()?,
+            subject: next()?.to_string(),
+            from: next()?.to_string(),
+            date: next()?.to_string(),
+            message_id: next()?.to_string(),
+            references: next()?.to_string(),
+            bytes: next()?.parse()?,
+            lines: next()?.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for OverviewLine {
+    /// Write the article number followed by the fields of
+    /// [`OVERVIEW_FORMAT`] in order, separated by tabs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number)?;
+        for entry in OVERVIEW_FORMAT {
+            let value = self.field(entry).ok_or(fmt::Error)?;
+            write!(f, "\t{value}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Generate overview line for an article according to the standard format.
 /// Returns a tab-separated line with article number and overview fields.
 pub async fn generate_overview_line(
@@ -27,32 +147,51 @@ pub async fn generate_overview_line(
     article_number: u64,
     article: &Message,
 ) -> Result<String> {
-    let subject = get_header_value(article, "Subject").unwrap_or_default();
-    let from = get_header_value(article, "From").unwrap_or_default();
-    let date = get_header_value(article, "Date").unwrap_or_default();
-    let msgid = get_header_value(article, "Message-ID").unwrap_or_default();
-    let refs = get_header_value(article, "References").unwrap_or_default();
-
-    let bytes = if let Some(id) = extract_message_id(article) {
-        storage
-            .get_message_size(&id)
-            .await?
-            .unwrap_or(article.body.len() as u64)
-    } else {
-        article.body.len() as u64
-    };
-
-    let lines = article.body.lines().count();
-
-    Ok(format!(
-        "{article_number}\t{subject}\t{from}\t{date}\t{msgid}\t{refs}\t{bytes}\t{lines}"
-    ))
-}
-
-/// Get the overview format fields for LIST OVERVIEW.FMT command.
-pub fn get_overview_format_lines() -> Vec<String> {
+    Ok(OverviewLine::from_article(storage, article_number, article)
+        .await?
+        .to_string())
+}
+
+/// Header name of a configured extra overview field, accepting the
+/// `Name`, `Name:` and `Name:full` spellings.
+pub fn extra_field_name(entry: &str) -> &str {
+    let entry = entry.trim();
+    entry
+        .strip_suffix(":full")
+        .or_else(|| entry.strip_suffix(':'))
+        .unwrap_or(entry)
+}
+
+/// Overview field for the extra header `name`: the header name and value
+/// as RFC 3977 requires for `:full` fields, or empty when it is missing.
+pub fn extra_field(name: &str, value: Option<&str>) -> String {
+    value.map_or_else(String::new, |v| format!("{name}: {}", sanitize_field(v)))
+}
+
+/// Get the overview format fields for LIST OVERVIEW.FMT command: the
+/// standard fields followed by the configured `extra` headers.
+pub fn get_overview_format_lines(extra: &[String]) -> Vec<String> {
     OVERVIEW_FORMAT
         .iter()
         .map(|&s| format!("{s}\r\n"))
+        .chain(
+            extra
+                .iter()
+                .map(|e| format!("{}:full\r\n", extra_field_name(e))),
+        )
+        .collect()
+}
+
+/// Get the fields HDR can answer for LIST HEADERS: any header, shown as
+/// `:`, plus the metadata items of the overview format.
+pub fn get_header_list_lines() -> Vec<String> {
+    std::iter::once(":")
+        .chain(
+            OVERVIEW_FORMAT
+                .iter()
+                .copied()
+                .filter(|s| s.starts_with(':')),
+        )
+        .map(|s| format!("{s}\r\n"))
         .collect()
 }
@@ -1,7 +1,7 @@
 use crate::storage::DynStorage;
 use anyhow;
-use chrono::TimeZone;
-use futures_util::{TryStreamExt, future};
+use chrono::{Datelike, TimeZone};
+use futures_util::TryStreamExt;
 use nom::IResult;
 use nom::{
     bytes::complete::{is_not, take_till, take_while1},
@@ -77,7 +77,43 @@ pub fn parse_response(input: &str) -> IResult<&str, Response> {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Message {
     pub headers: SmallVec<[(String, String); 8]>,
-    pub body: String,
+    /// The body as received; 8-bit and binary content is kept octet for octet.
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    /// Render the article the way it travels on the wire, without
+    /// dot-stuffing.
+    #[must_use]
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut text = Vec::with_capacity(self.body.len() + 512);
+        for (name, value) in &self.headers {
+            text.extend_from_slice(name.as_bytes());
+            text.extend_from_slice(b": ");
+            text.extend_from_slice(value.as_bytes());
+            text.extend_from_slice(b"\r\n");
+        }
+        text.extend_from_slice(b"\r\n");
+        text.extend_from_slice(&self.body);
+        text
+    }
+
+    /// The body as text, with any octets that are not UTF-8 replaced.
+    #[must_use]
+    pub fn body_text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+}
+
+/// Split `body` into lines the way [`str::lines`] does, on LF with an
+/// optional CR before it, without requiring the body to be UTF-8.
+pub fn body_lines(body: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let lines = if body.is_empty() { 0 } else { usize::MAX };
+    body.strip_suffix(b"\n")
+        .unwrap_or(body)
+        .split(|&b| b == b'\n')
+        .take(lines)
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
 }
 
 /// Unescape a Message-ID according to RFC 2822 quoted-pair rules.
@@ -252,18 +288,34 @@ fn parse_headers(mut input: &str) -> IResult<&str, SmallVec<[(String, String); 8
 /// Parse an entire article consisting of headers and body
 /// following the rules in RFC 3977 Section 3.6.
 ///
+/// The body is taken as raw octets. Header octets that are not UTF-8 are
+/// decoded lossily so the article still parses and filters can refuse it.
+///
 /// # Errors
 ///
 /// Returns a parsing error if the input is not a valid message format.
-pub fn parse_message(input: &str) -> IResult<&str, Message> {
-    let (input, mut headers) = parse_headers(input)?;
+pub fn parse_message<T: AsRef<[u8]> + ?Sized>(input: &T) -> IResult<&[u8], Message> {
+    let input = input.as_ref();
+    let invalid = || nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Verify));
+    // The header block runs up to and including the first empty line
+    let end = if input.starts_with(b"\r\n") {
+        2
+    } else {
+        input
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(invalid)?
+            + 4
+    };
+    let text = String::from_utf8_lossy(&input[..end]);
+    let (_, mut headers) = parse_headers(&text).map_err(|_| invalid())?;
     for (name, val) in &mut headers {
         if name.eq_ignore_ascii_case("Message-ID") {
             *val = unescape_message_id(val);
         }
     }
-    let body = input.to_string();
-    Ok(("", Message { headers, body }))
+    let body = input[end..].to_vec();
+    Ok((&input[input.len()..], Message { headers, body }))
 }
 
 /// Ensure a Message-ID header is present. When missing, one is
@@ -276,7 +328,7 @@ pub fn ensure_message_id(msg: &mut Message, domain: &str) {
     {
         return;
     }
-    let hash = Sha1::digest(msg.body.as_bytes());
+    let hash = Sha1::digest(&msg.body);
     let mut hex = String::new();
     for b in hash {
         let _ = write!(hex, "{b:02x}");
@@ -285,6 +337,17 @@ pub fn ensure_message_id(msg: &mut Message, domain: &str) {
         .push(("Message-ID".into(), format!("<{hex}@{domain}>")));
 }
 
+/// A new Message-ID of the form `<timestamp.random@domain>`, with the
+/// timestamp in milliseconds and 64 random bits.
+#[must_use]
+pub fn generate_message_id(domain: &str) -> String {
+    format!(
+        "<{}.{:016x}@{domain}>",
+        chrono::Utc::now().timestamp_millis(),
+        rand::random::<u64>()
+    )
+}
+
 /// Ensure a Date header is present. When missing, one is set to the current
 /// time in RFC 2822 format.
 pub fn ensure_date(msg: &mut Message) {
@@ -309,6 +372,21 @@ pub fn parse_datetime(
     date: &str,
     time: &str,
     gmt: bool,
+) -> Result<chrono::DateTime<chrono::Utc>, &'static str> {
+    parse_datetime_at(date, time, gmt, chrono::Utc::now().date_naive())
+}
+
+/// [`parse_datetime`] with two-digit years resolved relative to `today`
+/// rather than the system clock.
+///
+/// # Errors
+///
+/// Returns an error if the date or time format is invalid.
+pub fn parse_datetime_at(
+    date: &str,
+    time: &str,
+    gmt: bool,
+    today: chrono::NaiveDate,
 ) -> Result<chrono::DateTime<chrono::Utc>, &'static str> {
     if !(date.len() == 6 || date.len() == 8) || !date.chars().all(|c| c.is_ascii_digit()) {
         return Err("invalid date");
@@ -316,8 +394,24 @@ pub fn parse_datetime(
     if time.len() != 6 || !time.chars().all(|c| c.is_ascii_digit()) {
         return Err("invalid time");
     }
-    let fmt = if date.len() == 6 { "%y%m%d" } else { "%Y%m%d" };
-    let naive_date = chrono::NaiveDate::parse_from_str(date, fmt).map_err(|_| "invalid date")?;
+    let naive_date = if date.len() == 6 {
+        // RFC 3977 7.3.2: a two-digit year is in the current century unless
+        // that would put the date in the future, in which case the previous one
+        let century = today.year() - today.year().rem_euclid(100);
+        let yy: i32 = date[..2].parse().map_err(|_| "invalid date")?;
+        let in_year = |year: i32| {
+            chrono::NaiveDate::parse_from_str(&format!("{year:04}{}", &date[2..]), "%Y%m%d")
+                .map_err(|_| "invalid date")
+        };
+        let candidate = in_year(century + yy)?;
+        if candidate > today {
+            in_year(century - 100 + yy)?
+        } else {
+            candidate
+        }
+    } else {
+        chrono::NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| "invalid date")?
+    };
     let naive_time =
         chrono::NaiveTime::parse_from_str(time, "%H%M%S").map_err(|_| "invalid time")?;
     let naive = naive_date.and_time(naive_time);
@@ -332,6 +426,31 @@ pub fn parse_datetime(
     })
 }
 
+/// Parse an article number range into inclusive bounds without consulting
+/// storage. An open-ended range (`N-`) extends to `u64::MAX`.
+///
+/// # Errors
+///
+/// Returns an error if the range format is invalid.
+pub fn parse_range_bounds(spec: &str) -> anyhow::Result<std::ops::RangeInclusive<u64>> {
+    if let Some((start_s, end_s)) = spec.split_once('-') {
+        let start: u64 = start_s
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid range"))?;
+        let end: u64 = if end_s.is_empty() {
+            u64::MAX
+        } else {
+            end_s
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid range"))?
+        };
+        Ok(start..=end)
+    } else {
+        let n: u64 = spec.parse()?;
+        Ok(n..=n)
+    }
+}
+
 /// Parse the article number range format used by several commands
 /// such as LISTGROUP as defined in RFC 3977 Section 6.1.2.
 ///
@@ -348,12 +467,10 @@ pub async fn parse_range(
             .parse()
             .map_err(|_| anyhow::anyhow!("invalid range"))?;
         if end_s.is_empty() {
-            let stream = storage.list_article_numbers(group);
-            let nums = stream
-                .try_filter(|n| future::ready(*n >= start))
-                .try_collect::<Vec<u64>>()
-                .await?;
-            Ok(nums)
+            storage
+                .list_article_numbers_in_range(group, start, u64::MAX)
+                .try_collect()
+                .await
         } else {
             let end: u64 = end_s
                 .parse()
@@ -372,6 +489,48 @@ pub async fn parse_range(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_range_bounds() {
+        assert_eq!(parse_range_bounds("5").unwrap(), 5..=5);
+        assert_eq!(parse_range_bounds("3-7").unwrap(), 3..=7);
+        assert_eq!(parse_range_bounds("3-").unwrap(), 3..=u64::MAX);
+        assert!(parse_range_bounds("x-7").is_err());
+        assert!(parse_range_bounds("abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_datetime_two_digit_year() {
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let at = |date: &str| parse_datetime_at(date, "000000", true, today).unwrap();
+
+        assert_eq!(at("000101").year(), 2000);
+        assert_eq!(at("261016").year(), 2026);
+        // A two-digit date later than today belongs to the previous century
+        assert_eq!(at("261017").year(), 1926);
+        assert_eq!(at("990101").year(), 1999);
+
+        let full = parse_datetime("19991231", "235959", true).unwrap();
+        assert_eq!(full.year(), 1999);
+    }
+
+    #[test]
+    fn test_parse_datetime_two_digit_year_at_century_start() {
+        let today = chrono::NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
+        let at = |date: &str| parse_datetime_at(date, "000000", true, today).unwrap();
+
+        assert_eq!(at("000101").year(), 2100);
+        assert_eq!(at("000102").year(), 2000);
+        assert_eq!(at("991231").year(), 2099);
+    }
+
+    #[test]
+    fn test_parse_datetime_rejects_bad_forms() {
+        assert!(parse_datetime("2601016", "000000", true).is_err());
+        assert!(parse_datetime("20261301", "000000", true).is_err());
+        assert!(parse_datetime("20261016", "246000", true).is_err());
+        assert!(parse_datetime("20261016", "0000", true).is_err());
+    }
+
     #[test]
     fn test_parse_command_simple() {
         let (_, cmd) = parse_command("ARTICLE\r\n").unwrap();
@@ -407,7 +566,7 @@ mod tests {
         assert_eq!(msg.headers.len(), 2);
         assert_eq!(msg.headers[0], ("Subject".into(), "Test".into()));
         assert_eq!(msg.headers[1], ("From".into(), "user@example.com".into()));
-        assert_eq!(msg.body, "This is the body.");
+        assert_eq!(msg.body, b"This is the body.");
     }
 
     #[test]
@@ -420,7 +579,7 @@ mod tests {
         let expected_headers: SmallVec<[(String, String); 8]> =
             smallvec![("Subject".to_string(), "Example".to_string())];
         assert_eq!(msg.headers, expected_headers);
-        assert_eq!(msg.body, "Body text");
+        assert_eq!(msg.body, b"Body text");
     }
 
     #[test]
@@ -441,7 +600,7 @@ mod tests {
             ("Subject".into(), "A first continued".into())
         );
         assert_eq!(msg.headers[1], ("From".into(), "user@example.com".into()));
-        assert_eq!(msg.body, "Body");
+        assert_eq!(msg.body, b"Body");
     }
 
     #[test]
@@ -468,6 +627,14 @@ mod tests {
         assert_eq!(ids, vec!["<1@test>".to_string()]);
     }
 
+    #[test]
+    fn test_generated_message_ids_differ() {
+        let first = generate_message_id("example.com");
+        let second = generate_message_id("example.com");
+        assert!(first.starts_with('<') && first.ends_with("@example.com>"));
+        assert_ne!(first, second);
+    }
+
     #[test]
     fn test_ensure_message_id_format() {
         let (_, mut msg) = parse_message("Newsgroups: misc\r\n\r\nTestBody").unwrap();
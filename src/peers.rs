@@ -7,45 +7,66 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use futures_util::{StreamExt, TryStreamExt};
-use rustls_native_certs::load_native_certs;
 use sqlx::{
     Row, SqlitePool,
     sqlite::{SqliteConnectOptions, SqlitePoolOptions},
 };
 use std::str::FromStr;
-use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::TcpStream;
 use tokio_cron_scheduler::{Job, JobScheduler};
-use tokio_rustls::{
-    TlsConnector,
-    rustls::{self, RootCertStore},
-};
 use uuid;
 
+use crate::client::NntpClient;
 use crate::storage::DynStorage;
+use crate::storage::common::{path_contains, stamp_path};
 use crate::wildmat::wildmat;
-use crate::{
-    Message,
-    handlers::utils::{extract_message_id, send_body, send_headers, write_simple},
-};
+use crate::{Message, handlers::utils::extract_message_id};
 
 /// Result type for peer operations.
 type PeerResult<T> = Result<T>;
 
 /// Connection credentials for peer authentication.
 #[derive(Debug, Clone)]
-struct PeerCredentials {
-    username: String,
-    password: String,
+pub(crate) struct PeerCredentials {
+    pub(crate) username: String,
+    pub(crate) password: String,
 }
 
 /// Parsed peer connection information.
 #[derive(Debug, Clone)]
-struct PeerConnectionInfo {
-    host: String,
-    port: u16,
-    credentials: Option<PeerCredentials>,
+pub(crate) struct PeerConnectionInfo {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) credentials: Option<PeerCredentials>,
+    pub(crate) tls: bool,
+    /// Upgrade a plain connection with STARTTLS before logging in.
+    pub(crate) starttls: bool,
+}
+
+impl PeerConnectionInfo {
+    /// Connection details from explicit settings. The port defaults to 563
+    /// with TLS and 119 without; credentials are used only when both are set.
+    pub(crate) fn from_parts(
+        host: &str,
+        port: Option<u16>,
+        tls: bool,
+        username: Option<&str>,
+        password: Option<&str>,
+    ) -> Self {
+        let credentials = match (username, password) {
+            (Some(username), Some(password)) => Some(PeerCredentials {
+                username: username.to_string(),
+                password: password.to_string(),
+            }),
+            _ => None,
+        };
+        Self {
+            host: host.to_string(),
+            port: port.unwrap_or(if tls { 563 } else { 119 }),
+            credentials,
+            tls,
+            starttls: false,
+        }
+    }
 }
 
 /// Parse peer address string into connection components.
@@ -63,6 +84,8 @@ fn parse_peer_address(addr: &str, default_port: u16) -> PeerConnectionInfo {
         host,
         port,
         credentials,
+        tls: true,
+        starttls: false,
     }
 }
 
@@ -129,145 +152,6 @@ fn parse_regular_address(host_port: &str, default_port: u16) -> (String, u16) {
     (host_port[..colon_pos].to_string(), port)
 }
 
-/// Creates a TLS connector for secure peer connections.
-fn create_tls_connector() -> PeerResult<TlsConnector> {
-    let mut roots = RootCertStore::empty();
-    for cert in load_native_certs()? {
-        roots.add(&rustls::Certificate(cert.0))?;
-    }
-    let config = rustls::ClientConfig::builder()
-        .with_safe_defaults()
-        .with_root_certificates(roots)
-        .with_no_client_auth();
-    Ok(TlsConnector::from(Arc::new(config)))
-}
-
-/// Manages a connection to a peer NNTP server.
-struct PeerConnection {
-    reader: BufReader<tokio::io::ReadHalf<tokio_rustls::client::TlsStream<TcpStream>>>,
-    writer: tokio::io::WriteHalf<tokio_rustls::client::TlsStream<TcpStream>>,
-    line_buffer: String,
-}
-
-impl PeerConnection {
-    /// Establish a connection to a peer server.
-    async fn connect(connection_info: &PeerConnectionInfo) -> PeerResult<Self> {
-        let addr = format!("{}:{}", connection_info.host, connection_info.port);
-        let tcp = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to connect to {addr}: {e}"))?;
-
-        let connector = create_tls_connector()
-            .map_err(|e| anyhow::anyhow!("Failed to create TLS connector: {e}"))?;
-
-        let server_name =
-            rustls::ServerName::try_from(connection_info.host.as_str()).map_err(|e| {
-                anyhow::anyhow!("Invalid server name '{}': {}", connection_info.host, e)
-            })?;
-
-        let tls_stream = connector
-            .connect(server_name, tcp)
-            .await
-            .map_err(|e| anyhow::anyhow!("TLS handshake failed for {addr}: {e}"))?;
-
-        let (read_half, write_half) = tokio::io::split(tls_stream);
-
-        let mut connection = Self {
-            reader: BufReader::new(read_half),
-            writer: write_half,
-            line_buffer: String::new(),
-        };
-
-        // Read and validate greeting
-        let greeting = connection.read_response().await?;
-        if !greeting.starts_with("200") && !greeting.starts_with("201") {
-            return Err(anyhow::anyhow!(
-                "Unexpected greeting from {}: {}",
-                addr,
-                greeting.trim()
-            ));
-        }
-
-        // Authenticate if credentials are provided
-        if let Some(creds) = &connection_info.credentials {
-            connection
-                .authenticate(creds)
-                .await
-                .map_err(|e| anyhow::anyhow!("Authentication failed for {addr}: {e}"))?;
-        }
-
-        Ok(connection)
-    }
-
-    /// Read a response line from the server.
-    async fn read_response(&mut self) -> PeerResult<&str> {
-        self.line_buffer.clear();
-        self.reader.read_line(&mut self.line_buffer).await?;
-        Ok(&self.line_buffer)
-    }
-
-    /// Send a command to the server.
-    async fn send_command(&mut self, command: &str) -> PeerResult<()> {
-        write_simple(&mut self.writer, command).await
-    }
-
-    /// Authenticate with the server using provided credentials.
-    async fn authenticate(&mut self, creds: &PeerCredentials) -> PeerResult<()> {
-        self.send_command(&format!("AUTHINFO USER {}\r\n", creds.username))
-            .await?;
-        let response = self.read_response().await?;
-
-        if response.starts_with("381") {
-            self.send_command(&format!("AUTHINFO PASS {}\r\n", creds.password))
-                .await?;
-            let response = self.read_response().await?;
-            if !response.starts_with("281") {
-                return Err(anyhow::anyhow!("Authentication failed"));
-            }
-        } else if !response.starts_with("281") {
-            return Err(anyhow::anyhow!("Authentication failed"));
-        }
-
-        Ok(())
-    }
-
-    /// Transfer an article using IHAVE protocol.
-    async fn transfer_article(&mut self, article: &Message, msg_id: &str) -> PeerResult<()> {
-        // Send IHAVE command
-        self.send_command(&format!("IHAVE {msg_id}\r\n")).await?;
-        let response = self.read_response().await?;
-        if !response.starts_with("335") {
-            return Ok(()); // Article not wanted by peer
-        }
-
-        // Send article content
-        self.send_article_content(article).await?;
-
-        // Read and validate final response
-        let response = self.read_response().await?;
-        if !response.starts_with("2") {
-            return Err(anyhow::anyhow!("Transfer failed: {}", response.trim()));
-        }
-
-        Ok(())
-    }
-
-    /// Send the complete article content including headers and body.
-    async fn send_article_content(&mut self, article: &Message) -> PeerResult<()> {
-        send_headers(&mut self.writer, article).await?;
-        self.send_command("\r\n").await?;
-        send_body(&mut self.writer, &article.body).await?;
-        self.send_command(".\r\n").await?;
-        Ok(())
-    }
-
-    /// Close the connection gracefully.
-    async fn close(mut self) -> PeerResult<()> {
-        let _ = self.writer.shutdown().await;
-        Ok(())
-    }
-}
-
 #[derive(Clone)]
 pub struct PeerDb {
     pool: SqlitePool,
@@ -480,7 +364,7 @@ async fn send_article_to_peer(host: &str, article: &Message) -> PeerResult<()> {
         .ok_or_else(|| anyhow::anyhow!("Article missing Message-ID header"))?;
 
     let connection_info = parse_peer_address(host, 563);
-    let mut connection = PeerConnection::connect(&connection_info)
+    let mut connection = NntpClient::open(&connection_info)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to connect to peer {host}: {e}"))?;
 
@@ -628,31 +512,14 @@ async fn process_fetched_article(
 }
 
 /// Creates a copy of an article with appropriate Path header for peer distribution.
-fn create_peer_article(orig: &Message, site_name: &str) -> PeerResult<Message> {
+pub(crate) fn create_peer_article(orig: &Message, site_name: &str) -> PeerResult<Message> {
     let mut article = orig.clone();
-
-    // Update or add Path header
-    if let Some((_, path_value)) = article
-        .headers
-        .iter_mut()
-        .find(|(k, _)| k.eq_ignore_ascii_case("Path"))
-    {
-        *path_value = format!("{site_name}!{path_value}");
-    } else {
-        article.headers.push(("Path".into(), site_name.to_string()));
-    }
-
+    // Articles stored since Path stamping was added already carry our name
+    stamp_path(&mut article, site_name);
     Ok(article)
 }
 
 /// Checks if an article should be skipped for a specific peer.
-fn should_skip_article(article: &Message, peer_sitename: &str) -> bool {
-    article
-        .headers
-        .iter()
-        .filter(|(k, _)| k.eq_ignore_ascii_case("Path"))
-        .any(|(_, path)| {
-            path.split('!')
-                .any(|segment| segment.trim() == peer_sitename)
-        })
+pub(crate) fn should_skip_article(article: &Message, peer_sitename: &str) -> bool {
+    path_contains(article, peer_sitename)
 }
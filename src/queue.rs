@@ -6,13 +6,23 @@
 
 use crate::Message;
 use crate::auth::DynAuth;
-use crate::config::Config;
+use crate::config::{Config, QueueFullPolicy};
+use crate::feed::Feeds;
+use crate::filters::duplicate_body::RecentBodies;
+use crate::filters::history::HistoryCache;
+use crate::ratelimit::RateLimiter;
+use crate::shutdown::Shutdown;
 use crate::storage::DynStorage;
+use crate::storage::common::{extract_message_id, prepare_for_storage};
 use anyhow::Result;
 use flume::{Receiver, Sender};
+use futures_util::FutureExt;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{RwLock, watch};
+use tracing::{debug, error, info, warn};
 
 /// An article queued for processing
 #[derive(Debug, Clone)]
@@ -25,20 +35,109 @@ pub struct QueuedArticle {
     pub is_control: bool,
     /// Whether comprehensive validation has already been done
     pub already_validated: bool,
+    /// Notified with the outcome once a worker has finished with the article
+    pub ack: Option<Sender<Outcome>>,
 }
 
-/// Article processing queue using flume MPMC
+/// What a worker made of a queued article, as sent on its `ack` channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Stored, or acted on or already known so there was nothing to store
+    Stored,
+    /// Turned away by the filters; offering it again would not change that
+    Refused,
+    /// Processing failed, possibly only for now
+    Failed,
+}
+
+/// A filter rejection raised in a worker, told apart from failures that
+/// may go away on a retry
+#[derive(Debug)]
+struct Refused(anyhow::Error);
+
+impl std::fmt::Display for Refused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// Returned by [`ArticleQueue::try_submit`] when every slot is taken or the
+/// queue was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("article queue is full")
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+/// Article processing queue using flume MPMC. Control messages travel in a
+/// lane of their own that workers drain first, so cancels and group changes
+/// are not stuck behind a backlog of ordinary articles.
 #[derive(Clone)]
 pub struct ArticleQueue {
     sender: Sender<QueuedArticle>,
     receiver: Receiver<QueuedArticle>,
+    control_sender: Sender<QueuedArticle>,
+    control_receiver: Receiver<QueuedArticle>,
+    limiter: Arc<RateLimiter>,
+    history: Option<Arc<HistoryCache>>,
+    recent_bodies: Arc<RecentBodies>,
+    feeds: Option<Arc<Feeds>>,
+    shutdown: Shutdown,
+    closed: Arc<AtomicBool>,
+    /// Articles submitted and not yet finished by a worker
+    in_flight: Arc<watch::Sender<usize>>,
 }
 
 impl ArticleQueue {
-    /// Create a new article queue with the specified capacity
+    /// Create a new article queue with the specified capacity. The control
+    /// lane holds as many messages again.
     pub fn new(capacity: usize) -> Self {
         let (sender, receiver) = flume::bounded(capacity);
-        Self { sender, receiver }
+        let (control_sender, control_receiver) = flume::bounded(capacity);
+        Self {
+            sender,
+            receiver,
+            control_sender,
+            control_receiver,
+            limiter: Arc::new(RateLimiter::new()),
+            history: None,
+            recent_bodies: Arc::new(RecentBodies::default()),
+            feeds: None,
+            shutdown: Shutdown::new(),
+            closed: Arc::new(AtomicBool::new(false)),
+            in_flight: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// Remember the last `size` offered Message-IDs so duplicates are refused
+    /// without a storage lookup. A size of zero keeps no history.
+    pub fn with_history(mut self, size: usize) -> Self {
+        self.history = (size > 0).then(|| Arc::new(HistoryCache::new(size)));
+        self
+    }
+
+    /// Queue every stored article for the push feeds of `feeds`.
+    pub fn with_feeds(mut self, feeds: Arc<Feeds>) -> Self {
+        self.feeds = (!feeds.is_empty()).then_some(feeds);
+        self
+    }
+
+    /// Hand a newly stored article to the push feeds, if any. A failure is
+    /// logged rather than returned since the article is already stored.
+    pub async fn article_stored(&self, article: &Message) {
+        let Some(feeds) = &self.feeds else {
+            return;
+        };
+        if let Err(e) = feeds.article_accepted(article).await {
+            error!("Failed to queue article for peers: {}", e);
+        }
     }
 
     /// Submit an article to the queue for processing
@@ -46,16 +145,173 @@ impl ArticleQueue {
     /// Returns Ok(()) if the article was queued successfully,
     /// Err if the queue is full or closed.
     pub async fn submit(&self, article: QueuedArticle) -> Result<()> {
-        self.sender
-            .send_async(article)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to queue article: {e}"))
+        if !self.begin() {
+            return Err(anyhow::anyhow!("Failed to queue article: queue is closed"));
+        }
+        self.lane(&article).send_async(article).await.map_err(|e| {
+            self.finished();
+            anyhow::anyhow!("Failed to queue article: {e}")
+        })
+    }
+
+    /// Submit an article without waiting, failing if the queue is full or
+    /// closed
+    pub fn try_submit(&self, article: QueuedArticle) -> Result<(), QueueFull> {
+        if !self.begin() {
+            return Err(QueueFull);
+        }
+        // The queue holds a receiver itself, so sending can only fail when full
+        self.lane(&article).try_send(article).map_err(|_| {
+            self.finished();
+            QueueFull
+        })
+    }
+
+    /// The lane `article` is queued in.
+    fn lane(&self, article: &QueuedArticle) -> &Sender<QueuedArticle> {
+        if article.is_control {
+            &self.control_sender
+        } else {
+            &self.sender
+        }
+    }
+
+    /// Count a submission as in flight unless the queue is closed.
+    fn begin(&self) -> bool {
+        self.in_flight.send_modify(|n| *n += 1);
+        if self.closed.load(Ordering::SeqCst) {
+            self.finished();
+            return false;
+        }
+        true
+    }
+
+    /// Mark one submitted article as finished.
+    fn finished(&self) {
+        self.in_flight.send_modify(|n| *n = n.saturating_sub(1));
+    }
+
+    /// Refuse further submissions and wait until the workers have finished
+    /// every article already queued.
+    pub async fn close_and_drain(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let mut rx = self.in_flight.subscribe();
+        let _ = rx.wait_for(|n| *n == 0).await;
+    }
+
+    /// Submitted articles the workers have not finished yet.
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.borrow()
+    }
+
+    /// Whether [`ArticleQueue::close_and_drain`] was called.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Shutdown signal shared by the sessions using this queue.
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// Submit an article, waiting at most `timeout` for room and failing if
+    /// the queue is still full or closed
+    pub async fn submit_timeout(
+        &self,
+        article: QueuedArticle,
+        timeout: Duration,
+    ) -> Result<(), QueueFull> {
+        if !self.begin() {
+            return Err(QueueFull);
+        }
+        match tokio::time::timeout(timeout, self.lane(&article).send_async(article)).await {
+            Ok(Ok(())) => Ok(()),
+            _ => {
+                self.finished();
+                Err(QueueFull)
+            }
+        }
+    }
+
+    /// Submit an article, waiting for room or failing at once as `policy`
+    /// says. Under [`QueueFullPolicy::Wait`] a `wait_timeout` bounds the wait.
+    /// A full queue is reported as a [`QueueFull`] error.
+    pub async fn submit_with(
+        &self,
+        article: QueuedArticle,
+        policy: QueueFullPolicy,
+        wait_timeout: Option<Duration>,
+    ) -> Result<()> {
+        match (policy, wait_timeout) {
+            (QueueFullPolicy::Wait, None) => self.submit(article).await,
+            (QueueFullPolicy::Wait, Some(timeout)) => {
+                Ok(self.submit_timeout(article, timeout).await?)
+            }
+            (QueueFullPolicy::Reject, _) => Ok(self.try_submit(article)?),
+        }
+    }
+
+    /// Number of articles waiting for a worker, control messages included
+    pub fn len(&self) -> usize {
+        self.sender.len() + self.control_sender.len()
+    }
+
+    /// Whether no article is waiting for a worker
+    pub fn is_empty(&self) -> bool {
+        self.sender.is_empty() && self.control_sender.is_empty()
+    }
+
+    /// Maximum number of waiting ordinary articles, if bounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.sender.capacity()
+    }
+
+    /// Whether the ordinary lane is at capacity and a submission would have
+    /// to wait
+    pub fn is_full(&self) -> bool {
+        self.sender.is_full()
+    }
+
+    /// Rate limiter shared by every connection submitting to this queue
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Message-ID history shared by every connection and worker, if enabled
+    pub fn history(&self) -> Option<&Arc<HistoryCache>> {
+        self.history.as_ref()
+    }
+
+    /// Recently seen article bodies, shared by every connection and worker
+    pub fn recent_bodies(&self) -> &Arc<RecentBodies> {
+        &self.recent_bodies
     }
 
-    /// Get the receiver for worker tasks
+    /// Get the receiver of the ordinary lane. Workers use
+    /// [`ArticleQueue::recv`] so control messages are taken first.
     pub fn receiver(&self) -> Receiver<QueuedArticle> {
         self.receiver.clone()
     }
+
+    /// Wait for the next article to process, preferring any waiting control
+    /// message over ordinary articles.
+    pub async fn recv(&self) -> Option<QueuedArticle> {
+        tokio::select! {
+            biased;
+            Ok(article) = self.control_receiver.recv_async() => Some(article),
+            Ok(article) = self.receiver.recv_async() => Some(article),
+            else => None,
+        }
+    }
+
+    /// Take an article that is already waiting without blocking, preferring
+    /// control messages like [`ArticleQueue::recv`].
+    pub fn try_recv(&self) -> Option<QueuedArticle> {
+        self.control_receiver
+            .try_recv()
+            .or_else(|_| self.receiver.try_recv())
+            .ok()
+    }
 }
 
 /// Article worker pool configuration
@@ -90,13 +346,13 @@ impl WorkerPool {
         let mut handles = Vec::with_capacity(self.worker_count);
 
         for worker_id in 0..self.worker_count {
-            let receiver = self.queue.receiver();
+            let queue = self.queue.clone();
             let storage = self.storage.clone();
             let auth = self.auth.clone();
             let config = self.config.clone();
 
             let handle = tokio::spawn(async move {
-                worker_task(worker_id, receiver, storage, auth, config).await;
+                worker_task(worker_id, queue, storage, auth, config).await;
             });
 
             handles.push(handle);
@@ -110,31 +366,210 @@ impl WorkerPool {
 /// Worker task that processes articles from the queue
 async fn worker_task(
     worker_id: usize,
-    receiver: Receiver<QueuedArticle>,
+    queue: ArticleQueue,
     storage: DynStorage,
     auth: DynAuth,
     config: Arc<RwLock<Config>>,
 ) {
     info!("Article worker {} started", worker_id);
 
-    while let Ok(queued_article) = receiver.recv_async().await {
-        debug!("Worker {} processing article", worker_id);
-
-        if let Err(e) = process_article(&queued_article, &storage, &auth, &config).await {
-            error!("Worker {} failed to process article: {}", worker_id, e);
+    while let Some(first) = queue.recv().await {
+        // Take whatever else is already waiting, so a busy feed is stored
+        // in a few large batches instead of one transaction per article
+        let batch_size = config.read().await.article_batch_size;
+        let mut batch = vec![first];
+        while batch.len() < batch_size {
+            match queue.try_recv() {
+                Some(article) => batch.push(article),
+                None => break,
+            }
         }
+        debug!("Worker {} processing {} articles", worker_id, batch.len());
+
+        let started = std::time::Instant::now();
+        process_batch(worker_id, &batch, &queue, &storage, &auth, &config).await;
+        crate::metrics::global().worker_busy(started.elapsed());
     }
 
     info!("Article worker {} stopped", worker_id);
 }
 
-/// Process a single article: comprehensive validation and storage
-async fn process_article(
+/// Run `work`, turning a panic into an error. A panic is thereby confined
+/// to the article that caused it and the worker keeps serving the queue.
+async fn guarded<T>(work: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    AssertUnwindSafe(work)
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|panic| {
+            Err(anyhow::anyhow!(
+                "worker panicked: {}",
+                panic_message(&*panic)
+            ))
+        })
+}
+
+/// Report the outcome for one article: failures are logged and
+/// dead-lettered, and the submitter is told either way.
+async fn finish(
+    worker_id: usize,
+    queued_article: &QueuedArticle,
+    result: Result<()>,
+    queue: &ArticleQueue,
+    config: &Arc<RwLock<Config>>,
+) {
+    if let Err(e) = &result {
+        error!("Worker {} failed to process article: {}", worker_id, e);
+        crate::metrics::global().worker_failed();
+        dead_letter(queued_article, e, config).await;
+    }
+    queue.finished();
+    if let Some(ack) = &queued_article.ack {
+        let outcome = match &result {
+            Ok(()) => Outcome::Stored,
+            Err(e) if e.is::<Refused>() => Outcome::Refused,
+            Err(_) => Outcome::Failed,
+        };
+        let _ = ack.send(outcome);
+    }
+}
+
+/// The text a panic was raised with, if it was a string.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string payload")
+}
+
+/// Keep a failed article in the dead-letter directory, if one is configured
+async fn dead_letter(
+    queued_article: &QueuedArticle,
+    error: &anyhow::Error,
+    config: &Arc<RwLock<Config>>,
+) {
+    let Some(dir) = config.read().await.dead_letter_dir.clone() else {
+        return;
+    };
+    match crate::dead_letter::write(
+        std::path::Path::new(&dir),
+        queued_article,
+        &error.to_string(),
+    )
+    .await
+    {
+        Ok(path) => debug!("Dead-lettered article to {}", path.display()),
+        Err(e) => error!("Failed to write dead-letter article to {}: {}", dir, e),
+    }
+}
+
+/// What a worker still has to do with an article once it has been checked
+enum Prepared {
+    /// Nothing: a control message that was acted on, or a duplicate
+    Done,
+    /// Store this copy of the article
+    Store(Box<Message>),
+}
+
+/// Validate and store a batch of articles. Articles that only need storing
+/// go to storage in one batch; Supersedes and a Message-ID repeated within
+/// the batch are stored one at a time after it.
+async fn process_batch(
+    worker_id: usize,
+    batch: &[QueuedArticle],
+    queue: &ArticleQueue,
+    storage: &DynStorage,
+    auth: &DynAuth,
+    config: &Arc<RwLock<Config>>,
+) {
+    let host = config.read().await.site_name.clone();
+    let mut ready = Vec::new();
+    let mut articles = Vec::new();
+    let mut deferred = Vec::new();
+    for queued_article in batch {
+        match guarded(prepare_article(
+            queued_article,
+            queue,
+            storage,
+            auth,
+            config,
+        ))
+        .await
+        {
+            Ok(Prepared::Store(stored)) => {
+                let stored = *stored;
+                let id = extract_message_id(&stored);
+                let repeated = articles.iter().any(|a| extract_message_id(a) == id);
+                if repeated || crate::control::superseded_id(&stored).is_some() {
+                    deferred.push((queued_article, stored));
+                } else {
+                    ready.push(queued_article);
+                    articles.push(stored);
+                }
+            }
+            Ok(Prepared::Done) => finish(worker_id, queued_article, Ok(()), queue, config).await,
+            Err(e) => finish(worker_id, queued_article, Err(e), queue, config).await,
+        }
+    }
+
+    if !articles.is_empty() {
+        let batched = guarded(storage.store_articles(&articles)).await;
+        if let Err(e) = &batched {
+            warn!(
+                "Worker {} failed to store a batch of {} articles, storing them one at a time: {}",
+                worker_id,
+                articles.len(),
+                e
+            );
+        }
+        for (queued_article, stored) in ready.into_iter().zip(&articles) {
+            let result = guarded(async {
+                // A backend without transactions may have stored some of
+                // the batch before failing
+                if batched.is_err() && !stored_already(stored, storage).await? {
+                    storage.store_article(stored).await?;
+                }
+                crate::control::record_stored(stored, storage, &host).await?;
+                queue.article_stored(stored).await;
+                anyhow::Ok(())
+            })
+            .await;
+            finish(worker_id, queued_article, result, queue, config).await;
+        }
+    }
+
+    for (queued_article, stored) in deferred {
+        let result = guarded(async {
+            if stored_already(&stored, storage).await? {
+                debug!("Article already exists, skipping storage");
+                return Ok(());
+            }
+            crate::control::store_superseding(&stored, storage, &host).await?;
+            queue.article_stored(&stored).await;
+            anyhow::Ok(())
+        })
+        .await;
+        finish(worker_id, queued_article, result, queue, config).await;
+    }
+}
+
+/// Whether an article with the Message-ID of `article` is already stored.
+async fn stored_already(article: &Message, storage: &DynStorage) -> Result<bool> {
+    match extract_message_id(article) {
+        Some(id) => storage.article_exists(&id).await,
+        None => Ok(false),
+    }
+}
+
+/// Check a single article: act on control messages, run comprehensive
+/// validation and skip articles that are already stored
+async fn prepare_article(
     queued_article: &QueuedArticle,
+    queue: &ArticleQueue,
     storage: &DynStorage,
     auth: &DynAuth,
     config: &Arc<RwLock<Config>>,
-) -> Result<()> {
+) -> Result<Prepared> {
     let article = &queued_article.message;
 
     // Handle control messages first
@@ -142,7 +577,7 @@ async fn process_article(
         let cfg_guard = config.read().await;
         if crate::control::handle_control(article, storage, auth, &cfg_guard).await? {
             debug!("Processed control message");
-            return Ok(());
+            return Ok(Prepared::Done);
         }
     }
 
@@ -151,14 +586,11 @@ async fn process_article(
         let cfg_guard = config.read().await;
 
         // Create filter chain from configuration
-        let filter_chain = match crate::filters::factory::create_filter_chain(&cfg_guard.filters) {
-            Ok(chain) => chain,
-            Err(e) => {
-                error!("Failed to create filter chain: {}", e);
-                // Fall back to default chain if configuration is invalid
-                crate::filters::FilterChain::default()
-            }
-        };
+        let mut filter_chain = crate::filters::FilterChain::from_config(&cfg_guard)
+            .with_recent_bodies(queue.recent_bodies().clone());
+        if let Some(history) = queue.history() {
+            filter_chain = filter_chain.with_history(history.clone());
+        }
 
         // Use the configured filter chain for validation
         crate::handlers::utils::validate_article_with_filters(
@@ -169,7 +601,8 @@ async fn process_article(
             queued_article.size,
             &filter_chain,
         )
-        .await?;
+        .await
+        .map_err(|e| anyhow::Error::new(Refused(e)))?;
         drop(cfg_guard);
     }
 
@@ -181,13 +614,13 @@ async fn process_article(
         .map(|(_, v)| v.as_str())
         .unwrap_or("");
 
-    if !message_id.is_empty() && storage.get_article_by_id(message_id).await?.is_some() {
+    if !message_id.is_empty() && storage.message_seen(message_id).await? {
         debug!("Article already exists, skipping storage");
-        return Ok(());
+        return Ok(Prepared::Done);
     }
 
-    storage.store_article(article).await?;
-    debug!("Article stored successfully");
-
-    Ok(())
+    let cfg = config.read().await;
+    Ok(Prepared::Store(Box::new(prepare_for_storage(
+        &cfg, article,
+    ))))
 }
@@ -0,0 +1,273 @@
+//! Token bucket rate limiting for article submission.
+//!
+//! Every authenticated user and every remote address gets its own bucket
+//! that refills continuously at the configured per-minute rate and holds at
+//! most one minute's worth of articles and bytes, plus an hour's worth of
+//! articles under `posts_per_hour`. A submission is admitted
+//! while the buckets of all its keys still hold tokens; its full cost is then
+//! charged, so an article larger than the byte budget leaves the bucket in
+//! debt until it has refilled.
+//!
+//! The bucket map sits behind a plain mutex that is only held for the
+//! arithmetic of a single check and never across an await point. Idle
+//! buckets are swept out at most once per [`SWEEP_INTERVAL`].
+
+use crate::config::RateLimitConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often a check sweeps idle buckets out of the map.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Buckets idle for longer than this are dropped on the next sweep.
+const IDLE_EXPIRY: Duration = Duration::from_secs(600);
+
+/// Idle expiry when an hourly limit is set, by which time any bucket has
+/// refilled completely.
+const HOURLY_IDLE_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// Identifies the owner of a bucket.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    User(String),
+    Ip(IpAddr),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    articles: f64,
+    bytes: f64,
+    hourly: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limits: &RateLimitConfig, now: Instant) -> Self {
+        Self {
+            articles: limits.articles_per_minute.map_or(0.0, f64::from),
+            bytes: limits.bytes_per_minute.map_or(0.0, |b| b as f64),
+            hourly: limits.posts_per_hour.map_or(0.0, f64::from),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limits: &RateLimitConfig, now: Instant) {
+        let minutes = now.duration_since(self.updated).as_secs_f64() / 60.0;
+        self.updated = now;
+        if let Some(rate) = limits.articles_per_minute {
+            let rate = f64::from(rate);
+            self.articles = (self.articles + rate * minutes).min(rate);
+        }
+        if let Some(rate) = limits.bytes_per_minute {
+            let rate = rate as f64;
+            self.bytes = (self.bytes + rate * minutes).min(rate);
+        }
+        if let Some(rate) = limits.posts_per_hour {
+            let rate = f64::from(rate);
+            self.hourly = (self.hourly + rate * minutes / 60.0).min(rate);
+        }
+    }
+
+    /// Take one article of `bytes` bytes from each configured dimension.
+    fn charge(&mut self, bytes: u64, limits: &RateLimitConfig) {
+        if limits.articles_per_minute.is_some() {
+            self.articles -= 1.0;
+        }
+        if limits.bytes_per_minute.is_some() {
+            self.bytes -= bytes as f64;
+        }
+        if limits.posts_per_hour.is_some() {
+            self.hourly -= 1.0;
+        }
+    }
+
+    fn has_room(&self, limits: &RateLimitConfig) -> bool {
+        (limits.articles_per_minute.is_none() || self.articles >= 1.0)
+            && (limits.bytes_per_minute.is_none() || self.bytes > 0.0)
+            && (limits.posts_per_hour.is_none() || self.hourly >= 1.0)
+    }
+
+    /// Time until the bucket has room again; zero if it has room now.
+    fn wait(&self, limits: &RateLimitConfig) -> Duration {
+        // Seconds to refill `deficit` tokens at `per_second`; a zero rate
+        // never refills, so it contributes nothing to wait for
+        let refill = |deficit: f64, per_second: f64| {
+            if deficit > 0.0 && per_second > 0.0 {
+                deficit / per_second
+            } else {
+                0.0
+            }
+        };
+        let articles = limits.articles_per_minute.map_or(0.0, |rate| {
+            refill(1.0 - self.articles, f64::from(rate) / 60.0)
+        });
+        let bytes = limits
+            .bytes_per_minute
+            .filter(|_| self.bytes <= 0.0)
+            .map_or(0.0, |rate| refill(1.0 - self.bytes, rate as f64 / 60.0));
+        let hourly = limits.posts_per_hour.map_or(0.0, |rate| {
+            refill(1.0 - self.hourly, f64::from(rate) / 3600.0)
+        });
+        Duration::from_secs_f64(articles.max(bytes).max(hourly))
+    }
+}
+
+/// Snapshot of the limiter for reporting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// Users and addresses that currently have a bucket.
+    pub tracked: usize,
+    /// Submissions admitted since startup.
+    pub admitted: u64,
+    /// Submissions refused since startup.
+    pub throttled: u64,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    map: HashMap<RateLimitKey, Bucket>,
+    swept: Option<Instant>,
+}
+
+impl Buckets {
+    /// Drop buckets idle for longer than `expiry`, unless the map was swept
+    /// less than [`SWEEP_INTERVAL`] ago.
+    fn sweep(&mut self, expiry: Duration, now: Instant) {
+        if self
+            .swept
+            .is_some_and(|at| now.saturating_duration_since(at) < SWEEP_INTERVAL)
+        {
+            return;
+        }
+        self.swept = Some(now);
+        self.map
+            .retain(|_, b| now.saturating_duration_since(b.updated) < expiry);
+    }
+}
+
+/// Shared per-user and per-address token buckets.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+    admitted: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try to charge one article of `bytes` bytes to every key.
+    ///
+    /// Returns `false` without charging anything if any key is out of
+    /// tokens. Always admits when `limits` sets no rate.
+    pub fn check(&self, keys: &[RateLimitKey], bytes: u64, limits: &RateLimitConfig) -> bool {
+        self.check_at(keys, bytes, limits, Instant::now())
+    }
+
+    /// [`check`](Self::check) against an explicit clock reading.
+    pub fn check_at(
+        &self,
+        keys: &[RateLimitKey],
+        bytes: u64,
+        limits: &RateLimitConfig,
+        now: Instant,
+    ) -> bool {
+        if !limits.is_enabled() || keys.is_empty() {
+            return true;
+        }
+        let admitted = {
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            let expiry = if limits.posts_per_hour.is_some() {
+                HOURLY_IDLE_EXPIRY
+            } else {
+                IDLE_EXPIRY
+            };
+            buckets.sweep(expiry, now);
+            let mut admitted = true;
+            for key in keys {
+                let bucket = buckets
+                    .map
+                    .entry(key.clone())
+                    .or_insert_with(|| Bucket::full(limits, now));
+                bucket.refill(limits, now);
+                admitted &= bucket.has_room(limits);
+            }
+            if admitted {
+                for key in keys {
+                    if let Some(bucket) = buckets.map.get_mut(key) {
+                        bucket.charge(bytes, limits);
+                    }
+                }
+            }
+            admitted
+        };
+        let counter = if admitted {
+            &self.admitted
+        } else {
+            &self.throttled
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        admitted
+    }
+
+    /// Whether a submission from `keys` would currently be refused, without
+    /// charging for it. Used to answer `CHECK` before the article is sent.
+    pub fn is_throttled(&self, keys: &[RateLimitKey], limits: &RateLimitConfig) -> bool {
+        if !limits.is_enabled() {
+            return false;
+        }
+        let now = Instant::now();
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        keys.iter().any(|key| {
+            buckets.map.get(key).is_some_and(|bucket| {
+                let mut bucket = *bucket;
+                bucket.refill(limits, now);
+                !bucket.has_room(limits)
+            })
+        })
+    }
+
+    /// How long until a submission from `keys` would be admitted again,
+    /// for the hint sent with a refusal.
+    pub fn retry_after(&self, keys: &[RateLimitKey], limits: &RateLimitConfig) -> Duration {
+        self.retry_after_at(keys, limits, Instant::now())
+    }
+
+    /// [`retry_after`](Self::retry_after) against an explicit clock reading.
+    pub fn retry_after_at(
+        &self,
+        keys: &[RateLimitKey],
+        limits: &RateLimitConfig,
+        now: Instant,
+    ) -> Duration {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        keys.iter()
+            .filter_map(|key| buckets.map.get(key))
+            .map(|bucket| {
+                let mut bucket = *bucket;
+                bucket.refill(limits, now);
+                bucket.wait(limits)
+            })
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Current counters for the metrics endpoint.
+    pub fn stats(&self) -> RateLimitStats {
+        RateLimitStats {
+            tracked: self
+                .buckets
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .map
+                .len(),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub const RESP_200_POSTING_ALLOWED: &str = "200 Posting allowed\r\n";
 pub const RESP_201_POSTING_PROHIBITED: &str = "201 Posting prohibited\r\n";
 pub const RESP_203_STREAMING: &str = "203 Streaming permitted\r\n";
 pub const RESP_205_CLOSING: &str = "205 closing connection\r\n";
+pub const RESP_206_COMPRESSION_ACTIVE: &str = "206 Compression active\r\n";
 
 // Article responses
 pub const RESP_220_ARTICLE: &str = "220";
@@ -24,33 +25,46 @@ pub const RESP_225_HEADERS: &str = "225 Headers follow\r\n";
 
 // Group and list responses
 pub const RESP_211_GROUP: &str = "211";
-pub const RESP_211_LISTGROUP: &str = "211 article numbers follow\r\n";
 pub const RESP_215_LIST_FOLLOWS: &str = "215 list of newsgroups follows\r\n";
 pub const RESP_215_DESCRIPTIONS: &str = "215 descriptions follow\r\n";
 pub const RESP_215_INFO_FOLLOWS: &str = "215 information follows\r\n";
 pub const RESP_215_OVERVIEW_FMT: &str = "215 Order of fields in overview database.\r\n";
 pub const RESP_215_METADATA: &str = "215 metadata items supported:\r\n";
+pub const RESP_215_DISTRIBUTIONS: &str = "215 list of distributions follows\r\n";
+pub const RESP_215_DISTRIB_PATS: &str = "215 default distributions follow\r\n";
 pub const RESP_221_HEADER_FOLLOWS: &str = "221 Header follows\r\n";
 pub const RESP_230_NEWNEWS: &str = "230 list of new articles follows\r\n";
 pub const RESP_231_NEWGROUPS: &str = "231 list of new newsgroups follows\r\n";
 
 // Posting responses
 pub const RESP_235_TRANSFER_OK: &str = "235 Article transferred OK\r\n";
+pub const RESP_240_ARTICLE_RECEIVED: &str = "240 article received\r\n";
+
+// Streaming responses (RFC 4644). Only the code: the handlers complete each
+// reply with the message-id it concerns.
 pub const RESP_238_CHECK_OK: &str = "238";
 pub const RESP_239_TAKETHIS_OK: &str = "239";
-pub const RESP_240_ARTICLE_RECEIVED: &str = "240 article received\r\n";
+pub const RESP_431_CHECK_LATER: &str = "431";
+pub const RESP_438_CHECK_REJECT: &str = "438";
+pub const RESP_439_TAKETHIS_REJECT: &str = "439";
 
 // Authentication responses
 pub const RESP_281_AUTH_OK: &str = "281 authentication accepted\r\n";
+pub const RESP_283_SASL_SUCCESS: &str = "283";
 pub const RESP_290_PASSWORD_OK: &str = "290 Password for {user} accepted\r\n";
+pub const RESP_290_BACKEND: &str = "290";
 
 // Error responses
 pub const RESP_340_SEND_ARTICLE: &str =
     "340 send article to be posted. End with <CR-LF>.<CR-LF>\r\n";
 pub const RESP_335_SEND_IT: &str = "335 Send it; end with <CR-LF>.<CR-LF>\r\n";
 pub const RESP_381_PASSWORD_REQ: &str = "381 password required\r\n";
+pub const RESP_383_SASL_CHALLENGE: &str = "383";
 
 // 4xx error responses
+pub const RESP_400_DISCONTINUED: &str = "400 service discontinued\r\n";
+pub const RESP_400_TIMEOUT: &str = "400 timeout\r\n";
+pub const RESP_400_TOO_MANY_CONNECTIONS: &str = "400 too many connections\r\n";
 pub const RESP_411_NO_SUCH_GROUP: &str = "411 no such newsgroup\r\n";
 pub const RESP_412_NO_GROUP: &str = "412 no newsgroup selected\r\n";
 pub const RESP_420_NO_CURRENT: &str = "420 no current article selected\r\n";
@@ -60,10 +74,11 @@ pub const RESP_423_RANGE_EMPTY: &str = "423 no articles in that range\r\n";
 pub const RESP_423_NO_ARTICLE_NUM: &str = "423 no such article number in this group\r\n";
 pub const RESP_430_NO_ARTICLE: &str = "430 no such article\r\n";
 pub const RESP_435_NOT_WANTED: &str = "435 article not wanted\r\n";
+pub const RESP_436_TRY_LATER: &str = "436 transfer not possible; try again later\r\n";
 pub const RESP_437_REJECTED: &str = "437 article rejected\r\n";
-pub const RESP_438_CHECK_REJECT: &str = "438";
-pub const RESP_439_TAKETHIS_REJECT: &str = "439";
+pub const RESP_440_POSTING_NOT_PERMITTED: &str = "440 posting not permitted\r\n";
 pub const RESP_441_POSTING_FAILED: &str = "441 posting failed\r\n";
+pub const RESP_441_QUEUE_FULL: &str = "441 article queue full; try again later\r\n";
 pub const RESP_480_AUTH_REQUIRED: &str = "480 authentication required\r\n";
 pub const RESP_481_AUTH_REJECTED: &str = "481 Authentication rejected\r\n";
 pub const RESP_483_SECURE_REQ: &str = "483 Secure connection required\r\n";
@@ -80,7 +95,12 @@ pub const RESP_501_NOT_ENOUGH: &str = "501 not enough arguments\r\n";
 pub const RESP_501_UNKNOWN_KEYWORD: &str = "501 unknown keyword\r\n";
 pub const RESP_501_UNKNOWN_MODE: &str = "501 unknown mode\r\n";
 pub const RESP_501_MISSING_MODE: &str = "501 missing mode\r\n";
+pub const RESP_502_NOT_PERMITTED: &str = "502 permission denied\r\n";
+pub const RESP_502_COMPRESSION_ACTIVE: &str = "502 Compression already active\r\n";
+pub const RESP_502_WRONG_MODE: &str = "502 Command unavailable in this mode\r\n";
 pub const RESP_503_NOT_SUPPORTED: &str = "503 feature not supported\r\n";
+pub const RESP_503_SASL_MECHANISM: &str = "503 Mechanism not recognized\r\n";
+pub const RESP_504_BASE64: &str = "504 Base64 encoding error\r\n";
 
 // Capability responses
 pub const RESP_101_CAPABILITIES: &str = "101 Capability list follows\r\n";
@@ -91,18 +111,25 @@ pub const RESP_CAP_VERSION: &str = "VERSION 2\r\n";
 pub const RESP_CAP_IMPLEMENTATION: &str =
     concat!("IMPLEMENTATION Renews ", env!("CARGO_PKG_VERSION"), "\r\n");
 pub const RESP_CAP_READER: &str = "READER\r\n";
+pub const RESP_CAP_MODE_READER: &str = "MODE-READER\r\n";
 pub const RESP_CAP_IHAVE: &str = "IHAVE\r\n";
 pub const RESP_CAP_POST: &str = "POST\r\n";
 pub const RESP_CAP_NEWNEWS: &str = "NEWNEWS\r\n";
 pub const RESP_CAP_HDR: &str = "HDR\r\n";
 pub const RESP_CAP_OVER: &str = "OVER MSGID\r\n";
-pub const RESP_CAP_LIST: &str = "LIST ACTIVE NEWSGROUPS ACTIVE.TIMES OVERVIEW.FMT HEADERS\r\n";
-pub const RESP_CAP_AUTHINFO: &str = "AUTHINFO USER\r\n";
+pub const RESP_CAP_LIST: &str =
+    "LIST ACTIVE NEWSGROUPS ACTIVE.TIMES COUNTS OVERVIEW.FMT HEADERS\r\n";
+pub const RESP_CAP_AUTHINFO: &str = "AUTHINFO USER SASL\r\n";
+pub const RESP_CAP_SASL: &str = "SASL SCRAM-SHA-256\r\n";
+pub const RESP_CAP_SASL_WITH_PLAIN: &str = "SASL PLAIN SCRAM-SHA-256\r\n";
+pub const RESP_CAP_SASL_WITH_EXTERNAL: &str = "SASL EXTERNAL PLAIN SCRAM-SHA-256\r\n";
 pub const RESP_CAP_STREAMING: &str = "STREAMING\r\n";
+pub const RESP_CAP_COMPRESS: &str = "COMPRESS DEFLATE\r\n";
 
 // Help text
 pub const RESP_HELP_TEXT: &str = concat!(
     "CAPABILITIES\r\n",
+    "COMPRESS\r\n",
     "MODE READER\r\n",
     "MODE STREAM\r\n",
     "GROUP\r\n",
@@ -1,4 +1,3 @@
-use crate::Message;
 use crate::config::Config;
 use crate::storage::Storage;
 use anyhow::Result;
@@ -8,9 +7,14 @@ use tracing::{debug, info, warn};
 
 /// Clean up expired articles based on retention policies.
 ///
-/// This function performs two types of cleanup:
+/// This function performs five types of cleanup:
 /// 1. Time-based retention: Removes articles older than the configured retention period for each group
-/// 2. Expires header cleanup: Removes articles with an `Expires` header that has passed
+/// 2. Count-based retention: Keeps only the newest `max_articles` articles of each group
+/// 3. Expires header cleanup: Removes articles whose stored `Expires` date has passed
+/// 4. History pruning: Forgets Message-IDs recorded longer ago than `history_retention`
+/// 5. Body pruning: Forgets body sightings older than the `[spam]` duplicate window
+///
+/// An article is therefore removed at whichever of the deadlines comes first.
 ///
 /// # Errors
 ///
@@ -21,31 +25,63 @@ pub async fn cleanup_expired_articles(storage: &dyn Storage, cfg: &Config) -> Re
     let mut groups = storage.list_groups();
     while let Some(result) = groups.next().await {
         let group = result?;
-        // Apply time-based retention policies for  group
-        if let Err(e) = cleanup_group_by_retention(storage, cfg, group.as_str(), now).await {
-            warn!(
+        match cleanup_group(storage, cfg, group.as_str(), now).await {
+            Ok(0) => debug!("Nothing to expire in group {}", group),
+            Ok(removed) => info!("Expired {} articles from group {}", removed, group),
+            Err(e) => warn!(
                 "Failed to apply retention policy for group '{}': {}",
                 group, e
-            );
-        }
-        // Remove articles with expired Expires headers
-        if let Err(e) = cleanup_group_by_expires_header(storage, group.as_str(), now).await {
-            warn!(
-                "Failed to clean up expired articles in group '{}': {}",
-                group, e
-            );
+            ),
         }
-        info!("Finished cleanup for group {}", group);
     }
 
+    // Remove articles with expired Expires headers
+    info!("Removing articles past their Expires date");
+    storage.purge_expired(now).await?;
+
     // Clean up orphaned messages that are no longer referenced by any group
     info!("Cleaning up orphaned messages");
     storage.purge_orphan_messages().await?;
 
+    // Forget old Message-IDs; stored articles are still refused by their presence
+    let window = cfg.history_retention_or_default();
+    info!(
+        "Pruning Message-ID history older than {} days",
+        window.num_days()
+    );
+    storage.purge_history_before(now - window).await?;
+
+    // Sightings outside the duplicate window no longer count against a body
+    storage.purge_bodies_before(now - cfg.spam.window()).await?;
+
     info!("Finished cleaning up expired articles");
     Ok(())
 }
 
+/// Apply the age and count limits for a single group, returning how many
+/// articles were removed.
+async fn cleanup_group(
+    storage: &dyn Storage,
+    cfg: &Config,
+    group: &str,
+    now: DateTime<Utc>,
+) -> Result<u64> {
+    let (before, _, _) = storage.group_counts(group).await?;
+    cleanup_group_by_retention(storage, cfg, group, now).await?;
+    if let Some(keep) = cfg.max_articles_for_group(group) {
+        debug!(
+            "Applying count limit for group '{}': keeping the newest {}",
+            group, keep
+        );
+        storage
+            .purge_group_keep_latest(group, keep)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to trim group '{group}' to {keep}: {e}"))?;
+    }
+    let (after, _, _) = storage.group_counts(group).await?;
+    Ok(before.saturating_sub(after))
+}
+
 /// Apply time-based retention policy for a single group.
 async fn cleanup_group_by_retention(
     storage: &dyn Storage,
@@ -80,72 +116,3 @@ async fn cleanup_group_by_retention(
 
     Ok(())
 }
-
-/// Remove articles with expired Expires headers from a single group.
-async fn cleanup_group_by_expires_header(
-    storage: &dyn Storage,
-    group: &str,
-    now: DateTime<Utc>,
-) -> Result<()> {
-    let mut stream = storage.list_article_ids(group);
-
-    let mut expired_count = 0;
-    while let Some(result) = stream.next().await {
-        let id = result?;
-        match storage.get_article_by_id(&id).await {
-            Ok(Some(article)) => {
-                if let Some(expires_time) = parse_expires_header(&article) {
-                    if expires_time <= now {
-                        if let Err(e) = storage.delete_article_by_id(&id).await {
-                            warn!("Failed to delete expired article '{}': {}", id, e);
-                        } else {
-                            expired_count += 1;
-                        }
-                    }
-                }
-            }
-            Ok(None) => {
-                // Article doesn't exist anymore, skip
-                continue;
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to retrieve article '{}' for expiration check: {}",
-                    id, e
-                );
-            }
-        }
-    }
-
-    if expired_count > 0 {
-        debug!(
-            "Removed {} articles with expired Expires headers from group '{}'",
-            expired_count, group
-        );
-    }
-
-    Ok(())
-}
-
-/// Parse the Expires header from a message and return the expiration time.
-///
-/// This function looks for an `Expires` header in the message and attempts to parse it
-/// using both RFC 2822 and RFC 3339 formats.
-///
-/// # Arguments
-/// * `msg` - The message to parse the Expires header from
-///
-/// # Returns
-/// * `Some(DateTime<Utc>)` if a valid Expires header is found and parsed successfully
-/// * `None` if no Expires header is found or it cannot be parsed
-fn parse_expires_header(msg: &Message) -> Option<DateTime<Utc>> {
-    msg.headers
-        .iter()
-        .find(|(k, _)| k.eq_ignore_ascii_case("Expires"))
-        .and_then(|(_, v)| {
-            chrono::DateTime::parse_from_rfc2822(v)
-                .or_else(|_| chrono::DateTime::parse_from_rfc3339(v))
-                .ok()
-        })
-        .map(|dt| dt.with_timezone(&Utc))
-}
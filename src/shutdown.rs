@@ -0,0 +1,117 @@
+//! Cancellation signal for a graceful shutdown.
+//!
+//! The server triggers one [`Shutdown`] on SIGTERM or SIGINT. Listeners stop
+//! accepting once it fires, and each session holds a [`SessionGuard`] so the
+//! server can wait for open sessions to finish their current command before
+//! draining the article queue.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::queue::ArticleQueue;
+
+/// Shared shutdown flag together with a count of open sessions.
+#[derive(Clone)]
+pub struct Shutdown {
+    triggered: Arc<watch::Sender<bool>>,
+    sessions: Arc<watch::Sender<usize>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts a session as open until it is dropped.
+pub struct SessionGuard {
+    sessions: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.send_modify(|n| *n -= 1);
+    }
+}
+
+impl Shutdown {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            triggered: Arc::new(watch::Sender::new(false)),
+            sessions: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// Ask every listener and session to stop.
+    pub fn trigger(&self) {
+        self.triggered.send_replace(true);
+    }
+
+    /// Whether [`Shutdown::trigger`] was called.
+    #[must_use]
+    pub fn is_triggered(&self) -> bool {
+        *self.triggered.borrow()
+    }
+
+    /// Resolve once the shutdown is triggered, at once if it already was.
+    pub async fn triggered(&self) {
+        let mut rx = self.triggered.subscribe();
+        // The sender lives as long as `self`, so waiting cannot fail
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Count a session as open until the guard is dropped.
+    #[must_use]
+    pub fn session(&self) -> SessionGuard {
+        self.sessions.send_modify(|n| *n += 1);
+        SessionGuard {
+            sessions: self.sessions.clone(),
+        }
+    }
+
+    /// Number of sessions currently open.
+    #[must_use]
+    pub fn active_sessions(&self) -> usize {
+        *self.sessions.borrow()
+    }
+
+    /// Resolve once no session is open.
+    pub async fn sessions_finished(&self) {
+        let mut rx = self.sessions.subscribe();
+        let _ = rx.wait_for(|n| *n == 0).await;
+    }
+}
+
+/// Stop the sessions served through `queue` and drain it. Idle sessions are
+/// told the service is discontinued and busy ones get up to `grace` to
+/// finish their current command; then the queue stops taking articles and
+/// this waits up to `drain` for the workers to finish every one already
+/// queued.
+pub async fn shut_down(queue: &ArticleQueue, grace: Duration, drain: Duration) {
+    let shutdown = queue.shutdown();
+    shutdown.trigger();
+    if tokio::time::timeout(grace, shutdown.sessions_finished())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "{} sessions still open after {:?}; closing them",
+            shutdown.active_sessions(),
+            grace
+        );
+    }
+    if tokio::time::timeout(drain, queue.close_and_drain())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "{} queued articles abandoned after {:?}",
+            queue.in_flight(),
+            drain
+        );
+        return;
+    }
+    tracing::info!("Article queue drained");
+}
@@ -0,0 +1,95 @@
+//! Minimal SMTP client used to mail articles to group moderators.
+//!
+//! Only what a local relay needs is spoken: `HELO`, one sender, one
+//! recipient and `DATA`. Authentication and TLS are left to the relay.
+
+use anyhow::{Result, anyhow, bail};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Hand `message` to the SMTP relay at `relay` (`host:port`) for delivery
+/// from `sender` to `recipient`. `message` holds the mail headers and body;
+/// line endings are normalized and lines are dot-stuffed here.
+///
+/// # Errors
+///
+/// Returns an error if the relay cannot be reached or refuses any step.
+pub async fn send_mail(
+    relay: &str,
+    helo: &str,
+    sender: &str,
+    recipient: &str,
+    message: &str,
+) -> Result<()> {
+    let stream = TcpStream::connect(relay).await?;
+    let (read, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read);
+
+    expect_reply(&mut reader, 2).await?;
+    command(&mut reader, &mut writer, &format!("HELO {helo}"), 2).await?;
+    command(
+        &mut reader,
+        &mut writer,
+        &format!("MAIL FROM:<{sender}>"),
+        2,
+    )
+    .await?;
+    command(
+        &mut reader,
+        &mut writer,
+        &format!("RCPT TO:<{recipient}>"),
+        2,
+    )
+    .await?;
+    command(&mut reader, &mut writer, "DATA", 3).await?;
+
+    let mut data = String::with_capacity(message.len() + 64);
+    for line in message.lines() {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push_str(".\r\n");
+    writer.write_all(data.as_bytes()).await?;
+    writer.flush().await?;
+    expect_reply(&mut reader, 2).await?;
+
+    // The message is accepted; a relay that hangs up early is harmless
+    let _ = command(&mut reader, &mut writer, "QUIT", 2).await;
+    Ok(())
+}
+
+/// Send one command line and check the class of the reply.
+async fn command<R, W>(reader: &mut R, writer: &mut W, line: &str, class: u16) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    writer.flush().await?;
+    expect_reply(reader, class).await
+}
+
+/// Read a possibly multi-line reply and check that its code is `class`xx.
+async fn expect_reply<R: AsyncBufRead + Unpin>(reader: &mut R, class: u16) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            bail!("SMTP relay closed the connection");
+        }
+        let code: u16 = line
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| anyhow!("malformed SMTP reply: {}", line.trim_end()))?;
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if code / 100 != class {
+            bail!("SMTP relay replied: {}", line.trim_end());
+        }
+        return Ok(());
+    }
+}
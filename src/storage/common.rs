@@ -1,11 +1,101 @@
 use crate::Message;
+use crate::config::Config;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 /// Serializable wrapper for message headers.
 #[derive(Serialize, Deserialize)]
 pub struct Headers(pub SmallVec<[(String, String); 8]>);
 
+/// Marker header added to articles whose body was truncated on storage.
+/// Its value is the original body size in bytes.
+pub const TRUNCATED_HEADER: &str = "X-Renews-Truncated";
+
+/// Size in bytes of the body as received, before any archive truncation.
+pub fn original_body_size(article: &Message) -> u64 {
+    article
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(TRUNCATED_HEADER))
+        .and_then(|(_, v)| v.trim().parse().ok())
+        .unwrap_or(article.body.len() as u64)
+}
+
+/// Trim the body to the smallest `body_truncate_bytes` configured for the
+/// article's groups, recording the original size in a marker header.
+/// Articles within the limit are returned unchanged.
+pub fn apply_body_truncation<'a>(cfg: &Config, article: &'a Message) -> Cow<'a, Message> {
+    let limit = parse_newsgroups_from_message(article)
+        .iter()
+        .filter_map(|g| cfg.body_truncate_for_group(g))
+        .min();
+    let Some(limit) = limit.and_then(|l| usize::try_from(l).ok()) else {
+        return Cow::Borrowed(article);
+    };
+    if article.body.len() <= limit {
+        return Cow::Borrowed(article);
+    }
+
+    // Back off to a character boundary when the cut lands inside a UTF-8
+    // sequence; binary bodies are cut at the limit itself
+    let mut end = limit;
+    if let Err(e) = std::str::from_utf8(&article.body[..end])
+        && e.error_len().is_none()
+    {
+        end = e.valid_up_to();
+    }
+    let mut truncated = article.clone();
+    truncated.body.truncate(end);
+    truncated
+        .headers
+        .push((TRUNCATED_HEADER.to_string(), article.body.len().to_string()));
+    Cow::Owned(truncated)
+}
+
+/// Whether `site` is one of the entries of the article's Path header.
+pub fn path_contains(article: &Message, site: &str) -> bool {
+    article
+        .headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Path"))
+        .any(|(_, path)| path.split('!').any(|entry| entry.trim() == site))
+}
+
+/// Prepend `site_name` to the Path header, adding the header if it is
+/// missing. A Path that already starts with `site_name` is left alone.
+pub fn stamp_path(article: &mut Message, site_name: &str) {
+    match article
+        .headers
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Path"))
+    {
+        Some((_, path)) => {
+            let trimmed = path.trim();
+            if trimmed.split('!').next() != Some(site_name) {
+                *path = if trimmed.is_empty() {
+                    site_name.to_string()
+                } else {
+                    format!("{site_name}!{trimmed}")
+                };
+            }
+        }
+        None => article
+            .headers
+            .push(("Path".to_string(), site_name.to_string())),
+    }
+}
+
+/// The article as it is stored: truncated for its groups and with this
+/// site's path identity on its Path.
+pub fn prepare_for_storage(cfg: &Config, article: &Message) -> Message {
+    let mut stored = apply_body_truncation(cfg, article).into_owned();
+    stamp_path(&mut stored, cfg.path_identity());
+    stored
+}
+
 /// Extract the Message-ID header from an article.
 ///
 /// Returns the Message-ID value if found, None otherwise.
@@ -19,6 +109,57 @@ pub fn extract_message_id(article: &Message) -> Option<String> {
     })
 }
 
+/// Parse the `Expires` header of an article into a UTC timestamp.
+///
+/// Both RFC 2822 and RFC 3339 dates are accepted. Returns `None` when the
+/// header is absent or cannot be parsed.
+pub fn parse_expires(article: &Message) -> Option<DateTime<Utc>> {
+    article
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Expires"))
+        .and_then(|(_, v)| {
+            DateTime::parse_from_rfc2822(v.trim())
+                .or_else(|_| DateTime::parse_from_rfc3339(v.trim()))
+                .ok()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Set the Xref header of `headers` to `host` followed by `group:number`
+/// for each entry of `numbers`, ordered as the groups appear in Newsgroups.
+/// Any Xref already present is removed; with no numbers none is added.
+/// Returns the new value and whether `headers` changed.
+pub fn apply_xref(
+    headers: &mut SmallVec<[(String, String); 8]>,
+    host: &str,
+    mut numbers: Vec<(String, u64)>,
+) -> (Option<String>, bool) {
+    let before = headers.len();
+    headers.retain(|(k, _)| !k.eq_ignore_ascii_case("Xref"));
+    if numbers.is_empty() {
+        return (None, headers.len() != before);
+    }
+
+    let newsgroups: Vec<&str> = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Newsgroups"))
+        .map(|(_, v)| v.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+    numbers.sort_by_key(|(group, _)| {
+        newsgroups
+            .iter()
+            .position(|g| g == group)
+            .unwrap_or(usize::MAX)
+    });
+    let mut value = host.to_string();
+    for (group, number) in &numbers {
+        value.push_str(&format!(" {group}:{number}"));
+    }
+    headers.push(("Xref".to_string(), value.clone()));
+    (Some(value), true)
+}
+
 /// Parse newsgroups from a message, returning a SmallVec for efficiency
 pub fn parse_newsgroups_from_message(article: &Message) -> SmallVec<[String; 4]> {
     article
@@ -35,11 +176,106 @@ pub fn parse_newsgroups_from_message(article: &Message) -> SmallVec<[String; 4]>
         .unwrap_or_default()
 }
 
+/// Most rows a backend puts in one multi-row INSERT of a batch, keeping
+/// the statement well within the bind parameter limits.
+pub const BATCH_INSERT_ROWS: usize = 100;
+
+/// A `messages` row of a batch insert.
+pub struct MessageRow<'a> {
+    pub message_id: String,
+    /// Headers serialized as stored
+    pub headers: String,
+    pub body: &'a [u8],
+    pub size: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// A `group_articles` row of a batch insert with its overview line.
+pub struct GroupRow {
+    pub group: String,
+    pub number: i64,
+    pub message_id: String,
+    pub overview: String,
+}
+
+/// Every group the articles of a batch are posted to, in name order.
+pub fn batch_groups(articles: &[Message]) -> BTreeSet<String> {
+    articles
+        .iter()
+        .flat_map(parse_newsgroups_from_message)
+        .collect()
+}
+
+/// Rows storing `articles` in order. `next` maps each group to its first
+/// free article number and is advanced past the numbers handed out, so the
+/// batch is numbered as the same `store_article` calls in a row would
+/// number it; groups missing from `next` start at 1. A Message-ID repeated
+/// within the batch gets one message row.
+///
+/// # Errors
+///
+/// Returns an error if an article has no Message-ID.
+pub fn batch_rows<'a>(
+    articles: &'a [Message],
+    next: &mut HashMap<String, i64>,
+) -> anyhow::Result<(Vec<MessageRow<'a>>, Vec<GroupRow>)> {
+    let mut messages = Vec::with_capacity(articles.len());
+    let mut groups = Vec::with_capacity(articles.len());
+    let mut seen = HashSet::with_capacity(articles.len());
+    for article in articles {
+        let message_id =
+            extract_message_id(article).ok_or_else(|| anyhow::anyhow!("missing Message-ID"))?;
+        let size = original_body_size(article);
+        for group in parse_newsgroups_from_message(article) {
+            let number = next.entry(group.clone()).or_insert(1);
+            groups.push(GroupRow {
+                overview: crate::overview::OverviewLine::with_size(*number as u64, article, size)
+                    .to_string(),
+                group,
+                number: *number,
+                message_id: message_id.clone(),
+            });
+            *number += 1;
+        }
+        if seen.insert(message_id.clone()) {
+            messages.push(MessageRow {
+                message_id,
+                headers: serde_json::to_string(&Headers(article.headers.clone()))?,
+                body: &article.body,
+                size: i64::try_from(size).unwrap_or(i64::MAX),
+                expires_at: parse_expires(article).map(|t| t.timestamp()),
+            });
+        }
+    }
+    Ok((messages, groups))
+}
+
 /// Common logic for reconstructing a Message from database row data
-pub fn reconstruct_message_from_row(headers_str: &str, body: &str) -> anyhow::Result<Message> {
+pub fn reconstruct_message_from_row(headers_str: &str, body: Vec<u8>) -> anyhow::Result<Message> {
     let Headers(headers) = serde_json::from_str(headers_str)?;
-    Ok(Message {
-        headers,
-        body: body.to_string(),
-    })
+    Ok(Message { headers, body })
+}
+
+/// Resolve an HDR field for a stored article without loading its body.
+/// `:bytes` comes from the stored size and `:lines` from the overview line.
+pub fn header_field_from_row(
+    field: &str,
+    headers_str: &str,
+    size: u64,
+    overview_data: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    match field {
+        ":bytes" => Ok(Some(size.to_string())),
+        ":lines" => Ok(overview_data
+            .map(crate::overview::OverviewLine::parse)
+            .transpose()?
+            .map(|o| o.lines.to_string())),
+        _ => {
+            let Headers(headers) = serde_json::from_str(headers_str)?;
+            Ok(headers
+                .into_iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(field))
+                .map(|(_, v)| v))
+        }
+    }
 }
@@ -8,6 +8,251 @@ const CREATE_VERSION_TABLE_POSTGRES: &str = "CREATE TABLE IF NOT EXISTS schema_v
     version INTEGER PRIMARY KEY
 )";
 
+/// Schema version of a freshly initialized PostgreSQL storage database
+pub const LATEST_VERSION: u32 = 10;
+
+/// Adds the `description` column used by LIST NEWSGROUPS
+#[cfg(feature = "postgres")]
+struct AddGroupDescription {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddGroupDescription {
+    fn target_version(&self) -> u32 {
+        2
+    }
+
+    fn description(&self) -> &str {
+        "Add description column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query("ALTER TABLE groups ADD COLUMN IF NOT EXISTS description TEXT")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `expires_at` column populated from the `Expires` header
+#[cfg(feature = "postgres")]
+struct AddMessageExpiry {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddMessageExpiry {
+    fn target_version(&self) -> u32 {
+        3
+    }
+
+    fn description(&self) -> &str {
+        "Add expires_at column to messages"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query("ALTER TABLE messages ADD COLUMN IF NOT EXISTS expires_at BIGINT")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `created_by` column reported by LIST ACTIVE.TIMES
+#[cfg(feature = "postgres")]
+struct AddGroupCreator {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddGroupCreator {
+    fn target_version(&self) -> u32 {
+        4
+    }
+
+    fn description(&self) -> &str {
+        "Add created_by column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query("ALTER TABLE groups ADD COLUMN IF NOT EXISTS created_by TEXT")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `moderator` column holding a group's submission address
+#[cfg(feature = "postgres")]
+struct AddGroupModerator {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddGroupModerator {
+    fn target_version(&self) -> u32 {
+        5
+    }
+
+    fn description(&self) -> &str {
+        "Add moderator column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query("ALTER TABLE groups ADD COLUMN IF NOT EXISTS moderator TEXT")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `history` table of seen, cancelled and expired Message-IDs,
+/// seeded with the messages already stored
+#[cfg(feature = "postgres")]
+struct AddHistory {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddHistory {
+    fn target_version(&self) -> u32 {
+        6
+    }
+
+    fn description(&self) -> &str {
+        "Add Message-ID history table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::postgres::HISTORY_TABLE)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(crate::storage::postgres::HISTORY_INDEX)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(
+            "INSERT INTO history (message_id, status, recorded_at) \
+             SELECT message_id, 'stored', EXTRACT(EPOCH FROM NOW())::BIGINT FROM messages \
+             ON CONFLICT DO NOTHING",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `feed_pending` table of articles waiting for each push peer
+#[cfg(feature = "postgres")]
+struct AddFeedQueue {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddFeedQueue {
+    fn target_version(&self) -> u32 {
+        7
+    }
+
+    fn description(&self) -> &str {
+        "Add outbound feed queue table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::postgres::FEED_TABLE)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `subscription_marks` table of groups fetched from upstream servers
+#[cfg(feature = "postgres")]
+struct AddSubscriptionMarks {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddSubscriptionMarks {
+    fn target_version(&self) -> u32 {
+        8
+    }
+
+    fn description(&self) -> &str {
+        "Add upstream subscription marks table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::postgres::SUBSCRIPTION_TABLE)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `recent_bodies` table of body hashes seen by the duplicate body
+/// filter
+#[cfg(feature = "postgres")]
+struct AddRecentBodies {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for AddRecentBodies {
+    fn target_version(&self) -> u32 {
+        9
+    }
+
+    fn description(&self) -> &str {
+        "Add recent body hashes table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::postgres::RECENT_BODIES_TABLE)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(crate::storage::postgres::RECENT_BODIES_INDEX)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Stores message bodies as `BYTEA`, since `TEXT` cannot hold NUL bytes
+#[cfg(feature = "postgres")]
+struct BinaryBodies {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl Migration for BinaryBodies {
+    fn target_version(&self) -> u32 {
+        10
+    }
+
+    fn description(&self) -> &str {
+        "Store message bodies as bytes"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(
+            "ALTER TABLE messages ALTER COLUMN body TYPE BYTEA USING convert_to(body, 'UTF8')",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
 /// PostgreSQL storage migrator
 #[cfg(feature = "postgres")]
 pub struct PostgresStorageMigrator {
@@ -67,9 +312,35 @@ impl Migrator for PostgresStorageMigrator {
     }
 
     fn get_migrations(&self) -> Vec<Box<dyn Migration>> {
-        // Since this is pre-1.0, no migrations exist yet
-        // This will serve as a template for future migrations
-        vec![]
+        vec![
+            Box::new(AddGroupDescription {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddMessageExpiry {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddGroupCreator {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddGroupModerator {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddHistory {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddFeedQueue {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddSubscriptionMarks {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddRecentBodies {
+                pool: self.pool.clone(),
+            }),
+            Box::new(BinaryBodies {
+                pool: self.pool.clone(),
+            }),
+        ]
     }
 }
 
@@ -104,7 +375,7 @@ mod tests {
 
     #[cfg(feature = "postgres")]
     #[tokio::test]
-    async fn test_postgres_storage_migrator_no_migrations() {
+    async fn test_postgres_storage_migrator_upgrades_version_1() {
         if std::env::var("POSTGRES_TEST_URL").is_err() {
             return;
         }
@@ -113,15 +384,37 @@ mod tests {
         let pool = sqlx::PgPool::connect(&db_url).await.unwrap();
         let migrator = PostgresStorageMigrator::new(pool.clone());
 
-        // Pre-1.0, so no migrations should exist
-        let migrations = migrator.get_migrations();
-        assert!(migrations.is_empty());
+        // Simulate a version 1 database created before descriptions existed
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS groups (name TEXT PRIMARY KEY, created_at BIGINT NOT NULL, moderated BOOLEAN NOT NULL DEFAULT FALSE)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS messages (message_id TEXT PRIMARY KEY, headers TEXT, body TEXT, size BIGINT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO messages (message_id, headers, body, size) VALUES ('<old@test>', '[]', 'Old body', 8)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        migrator.set_version(1).await.unwrap();
 
-        // Migration should be a no-op
-        migrator.set_version(1).await.unwrap(); // Simulate fresh initialization
+        assert_eq!(migrator.get_migrations().len(), 9);
         migrator.migrate_to_latest().await.unwrap();
 
         let version = migrator.get_current_version().await.unwrap();
-        assert_eq!(version, 1); // Should remain at 1
+        assert_eq!(version, LATEST_VERSION);
+        let body: Vec<u8> =
+            sqlx::query_scalar("SELECT body FROM messages WHERE message_id = '<old@test>'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(body, b"Old body");
     }
 }
@@ -0,0 +1,40 @@
+use crate::migrations::{Migration, Migrator};
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+
+/// Key holding the schema version for Redis storage
+const VERSION_KEY: &str = "schema_version";
+
+/// Redis storage migrator
+pub struct RedisStorageMigrator {
+    con: ConnectionManager,
+}
+
+impl RedisStorageMigrator {
+    pub fn new(con: ConnectionManager) -> Self {
+        Self { con }
+    }
+}
+
+#[async_trait]
+impl Migrator for RedisStorageMigrator {
+    async fn get_current_version(&self) -> Result<u32> {
+        let mut con = self.con.clone();
+        let version: Option<u32> = con.get(VERSION_KEY).await?;
+        // A missing key means the store has never been initialized
+        version.ok_or_else(|| anyhow::anyhow!("Version key does not exist"))
+    }
+
+    async fn set_version(&self, version: u32) -> Result<()> {
+        let mut con = self.con.clone();
+        let () = con.set(VERSION_KEY, version).await?;
+        Ok(())
+    }
+
+    fn get_migrations(&self) -> Vec<Box<dyn Migration>> {
+        // Since this is pre-1.0, no migrations exist yet
+        vec![]
+    }
+}
@@ -8,6 +8,235 @@ const CREATE_VERSION_TABLE_SQLITE: &str = "CREATE TABLE IF NOT EXISTS schema_ver
     version INTEGER PRIMARY KEY
 )";
 
+/// Schema version of a freshly initialized SQLite storage database
+pub const LATEST_VERSION: u32 = 9;
+
+/// Adds the `description` column used by LIST NEWSGROUPS
+struct AddGroupDescription {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddGroupDescription {
+    fn target_version(&self) -> u32 {
+        2
+    }
+
+    fn description(&self) -> &str {
+        "Add description column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('groups') WHERE name = 'description'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        if !exists {
+            sqlx::query("ALTER TABLE groups ADD COLUMN description TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the `expires_at` column populated from the `Expires` header
+struct AddMessageExpiry {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddMessageExpiry {
+    fn target_version(&self) -> u32 {
+        3
+    }
+
+    fn description(&self) -> &str {
+        "Add expires_at column to messages"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('messages') WHERE name = 'expires_at'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        if !exists {
+            sqlx::query("ALTER TABLE messages ADD COLUMN expires_at INTEGER")
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the `created_by` column reported by LIST ACTIVE.TIMES
+struct AddGroupCreator {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddGroupCreator {
+    fn target_version(&self) -> u32 {
+        4
+    }
+
+    fn description(&self) -> &str {
+        "Add created_by column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('groups') WHERE name = 'created_by'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        if !exists {
+            sqlx::query("ALTER TABLE groups ADD COLUMN created_by TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the `moderator` column holding a group's submission address
+struct AddGroupModerator {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddGroupModerator {
+    fn target_version(&self) -> u32 {
+        5
+    }
+
+    fn description(&self) -> &str {
+        "Add moderator column to groups"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('groups') WHERE name = 'moderator'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        if !exists {
+            sqlx::query("ALTER TABLE groups ADD COLUMN moderator TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the `history` table of seen, cancelled and expired Message-IDs,
+/// seeded with the messages already stored
+struct AddHistory {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddHistory {
+    fn target_version(&self) -> u32 {
+        6
+    }
+
+    fn description(&self) -> &str {
+        "Add Message-ID history table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::sqlite::HISTORY_TABLE)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(crate::storage::sqlite::HISTORY_INDEX)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO history (message_id, status, recorded_at) \
+             SELECT message_id, 'stored', CAST(strftime('%s', 'now') AS INTEGER) FROM messages",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `feed_pending` table of articles waiting for each push peer
+struct AddFeedQueue {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddFeedQueue {
+    fn target_version(&self) -> u32 {
+        7
+    }
+
+    fn description(&self) -> &str {
+        "Add outbound feed queue table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::sqlite::FEED_TABLE)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `subscription_marks` table of groups fetched from upstream servers
+struct AddSubscriptionMarks {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddSubscriptionMarks {
+    fn target_version(&self) -> u32 {
+        8
+    }
+
+    fn description(&self) -> &str {
+        "Add upstream subscription marks table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::sqlite::SUBSCRIPTION_TABLE)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Adds the `recent_bodies` table of body hashes seen by the duplicate body
+/// filter
+struct AddRecentBodies {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl Migration for AddRecentBodies {
+    fn target_version(&self) -> u32 {
+        9
+    }
+
+    fn description(&self) -> &str {
+        "Add recent body hashes table"
+    }
+
+    async fn apply(&self) -> Result<()> {
+        sqlx::query(crate::storage::sqlite::RECENT_BODIES_TABLE)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(crate::storage::sqlite::RECENT_BODIES_INDEX)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
 /// SQLite storage migrator
 pub struct SqliteStorageMigrator {
     pool: SqlitePool,
@@ -64,9 +293,32 @@ impl Migrator for SqliteStorageMigrator {
     }
 
     fn get_migrations(&self) -> Vec<Box<dyn Migration>> {
-        // Since this is pre-1.0, no migrations exist yet
-        // This will serve as a template for future migrations
-        vec![]
+        vec![
+            Box::new(AddGroupDescription {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddMessageExpiry {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddGroupCreator {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddGroupModerator {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddHistory {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddFeedQueue {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddSubscriptionMarks {
+                pool: self.pool.clone(),
+            }),
+            Box::new(AddRecentBodies {
+                pool: self.pool.clone(),
+            }),
+        ]
     }
 }
 
@@ -116,22 +368,100 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_sqlite_storage_migrator_no_migrations() {
+    async fn test_sqlite_storage_migrator_upgrades_version_1() {
         let temp_file = NamedTempFile::new().unwrap();
         let db_path = format!("sqlite://{}", temp_file.path().display());
 
         let pool = sqlx::SqlitePool::connect(&db_path).await.unwrap();
         let migrator = SqliteStorageMigrator::new(pool.clone());
 
-        // Pre-1.0, so no migrations should exist
-        let migrations = migrator.get_migrations();
-        assert!(migrations.is_empty());
+        // Simulate a version 1 database created before descriptions existed
+        sqlx::query(
+            "CREATE TABLE groups (name TEXT PRIMARY KEY, created_at INTEGER NOT NULL, moderated INTEGER NOT NULL DEFAULT 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE messages (message_id TEXT PRIMARY KEY, headers TEXT, body TEXT, size INTEGER NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO messages VALUES ('<old@test>', '[]', '', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        migrator.set_version(1).await.unwrap();
 
-        // Migration should be a no-op
-        migrator.set_version(1).await.unwrap(); // Simulate fresh initialization
+        assert_eq!(migrator.get_migrations().len(), 8);
         migrator.migrate_to_latest().await.unwrap();
+        assert_eq!(
+            migrator.get_current_version().await.unwrap(),
+            LATEST_VERSION
+        );
 
-        let version = migrator.get_current_version().await.unwrap();
-        assert_eq!(version, 1); // Should remain at 1
+        sqlx::query("UPDATE groups SET description = 'ok'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE messages SET expires_at = 0")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE groups SET created_by = 'admin@example.com'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE groups SET moderator = 'mod@example.com'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let status: String =
+            sqlx::query_scalar("SELECT status FROM history WHERE message_id = '<old@test>'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, "stored");
+        sqlx::query("INSERT INTO feed_pending VALUES ('peer', '<old@test>', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO subscription_marks VALUES ('upstream', 'misc.test', 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO recent_bodies VALUES ('abc', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Re-applying is harmless
+        AddGroupDescription { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
+        AddMessageExpiry { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
+        AddGroupCreator { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
+        AddGroupModerator { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
+        AddHistory { pool: pool.clone() }.apply().await.unwrap();
+        AddFeedQueue { pool: pool.clone() }.apply().await.unwrap();
+        AddSubscriptionMarks { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
+        AddRecentBodies { pool: pool.clone() }
+            .apply()
+            .await
+            .unwrap();
     }
 }
@@ -0,0 +1,346 @@
+//! Storage wrapper that mirrors writes to a secondary backend.
+//!
+//! Reads are always served by the primary. Every write is applied to the
+//! primary first and then repeated on the secondary, which is useful for
+//! migrating between backends or keeping a hot standby.
+
+use super::{
+    ArticleStream, Direction, DynStorage, HeaderStream, HistoryStatus, Message, OverviewStream,
+    Storage, StringPairStream, StringStream, StringTimestampStream, U64Stream,
+};
+use crate::config::MirrorFailurePolicy;
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::warn;
+
+/// Delegates reads to `primary` and fans writes out to both backends.
+pub struct MirrorStorage {
+    primary: DynStorage,
+    secondary: DynStorage,
+    policy: MirrorFailurePolicy,
+}
+
+impl MirrorStorage {
+    pub fn new(primary: DynStorage, secondary: DynStorage, policy: MirrorFailurePolicy) -> Self {
+        Self {
+            primary,
+            secondary,
+            policy,
+        }
+    }
+
+    /// Apply the failure policy to the outcome of a secondary write.
+    fn secondary_result(&self, op: &str, result: Result<()>) -> Result<()> {
+        match (result, self.policy) {
+            (Ok(()), _) => Ok(()),
+            (Err(e), MirrorFailurePolicy::Warn) => {
+                warn!(
+                    "Mirror {op} failed on secondary {} storage: {e}",
+                    self.secondary.backend_name()
+                );
+                Ok(())
+            }
+            (Err(e), MirrorFailurePolicy::Fail) => Err(anyhow::anyhow!(
+                "Mirror {op} failed on secondary {} storage: {e}",
+                self.secondary.backend_name()
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for MirrorStorage {
+    async fn store_article(&self, article: &Message) -> Result<()> {
+        self.primary.store_article(article).await?;
+        let result = self.secondary.store_article(article).await;
+        self.secondary_result("store_article", result)
+    }
+
+    async fn store_articles(&self, articles: &[Message]) -> Result<()> {
+        self.primary.store_articles(articles).await?;
+        let result = self.secondary.store_articles(articles).await;
+        self.secondary_result("store_articles", result)
+    }
+
+    async fn get_article_by_number(&self, group: &str, number: u64) -> Result<Option<Message>> {
+        self.primary.get_article_by_number(group, number).await
+    }
+
+    async fn get_article_by_id(&self, message_id: &str) -> Result<Option<Message>> {
+        self.primary.get_article_by_id(message_id).await
+    }
+
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        self.primary.article_exists(message_id).await
+    }
+
+    fn get_articles_by_ids<'a>(&'a self, message_ids: &'a [String]) -> ArticleStream<'a> {
+        self.primary.get_articles_by_ids(message_ids)
+    }
+
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        self.primary.get_overview_range(group, start, end)
+    }
+
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        self.primary.get_header_for_range(group, header, start, end)
+    }
+
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
+        self.primary
+            .add_group_with_creator(group, moderated, created_by)
+            .await?;
+        let result = self
+            .secondary
+            .add_group_with_creator(group, moderated, created_by)
+            .await;
+        self.secondary_result("add_group_with_creator", result)
+    }
+
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        self.primary.get_group_creator(group).await
+    }
+
+    async fn set_group_moderated(&self, group: &str, moderated: bool) -> Result<()> {
+        self.primary.set_group_moderated(group, moderated).await?;
+        let result = self.secondary.set_group_moderated(group, moderated).await;
+        self.secondary_result("set_group_moderated", result)
+    }
+
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        self.primary
+            .set_group_description(group, description)
+            .await?;
+        let result = self
+            .secondary
+            .set_group_description(group, description)
+            .await;
+        self.secondary_result("set_group_description", result)
+    }
+
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        self.primary.get_group_description(group).await
+    }
+
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        self.primary.set_group_moderator(group, address).await?;
+        let result = self.secondary.set_group_moderator(group, address).await;
+        self.secondary_result("set_group_moderator", result)
+    }
+
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        self.primary.get_group_moderator(group).await
+    }
+
+    async fn remove_group(&self, group: &str) -> Result<()> {
+        self.primary.remove_group(group).await?;
+        let result = self.secondary.remove_group(group).await;
+        self.secondary_result("remove_group", result)
+    }
+
+    async fn remove_groups_by_pattern(&self, pattern: &str) -> Result<()> {
+        self.primary.remove_groups_by_pattern(pattern).await?;
+        let result = self.secondary.remove_groups_by_pattern(pattern).await;
+        self.secondary_result("remove_groups_by_pattern", result)
+    }
+
+    fn list_groups(&self) -> StringStream<'_> {
+        self.primary.list_groups()
+    }
+
+    fn list_groups_since(&self, since: chrono::DateTime<chrono::Utc>) -> StringStream<'_> {
+        self.primary.list_groups_since(since)
+    }
+
+    fn list_groups_with_times(&self) -> StringTimestampStream<'_> {
+        self.primary.list_groups_with_times()
+    }
+
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        self.primary.list_group_descriptions(pattern)
+    }
+
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        self.primary.group_counts(group).await
+    }
+
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        self.primary
+            .next_article_number(group, current, direction)
+            .await
+    }
+
+    fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
+        self.primary.list_article_numbers(group)
+    }
+
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        self.primary
+            .list_article_numbers_in_range(group, start, end)
+    }
+
+    fn list_article_ids(&self, group: &str) -> StringStream<'_> {
+        self.primary.list_article_ids(group)
+    }
+
+    fn list_article_ids_since(
+        &self,
+        group: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> StringStream<'_> {
+        self.primary.list_article_ids_since(group, since)
+    }
+
+    async fn purge_group_before(
+        &self,
+        group: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        self.primary.purge_group_before(group, before).await?;
+        let result = self.secondary.purge_group_before(group, before).await;
+        self.secondary_result("purge_group_before", result)
+    }
+
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        self.primary.purge_group_keep_latest(group, keep).await?;
+        let result = self.secondary.purge_group_keep_latest(group, keep).await;
+        self.secondary_result("purge_group_keep_latest", result)
+    }
+
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.primary.purge_expired(now).await?;
+        let result = self.secondary.purge_expired(now).await;
+        self.secondary_result("purge_expired", result)
+    }
+
+    async fn purge_orphan_messages(&self) -> Result<()> {
+        self.primary.purge_orphan_messages().await?;
+        let result = self.secondary.purge_orphan_messages().await;
+        self.secondary_result("purge_orphan_messages", result)
+    }
+
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        self.primary.history_status(message_id).await
+    }
+
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.primary.purge_history_before(before).await?;
+        let result = self.secondary.purge_history_before(before).await;
+        self.secondary_result("purge_history_before", result)
+    }
+
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.primary.enqueue_feed(peer, message_id).await?;
+        let result = self.secondary.enqueue_feed(peer, message_id).await;
+        self.secondary_result("enqueue_feed", result)
+    }
+
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        self.primary.peek_feed(peer, limit).await
+    }
+
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.primary.dequeue_feed(peer, message_id).await?;
+        let result = self.secondary.dequeue_feed(peer, message_id).await;
+        self.secondary_result("dequeue_feed", result)
+    }
+
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        let dropped = self.primary.trim_feed(peer, keep).await?;
+        let result = self.secondary.trim_feed(peer, keep).await.map(|_| ());
+        self.secondary_result("trim_feed", result)?;
+        Ok(dropped)
+    }
+
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        self.primary.feed_len(peer).await
+    }
+
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        self.primary.subscription_mark(server, group).await
+    }
+
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        self.primary
+            .set_subscription_mark(server, group, number)
+            .await?;
+        let result = self
+            .secondary
+            .set_subscription_mark(server, group, number)
+            .await;
+        self.secondary_result("set_subscription_mark", result)
+    }
+
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        self.primary.recent_body_times(hash, since).await
+    }
+
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.primary.record_body(hash, at).await?;
+        let result = self.secondary.record_body(hash, at).await;
+        self.secondary_result("record_body", result)
+    }
+
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.primary.purge_bodies_before(before).await?;
+        let result = self.secondary.purge_bodies_before(before).await;
+        self.secondary_result("purge_bodies_before", result)
+    }
+
+    async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
+        self.primary.get_message_size(message_id).await
+    }
+
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        let xref = self.primary.record_xref(message_id, host).await?;
+        let result = self.secondary.record_xref(message_id, host).await;
+        self.secondary_result("record_xref", result.map(|_| ()))?;
+        Ok(xref)
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        self.primary.get_article_numbers_by_id(message_id).await
+    }
+
+    async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        self.primary.delete_article_by_id(message_id).await?;
+        let result = self.secondary.delete_article_by_id(message_id).await;
+        self.secondary_result("delete_article_by_id", result)
+    }
+
+    async fn is_group_moderated(&self, group: &str) -> Result<bool> {
+        self.primary.is_group_moderated(group).await
+    }
+
+    async fn group_exists(&self, group: &str) -> Result<bool> {
+        self.primary.group_exists(group).await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.primary.backend_name()
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        self.primary.schema_version().await
+    }
+}
@@ -1,25 +1,30 @@
 use super::{
-    ArticleStream, Message, Storage, StringStream, StringTimestampStream, U64Stream,
-    common::{Headers, extract_message_id},
+    ArticleStream, Direction, HeaderStream, HistoryStatus, Message, OverviewStream, Storage,
+    StringPairStream, StringStream, StringTimestampStream, U64Stream,
+    common::{
+        BATCH_INSERT_ROWS, Headers, apply_xref, batch_groups, batch_rows, header_field_from_row,
+    },
 };
 use crate::migrations::Migrator;
+use crate::overview::OverviewLine;
 use anyhow::Result;
 use async_stream::stream;
 use async_trait::async_trait;
 use futures_util::StreamExt;
-use smallvec::SmallVec;
 use sqlx::{
-    PgPool, Row,
+    PgPool, Postgres, QueryBuilder, Row,
     postgres::{PgConnectOptions, PgPoolOptions},
 };
+use std::collections::HashMap;
 use std::str::FromStr;
 
 // SQL schemas for PostgreSQL storage
 const MESSAGES_TABLE: &str = "CREATE TABLE IF NOT EXISTS messages (
         message_id TEXT PRIMARY KEY,
         headers TEXT,
-        body TEXT,
-        size BIGINT NOT NULL
+        body BYTEA,
+        size BIGINT NOT NULL,
+        expires_at BIGINT
     )";
 
 const GROUP_ARTICLES_TABLE: &str = "CREATE TABLE IF NOT EXISTS group_articles (
@@ -34,7 +39,10 @@ const GROUP_ARTICLES_TABLE: &str = "CREATE TABLE IF NOT EXISTS group_articles (
 const GROUPS_TABLE: &str = "CREATE TABLE IF NOT EXISTS groups (
         name TEXT PRIMARY KEY,
         created_at BIGINT NOT NULL,
-        moderated BOOLEAN NOT NULL DEFAULT FALSE
+        moderated BOOLEAN NOT NULL DEFAULT FALSE,
+        description TEXT,
+        created_by TEXT,
+        moderator TEXT
     )";
 
 const OVERVIEW_TABLE: &str = "CREATE TABLE IF NOT EXISTS overview (
@@ -44,6 +52,40 @@ const OVERVIEW_TABLE: &str = "CREATE TABLE IF NOT EXISTS overview (
         PRIMARY KEY(group_name, article_number)
     )";
 
+pub(crate) const HISTORY_TABLE: &str = "CREATE TABLE IF NOT EXISTS history (
+        message_id TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        recorded_at BIGINT NOT NULL
+    )";
+
+pub(crate) const HISTORY_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS history_recorded_at ON history (recorded_at)";
+
+const RECORD_HISTORY: &str = "INSERT INTO history (message_id, status, recorded_at) VALUES ($1, $2, $3) \
+     ON CONFLICT (message_id) DO UPDATE SET status = EXCLUDED.status, recorded_at = EXCLUDED.recorded_at";
+
+pub(crate) const FEED_TABLE: &str = "CREATE TABLE IF NOT EXISTS feed_pending (
+        peer TEXT NOT NULL,
+        message_id TEXT NOT NULL,
+        queued_at BIGINT NOT NULL,
+        PRIMARY KEY(peer, message_id)
+    )";
+
+pub(crate) const SUBSCRIPTION_TABLE: &str = "CREATE TABLE IF NOT EXISTS subscription_marks (
+        server TEXT NOT NULL,
+        group_name TEXT NOT NULL,
+        high BIGINT NOT NULL,
+        PRIMARY KEY(server, group_name)
+    )";
+
+pub(crate) const RECENT_BODIES_TABLE: &str = "CREATE TABLE IF NOT EXISTS recent_bodies (
+        hash TEXT NOT NULL,
+        seen_at BIGINT NOT NULL
+    )";
+
+pub(crate) const RECENT_BODIES_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS recent_bodies_hash ON recent_bodies (hash, seen_at)";
+
 #[derive(Clone)]
 pub struct PostgresStorage {
     pool: PgPool,
@@ -151,9 +193,25 @@ Please verify:
                         e
                     )
                 })?;
+            for statement in [
+                HISTORY_TABLE,
+                HISTORY_INDEX,
+                FEED_TABLE,
+                SUBSCRIPTION_TABLE,
+                RECENT_BODIES_TABLE,
+                RECENT_BODIES_INDEX,
+            ] {
+                sqlx::query(statement).execute(&pool).await.map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to create history and feed tables in PostgreSQL database '{}': {}",
+                        uri,
+                        e
+                    )
+                })?;
+            }
 
-            // Set current version (since pre-1.0, we use version 1 as the baseline)
-            migrator.set_version(1).await.map_err(|e| {
+            // The schema above already includes every migration
+            migrator.set_version(super::migrations::postgres::LATEST_VERSION).await.map_err(|e| {
                 anyhow::anyhow!(
                     "Failed to set initial schema version for PostgreSQL storage database '{}': {}",
                     uri,
@@ -161,7 +219,10 @@ Please verify:
                 )
             })?;
 
-            tracing::info!("Successfully initialized PostgreSQL storage database at version 1");
+            tracing::info!(
+                "Successfully initialized PostgreSQL storage database at version {}",
+                super::migrations::postgres::LATEST_VERSION
+            );
         } else {
             // Existing database: apply any pending migrations
             tracing::info!("Found existing PostgreSQL storage database, checking for migrations");
@@ -182,70 +243,92 @@ Please verify:
 impl Storage for PostgresStorage {
     #[tracing::instrument(skip_all)]
     async fn store_article(&self, article: &Message) -> Result<()> {
-        let msg_id = extract_message_id(article).ok_or("missing Message-ID")?;
-        let headers = serde_json::to_string(&Headers(article.headers.clone()))?;
-
-        // Store the message once
-        sqlx::query(
-            "INSERT INTO messages (message_id, headers, body, size) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
-        )
-        .bind(&msg_id)
-        .bind(&headers)
-        .bind(&article.body)
-        .bind(i64::try_from(article.body.len()).unwrap_or(i64::MAX))
-        .execute(&self.pool)
-        .await?;
-
-        // Extract newsgroups from headers
-        let newsgroups: SmallVec<[String; 4]> = article
-            .headers
-            .iter()
-            .find(|(k, _)| k.eq_ignore_ascii_case("Newsgroups"))
-            .map(|(_, v)| {
-                v.split(',')
-                    .map(str::trim)
-                    .filter(|s| !s.is_empty())
-                    .map(std::string::ToString::to_string)
-                    .collect::<SmallVec<[String; 4]>>()
-            })
-            .unwrap_or_default();
+        self.store_articles(std::slice::from_ref(article)).await
+    }
 
-        // Associate with each group and create overview data
-        let now = chrono::Utc::now().timestamp();
-        for group in newsgroups {
-            let next: i64 = sqlx::query_scalar(
+    #[tracing::instrument(skip_all)]
+    async fn store_articles(&self, articles: &[Message]) -> Result<()> {
+        // Every group of the batch is locked up front, in name order so that
+        // concurrent batches cannot deadlock. The locks are held until the
+        // commit, so no other store can read the same highest number.
+        let mut tx = self.pool.begin().await?;
+        let mut next = HashMap::new();
+        for group in batch_groups(articles) {
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind(&group)
+                .execute(&mut *tx)
+                .await?;
+            let first: i64 = sqlx::query_scalar(
                 "SELECT COALESCE(MAX(number),0)+1 FROM group_articles WHERE group_name = $1",
             )
             .bind(&group)
-            .fetch_one(&self.pool)
+            .fetch_one(&mut *tx)
             .await?;
+            next.insert(group, first);
+        }
+        let (messages, groups) = batch_rows(articles, &mut next)?;
 
-            sqlx::query(
-                "INSERT INTO group_articles (group_name, number, message_id, inserted_at) VALUES ($1, $2, $3, $4)",
+        let now = chrono::Utc::now().timestamp();
+        for chunk in messages.chunks(BATCH_INSERT_ROWS) {
+            QueryBuilder::<Postgres>::new(
+                "INSERT INTO messages (message_id, headers, body, size, expires_at) ",
             )
-            .bind(&group)
-            .bind(next)
-            .bind(&msg_id)
-            .bind(now)
-            .execute(&self.pool)
+            .push_values(chunk, |mut row, m| {
+                row.push_bind(m.message_id.as_str())
+                    .push_bind(m.headers.as_str())
+                    .push_bind(m.body)
+                    .push_bind(m.size)
+                    .push_bind(m.expires_at);
+            })
+            .push(" ON CONFLICT DO NOTHING")
+            .build()
+            .execute(&mut *tx)
             .await?;
-
-            // Generate and store overview data
-            let overview_data = {
-                use crate::overview::generate_overview_line;
-                generate_overview_line(self, next as u64, article).await?
-            };
-
-            sqlx::query(
-                "INSERT INTO overview (group_name, article_number, overview_data) VALUES ($1, $2, $3) ON CONFLICT (group_name, article_number) DO UPDATE SET overview_data = EXCLUDED.overview_data",
+            QueryBuilder::<Postgres>::new("INSERT INTO history (message_id, status, recorded_at) ")
+                .push_values(chunk, |mut row, m| {
+                    row.push_bind(m.message_id.as_str())
+                        .push_bind(HistoryStatus::Stored.as_str())
+                        .push_bind(now);
+                })
+                .push(
+                    " ON CONFLICT (message_id) DO UPDATE SET status = EXCLUDED.status, \
+                     recorded_at = EXCLUDED.recorded_at",
+                )
+                .build()
+                .execute(&mut *tx)
+                .await?;
+        }
+        for chunk in groups.chunks(BATCH_INSERT_ROWS) {
+            QueryBuilder::<Postgres>::new(
+                "INSERT INTO group_articles (group_name, number, message_id, inserted_at) ",
             )
-            .bind(&group)
-            .bind(next)
-            .bind(&overview_data)
-            .execute(&self.pool)
+            .push_values(chunk, |mut row, g| {
+                row.push_bind(g.group.as_str())
+                    .push_bind(g.number)
+                    .push_bind(g.message_id.as_str())
+                    .push_bind(now);
+            })
+            .build()
+            .execute(&mut *tx)
+            .await?;
+            QueryBuilder::<Postgres>::new(
+                "INSERT INTO overview (group_name, article_number, overview_data) ",
+            )
+            .push_values(chunk, |mut row, g| {
+                row.push_bind(g.group.as_str())
+                    .push_bind(g.number)
+                    .push_bind(g.overview.as_str());
+            })
+            .push(
+                " ON CONFLICT (group_name, article_number) \
+                 DO UPDATE SET overview_data = EXCLUDED.overview_data",
+            )
+            .build()
+            .execute(&mut *tx)
             .await?;
         }
 
+        tx.commit().await?;
         Ok(())
     }
 
@@ -260,8 +343,8 @@ impl Storage for PostgresStorage {
         .await?
         {
             let headers_str: String = row.try_get("headers")?;
-            let body: String = row.try_get("body")?;
-            Ok(Some(crate::storage::common::reconstruct_message_from_row(&headers_str, &body)?))
+            let body: Vec<u8> = row.try_get("body")?;
+            Ok(Some(crate::storage::common::reconstruct_message_from_row(&headers_str, body)?))
         } else {
             Ok(None)
         }
@@ -275,10 +358,10 @@ impl Storage for PostgresStorage {
             .await?
         {
             let headers_str: String = row.try_get("headers")?;
-            let body: String = row.try_get("body")?;
+            let body: Vec<u8> = row.try_get("body")?;
             Ok(Some(crate::storage::common::reconstruct_message_from_row(
                 &headers_str,
-                &body,
+                body,
             )?))
         } else {
             Ok(None)
@@ -311,10 +394,10 @@ impl Storage for PostgresStorage {
                         match (
                             r.try_get::<String, _>("message_id"),
                             r.try_get::<String, _>("headers"),
-                            r.try_get::<String, _>("body")
+                            r.try_get::<Vec<u8>, _>("body")
                         ) {
                             (Ok(message_id), Ok(headers_str), Ok(body)) => {
-                                match crate::storage::common::reconstruct_message_from_row(&headers_str, &body) {
+                                match crate::storage::common::reconstruct_message_from_row(&headers_str, body) {
                                     Ok(message) => yield Ok((message_id, message)),
                                     Err(e) => yield Err(e),
                                 }
@@ -331,19 +414,37 @@ impl Storage for PostgresStorage {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn add_group(&self, group: &str, moderated: bool) -> Result<()> {
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
         sqlx::query(
-            "INSERT INTO groups (name, created_at, moderated) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            "INSERT INTO groups (name, created_at, moderated, created_by) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
         )
         .bind(group)
         .bind(now)
         .bind(moderated)
+        .bind(created_by)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT created_by FROM groups WHERE name = $1")
+            .bind(group)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get("created_by")?),
+            None => Ok(None),
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     async fn set_group_moderated(&self, group: &str, moderated: bool) -> Result<()> {
         sqlx::query("UPDATE groups SET moderated = $1 WHERE name = $2")
@@ -354,8 +455,56 @@ impl Storage for PostgresStorage {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        sqlx::query("UPDATE groups SET description = $1 WHERE name = $2")
+            .bind(description)
+            .bind(group)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT description FROM groups WHERE name = $1")
+            .bind(group)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get("description")?),
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        sqlx::query("UPDATE groups SET moderator = $1 WHERE name = $2")
+            .bind(address)
+            .bind(group)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT moderator FROM groups WHERE name = $1")
+            .bind(group)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get("moderator")?),
+            None => Ok(None),
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     async fn remove_group(&self, group: &str) -> Result<()> {
+        sqlx::query("DELETE FROM overview WHERE group_name = $1")
+            .bind(group)
+            .execute(&self.pool)
+            .await?;
         sqlx::query("DELETE FROM group_articles WHERE group_name = $1")
             .bind(group)
             .execute(&self.pool)
@@ -480,6 +629,77 @@ impl Storage for PostgresStorage {
         })
     }
 
+    #[tracing::instrument(skip_all)]
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        let pool = self.pool.clone();
+        let pattern = pattern.map(str::to_string);
+        Box::pin(stream! {
+            let mut rows = sqlx::query(
+                "SELECT name, COALESCE(description, '') AS description FROM groups ORDER BY name",
+            )
+            .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => {
+                        match (r.try_get::<String, _>("name"), r.try_get::<String, _>("description")) {
+                            (Ok(name), Ok(desc)) => {
+                                if pattern.as_deref().is_none_or(|p| crate::wildmat::wildmat_list(p, &name)) {
+                                    yield Ok((name, desc));
+                                }
+                            }
+                            (Err(e), _) => yield Err(anyhow::Error::from(e)),
+                            (_, Err(e)) => yield Err(anyhow::Error::from(e)),
+                        }
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS count, COALESCE(MIN(number), 1) AS low, COALESCE(MAX(number), 0) AS high FROM group_articles WHERE group_name = $1",
+        )
+        .bind(group)
+        .fetch_one(&self.pool)
+        .await?;
+        let count: i64 = row.try_get("count")?;
+        let low: i64 = row.try_get("low")?;
+        let high: i64 = row.try_get("high")?;
+        Ok((
+            u64::try_from(count).unwrap_or(0),
+            u64::try_from(low).unwrap_or(0),
+            u64::try_from(high).unwrap_or(0),
+        ))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        let sql = match direction {
+            Direction::Next => {
+                "SELECT MIN(number) AS number FROM group_articles WHERE group_name = $1 AND number > $2"
+            }
+            Direction::Previous => {
+                "SELECT MAX(number) AS number FROM group_articles WHERE group_name = $1 AND number < $2"
+            }
+        };
+        let row = sqlx::query(sql)
+            .bind(group)
+            .bind(i64::try_from(current).unwrap_or(i64::MAX))
+            .fetch_one(&self.pool)
+            .await?;
+        let number: Option<i64> = row.try_get("number")?;
+        Ok(number.and_then(|n| u64::try_from(n).ok()))
+    }
+
     #[tracing::instrument(skip_all)]
     fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
         let pool = self.pool.clone();
@@ -501,6 +721,29 @@ impl Storage for PostgresStorage {
         })
     }
 
+    #[tracing::instrument(skip_all)]
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT number FROM group_articles WHERE group_name = $1 AND number BETWEEN $2 AND $3 ORDER BY number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => match r.try_get::<i64, _>("number") {
+                        Ok(number) => yield Ok(u64::try_from(number).unwrap_or(0)),
+                        Err(e) => yield Err(anyhow::Error::from(e)),
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
     #[tracing::instrument(skip_all)]
     fn list_article_ids(&self, group: &str) -> StringStream<'_> {
         let pool = self.pool.clone();
@@ -555,6 +798,13 @@ impl Storage for PostgresStorage {
         group: &str,
         before: chrono::DateTime<chrono::Utc>,
     ) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM overview WHERE group_name = $1 AND article_number IN (SELECT number FROM group_articles WHERE group_name = $1 AND inserted_at < $2)",
+        )
+        .bind(group)
+        .bind(before.timestamp())
+        .execute(&self.pool)
+        .await?;
         sqlx::query("DELETE FROM group_articles WHERE group_name = $1 AND inserted_at < $2")
             .bind(group)
             .bind(before.timestamp())
@@ -563,8 +813,67 @@ impl Storage for PostgresStorage {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        // Everything at or below the first article past the newest `keep`
+        let keep = i64::try_from(keep).unwrap_or(i64::MAX);
+        sqlx::query(
+            "DELETE FROM overview WHERE group_name = $1 AND article_number <= (SELECT number FROM group_articles WHERE group_name = $1 ORDER BY number DESC LIMIT 1 OFFSET $2)",
+        )
+        .bind(group)
+        .bind(keep)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            "DELETE FROM group_articles WHERE group_name = $1 AND number <= (SELECT number FROM group_articles WHERE group_name = $1 ORDER BY number DESC LIMIT 1 OFFSET $2)",
+        )
+        .bind(group)
+        .bind(keep)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let cutoff = now.timestamp();
+        sqlx::query(
+            "DELETE FROM overview WHERE (group_name, article_number) IN (SELECT ga.group_name, ga.number FROM group_articles ga JOIN messages m ON m.message_id = ga.message_id WHERE m.expires_at <= $1)",
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            "DELETE FROM group_articles WHERE message_id IN (SELECT message_id FROM messages WHERE expires_at <= $1)",
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO history (message_id, status, recorded_at) SELECT message_id, $1, $2 FROM messages WHERE expires_at <= $2 \
+             ON CONFLICT (message_id) DO UPDATE SET status = EXCLUDED.status, recorded_at = EXCLUDED.recorded_at",
+        )
+        .bind(HistoryStatus::Expired.as_str())
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("DELETE FROM messages WHERE expires_at <= $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     #[tracing::instrument(skip_all)]
     async fn purge_orphan_messages(&self) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO history (message_id, status, recorded_at) SELECT message_id, $1, $2 FROM messages WHERE message_id NOT IN (SELECT DISTINCT message_id FROM group_articles) \
+             ON CONFLICT (message_id) DO UPDATE SET status = EXCLUDED.status, recorded_at = EXCLUDED.recorded_at",
+        )
+        .bind(HistoryStatus::Expired.as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
         sqlx::query(
             "DELETE FROM messages WHERE message_id NOT IN (SELECT DISTINCT message_id FROM group_articles)",
         )
@@ -573,6 +882,154 @@ impl Storage for PostgresStorage {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        let status: Option<String> =
+            sqlx::query_scalar("SELECT status FROM history WHERE message_id = $1")
+                .bind(message_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(status.as_deref().and_then(HistoryStatus::parse))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM history WHERE recorded_at < $1")
+            .bind(before.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO feed_pending (peer, message_id, queued_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(peer)
+        .bind(message_id)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT message_id FROM feed_pending WHERE peer = $1 ORDER BY queued_at, message_id LIMIT $2",
+        )
+        .bind(peer)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM feed_pending WHERE peer = $1 AND message_id = $2")
+            .bind(peer)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM feed_pending WHERE peer = $1 AND message_id IN (SELECT message_id \
+             FROM feed_pending WHERE peer = $1 ORDER BY queued_at DESC, message_id DESC OFFSET $2)",
+        )
+        .bind(peer)
+        .bind(keep as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM feed_pending WHERE peer = $1")
+            .bind(peer)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        let high: Option<i64> = sqlx::query_scalar(
+            "SELECT high FROM subscription_marks WHERE server = $1 AND group_name = $2",
+        )
+        .bind(server)
+        .bind(group)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(high.map(|n| n as u64))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO subscription_marks (server, group_name, high) VALUES ($1, $2, $3) \
+             ON CONFLICT (server, group_name) DO UPDATE SET high = EXCLUDED.high",
+        )
+        .bind(server)
+        .bind(group)
+        .bind(number as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        let times: Vec<i64> = sqlx::query_scalar(
+            "SELECT seen_at FROM recent_bodies WHERE hash = $1 AND seen_at >= $2 ORDER BY seen_at",
+        )
+        .bind(hash)
+        .bind(since.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(times
+            .into_iter()
+            .filter_map(|t| chrono::DateTime::from_timestamp(t, 0))
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("INSERT INTO recent_bodies (hash, seen_at) VALUES ($1, $2)")
+            .bind(hash)
+            .bind(at.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM recent_bodies WHERE seen_at < $1")
+            .bind(before.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM messages WHERE message_id = $1 LIMIT 1")
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
     #[tracing::instrument(skip_all)]
     async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
         if let Some(row) = sqlx::query("SELECT size FROM messages WHERE message_id = $1")
@@ -587,7 +1044,48 @@ impl Storage for PostgresStorage {
         }
     }
 
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        let Some(headers_str) =
+            sqlx::query_scalar::<_, String>("SELECT headers FROM messages WHERE message_id = $1")
+                .bind(message_id)
+                .fetch_optional(&self.pool)
+                .await?
+        else {
+            return Ok(None);
+        };
+        let numbers = self.get_article_numbers_by_id(message_id).await?;
+        let Headers(mut headers) = serde_json::from_str(&headers_str)?;
+        let (xref, changed) = apply_xref(&mut headers, host, numbers);
+        if changed {
+            sqlx::query("UPDATE messages SET headers = $1 WHERE message_id = $2")
+                .bind(serde_json::to_string(&Headers(headers))?)
+                .bind(message_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(xref)
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT g.group_name, g.number FROM group_articles g JOIN groups n ON n.name = g.group_name WHERE g.message_id = $1 ORDER BY g.group_name",
+        )
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(group, number)| (group, u64::try_from(number).unwrap_or(0)))
+            .collect())
+    }
+
     async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM overview WHERE (group_name, article_number) IN (SELECT group_name, number FROM group_articles WHERE message_id = $1)",
+        )
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
         sqlx::query("DELETE FROM group_articles WHERE message_id = $1")
             .bind(message_id)
             .execute(&self.pool)
@@ -598,26 +1096,81 @@ impl Storage for PostgresStorage {
         .bind(message_id)
         .execute(&self.pool)
         .await?;
+        sqlx::query(RECORD_HISTORY)
+            .bind(message_id)
+            .bind(HistoryStatus::Cancelled.as_str())
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        super::migrations::postgres::PostgresStorageMigrator::new(self.pool.clone())
+            .get_current_version()
+            .await
+    }
+
     #[tracing::instrument(skip_all)]
-    async fn get_overview_range(&self, group: &str, start: u64, end: u64) -> Result<Vec<String>> {
-        let rows = sqlx::query(
-            "SELECT overview_data FROM overview WHERE group_name = $1 AND article_number >= $2 AND article_number <= $3 ORDER BY article_number",
-        )
-        .bind(group)
-        .bind(i64::try_from(start).unwrap_or(0))
-        .bind(i64::try_from(end).unwrap_or(i64::MAX))
-        .fetch_all(&self.pool)
-        .await?;
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT overview_data FROM overview WHERE group_name = $1 AND article_number >= $2 AND article_number <= $3 ORDER BY article_number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
 
-        let mut overview_lines = Vec::new();
-        for row in rows {
-            let overview_data: String = row.try_get("overview_data")?;
-            overview_lines.push(overview_data);
-        }
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => match r.try_get::<String, _>("overview_data") {
+                        Ok(data) => yield OverviewLine::parse(&data),
+                        Err(e) => yield Err(anyhow::Error::from(e)),
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        let header = header.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT g.number, m.headers, m.size, o.overview_data FROM group_articles g JOIN messages m ON m.message_id = g.message_id LEFT JOIN overview o ON o.group_name = g.group_name AND o.article_number = g.number WHERE g.group_name = $1 AND g.number >= $2 AND g.number <= $3 ORDER BY g.number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
 
-        Ok(overview_lines)
+            while let Some(row) = rows.next().await {
+                yield row.map_err(anyhow::Error::from).and_then(|r| {
+                    let number: i64 = r.try_get("number")?;
+                    let headers: String = r.try_get("headers")?;
+                    let size: i64 = r.try_get("size")?;
+                    let overview: Option<String> = r.try_get("overview_data")?;
+                    let value = header_field_from_row(
+                        &header,
+                        &headers,
+                        u64::try_from(size).unwrap_or(0),
+                        overview.as_deref(),
+                    )?;
+                    Ok((u64::try_from(number).unwrap_or(0), value))
+                });
+            }
+        })
     }
 }
@@ -1,25 +1,31 @@
 use super::{
-    ArticleStream, Message, Storage, StringStream, StringTimestampStream, U64Stream,
-    common::{Headers, extract_message_id},
+    ArticleStream, Direction, HeaderStream, HistoryStatus, Message, OverviewStream, Storage,
+    StringPairStream, StringStream, StringTimestampStream, U64Stream,
+    common::{
+        BATCH_INSERT_ROWS, Headers, apply_xref, batch_groups, batch_rows, header_field_from_row,
+    },
 };
 use crate::migrations::Migrator;
+use crate::overview::OverviewLine;
 use anyhow::Result;
 use async_stream::stream;
 use async_trait::async_trait;
 use futures_util::StreamExt;
-use smallvec::SmallVec;
 use sqlx::{
-    Row, SqlitePool,
-    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    QueryBuilder, Row, Sqlite, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
 };
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 // SQL schemas for SQLite storage
 const MESSAGES_TABLE: &str = "CREATE TABLE IF NOT EXISTS messages (
         message_id TEXT PRIMARY KEY,
         headers TEXT,
-        body TEXT,
-        size INTEGER NOT NULL
+        body BLOB,
+        size INTEGER NOT NULL,
+        expires_at INTEGER
     )";
 
 const GROUP_ARTICLES_TABLE: &str = "CREATE TABLE IF NOT EXISTS group_articles (
@@ -34,7 +40,10 @@ const GROUP_ARTICLES_TABLE: &str = "CREATE TABLE IF NOT EXISTS group_articles (
 const GROUPS_TABLE: &str = "CREATE TABLE IF NOT EXISTS groups (
         name TEXT PRIMARY KEY,
         created_at INTEGER NOT NULL,
-        moderated INTEGER NOT NULL DEFAULT 0
+        moderated INTEGER NOT NULL DEFAULT 0,
+        description TEXT,
+        created_by TEXT,
+        moderator TEXT
     )";
 
 const OVERVIEW_TABLE: &str = "CREATE TABLE IF NOT EXISTS overview (
@@ -44,39 +53,85 @@ const OVERVIEW_TABLE: &str = "CREATE TABLE IF NOT EXISTS overview (
         PRIMARY KEY(group_name, article_number)
     )";
 
+pub(crate) const HISTORY_TABLE: &str = "CREATE TABLE IF NOT EXISTS history (
+        message_id TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL
+    )";
+
+pub(crate) const HISTORY_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS history_recorded_at ON history (recorded_at)";
+
+const RECORD_HISTORY: &str = "INSERT INTO history (message_id, status, recorded_at) VALUES (?, ?, ?) \
+     ON CONFLICT(message_id) DO UPDATE SET status = excluded.status, recorded_at = excluded.recorded_at";
+
+pub(crate) const FEED_TABLE: &str = "CREATE TABLE IF NOT EXISTS feed_pending (
+        peer TEXT NOT NULL,
+        message_id TEXT NOT NULL,
+        queued_at INTEGER NOT NULL,
+        PRIMARY KEY(peer, message_id)
+    )";
+
+pub(crate) const SUBSCRIPTION_TABLE: &str = "CREATE TABLE IF NOT EXISTS subscription_marks (
+        server TEXT NOT NULL,
+        group_name TEXT NOT NULL,
+        high INTEGER NOT NULL,
+        PRIMARY KEY(server, group_name)
+    )";
+
+pub(crate) const RECENT_BODIES_TABLE: &str = "CREATE TABLE IF NOT EXISTS recent_bodies (
+        hash TEXT NOT NULL,
+        seen_at INTEGER NOT NULL
+    )";
+
+pub(crate) const RECENT_BODIES_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS recent_bodies_hash ON recent_bodies (hash, seen_at)";
+
 #[derive(Clone)]
 pub struct SqliteStorage {
+    /// Connections used for lookups and the streaming list methods
     pool: SqlitePool,
+    /// Single connection every write goes through, since SQLite allows only
+    /// one writer at a time
+    writer: SqlitePool,
+}
+
+/// Parse a `sqlite:` or `file:` URI into connection options.
+fn connect_options(uri: &str) -> std::result::Result<SqliteConnectOptions, sqlx::Error> {
+    let uri = match uri.strip_prefix("file:") {
+        Some(rest) => Cow::Owned(format!("sqlite:{rest}")),
+        None => Cow::Borrowed(uri),
+    };
+    SqliteConnectOptions::from_str(&uri)
 }
 
 impl SqliteStorage {
     #[tracing::instrument(skip_all)]
-    /// Create a new SQLite storage backend.
+    /// Create a new SQLite storage backend from a `sqlite://` or `file:` URI.
+    /// Connections use WAL journaling and enforce foreign keys.
     ///
     /// # Errors
     ///
     /// Returns an error if the database connection fails or schema creation fails.
     pub async fn new(path: &str) -> Result<Self> {
-        let options = SqliteConnectOptions::from_str(path)
+        let options = connect_options(path)
             .map_err(|e| {
                 anyhow::anyhow!(
                     "Invalid SQLite connection URI '{path}': {e}
 
 Please ensure the URI is in the correct format:
-- File database: sqlite:///path/to/database.db
+- File database: sqlite:///path/to/database.db or file:///path/to/database.db
 - In-memory database: sqlite::memory:
 - Relative path: sqlite://relative/path.db"
                 )
             })?
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect_with(options)
-            .await
-            .map_err(|e| {
-                anyhow::anyhow!(
-                    "Failed to connect to SQLite database '{path}': {e}
+        let connect_error = |e: sqlx::Error| {
+            anyhow::anyhow!(
+                "Failed to connect to SQLite database '{path}': {e}
 
 Possible causes:
 - Parent directory does not exist and cannot be created
@@ -85,11 +140,22 @@ Possible causes:
 - Path contains invalid characters for the filesystem
 - Disk space is full
 - Database is locked by another process"
-                )
-            })?;
+            )
+        };
+        // The writer connects first so that it creates a missing database file
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone())
+            .await
+            .map_err(connect_error)?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(connect_error)?;
 
         // Set up migrator to check database state
-        let migrator = super::migrations::sqlite::SqliteStorageMigrator::new(pool.clone());
+        let migrator = super::migrations::sqlite::SqliteStorageMigrator::new(writer.clone());
 
         if migrator.is_fresh_database().await {
             // Fresh database: initialize with current schema
@@ -97,7 +163,7 @@ Possible causes:
 
             // Create database schema
             sqlx::query(MESSAGES_TABLE)
-                .execute(&pool)
+                .execute(&writer)
                 .await
                 .map_err(|e| {
                     anyhow::anyhow!(
@@ -105,7 +171,7 @@ Possible causes:
                     )
                 })?;
             sqlx::query(GROUP_ARTICLES_TABLE)
-                .execute(&pool)
+                .execute(&writer)
                 .await
                 .map_err(|e| {
                     anyhow::anyhow!(
@@ -113,7 +179,7 @@ Possible causes:
                     )
                 })?;
             sqlx::query(GROUPS_TABLE)
-                .execute(&pool)
+                .execute(&writer)
                 .await
                 .map_err(|e| {
                     anyhow::anyhow!(
@@ -121,22 +187,42 @@ Possible causes:
                     )
                 })?;
             sqlx::query(OVERVIEW_TABLE)
-                .execute(&pool)
+                .execute(&writer)
                 .await
                 .map_err(|e| {
                     anyhow::anyhow!(
                         "Failed to create overview table in SQLite database '{path}': {e}"
                     )
                 })?;
+            for statement in [HISTORY_TABLE, HISTORY_INDEX] {
+                sqlx::query(statement).execute(&writer).await.map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to create history table in SQLite database '{path}': {e}"
+                    )
+                })?;
+            }
+            for statement in [
+                FEED_TABLE,
+                SUBSCRIPTION_TABLE,
+                RECENT_BODIES_TABLE,
+                RECENT_BODIES_INDEX,
+            ] {
+                sqlx::query(statement).execute(&writer).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to create feed tables in SQLite database '{path}': {e}")
+                })?;
+            }
 
-            // Set current version (since pre-1.0, we use version 1 as the baseline)
-            migrator.set_version(1).await.map_err(|e| {
+            // The schema above already includes every migration
+            migrator.set_version(super::migrations::sqlite::LATEST_VERSION).await.map_err(|e| {
                 anyhow::anyhow!(
                     "Failed to set initial schema version for SQLite storage database '{path}': {e}"
                 )
             })?;
 
-            tracing::info!("Successfully initialized SQLite storage database at version 1");
+            tracing::info!(
+                "Successfully initialized SQLite storage database at version {}",
+                super::migrations::sqlite::LATEST_VERSION
+            );
         } else {
             // Existing database: apply any pending migrations
             tracing::info!("Found existing SQLite storage database, checking for migrations");
@@ -147,7 +233,26 @@ Possible causes:
             })?;
         }
 
-        Ok(Self { pool })
+        Ok(Self { pool, writer })
+    }
+
+    /// Numbers of `message_id` in groups that exist, read through `pool`
+    /// so the writer can see numbers it has just assigned.
+    async fn article_numbers(
+        &self,
+        pool: &SqlitePool,
+        message_id: &str,
+    ) -> Result<Vec<(String, u64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT g.group_name, g.number FROM group_articles g JOIN groups n ON n.name = g.group_name WHERE g.message_id = ? ORDER BY g.group_name",
+        )
+        .bind(message_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(group, number)| (group, u64::try_from(number).unwrap_or(0)))
+            .collect())
     }
 }
 
@@ -155,71 +260,83 @@ Possible causes:
 impl Storage for SqliteStorage {
     #[tracing::instrument(skip_all)]
     async fn store_article(&self, article: &Message) -> Result<()> {
-        let msg_id =
-            extract_message_id(article).ok_or_else(|| anyhow::anyhow!("missing Message-ID"))?;
-        let headers = serde_json::to_string(&Headers(article.headers.clone()))?;
-
-        // Store the message once
-        sqlx::query(
-            "INSERT OR IGNORE INTO messages (message_id, headers, body, size) VALUES (?, ?, ?, ?)",
-        )
-        .bind(&msg_id)
-        .bind(&headers)
-        .bind(&article.body)
-        .bind(i64::try_from(article.body.len()).unwrap_or(i64::MAX))
-        .execute(&self.pool)
-        .await?;
-
-        // Extract newsgroups from headers
-        let newsgroups: SmallVec<[String; 4]> = article
-            .headers
-            .iter()
-            .find(|(k, _)| k.eq_ignore_ascii_case("Newsgroups"))
-            .map(|(_, v)| {
-                v.split(',')
-                    .map(str::trim)
-                    .filter(|s| !s.is_empty())
-                    .map(std::string::ToString::to_string)
-                    .collect::<SmallVec<[String; 4]>>()
-            })
-            .unwrap_or_default();
+        self.store_articles(std::slice::from_ref(article)).await
+    }
 
-        // Associate with each group and create overview data
-        let now = chrono::Utc::now().timestamp();
-        for group in newsgroups {
-            let next: i64 = sqlx::query_scalar(
+    #[tracing::instrument(skip_all)]
+    async fn store_articles(&self, articles: &[Message]) -> Result<()> {
+        // The whole batch is one transaction on the writer connection, so
+        // it costs a single commit and no concurrent store can pick the
+        // same article numbers
+        let mut tx = self.writer.begin().await?;
+        let mut next = HashMap::new();
+        for group in batch_groups(articles) {
+            let first: i64 = sqlx::query_scalar(
                 "SELECT COALESCE(MAX(number),0)+1 FROM group_articles WHERE group_name = ?",
             )
             .bind(&group)
-            .fetch_one(&self.pool)
+            .fetch_one(&mut *tx)
             .await?;
+            next.insert(group, first);
+        }
+        let (messages, groups) = batch_rows(articles, &mut next)?;
 
-            sqlx::query(
-                "INSERT INTO group_articles (group_name, number, message_id, inserted_at) VALUES (?, ?, ?, ?)",
+        let now = chrono::Utc::now().timestamp();
+        for chunk in messages.chunks(BATCH_INSERT_ROWS) {
+            QueryBuilder::<Sqlite>::new(
+                "INSERT OR IGNORE INTO messages (message_id, headers, body, size, expires_at) ",
             )
-            .bind(&group)
-            .bind(next)
-            .bind(&msg_id)
-            .bind(now)
-            .execute(&self.pool)
+            .push_values(chunk, |mut row, m| {
+                row.push_bind(m.message_id.as_str())
+                    .push_bind(m.headers.as_str())
+                    .push_bind(m.body)
+                    .push_bind(m.size)
+                    .push_bind(m.expires_at);
+            })
+            .build()
+            .execute(&mut *tx)
             .await?;
-
-            // Generate and store overview data
-            let overview_data = {
-                use crate::overview::generate_overview_line;
-                generate_overview_line(self, next as u64, article).await?
-            };
-
-            sqlx::query(
-                "INSERT OR REPLACE INTO overview (group_name, article_number, overview_data) VALUES (?, ?, ?)",
+            QueryBuilder::<Sqlite>::new("INSERT INTO history (message_id, status, recorded_at) ")
+                .push_values(chunk, |mut row, m| {
+                    row.push_bind(m.message_id.as_str())
+                        .push_bind(HistoryStatus::Stored.as_str())
+                        .push_bind(now);
+                })
+                .push(
+                    " ON CONFLICT(message_id) DO UPDATE SET status = excluded.status, \
+                     recorded_at = excluded.recorded_at",
+                )
+                .build()
+                .execute(&mut *tx)
+                .await?;
+        }
+        for chunk in groups.chunks(BATCH_INSERT_ROWS) {
+            QueryBuilder::<Sqlite>::new(
+                "INSERT INTO group_articles (group_name, number, message_id, inserted_at) ",
             )
-            .bind(&group)
-            .bind(next)
-            .bind(&overview_data)
-            .execute(&self.pool)
+            .push_values(chunk, |mut row, g| {
+                row.push_bind(g.group.as_str())
+                    .push_bind(g.number)
+                    .push_bind(g.message_id.as_str())
+                    .push_bind(now);
+            })
+            .build()
+            .execute(&mut *tx)
+            .await?;
+            QueryBuilder::<Sqlite>::new(
+                "INSERT OR REPLACE INTO overview (group_name, article_number, overview_data) ",
+            )
+            .push_values(chunk, |mut row, g| {
+                row.push_bind(g.group.as_str())
+                    .push_bind(g.number)
+                    .push_bind(g.overview.as_str());
+            })
+            .build()
+            .execute(&mut *tx)
             .await?;
         }
 
+        tx.commit().await?;
         Ok(())
     }
 
@@ -236,10 +353,10 @@ impl Storage for SqliteStorage {
         .await?
         {
             let headers_str: String = row.try_get("headers")?;
-            let body: String = row.try_get("body")?;
+            let body: Vec<u8> = row.try_get("body")?;
             Ok(Some(crate::storage::common::reconstruct_message_from_row(
                 &headers_str,
-                &body,
+                body,
             )?))
         } else {
             Ok(None)
@@ -254,10 +371,10 @@ impl Storage for SqliteStorage {
             .await?
         {
             let headers_str: String = row.try_get("headers")?;
-            let body: String = row.try_get("body")?;
+            let body: Vec<u8> = row.try_get("body")?;
             Ok(Some(crate::storage::common::reconstruct_message_from_row(
                 &headers_str,
-                &body,
+                body,
             )?))
         } else {
             Ok(None)
@@ -290,10 +407,10 @@ impl Storage for SqliteStorage {
                         match (
                             r.try_get::<String, _>("message_id"),
                             r.try_get::<String, _>("headers"),
-                            r.try_get::<String, _>("body")
+                            r.try_get::<Vec<u8>, _>("body")
                         ) {
                             (Ok(message_id), Ok(headers_str), Ok(body)) => {
-                                match crate::storage::common::reconstruct_message_from_row(&headers_str, &body) {
+                                match crate::storage::common::reconstruct_message_from_row(&headers_str, body) {
                                     Ok(message) => yield Ok((message_id, message)),
                                     Err(e) => yield Err(e),
                                 }
@@ -310,15 +427,35 @@ impl Storage for SqliteStorage {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn add_group(&self, group: &str, moderated: bool) -> Result<()> {
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
-        sqlx::query("INSERT OR IGNORE INTO groups (name, created_at, moderated) VALUES (?, ?, ?)")
+        sqlx::query(
+            "INSERT OR IGNORE INTO groups (name, created_at, moderated, created_by) VALUES (?, ?, ?, ?)",
+        )
+        .bind(group)
+        .bind(now)
+        .bind(i32::from(moderated))
+        .bind(created_by)
+        .execute(&self.writer)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT created_by FROM groups WHERE name = ?")
             .bind(group)
-            .bind(now)
-            .bind(i32::from(moderated))
-            .execute(&self.pool)
+            .fetch_optional(&self.pool)
             .await?;
-        Ok(())
+        match row {
+            Some(r) => Ok(r.try_get("created_by")?),
+            None => Ok(None),
+        }
     }
 
     #[tracing::instrument(skip_all)]
@@ -326,25 +463,73 @@ impl Storage for SqliteStorage {
         sqlx::query("UPDATE groups SET moderated = ? WHERE name = ?")
             .bind(i32::from(moderated))
             .bind(group)
-            .execute(&self.pool)
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        sqlx::query("UPDATE groups SET description = ? WHERE name = ?")
+            .bind(description)
+            .bind(group)
+            .execute(&self.writer)
             .await?;
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT description FROM groups WHERE name = ?")
+            .bind(group)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get("description")?),
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        sqlx::query("UPDATE groups SET moderator = ? WHERE name = ?")
+            .bind(address)
+            .bind(group)
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT moderator FROM groups WHERE name = ?")
+            .bind(group)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get("moderator")?),
+            None => Ok(None),
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     async fn remove_group(&self, group: &str) -> Result<()> {
+        sqlx::query("DELETE FROM overview WHERE group_name = ?")
+            .bind(group)
+            .execute(&self.writer)
+            .await?;
         sqlx::query("DELETE FROM group_articles WHERE group_name = ?")
             .bind(group)
-            .execute(&self.pool)
+            .execute(&self.writer)
             .await?;
         sqlx::query("DELETE FROM groups WHERE name = ?")
             .bind(group)
-            .execute(&self.pool)
+            .execute(&self.writer)
             .await?;
         sqlx::query(
             "DELETE FROM messages WHERE message_id NOT IN (SELECT DISTINCT message_id FROM group_articles)"
         )
-        .execute(&self.pool)
+        .execute(&self.writer)
         .await?;
         Ok(())
     }
@@ -457,6 +642,77 @@ impl Storage for SqliteStorage {
         })
     }
 
+    #[tracing::instrument(skip_all)]
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        let pool = self.pool.clone();
+        let pattern = pattern.map(str::to_string);
+        Box::pin(stream! {
+            let mut rows = sqlx::query(
+                "SELECT name, COALESCE(description, '') AS description FROM groups ORDER BY name",
+            )
+            .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => {
+                        match (r.try_get::<String, _>("name"), r.try_get::<String, _>("description")) {
+                            (Ok(name), Ok(desc)) => {
+                                if pattern.as_deref().is_none_or(|p| crate::wildmat::wildmat_list(p, &name)) {
+                                    yield Ok((name, desc));
+                                }
+                            }
+                            (Err(e), _) => yield Err(anyhow::Error::from(e)),
+                            (_, Err(e)) => yield Err(anyhow::Error::from(e)),
+                        }
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS count, COALESCE(MIN(number), 1) AS low, COALESCE(MAX(number), 0) AS high FROM group_articles WHERE group_name = ?",
+        )
+        .bind(group)
+        .fetch_one(&self.pool)
+        .await?;
+        let count: i64 = row.try_get("count")?;
+        let low: i64 = row.try_get("low")?;
+        let high: i64 = row.try_get("high")?;
+        Ok((
+            u64::try_from(count).unwrap_or(0),
+            u64::try_from(low).unwrap_or(0),
+            u64::try_from(high).unwrap_or(0),
+        ))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        let sql = match direction {
+            Direction::Next => {
+                "SELECT MIN(number) AS number FROM group_articles WHERE group_name = ? AND number > ?"
+            }
+            Direction::Previous => {
+                "SELECT MAX(number) AS number FROM group_articles WHERE group_name = ? AND number < ?"
+            }
+        };
+        let row = sqlx::query(sql)
+            .bind(group)
+            .bind(i64::try_from(current).unwrap_or(i64::MAX))
+            .fetch_one(&self.pool)
+            .await?;
+        let number: Option<i64> = row.try_get("number")?;
+        Ok(number.and_then(|n| u64::try_from(n).ok()))
+    }
+
     #[tracing::instrument(skip_all)]
     fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
         let pool = self.pool.clone();
@@ -478,6 +734,29 @@ impl Storage for SqliteStorage {
         })
     }
 
+    #[tracing::instrument(skip_all)]
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT number FROM group_articles WHERE group_name = ? AND number BETWEEN ? AND ? ORDER BY number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => match r.try_get::<i64, _>("number") {
+                        Ok(number) => yield Ok(u64::try_from(number).unwrap_or(0)),
+                        Err(e) => yield Err(anyhow::Error::from(e)),
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
     #[tracing::instrument(skip_all)]
     fn list_article_ids(&self, group: &str) -> StringStream<'_> {
         let pool = self.pool.clone();
@@ -532,24 +811,242 @@ impl Storage for SqliteStorage {
         group: &str,
         before: chrono::DateTime<chrono::Utc>,
     ) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM overview WHERE group_name = ? AND article_number IN (SELECT number FROM group_articles WHERE group_name = ? AND inserted_at < ?)",
+        )
+        .bind(group)
+        .bind(group)
+        .bind(before.timestamp())
+        .execute(&self.writer)
+        .await?;
         sqlx::query("DELETE FROM group_articles WHERE group_name = ? AND inserted_at < ?")
             .bind(group)
             .bind(before.timestamp())
-            .execute(&self.pool)
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        // Everything at or below the first article past the newest `keep`
+        let keep = i64::try_from(keep).unwrap_or(i64::MAX);
+        sqlx::query(
+            "DELETE FROM overview WHERE group_name = ? AND article_number <= (SELECT number FROM group_articles WHERE group_name = ? ORDER BY number DESC LIMIT 1 OFFSET ?)",
+        )
+        .bind(group)
+        .bind(group)
+        .bind(keep)
+        .execute(&self.writer)
+        .await?;
+        sqlx::query(
+            "DELETE FROM group_articles WHERE group_name = ? AND number <= (SELECT number FROM group_articles WHERE group_name = ? ORDER BY number DESC LIMIT 1 OFFSET ?)",
+        )
+        .bind(group)
+        .bind(group)
+        .bind(keep)
+        .execute(&self.writer)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let cutoff = now.timestamp();
+        sqlx::query(
+            "DELETE FROM overview WHERE (group_name, article_number) IN (SELECT ga.group_name, ga.number FROM group_articles ga JOIN messages m ON m.message_id = ga.message_id WHERE m.expires_at <= ?)",
+        )
+        .bind(cutoff)
+        .execute(&self.writer)
+        .await?;
+        sqlx::query(
+            "DELETE FROM group_articles WHERE message_id IN (SELECT message_id FROM messages WHERE expires_at <= ?)",
+        )
+        .bind(cutoff)
+        .execute(&self.writer)
+        .await?;
+        sqlx::query(
+            "INSERT INTO history (message_id, status, recorded_at) SELECT message_id, ?, ? FROM messages WHERE expires_at <= ? \
+             ON CONFLICT(message_id) DO UPDATE SET status = excluded.status, recorded_at = excluded.recorded_at",
+        )
+        .bind(HistoryStatus::Expired.as_str())
+        .bind(cutoff)
+        .bind(cutoff)
+        .execute(&self.writer)
+        .await?;
+        sqlx::query("DELETE FROM messages WHERE expires_at <= ?")
+            .bind(cutoff)
+            .execute(&self.writer)
             .await?;
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
     async fn purge_orphan_messages(&self) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO history (message_id, status, recorded_at) SELECT message_id, ?, ? FROM messages WHERE message_id NOT IN (SELECT DISTINCT message_id FROM group_articles) \
+             ON CONFLICT(message_id) DO UPDATE SET status = excluded.status, recorded_at = excluded.recorded_at",
+        )
+        .bind(HistoryStatus::Expired.as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.writer)
+        .await?;
         sqlx::query(
             "DELETE FROM messages WHERE message_id NOT IN (SELECT DISTINCT message_id FROM group_articles)"
         )
-        .execute(&self.pool)
+        .execute(&self.writer)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        let status: Option<String> =
+            sqlx::query_scalar("SELECT status FROM history WHERE message_id = ?")
+                .bind(message_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(status.as_deref().and_then(HistoryStatus::parse))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM history WHERE recorded_at < ?")
+            .bind(before.timestamp())
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO feed_pending (peer, message_id, queued_at) VALUES (?, ?, ?)",
+        )
+        .bind(peer)
+        .bind(message_id)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.writer)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT message_id FROM feed_pending WHERE peer = ? ORDER BY queued_at, rowid LIMIT ?",
+        )
+        .bind(peer)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM feed_pending WHERE peer = ? AND message_id = ?")
+            .bind(peer)
+            .bind(message_id)
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM feed_pending WHERE rowid IN (SELECT rowid FROM feed_pending \
+             WHERE peer = ? ORDER BY queued_at DESC, rowid DESC LIMIT -1 OFFSET ?)",
+        )
+        .bind(peer)
+        .bind(keep as i64)
+        .execute(&self.writer)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM feed_pending WHERE peer = ?")
+            .bind(peer)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        let high: Option<i64> = sqlx::query_scalar(
+            "SELECT high FROM subscription_marks WHERE server = ? AND group_name = ?",
+        )
+        .bind(server)
+        .bind(group)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(high.map(|n| n as u64))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO subscription_marks (server, group_name, high) VALUES (?, ?, ?) \
+             ON CONFLICT(server, group_name) DO UPDATE SET high = excluded.high",
+        )
+        .bind(server)
+        .bind(group)
+        .bind(number as i64)
+        .execute(&self.writer)
         .await?;
         Ok(())
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        let times: Vec<i64> = sqlx::query_scalar(
+            "SELECT seen_at FROM recent_bodies WHERE hash = ? AND seen_at >= ? ORDER BY seen_at",
+        )
+        .bind(hash)
+        .bind(since.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(times
+            .into_iter()
+            .filter_map(|t| chrono::DateTime::from_timestamp(t, 0))
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("INSERT INTO recent_bodies (hash, seen_at) VALUES (?, ?)")
+            .bind(hash)
+            .bind(at.timestamp())
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM recent_bodies WHERE seen_at < ?")
+            .bind(before.timestamp())
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM messages WHERE message_id = ? LIMIT 1")
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
     #[tracing::instrument(skip_all)]
     async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
         if let Some(row) = sqlx::query("SELECT size FROM messages WHERE message_id = ?")
@@ -564,38 +1061,125 @@ impl Storage for SqliteStorage {
         }
     }
 
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        let Some(headers_str) =
+            sqlx::query_scalar::<_, String>("SELECT headers FROM messages WHERE message_id = ?")
+                .bind(message_id)
+                .fetch_optional(&self.writer)
+                .await?
+        else {
+            return Ok(None);
+        };
+        let numbers = self.article_numbers(&self.writer, message_id).await?;
+        let Headers(mut headers) = serde_json::from_str(&headers_str)?;
+        let (xref, changed) = apply_xref(&mut headers, host, numbers);
+        if changed {
+            sqlx::query("UPDATE messages SET headers = ? WHERE message_id = ?")
+                .bind(serde_json::to_string(&Headers(headers))?)
+                .bind(message_id)
+                .execute(&self.writer)
+                .await?;
+        }
+        Ok(xref)
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        self.article_numbers(&self.pool, message_id).await
+    }
+
     async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM overview WHERE (group_name, article_number) IN (SELECT group_name, number FROM group_articles WHERE message_id = ?)",
+        )
+        .bind(message_id)
+        .execute(&self.writer)
+        .await?;
         sqlx::query("DELETE FROM group_articles WHERE message_id = ?")
             .bind(message_id)
-            .execute(&self.pool)
+            .execute(&self.writer)
             .await?;
         sqlx::query(
             "DELETE FROM messages WHERE message_id = ? AND NOT EXISTS (SELECT 1 FROM group_articles WHERE message_id = ?)",
         )
         .bind(message_id)
         .bind(message_id)
-        .execute(&self.pool)
+        .execute(&self.writer)
         .await?;
+        sqlx::query(RECORD_HISTORY)
+            .bind(message_id)
+            .bind(HistoryStatus::Cancelled.as_str())
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&self.writer)
+            .await?;
         Ok(())
     }
 
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        super::migrations::sqlite::SqliteStorageMigrator::new(self.pool.clone())
+            .get_current_version()
+            .await
+    }
+
     #[tracing::instrument(skip_all)]
-    async fn get_overview_range(&self, group: &str, start: u64, end: u64) -> Result<Vec<String>> {
-        let rows = sqlx::query(
-            "SELECT overview_data FROM overview WHERE group_name = ? AND article_number >= ? AND article_number <= ? ORDER BY article_number",
-        )
-        .bind(group)
-        .bind(i64::try_from(start).unwrap_or(0))
-        .bind(i64::try_from(end).unwrap_or(i64::MAX))
-        .fetch_all(&self.pool)
-        .await?;
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT overview_data FROM overview WHERE group_name = ? AND article_number >= ? AND article_number <= ? ORDER BY article_number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
 
-        let mut overview_lines = Vec::new();
-        for row in rows {
-            let overview_data: String = row.try_get("overview_data")?;
-            overview_lines.push(overview_data);
-        }
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(r) => match r.try_get::<String, _>("overview_data") {
+                        Ok(data) => yield OverviewLine::parse(&data),
+                        Err(e) => yield Err(anyhow::Error::from(e)),
+                    },
+                    Err(e) => yield Err(anyhow::Error::from(e)),
+                }
+            }
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        let pool = self.pool.clone();
+        let group = group.to_string();
+        let header = header.to_string();
+        Box::pin(stream! {
+            let mut rows = sqlx::query("SELECT g.number, m.headers, m.size, o.overview_data FROM group_articles g JOIN messages m ON m.message_id = g.message_id LEFT JOIN overview o ON o.group_name = g.group_name AND o.article_number = g.number WHERE g.group_name = ? AND g.number >= ? AND g.number <= ? ORDER BY g.number")
+                .bind(&group)
+                .bind(i64::try_from(start).unwrap_or(i64::MAX))
+                .bind(i64::try_from(end).unwrap_or(i64::MAX))
+                .fetch(&pool);
 
-        Ok(overview_lines)
+            while let Some(row) = rows.next().await {
+                yield row.map_err(anyhow::Error::from).and_then(|r| {
+                    let number: i64 = r.try_get("number")?;
+                    let headers: String = r.try_get("headers")?;
+                    let size: i64 = r.try_get("size")?;
+                    let overview: Option<String> = r.try_get("overview_data")?;
+                    let value = header_field_from_row(
+                        &header,
+                        &headers,
+                        u64::try_from(size).unwrap_or(0),
+                        overview.as_deref(),
+                    )?;
+                    Ok((u64::try_from(number).unwrap_or(0), value))
+                });
+            }
+        })
     }
 }
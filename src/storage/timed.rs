@@ -0,0 +1,340 @@
+//! Storage wrapper that records how long each operation takes.
+//!
+//! Every awaited call is timed and reported to [`crate::metrics`] under the
+//! name of the trait method. Streams are handed through untimed since their
+//! cost is spread over however long the caller keeps reading.
+
+use super::{
+    ArticleStream, Direction, DynStorage, HeaderStream, HistoryStatus, Message, OverviewStream,
+    Storage, StringPairStream, StringStream, StringTimestampStream, U64Stream,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::future::Future;
+use std::time::Instant;
+
+/// Delegates every call to `inner`, timing the ones that return a result.
+pub struct TimedStorage {
+    inner: DynStorage,
+}
+
+impl TimedStorage {
+    pub fn new(inner: DynStorage) -> Self {
+        Self { inner }
+    }
+}
+
+async fn timed<T>(op: &'static str, call: impl Future<Output = Result<T>>) -> Result<T> {
+    let started = Instant::now();
+    let result = call.await;
+    crate::metrics::global().storage_operation(op, started.elapsed());
+    result
+}
+
+#[async_trait]
+impl Storage for TimedStorage {
+    async fn store_article(&self, article: &Message) -> Result<()> {
+        timed("store_article", self.inner.store_article(article)).await
+    }
+
+    async fn store_articles(&self, articles: &[Message]) -> Result<()> {
+        timed("store_articles", self.inner.store_articles(articles)).await
+    }
+
+    async fn get_article_by_number(&self, group: &str, number: u64) -> Result<Option<Message>> {
+        timed(
+            "get_article_by_number",
+            self.inner.get_article_by_number(group, number),
+        )
+        .await
+    }
+
+    async fn get_article_by_id(&self, message_id: &str) -> Result<Option<Message>> {
+        timed(
+            "get_article_by_id",
+            self.inner.get_article_by_id(message_id),
+        )
+        .await
+    }
+
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        timed("article_exists", self.inner.article_exists(message_id)).await
+    }
+
+    fn get_articles_by_ids<'a>(&'a self, message_ids: &'a [String]) -> ArticleStream<'a> {
+        self.inner.get_articles_by_ids(message_ids)
+    }
+
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        self.inner.get_overview_range(group, start, end)
+    }
+
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        self.inner.get_header_for_range(group, header, start, end)
+    }
+
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
+        timed(
+            "add_group_with_creator",
+            self.inner
+                .add_group_with_creator(group, moderated, created_by),
+        )
+        .await
+    }
+
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        timed("get_group_creator", self.inner.get_group_creator(group)).await
+    }
+
+    async fn set_group_moderated(&self, group: &str, moderated: bool) -> Result<()> {
+        timed(
+            "set_group_moderated",
+            self.inner.set_group_moderated(group, moderated),
+        )
+        .await
+    }
+
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        timed(
+            "set_group_description",
+            self.inner.set_group_description(group, description),
+        )
+        .await
+    }
+
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        timed(
+            "get_group_description",
+            self.inner.get_group_description(group),
+        )
+        .await
+    }
+
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        timed(
+            "set_group_moderator",
+            self.inner.set_group_moderator(group, address),
+        )
+        .await
+    }
+
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        timed("get_group_moderator", self.inner.get_group_moderator(group)).await
+    }
+
+    async fn remove_group(&self, group: &str) -> Result<()> {
+        timed("remove_group", self.inner.remove_group(group)).await
+    }
+
+    async fn remove_groups_by_pattern(&self, pattern: &str) -> Result<()> {
+        timed(
+            "remove_groups_by_pattern",
+            self.inner.remove_groups_by_pattern(pattern),
+        )
+        .await
+    }
+
+    fn list_groups(&self) -> StringStream<'_> {
+        self.inner.list_groups()
+    }
+
+    fn list_groups_since(&self, since: chrono::DateTime<chrono::Utc>) -> StringStream<'_> {
+        self.inner.list_groups_since(since)
+    }
+
+    fn list_groups_with_times(&self) -> StringTimestampStream<'_> {
+        self.inner.list_groups_with_times()
+    }
+
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        self.inner.list_group_descriptions(pattern)
+    }
+
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        timed("group_counts", self.inner.group_counts(group)).await
+    }
+
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        timed(
+            "next_article_number",
+            self.inner.next_article_number(group, current, direction),
+        )
+        .await
+    }
+
+    fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
+        self.inner.list_article_numbers(group)
+    }
+
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        self.inner.list_article_numbers_in_range(group, start, end)
+    }
+
+    fn list_article_ids(&self, group: &str) -> StringStream<'_> {
+        self.inner.list_article_ids(group)
+    }
+
+    fn list_article_ids_since(
+        &self,
+        group: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> StringStream<'_> {
+        self.inner.list_article_ids_since(group, since)
+    }
+
+    async fn purge_group_before(
+        &self,
+        group: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        timed(
+            "purge_group_before",
+            self.inner.purge_group_before(group, before),
+        )
+        .await
+    }
+
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        timed(
+            "purge_group_keep_latest",
+            self.inner.purge_group_keep_latest(group, keep),
+        )
+        .await
+    }
+
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        timed("purge_expired", self.inner.purge_expired(now)).await
+    }
+
+    async fn purge_orphan_messages(&self) -> Result<()> {
+        timed("purge_orphan_messages", self.inner.purge_orphan_messages()).await
+    }
+
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        timed("history_status", self.inner.history_status(message_id)).await
+    }
+
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        timed(
+            "purge_history_before",
+            self.inner.purge_history_before(before),
+        )
+        .await
+    }
+
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        timed("enqueue_feed", self.inner.enqueue_feed(peer, message_id)).await
+    }
+
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        timed("peek_feed", self.inner.peek_feed(peer, limit)).await
+    }
+
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        timed("dequeue_feed", self.inner.dequeue_feed(peer, message_id)).await
+    }
+
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        timed("trim_feed", self.inner.trim_feed(peer, keep)).await
+    }
+
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        timed("feed_len", self.inner.feed_len(peer)).await
+    }
+
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        timed(
+            "subscription_mark",
+            self.inner.subscription_mark(server, group),
+        )
+        .await
+    }
+
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        timed(
+            "set_subscription_mark",
+            self.inner.set_subscription_mark(server, group, number),
+        )
+        .await
+    }
+
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        timed(
+            "recent_body_times",
+            self.inner.recent_body_times(hash, since),
+        )
+        .await
+    }
+
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        timed("record_body", self.inner.record_body(hash, at)).await
+    }
+
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        timed(
+            "purge_bodies_before",
+            self.inner.purge_bodies_before(before),
+        )
+        .await
+    }
+
+    async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
+        timed("get_message_size", self.inner.get_message_size(message_id)).await
+    }
+
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        timed("record_xref", self.inner.record_xref(message_id, host)).await
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        timed(
+            "get_article_numbers_by_id",
+            self.inner.get_article_numbers_by_id(message_id),
+        )
+        .await
+    }
+
+    async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        timed(
+            "delete_article_by_id",
+            self.inner.delete_article_by_id(message_id),
+        )
+        .await
+    }
+
+    async fn is_group_moderated(&self, group: &str) -> Result<bool> {
+        timed("is_group_moderated", self.inner.is_group_moderated(group)).await
+    }
+
+    async fn group_exists(&self, group: &str) -> Result<bool> {
+        timed("group_exists", self.inner.group_exists(group)).await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        timed("schema_version", self.inner.schema_version()).await
+    }
+}
@@ -0,0 +1,238 @@
+//! Pull ("suck") feeds from upstream servers.
+//!
+//! Each `[[subscriptions]]` entry names an upstream server and the local
+//! groups to fetch from it. A fetch asks OVER for every matching group from
+//! the last article number seen there, skips Message-IDs already in the
+//! history and hands the rest to the article queue, so they are filtered and
+//! stored like any other incoming article. Per group, the highest number up
+//! to which every article was stored or refused by the filters is kept in
+//! storage and the next fetch resumes after it.
+
+use anyhow::{Result, anyhow};
+use futures_util::TryStreamExt;
+use tokio::task::JoinHandle;
+
+use crate::client::NntpClient;
+use crate::config::SubscriptionRule;
+use crate::control::is_control_message;
+use crate::parse_message;
+use crate::peers::PeerConnectionInfo;
+use crate::queue::{ArticleQueue, Outcome, QueuedArticle};
+use crate::storage::DynStorage;
+
+fn connection_info(sub: &SubscriptionRule) -> PeerConnectionInfo {
+    let mut info = PeerConnectionInfo::from_parts(
+        &sub.host,
+        sub.port,
+        sub.tls,
+        sub.username.as_deref(),
+        sub.password.as_deref(),
+    );
+    info.starttls = sub.starttls && !sub.tls;
+    info
+}
+
+/// Fetch everything new in every wanted group from `sub` once, returning
+/// how many articles were handed to the queue.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be reached or answers unexpectedly,
+/// or if storage fails. Groups finished before the error keep their progress.
+pub async fn sync_once(
+    sub: &SubscriptionRule,
+    storage: &DynStorage,
+    queue: &ArticleQueue,
+) -> Result<u64> {
+    let server = sub.server();
+    let groups: Vec<String> = storage
+        .list_groups()
+        .try_filter(|g| std::future::ready(sub.wants(g)))
+        .try_collect()
+        .await?;
+    if groups.is_empty() {
+        return Ok(0);
+    }
+
+    let mut connection = NntpClient::open(&connection_info(sub)).await?;
+    // Transit-only servers refuse MODE READER; GROUP then fails below
+    connection.command("MODE READER\r\n").await?;
+
+    let mut fetched = 0;
+    for group in &groups {
+        fetched += sync_group(&mut connection, &server, group, storage, queue).await?;
+    }
+
+    if let Err(e) = connection.close().await {
+        tracing::debug!("Failed to close connection to {}: {}", server, e);
+    }
+    Ok(fetched)
+}
+
+/// Article numbers asked for in one OVER, so a first fetch of a group with a
+/// long history is taken in bounded pieces
+const OVER_PAGE: u64 = 1000;
+
+async fn sync_group(
+    connection: &mut NntpClient,
+    server: &str,
+    group: &str,
+    storage: &DynStorage,
+    queue: &ArticleQueue,
+) -> Result<u64> {
+    let Some(status) = connection.group(group).await? else {
+        tracing::debug!("{} does not carry {}", server, group);
+        return Ok(0);
+    };
+    let high = status.high;
+    let mut start = match storage.subscription_mark(server, group).await? {
+        Some(mark) => (mark + 1).max(status.low),
+        None => status.low,
+    };
+
+    let mut fetched = 0;
+    while start <= high {
+        let end = high.min(start.saturating_add(OVER_PAGE - 1));
+        let page = sync_page(connection, server, start, end, queue, storage).await?;
+        fetched += page.stored;
+        // The mark never runs ahead of storage: it stops before the first
+        // article that failed, so the next fetch tries it again
+        if let Some(settled) = page.settled {
+            storage
+                .set_subscription_mark(server, group, settled)
+                .await?;
+        }
+        if page.settled != Some(end) {
+            break;
+        }
+        start = end + 1;
+    }
+
+    if fetched > 0 {
+        tracing::info!("Fetched {} articles in {} from {}", fetched, group, server);
+    }
+    Ok(fetched)
+}
+
+/// What came of fetching one page of a group.
+struct Page {
+    /// Articles stored
+    stored: u64,
+    /// The highest number up to which every article was stored or
+    /// deliberately passed over, if any
+    settled: Option<u64>,
+}
+
+/// Fetch the articles numbered `start..=end` and hand them to the queue
+/// together, then wait for the workers' verdicts.
+async fn sync_page(
+    connection: &mut NntpClient,
+    server: &str,
+    start: u64,
+    end: u64,
+    queue: &ArticleQueue,
+    storage: &DynStorage,
+) -> Result<Page> {
+    // Numbers inside the range may have been cancelled or expired, so
+    // only the ones OVER reports are fetched
+    let mut pending = Vec::new();
+    for line in connection.over(start, end).await? {
+        if line.message_id.is_empty() || storage.message_seen(&line.message_id).await? {
+            continue;
+        }
+        let Some(text) = connection.article_text(&line.number.to_string()).await? else {
+            continue;
+        };
+        let Ok((_, message)) = parse_message(&text) else {
+            tracing::warn!(
+                "Skipping unparsable article {} from {}",
+                line.message_id,
+                server
+            );
+            continue;
+        };
+
+        let (ack, done) = flume::bounded(1);
+        let is_control = is_control_message(&message);
+        queue
+            .submit(QueuedArticle {
+                message,
+                size: text.len() as u64,
+                is_control,
+                already_validated: false,
+                ack: Some(ack),
+            })
+            .await?;
+        pending.push((line.number, done));
+    }
+
+    let mut page = Page {
+        stored: 0,
+        settled: Some(end),
+    };
+    for (number, done) in pending {
+        match done.recv_async().await {
+            Ok(Outcome::Stored) => page.stored += 1,
+            Ok(Outcome::Refused) => {}
+            Ok(Outcome::Failed) | Err(_) => {
+                if page.settled == Some(end) {
+                    page.settled = number.checked_sub(1).filter(|n| *n >= start);
+                }
+            }
+        }
+    }
+    Ok(page)
+}
+
+/// Fetch once from every subscription in turn.
+///
+/// # Errors
+///
+/// Returns an error naming how many subscriptions failed; each failure is
+/// logged and does not stop the others.
+pub async fn sync_all(
+    subs: &[SubscriptionRule],
+    storage: &DynStorage,
+    queue: &ArticleQueue,
+) -> Result<u64> {
+    let mut fetched = 0;
+    let mut failed = 0;
+    for sub in subs {
+        match sync_once(sub, storage, queue).await {
+            Ok(n) => fetched += n,
+            Err(e) => {
+                tracing::warn!("Fetching from {} failed: {}", sub.server(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} subscriptions failed", subs.len()));
+    }
+    Ok(fetched)
+}
+
+/// Spawn one task per subscription that fetches every poll interval.
+#[must_use]
+pub fn start(
+    subs: &[SubscriptionRule],
+    storage: &DynStorage,
+    queue: &ArticleQueue,
+) -> Vec<JoinHandle<()>> {
+    subs.iter()
+        .map(|sub| {
+            let sub = sub.clone();
+            let storage = storage.clone();
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                tracing::info!("Starting subscription to {}", sub.server());
+                loop {
+                    if let Err(e) = sync_once(&sub, &storage, &queue).await {
+                        tracing::warn!("Fetching from {} failed: {}", sub.server(), e);
+                    }
+                    tokio::time::sleep(sub.poll_interval_or_default()).await;
+                }
+            })
+        })
+        .collect()
+}
@@ -1,4 +1,4 @@
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 
 #[must_use]
 pub fn wildmat(pattern: &str, text: &str) -> bool {
@@ -9,7 +9,35 @@ pub fn wildmat(pattern: &str, text: &str) -> bool {
     }
 }
 
+/// Match `text` against an RFC 3977 wildmat list: comma-separated patterns,
+/// each optionally negated with `!`. The last pattern that matches decides.
+#[must_use]
+pub fn wildmat_list(list: &str, text: &str) -> bool {
+    let mut matched = false;
+    for pattern in list.split(',') {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildmat(negated, text) => matched = false,
+            Some(_) => {}
+            None if wildmat(pattern, text) => matched = true,
+            None => {}
+        }
+    }
+    matched
+}
+
+/// Compile `pattern` into a regex matching the same strings, ignoring case
+/// when `ignore_case` is set.
+pub fn wildmat_regex(pattern: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(&pattern_source(pattern))
+        .case_insensitive(ignore_case)
+        .build()
+}
+
 fn pattern_to_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&pattern_source(pattern))
+}
+
+fn pattern_source(pattern: &str) -> String {
     let mut regex = String::from("^");
     let mut chars = pattern.chars().peekable();
     while let Some(c) = chars.next() {
@@ -36,7 +64,7 @@ fn pattern_to_regex(pattern: &str) -> Result<Regex, regex::Error> {
         }
     }
     regex.push('$');
-    Regex::new(&regex)
+    regex
 }
 
 fn parse_class<I>(chars: &mut std::iter::Peekable<I>) -> Option<String>
@@ -56,11 +84,11 @@ where
     }
 
     let mut class = String::new();
-    if let Some(&first) = chars.peek() {
-        if first == '!' || first == '^' {
-            class.push('^');
-            chars.next();
-        }
+    if let Some(&first) = chars.peek()
+        && (first == '!' || first == '^')
+    {
+        class.push('^');
+        chars.next();
     }
     while let Some(ch) = chars.next() {
         if ch == ']' {
@@ -81,7 +109,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::wildmat;
+    use super::{wildmat, wildmat_list};
 
     #[test]
     fn test_simple() {
@@ -98,6 +126,17 @@ mod tests {
         assert!(wildmat("b[a-z]r", "bor"));
     }
 
+    #[test]
+    fn test_list_with_negation() {
+        assert!(wildmat_list("comp.*,!comp.lang.*", "comp.os.linux"));
+        assert!(!wildmat_list("comp.*,!comp.lang.*", "comp.lang.rust"));
+        assert!(wildmat_list(
+            "comp.*,!comp.lang.*,comp.lang.rust",
+            "comp.lang.rust"
+        ));
+        assert!(!wildmat_list("!misc.*", "misc.test"));
+    }
+
     #[test]
     fn test_escape() {
         assert!(wildmat("a\\*b", "a*b"));
@@ -21,22 +21,21 @@ fn listen_addr(raw: &str) -> String {
 }
 
 fn port_from_addr(addr: &str, default_port: u16) -> u16 {
-    if let Some(stripped) = addr.strip_prefix('[') {
-        if let Some(end) = stripped.find(']') {
-            if let Some(p) = stripped[end + 1..].strip_prefix(':') {
-                if let Ok(port) = p.parse() {
-                    return port;
-                }
-            }
-            return default_port;
+    if let Some(stripped) = addr.strip_prefix('[')
+        && let Some(end) = stripped.find(']')
+    {
+        if let Some(p) = stripped[end + 1..].strip_prefix(':')
+            && let Ok(port) = p.parse()
+        {
+            return port;
         }
+        return default_port;
     }
-    if let Some(idx) = addr.rfind(':') {
-        if addr[idx + 1..].chars().all(|c| c.is_ascii_digit()) {
-            if let Ok(port) = addr[idx + 1..].parse() {
-                return port;
-            }
-        }
+    if let Some(idx) = addr.rfind(':')
+        && addr[idx + 1..].chars().all(|c| c.is_ascii_digit())
+        && let Ok(port) = addr[idx + 1..].parse()
+    {
+        return port;
     }
     default_port
 }
@@ -51,7 +50,7 @@ pub async fn run_ws_bridge(cfg: Arc<RwLock<Config>>) -> Result<()> {
     let addr = listen_addr(&ws_addr_raw);
     info!("listening WebSocket on {addr}");
     let listener = TcpListener::bind(&addr).await.map_err(|e| {
-        format!(
+        anyhow::anyhow!(
             "Failed to bind to WebSocket address '{}': {}
 
 This error typically occurs when:
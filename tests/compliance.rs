@@ -10,6 +10,7 @@ fn help_lines() -> Vec<String> {
     vec![
         "100 help text follows".into(),
         "CAPABILITIES".into(),
+        "COMPRESS".into(),
         "MODE READER".into(),
         "MODE STREAM".into(),
         "GROUP".into(),
@@ -60,7 +61,7 @@ async fn head_and_list_commands() {
         )
         .expect_multi(
             "LIST",
-            vec!["215 list of newsgroups follows", "misc 1 1 y", "."],
+            vec!["215 list of newsgroups follows", "misc 1 1 n", "."],
         )
         .expect("QUIT", "205 closing connection")
         .run(storage, auth)
@@ -85,7 +86,7 @@ async fn listgroup_and_navigation_commands() {
         .expect("GROUP misc", "211 2 1 2 misc")
         .expect_multi(
             "LISTGROUP",
-            vec!["211 article numbers follow", "1", "2", "."],
+            vec!["211 2 1 2 misc list follows", "1", "2", "."],
         )
         .expect_multi(
             "HEAD 1",
@@ -100,7 +101,7 @@ async fn listgroup_and_navigation_commands() {
         .expect("LAST", "223 1 <1@test> article exists")
         .expect_multi(
             "NEWGROUPS 19700101 000000",
-            vec!["231 list of new newsgroups follows", "misc", "."],
+            vec!["231 list of new newsgroups follows", "misc 2 1 n", "."],
         )
         .expect_multi(
             &format!("NEWGROUPS {date} {time}"),
@@ -132,7 +133,7 @@ async fn capabilities_and_misc_commands() {
         .expect_multi("HELP", help_lines())
         .expect_multi(
             "LIST NEWSGROUPS",
-            vec!["215 descriptions follow", "misc ", "."],
+            vec!["215 descriptions follow", "misc\t", "."],
         )
         .expect("QUIT", "205 closing connection")
         .run(storage, auth)
@@ -201,7 +202,7 @@ async fn post_and_dot_stuffing() {
 
     ClientMock::new()
         .expect("MODE READER", "201 Posting prohibited")
-        .expect("GROUP misc", "211 0 0 0 misc")
+        .expect("GROUP misc", "211 0 1 0 misc")
         .expect("POST", "483 Secure connection required")
         .expect("QUIT", "205 closing connection")
         .run(storage.clone(), auth)
@@ -245,12 +246,36 @@ async fn newgroups_accepts_gmt_argument() {
         .expect("MODE READER", "201 Posting prohibited")
         .expect_multi(
             "NEWGROUPS 19700101 000000 GMT",
-            vec!["231 list of new newsgroups follows", "misc", "."],
+            vec!["231 list of new newsgroups follows", "misc 0 1 n", "."],
         )
         .run(storage, auth)
         .await;
 }
 
+#[tokio::test]
+async fn newgroups_lists_active_lines_and_ignores_future_dates() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    storage.add_group("mod.test", true).await.unwrap();
+
+    ClientMock::new()
+        .expect_multi(
+            "NEWGROUPS 700101 000000 GMT",
+            vec![
+                "231 list of new newsgroups follows",
+                "misc 0 1 y",
+                "mod.test 0 1 m",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "NEWGROUPS 99991231 235959 GMT",
+            vec!["231 list of new newsgroups follows", "."],
+        )
+        .run_with_cfg(utils::create_insecure_posting_config(), storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn newnews_accepts_gmt_argument() {
     let (storage, auth) = utils::setup().await;
@@ -419,7 +444,240 @@ fn parse_example_article() {
         msg.headers[3],
         ("Organization".into(), "An Example Net".into())
     );
-    assert_eq!(msg.body, "This is just a test article.");
+    assert_eq!(msg.body, b"This is just a test article.");
+}
+
+/// Capability lines without the given entries.
+fn capabilities_without(removed: &[&str]) -> Vec<String> {
+    utils::capabilities_lines()
+        .into_iter()
+        .filter(|l| !removed.contains(&l.as_str()))
+        .collect()
+}
+
+/// Capability lines with `extra` inserted after the line `after`.
+fn capabilities_with(after: &str, extra: &str, mut lines: Vec<String>) -> Vec<String> {
+    let idx = lines.iter().position(|l| l == after).unwrap();
+    lines.insert(idx + 1, extra.to_string());
+    lines
+}
+
+#[tokio::test]
+async fn capabilities_change_after_auth_and_mode_reader() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", utils::capabilities_lines())
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect_multi(
+            "CAPABILITIES",
+            capabilities_without(&["AUTHINFO USER SASL", "SASL SCRAM-SHA-256"]),
+        )
+        .expect("MODE READER", "201 Posting prohibited")
+        .expect_multi(
+            "CAPABILITIES",
+            capabilities_without(&[
+                "AUTHINFO USER SASL",
+                "SASL SCRAM-SHA-256",
+                "MODE-READER",
+                "IHAVE",
+                "STREAMING",
+            ]),
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn capabilities_over_tls_advertise_post_and_plain() {
+    let (storage, auth) = utils::setup().await;
+    let tls_lines = capabilities_with(
+        "MODE-READER",
+        "POST",
+        capabilities_without(&["SASL SCRAM-SHA-256"]),
+    );
+    let tls_lines = capabilities_with("AUTHINFO USER SASL", "SASL PLAIN SCRAM-SHA-256", tls_lines);
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", tls_lines)
+        .run_tls(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn capabilities_advertise_post_when_insecure_posting_allowed() {
+    let (storage, auth) = utils::setup().await;
+    ClientMock::new()
+        .expect_multi(
+            "CAPABILITIES",
+            capabilities_with("MODE-READER", "POST", utils::capabilities_lines()),
+        )
+        .run_with_cfg(utils::create_insecure_posting_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn capabilities_hide_post_from_users_who_may_not_post() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("reader", "pass").await.unwrap();
+    auth.add_user("poster", "pass").await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.access = toml::from_str::<renews::config::Config>(
+        "addr = \":119\"\n[[access]]\ngroups = \"*\"\npost = [\"poster\"]\n",
+    )
+    .unwrap()
+    .access;
+    let authenticated = capabilities_without(&["AUTHINFO USER SASL", "SASL SCRAM-SHA-256"]);
+
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", utils::capabilities_lines())
+        .expect("AUTHINFO USER reader", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect_multi("CAPABILITIES", authenticated.clone())
+        .run_with_cfg(cfg.clone(), storage.clone(), auth.clone())
+        .await;
+    ClientMock::new()
+        .expect("AUTHINFO USER poster", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect_multi(
+            "CAPABILITIES",
+            capabilities_with("MODE-READER", "POST", authenticated),
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn capabilities_list_distributions_once_configured() {
+    let (storage, auth) = utils::setup().await;
+    let mut cfg = utils::create_minimal_config();
+    cfg.distributions = toml::from_str("[[keywords]]\nname = \"local\"\n").unwrap();
+    let list = "LIST ACTIVE NEWSGROUPS ACTIVE.TIMES COUNTS OVERVIEW.FMT HEADERS";
+    let with_distributions = capabilities_with(
+        "HDR",
+        &format!("{list} DISTRIBUTIONS"),
+        capabilities_without(&[list]),
+    );
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", with_distributions)
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn next_moves_the_pointer_used_by_stat() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("empty", false).await.unwrap();
+    storage.add_group("misc", false).await.unwrap();
+    for i in 1..=3 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{i}@test>\r\nNewsgroups: misc\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+
+    ClientMock::new()
+        .expect("GROUP empty", "211 0 1 0 empty")
+        .expect("NEXT", "420 no current article selected")
+        .expect("LAST", "420 no current article selected")
+        .expect("GROUP misc", "211 3 1 3 misc")
+        .expect("NEXT", "223 2 <2@test> article exists")
+        .expect("STAT", "223 2 <2@test> article exists")
+        .expect("NEXT", "223 3 <3@test> article exists")
+        .expect("LAST", "223 2 <2@test> article exists")
+        .expect("STAT", "223 2 <2@test> article exists")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn last_and_next_skip_cancelled_articles() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    for i in 1..=5 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{i}@test>\r\nNewsgroups: misc\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<2@test>").await.unwrap();
+    storage.delete_article_by_id("<4@test>").await.unwrap();
+
+    ClientMock::new()
+        .expect("NEXT", "412 no newsgroup selected")
+        .expect("LAST", "412 no newsgroup selected")
+        .expect("GROUP misc", "211 3 1 5 misc")
+        .expect("LAST", "422 no previous article")
+        .expect("NEXT", "223 3 <3@test> article exists")
+        .expect("NEXT", "223 5 <5@test> article exists")
+        .expect("NEXT", "421 no next article")
+        .expect("LAST", "223 3 <3@test> article exists")
+        .expect("LAST", "223 1 <1@test> article exists")
+        .expect("LAST", "422 no previous article")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn last_and_next_need_a_current_article() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("empty", false).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP empty", "211 0 1 0 empty")
+        .expect("NEXT", "420 no current article selected")
+        .expect("LAST", "420 no current article selected")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn retrieval_without_argument_uses_current_article() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for i in 1..=5 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{i}@test>\r\nNewsgroups: misc.test\r\n\r\nBody {i}"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<1@test>").await.unwrap();
+
+    ClientMock::new()
+        .expect("ARTICLE", "412 no newsgroup selected")
+        .expect("GROUP misc.test", "211 4 2 5 misc.test")
+        .expect_multi(
+            "ARTICLE",
+            vec![
+                "220 2 <2@test> article follows",
+                "Message-ID: <2@test>",
+                "Newsgroups: misc.test",
+                "",
+                "Body 2",
+                ".",
+            ],
+        )
+        .expect("STAT 5", "223 5 <5@test> article exists")
+        .expect_multi(
+            "HEAD",
+            vec![
+                "221 5 <5@test> article headers follow",
+                "Message-ID: <5@test>",
+                "Newsgroups: misc.test",
+                ".",
+            ],
+        )
+        .expect("STAT <3@test>", "223 0 <3@test> article exists")
+        .expect("STAT 9", "423 no such article number in this group")
+        .expect_multi(
+            "BODY",
+            vec!["222 5 <5@test> article body follows", "Body 5", "."],
+        )
+        .expect("STAT", "223 5 <5@test> article exists")
+        .run(storage, auth)
+        .await;
 }
 
 #[tokio::test]
@@ -479,10 +737,60 @@ async fn list_distrib_pats_not_supported() {
     let (storage, auth) = utils::setup().await;
     ClientMock::new()
         .expect("LIST DISTRIB.PATS", "503 feature not supported")
+        .expect("LIST DISTRIBUTIONS", "503 feature not supported")
         .run(storage, auth)
         .await;
 }
 
+#[tokio::test]
+async fn list_distributions_and_distrib_pats_from_config() {
+    let (storage, auth) = utils::setup().await;
+    let mut cfg = utils::create_minimal_config();
+    cfg.distributions = toml::from_str(
+        r#"
+[[keywords]]
+name = "local"
+description = "Local to this site"
+
+[[keywords]]
+name = "world"
+description = "Everywhere"
+
+[[patterns]]
+weight = 10
+groups = "local.*"
+distribution = "local"
+
+[[patterns]]
+weight = 5
+groups = "*"
+distribution = "world"
+"#,
+    )
+    .unwrap();
+    ClientMock::new()
+        .expect_multi(
+            "LIST DISTRIBUTIONS",
+            vec![
+                "215 list of distributions follows",
+                "local\tLocal to this site",
+                "world\tEverywhere",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "LIST DISTRIB.PATS",
+            vec![
+                "215 default distributions follow",
+                "10:local.*:local",
+                "5:*:world",
+                ".",
+            ],
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn unknown_command_xencrypt() {
     let (storage, auth) = utils::setup().await;
@@ -516,7 +824,7 @@ async fn group_select_returns_211() {
     let (storage, auth) = utils::setup().await;
     storage.add_group("misc.test", false).await.unwrap();
     ClientMock::new()
-        .expect("GROUP misc.test", "211 0 0 0 misc.test")
+        .expect("GROUP misc.test", "211 0 1 0 misc.test")
         .run(storage, auth)
         .await;
 }
@@ -659,7 +967,7 @@ async fn head_no_current_article_selected() {
     let (storage, auth) = utils::setup().await;
     storage.add_group("misc.test", false).await.unwrap();
     ClientMock::new()
-        .expect("GROUP misc.test", "211 0 0 0 misc.test")
+        .expect("GROUP misc.test", "211 0 1 0 misc.test")
         .expect("HEAD", "420 no current article selected")
         .run(storage, auth)
         .await;
@@ -801,8 +1109,38 @@ async fn listgroup_returns_numbers() {
     ClientMock::new()
         .expect_multi(
             "LISTGROUP misc.test",
-            vec!["211 article numbers follow", "1", "."],
+            vec!["211 1 1 1 misc.test list follows", "1", "."],
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn listgroup_range_restricts_output_and_sets_pointer() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for i in 1..=4 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <lg{i}@test>\r\nNewsgroups: misc.test\r\nSubject: {i}\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect_multi(
+            "LISTGROUP misc.test 2-3",
+            vec!["211 4 1 4 misc.test list follows", "2", "3", "."],
+        )
+        .expect("STAT", "223 2 <lg2@test> article exists")
+        .expect_multi(
+            "LISTGROUP misc.test 3-",
+            vec!["211 4 1 4 misc.test list follows", "3", "4", "."],
+        )
+        .expect_multi(
+            "LISTGROUP",
+            vec!["211 4 1 4 misc.test list follows", "1", "2", "3", "4", "."],
         )
+        .expect("LISTGROUP misc.test x-y", "501 Syntax error")
         .run(storage, auth)
         .await;
 }
@@ -824,16 +1162,126 @@ async fn list_newsgroups_returns_groups() {
     ClientMock::new()
         .expect_multi(
             "LIST NEWSGROUPS",
-            vec!["215 descriptions follow", "alt.test ", "misc.test ", "."],
+            vec!["215 descriptions follow", "alt.test\t", "misc.test\t", "."],
         )
         .run(storage, auth)
         .await;
 }
 
 #[tokio::test]
-async fn list_all_keywords() {
+async fn list_newsgroups_with_descriptions_and_wildmat() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    storage.add_group("alt.test", false).await.unwrap();
+    storage
+        .set_group_description("misc.test", "Testing ground")
+        .await
+        .unwrap();
+    ClientMock::new()
+        .expect_multi(
+            "LIST NEWSGROUPS misc.*",
+            vec!["215 descriptions follow", "misc.test\tTesting ground", "."],
+        )
+        .expect_multi(
+            "LIST NEWSGROUPS *,!misc.*",
+            vec!["215 descriptions follow", "alt.test\t", "."],
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn list_active_filters_and_reports_watermarks() {
     let (storage, auth) = utils::setup().await;
+    storage.add_group("comp.lang.rust", false).await.unwrap();
+    storage.add_group("comp.moderated", true).await.unwrap();
     storage.add_group("misc.test", false).await.unwrap();
+    for i in 1..=3 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <w{i}@test>\r\nNewsgroups: comp.lang.rust\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<w1@test>").await.unwrap();
+    ClientMock::new()
+        .expect_multi(
+            "LIST ACTIVE comp.*",
+            vec![
+                "215 list of newsgroups follows",
+                "comp.lang.rust 3 2 y",
+                "comp.moderated 0 1 m",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "LIST ACTIVE *,!comp.*",
+            vec!["215 list of newsgroups follows", "misc.test 0 1 y", "."],
+        )
+        .expect_multi(
+            "LIST COUNTS comp.*",
+            vec![
+                "215 list of newsgroups follows",
+                "comp.lang.rust 3 2 2 y",
+                "comp.moderated 0 1 0 m",
+                ".",
+            ],
+        )
+        .expect("GROUP comp.lang.rust", "211 2 2 3 comp.lang.rust")
+        .run_with_cfg(utils::create_insecure_posting_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn list_active_times_filters_and_defaults_creator() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    storage
+        .add_group_with_creator("alt.test", false, Some("alice@example.com"))
+        .await
+        .unwrap();
+    storage.add_group("comp.lang.rust", false).await.unwrap();
+    let mut times = std::collections::HashMap::new();
+    let mut stream = storage.list_groups_with_times();
+    while let Some(result) = stream.next().await {
+        let (group, ts) = result.unwrap();
+        times.insert(group, ts);
+    }
+    drop(stream);
+    let mut cfg = utils::create_minimal_config();
+    cfg.admin_address = Some("news@example.com".into());
+    ClientMock::new()
+        .expect_multi(
+            "LIST ACTIVE.TIMES misc.*,alt.*",
+            vec![
+                "215 information follows".into(),
+                format!("alt.test {} alice@example.com", times["alt.test"]),
+                format!("misc.test {} news@example.com", times["misc.test"]),
+                ".".into(),
+            ],
+        )
+        .expect_multi(
+            "LIST ACTIVE.TIMES *,!misc.*,!alt.*",
+            vec![
+                "215 information follows".into(),
+                format!(
+                    "comp.lang.rust {} news@example.com",
+                    times["comp.lang.rust"]
+                ),
+                ".".into(),
+            ],
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn list_all_keywords() {
+    let (storage, auth) = utils::setup().await;
+    storage
+        .add_group_with_creator("misc.test", false, Some("admin@example.com"))
+        .await
+        .unwrap();
     let mut groups_with_times = Vec::new();
     let mut stream = storage.list_groups_with_times();
     while let Some(result) = stream.next().await {
@@ -847,13 +1295,13 @@ async fn list_all_keywords() {
     ClientMock::new()
         .expect_multi(
             "LIST ACTIVE",
-            vec!["215 list of newsgroups follows", "misc.test 0 0 y", "."],
+            vec!["215 list of newsgroups follows", "misc.test 0 1 n", "."],
         )
         .expect_multi(
             "LIST ACTIVE.TIMES",
             vec![
                 "215 information follows".into(),
-                format!("misc.test {} -", ts),
+                format!("misc.test {} admin@example.com", ts),
                 ".".into(),
             ],
         )
@@ -876,11 +1324,32 @@ async fn list_all_keywords() {
             vec![
                 "215 metadata items supported:",
                 ":",
+                ":bytes",
                 ":lines",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "LIST HEADERS MSGID",
+            vec![
+                "215 metadata items supported:",
+                ":",
+                ":bytes",
+                ":lines",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "LIST HEADERS RANGE",
+            vec![
+                "215 metadata items supported:",
+                ":",
                 ":bytes",
+                ":lines",
                 ".",
             ],
         )
+        .expect("LIST HEADERS BOGUS", "501 Syntax error")
         .run(storage.clone(), auth)
         .await;
 }
@@ -901,6 +1370,42 @@ async fn newnews_lists_recent_articles() {
         .await;
 }
 
+#[tokio::test]
+async fn newnews_wildmat_list_dedups_crossposts() {
+    let (storage, auth) = utils::setup().await;
+    for g in ["misc.a", "misc.b", "alt.c"] {
+        storage.add_group(g, false).await.unwrap();
+    }
+    for (id, groups) in [("x", "misc.a,misc.b"), ("y", "misc.b"), ("z", "alt.c")] {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{id}@test>\r\nNewsgroups: {groups}\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect_multi(
+            "NEWNEWS misc.* 700101 000000 GMT",
+            vec![
+                "230 list of new articles follows",
+                "<x@test>",
+                "<y@test>",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "NEWNEWS *,!misc.b 19700101 000000 GMT",
+            vec![
+                "230 list of new articles follows",
+                "<z@test>",
+                "<x@test>",
+                ".",
+            ],
+        )
+        .run(storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn newnews_no_matches_returns_empty() {
     let (storage, auth) = utils::setup().await;
@@ -961,6 +1466,63 @@ async fn hdr_subject_range() {
         .await;
 }
 
+#[tokio::test]
+async fn hdr_arbitrary_header_and_missing_values() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, m1) =
+        parse_message("Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nX-Tag: one\r\n\r\nBody")
+            .unwrap();
+    let (_, m2) =
+        parse_message("Message-ID: <2@test>\r\nNewsgroups: misc.test\r\nSubject: B\r\n\r\nBody")
+            .unwrap();
+    storage.store_article(&m1).await.unwrap();
+    storage.store_article(&m2).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 2 1 2 misc.test")
+        .expect_multi(
+            "HDR X-Tag 1-2",
+            vec!["225 Headers follow", "1 one", "2", "."],
+        )
+        .expect_multi(
+            "XHDR Subject 1-",
+            vec!["221 Header follows", "1", "2 B", "."],
+        )
+        .expect_multi(
+            "XHDR X-Tag <1@test>",
+            vec!["221 Header follows", "0 one", "."],
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn xhdr_references_and_metadata() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let refs = "<first-article-in-a-long-thread@example.com>\r\n <second-article-in-a-long-thread@example.com>";
+    let (_, m1) = parse_message(&format!(
+        "Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nReferences: {refs}\r\n\r\nOne\r\nTwo"
+    ))
+    .unwrap();
+    storage.store_article(&m1).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 1 1 1 misc.test")
+        .expect_multi(
+            "XHDR References 1-",
+            vec![
+                "221 Header follows",
+                "1 <first-article-in-a-long-thread@example.com> <second-article-in-a-long-thread@example.com>",
+                ".",
+            ],
+        )
+        .expect_multi("HDR :lines 1", vec!["225 Headers follow", "1 2", "."])
+        .expect("HDR Subject 5-9", "423 no articles in that range")
+        .expect("HDR Subject <missing@test>", "430 no such article")
+        .run(storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn hdr_all_headers_message_id() {
     let (storage, auth) = utils::setup().await;
@@ -1003,6 +1565,53 @@ async fn xpat_subject_message_id() {
         .await;
 }
 
+#[tokio::test]
+async fn xpat_patterns_are_alternatives() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for (id, subject) in [(1, "apple pie"), (2, "banana split"), (3, "cherry tart")] {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{id}@test>\r\nNewsgroups: misc.test\r\nSubject: {subject}\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 3 1 3 misc.test")
+        .expect_multi(
+            "XPAT Subject 1- apple* *tart",
+            vec!["221 Header follows", "1 apple pie", "3 cherry tart", "."],
+        )
+        .expect_multi("XPAT Subject 1-3 *kiwi*", vec!["221 Header follows", "."])
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn xpat_matches_headers_outside_the_overview() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for (id, org) in [(1, "Example Corp"), (2, "Acme Inc")] {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{id}@test>\r\nNewsgroups: misc.test\r\nSubject: s\r\nOrganization: {org}\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 2 1 2 misc.test")
+        .expect_multi(
+            "XPAT organization 1-2 Acme*",
+            vec!["221 Header follows", "2 Acme Inc", "."],
+        )
+        .expect_multi(
+            "XPAT Organization <1@test> *Corp",
+            vec!["221 Header follows", "0 Example Corp", "."],
+        )
+        .run(storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn xpat_subject_range() {
     let (storage, auth) = utils::setup().await;
@@ -1027,6 +1636,175 @@ async fn xpat_subject_range() {
         .await;
 }
 
+#[tokio::test]
+async fn xpat_empty_range_still_answers_221() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(
+        "Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nSubject: apple\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 1 1 1 misc.test")
+        .expect_multi("XPAT Subject 50-60 *", vec!["221 Header follows", "."])
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn xpat_garbage_range_is_a_syntax_error() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 0 1 0 misc.test")
+        .expect("XPAT Subject 1-x *", "501 Syntax error")
+        .expect("XPAT Subject abc *", "501 Syntax error")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn xpat_walks_ranges_wider_than_one_query() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for id in 1..=1002 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{id}@test>\r\nNewsgroups: misc.test\r\nSubject: s{id}\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 1002 1 1002 misc.test")
+        .expect_multi(
+            "XPAT Subject 1- s1 s1002",
+            vec!["221 Header follows", "1 s1", "1002 s1002", "."],
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn overview_fmt_matches_over_and_hdr() {
+    async fn multiline<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end_matches(['\r', '\n']).to_string();
+            if line == "." {
+                return lines;
+            }
+            lines.push(line);
+        }
+    }
+
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(concat!(
+        "Message-ID: <fmt@test>\r\n",
+        "Newsgroups: misc.test\r\n",
+        "Subject: Field order\r\n",
+        "From: a@test\r\n",
+        "Date: Mon, 1 Jan 2024 00:00:00 +0000\r\n",
+        "References: <parent@test>\r\n",
+        "\r\n",
+        "line one\r\nline two",
+    ))
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    let (addr, handle) = utils::setup_server(storage, auth).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut greeting = String::new();
+    reader.read_line(&mut greeting).await.unwrap();
+
+    writer.write_all(b"LIST OVERVIEW.FMT\r\n").await.unwrap();
+    let mut fields = multiline(&mut reader).await;
+    assert_eq!(
+        fields.remove(0),
+        "215 Order of fields in overview database."
+    );
+    writer
+        .write_all(b"GROUP misc.test\r\nOVER 1\r\n")
+        .await
+        .unwrap();
+    let mut group = String::new();
+    reader.read_line(&mut group).await.unwrap();
+    assert!(group.starts_with("211 "));
+    let over = multiline(&mut reader).await;
+    assert_eq!(over[0], "224 Overview information follows");
+    let columns: Vec<&str> = over[1].split('\t').collect();
+    assert_eq!(columns.len(), fields.len() + 1);
+    assert_eq!(columns[0], "1");
+
+    for (field, column) in fields.iter().zip(&columns[1..]) {
+        let name = field.strip_suffix(':').unwrap_or(field);
+        writer
+            .write_all(format!("HDR {name} 1\r\n").as_bytes())
+            .await
+            .unwrap();
+        let hdr = multiline(&mut reader).await;
+        assert_eq!(hdr[0], "225 Headers follow");
+        assert_eq!(hdr[1], format!("1 {column}"), "field {field}");
+    }
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn overview_fmt_lists_configured_extra_headers_and_over_appends_them() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for text in [
+        "Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nSubject: A\r\nFrom: a@test\r\nXref: host misc.test:1\r\nOrganization: Acme\r\n\r\nBody",
+        "Message-ID: <2@test>\r\nNewsgroups: misc.test\r\nSubject: B\r\nFrom: a@test\r\n\r\nBody",
+    ] {
+        let (_, msg) = parse_message(text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    let mut cfg = utils::create_minimal_config();
+    cfg.overview_extra_headers = vec!["Xref:full".into(), "Organization".into()];
+    ClientMock::new()
+        .expect_multi(
+            "LIST OVERVIEW.FMT",
+            vec![
+                "215 Order of fields in overview database.",
+                "Subject:",
+                "From:",
+                "Date:",
+                "Message-ID:",
+                "References:",
+                ":bytes",
+                ":lines",
+                "Xref:full",
+                "Organization:full",
+                ".",
+            ],
+        )
+        .expect("GROUP misc.test", "211 2 1 2 misc.test")
+        .expect_multi(
+            "OVER 1-2",
+            vec![
+                "224 Overview information follows",
+                "1\tA\ta@test\t\t<1@test>\t\t4\t1\tXref: host misc.test:1\tOrganization: Acme",
+                "2\tB\ta@test\t\t<2@test>\t\t4\t1\t\t",
+                ".",
+            ],
+        )
+        .expect_multi(
+            "OVER <1@test>",
+            vec![
+                "224 Overview information follows",
+                "0\tA\ta@test\t\t<1@test>\t\t4\t1\tXref: host misc.test:1\tOrganization: Acme",
+                ".",
+            ],
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn over_message_id() {
     let (storage, auth) = utils::setup().await;
@@ -1078,6 +1856,46 @@ async fn over_range() {
         .await;
 }
 
+#[tokio::test]
+async fn over_malformed_range_is_a_syntax_error() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(
+        "Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nSubject: A\r\nFrom: a@test\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 1 1 1 misc.test")
+        .expect("OVER abc", "501 Syntax error")
+        .expect("OVER 1-x", "501 Syntax error")
+        .expect("OVER 5-2", "423 no articles in that range")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn over_sanitizes_fields_and_blanks_missing_headers() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) =
+        parse_message("Message-ID: <1@test>\r\nNewsgroups: misc.test\r\nSubject: A\tB\r\n\r\nBody")
+            .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    ClientMock::new()
+        .expect("GROUP misc.test", "211 1 1 1 misc.test")
+        .expect_multi(
+            "XOVER 1-",
+            vec![
+                "224 Overview information follows",
+                "1\tA B\t\t\t<1@test>\t\t4\t1",
+                ".",
+            ],
+        )
+        .run(storage, auth)
+        .await;
+}
+
 #[tokio::test]
 async fn head_range() {
     let (storage, auth) = utils::setup().await;
@@ -1367,3 +2185,69 @@ async fn date_command_returns_valid_timestamp() {
 
     handle.abort();
 }
+
+#[tokio::test]
+async fn xbackend_reports_storage_backend_to_admins() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("admin", "pass").await.unwrap();
+    auth.add_admin_without_key("admin").await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let version = storage.schema_version().await.unwrap();
+    ClientMock::new()
+        .expect("XBACKEND", "480 authentication required")
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("XBACKEND", "502 permission denied")
+        .expect("AUTHINFO USER admin", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("XBACKEND", &format!("290 sqlite schema {version}"))
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn pipelined_streaming_stays_in_sync_after_rejections() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg: renews::config::Config = toml::from_str(
+        r#"
+addr = ":119"
+[[group_settings]]
+pattern = "*"
+max_article_bytes = 100
+"#,
+    )
+    .unwrap();
+
+    let big = format!(
+        "TAKETHIS <big@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: big\r\nMessage-ID: <big@test>\r\n\r\n{}\r\n.",
+        "x".repeat(200)
+    );
+    let small = "TAKETHIS <small@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: small\r\nMessage-ID: <small@test>\r\n\r\nok\r\n.";
+    let bare =
+        "TAKETHIS\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: bare\r\n\r\nbody\r\n.";
+
+    let mut pipeline = vec!["CHECK <small@test>".to_string()];
+    pipeline.extend(utils::request_lines(&big));
+    pipeline.extend(utils::request_lines(small));
+    pipeline.extend(utils::request_lines(bare));
+    pipeline.push("CHECK <small@test>".to_string());
+
+    ClientMock::new()
+        .expect("MODE STREAM", "203 Streaming permitted")
+        .expect_request_multi(
+            pipeline,
+            vec![
+                "238 <small@test>",
+                "439 <big@test>",
+                "239 <small@test>",
+                "501 message-id required",
+                "438 <small@test>",
+            ],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert!(!storage.article_exists("<big@test>").await.unwrap());
+    assert!(storage.article_exists("<small@test>").await.unwrap());
+}
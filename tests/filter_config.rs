@@ -10,7 +10,7 @@ async fn test_filter_pipeline_configuration() {
     // Test empty filter pipeline (should use default)
     let empty_config = vec![];
     let chain = create_filter_chain(&empty_config).unwrap();
-    assert_eq!(chain.filter_names().len(), 4); // Default chain has 4 filters
+    assert_eq!(chain.filter_names().len(), 10); // Default chain has 10 filters
 
     // Test custom filter pipeline
     let custom_config = vec![
@@ -224,3 +224,113 @@ name = "SizeFilter"
     assert_eq!(config1.filters[0].name, config2.filters[0].name);
     assert_eq!(config1.filters[1].name, config2.filters[1].name);
 }
+
+fn load(config_content: &str) -> anyhow::Result<Config> {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut temp_file, config_content.as_bytes()).unwrap();
+    Config::from_file(temp_file.path().to_str().unwrap())
+}
+
+#[tokio::test]
+async fn test_pipeline_section_lists_filters_by_short_name() {
+    let config = load(
+        r#"
+addr = ":119"
+site_name = "test.example.com"
+
+[filters]
+pipeline = ["size", "groups", "headers", "crosspost"]
+"#,
+    )
+    .unwrap();
+
+    let chain = create_filter_chain(&config.filters).unwrap();
+    assert_eq!(
+        chain.filter_names(),
+        vec![
+            "SizeFilter",
+            "GroupExistenceFilter",
+            "HeaderFilter",
+            "CrosspostFilter"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_pipeline_filter_is_rejected_at_load() {
+    let err = load(
+        r#"
+addr = ":119"
+site_name = "test.example.com"
+
+[filters]
+pipeline = ["size", "spam"]
+"#,
+    )
+    .err()
+    .expect("unknown filter accepted");
+    let message = err.to_string();
+    assert!(message.contains("Unknown filter: spam"), "{message}");
+    assert!(message.contains("GroupExistenceFilter"), "{message}");
+}
+
+#[tokio::test]
+async fn test_pipeline_without_size_filter_accepts_oversized_articles() {
+    use renews::auth::sqlite::SqliteAuth;
+    use renews::filters::Submitter;
+    use renews::handlers::utils::validate_submission;
+    use renews::queue::ArticleQueue;
+    use renews::storage::sqlite::SqliteStorage;
+    use std::sync::Arc;
+
+    let storage: renews::storage::DynStorage =
+        Arc::new(SqliteStorage::new(":memory:").await.unwrap());
+    let auth: renews::auth::DynAuth = Arc::new(SqliteAuth::new(":memory:").await.unwrap());
+    let queue = ArticleQueue::new(1);
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, article) = renews::parse_message(
+        "Message-ID: <big@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: big\r\n\r\nBody",
+    )
+    .unwrap();
+
+    let groups = r#"
+[[group_settings]]
+pattern = "*"
+max_article_bytes = 10
+"#;
+    let default = load(&format!(
+        "addr = \":119\"\nsite_name = \"test.example.com\"\n{groups}"
+    ))
+    .unwrap();
+    assert!(
+        validate_submission(
+            &storage,
+            &auth,
+            &default,
+            &article,
+            1000,
+            &Submitter::default(),
+            &queue
+        )
+        .await
+        .is_err()
+    );
+
+    let without_size = load(&format!(
+        "addr = \":119\"\nsite_name = \"test.example.com\"\n\n[filters]\npipeline = [\"headers\", \"groups\"]\n{groups}"
+    ))
+    .unwrap();
+    assert!(
+        validate_submission(
+            &storage,
+            &auth,
+            &without_size,
+            &article,
+            1000,
+            &Submitter::default(),
+            &queue
+        )
+        .await
+        .is_ok()
+    );
+}
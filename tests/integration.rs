@@ -1,25 +1,49 @@
+#[path = "integration/access.rs"]
+mod access;
 #[path = "integration/auth.rs"]
 mod auth;
 #[path = "integration/cancel_lock.rs"]
 mod cancel_lock;
+#[path = "integration/client.rs"]
+mod client;
+#[path = "integration/compress.rs"]
+mod compress;
+#[path = "integration/connections.rs"]
+mod connections;
 #[path = "integration/control.rs"]
 mod control;
+#[path = "integration/feeds.rs"]
+mod feeds;
 #[path = "integration/handler_failures.rs"]
 mod handler_failures;
 #[path = "integration/idle_timeout.rs"]
 mod idle_timeout;
 #[path = "integration/max_size.rs"]
 mod max_size;
+#[path = "integration/metrics.rs"]
+mod metrics;
 #[path = "integration/moderated.rs"]
 mod moderated;
+#[path = "integration/modes.rs"]
+mod modes;
+#[path = "integration/path_loop.rs"]
+mod path_loop;
 #[path = "integration/peers.rs"]
 mod peers;
+#[path = "integration/posting.rs"]
+mod posting;
+#[path = "integration/rate_limit.rs"]
+mod rate_limit;
 #[path = "integration/resource_exhaustion.rs"]
 mod resource_exhaustion;
 #[path = "integration/retention.rs"]
 mod retention;
+#[path = "integration/shutdown.rs"]
+mod shutdown;
 #[path = "integration/storage.rs"]
 mod storage;
+#[path = "integration/subscriptions.rs"]
+mod subscriptions;
 #[path = "integration/tls.rs"]
 mod tls;
 #[path = "utils.rs"]
@@ -27,3 +51,5 @@ mod utils;
 #[cfg(feature = "websocket")]
 #[path = "integration/ws.rs"]
 mod ws;
+#[path = "integration/xref.rs"]
+mod xref;
@@ -0,0 +1,193 @@
+use renews::config::{AccessRule, AccessRules, Config};
+use renews::parse_message;
+
+use crate::utils::{self, ClientMock};
+
+fn access_config() -> Config {
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.access = AccessRules(vec![AccessRule {
+        groups: "private.*".into(),
+        read: Some(vec!["alice".into(), "bob".into()]),
+        post: Some(vec!["alice".into()]),
+    }]);
+    cfg
+}
+
+async fn setup() -> (
+    std::sync::Arc<dyn renews::storage::Storage>,
+    std::sync::Arc<dyn renews::auth::AuthProvider>,
+) {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    storage.add_group("private.staff", false).await.unwrap();
+    let (_, msg) = parse_message(
+        "Message-ID: <secret@test>\r\nNewsgroups: private.staff\r\nFrom: a@test\r\nSubject: payroll\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    for user in ["alice", "bob", "carol"] {
+        auth.add_user(user, "pass").await.unwrap();
+    }
+    (storage, auth)
+}
+
+fn login(client: ClientMock, user: &str) -> ClientMock {
+    client
+        .expect(&format!("AUTHINFO USER {user}"), "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+}
+
+fn post_to(group: &str, id: &str) -> String {
+    format!(
+        "Message-ID: <{id}@test>\r\nNewsgroups: {group}\r\nFrom: a@test\r\nSubject: hi\r\n\r\nBody\r\n."
+    )
+}
+
+#[tokio::test]
+async fn anonymous_clients_cannot_see_restricted_groups() {
+    let (storage, auth) = setup().await;
+    ClientMock::new()
+        .expect("GROUP private.staff", "480 authentication required")
+        .expect("GROUP private.missing", "480 authentication required")
+        .expect("LISTGROUP private.staff", "480 authentication required")
+        .expect_multi(
+            "LIST ACTIVE",
+            vec!["215 list of newsgroups follows", "misc.test 0 1 y", "."],
+        )
+        .expect_multi(
+            "LIST NEWSGROUPS",
+            vec!["215 descriptions follow", "misc.test\t", "."],
+        )
+        .expect("ARTICLE <secret@test>", "430 no such article")
+        .expect("OVER <secret@test>", "430 no such article")
+        .run_with_cfg(access_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn unreadable_groups_can_be_listed_without_being_readable() {
+    let (storage, auth) = setup().await;
+    let mut cfg = access_config();
+    cfg.list_unreadable_groups = true;
+    ClientMock::new()
+        .expect_multi(
+            "LIST ACTIVE",
+            vec![
+                "215 list of newsgroups follows",
+                "misc.test 0 1 y",
+                "private.staff 1 1 n",
+                ".",
+            ],
+        )
+        .expect("GROUP private.staff", "480 authentication required")
+        .expect("ARTICLE <secret@test>", "430 no such article")
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn authorized_user_reads_and_posts() {
+    let (storage, auth) = setup().await;
+    login(ClientMock::new(), "alice")
+        .expect("GROUP private.staff", "211 1 1 1 private.staff")
+        .expect_multi(
+            "LIST ACTIVE private.*",
+            vec!["215 list of newsgroups follows", "private.staff 1 1 y", "."],
+        )
+        .expect("STAT <secret@test>", "223 0 <secret@test> article exists")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&post_to("private.staff", "alice"), "240 article received")
+        .run_with_cfg(access_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn reader_without_post_right_cannot_post() {
+    let (storage, auth) = setup().await;
+    login(ClientMock::new(), "bob")
+        .expect("GROUP private.staff", "211 1 1 1 private.staff")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&post_to("private.staff", "bob"), "441 posting failed")
+        .expect_multi(
+            "LIST ACTIVE",
+            vec![
+                "215 list of newsgroups follows",
+                "misc.test 0 1 y",
+                "private.staff 1 1 n",
+                ".",
+            ],
+        )
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&post_to("misc.test", "bob"), "240 article received")
+        .run_with_cfg(access_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sessions_that_cannot_post_list_groups_as_closed() {
+    let (storage, auth) = setup().await;
+    let mut cfg = access_config();
+    cfg.allow_posting_insecure_connections = false;
+    login(ClientMock::new(), "alice")
+        .expect_multi(
+            "LIST ACTIVE",
+            vec![
+                "215 list of newsgroups follows",
+                "misc.test 0 1 n",
+                "private.staff 1 1 n",
+                ".",
+            ],
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn unlisted_user_is_told_the_group_does_not_exist() {
+    let (storage, auth) = setup().await;
+    login(ClientMock::new(), "carol")
+        .expect("GROUP private.staff", "411 no such newsgroup")
+        .expect("ARTICLE <secret@test>", "430 no such article")
+        .run_with_cfg(access_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn announce_group_is_public_to_read_but_restricted_to_post() {
+    let (storage, auth) = setup().await;
+    storage.add_group("announce.news", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.access = AccessRules(vec![AccessRule {
+        groups: "announce.*".into(),
+        read: None,
+        post: Some(vec!["alice".into()]),
+    }]);
+
+    let client = ClientMock::new().expect("GROUP announce.news", "211 0 1 0 announce.news");
+    login(client, "carol")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&post_to("announce.news", "carol"), "441 posting failed")
+        .run_with_cfg(cfg.clone(), storage.clone(), auth.clone())
+        .await;
+
+    login(ClientMock::new(), "alice")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&post_to("announce.news", "alice"), "240 article received")
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
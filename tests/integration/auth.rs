@@ -1,3 +1,5 @@
+use crate::utils::{self, ClientMock};
+use renews::auth::scram::DEFAULT_ITERATIONS;
 use renews::auth::{AuthProvider, sqlite::SqliteAuth};
 
 #[tokio::test]
@@ -19,3 +21,335 @@ async fn add_and_remove_user() {
     auth.remove_user("user").await.unwrap();
     assert!(!auth.verify_user("user", "pass").await.unwrap());
 }
+
+#[tokio::test]
+async fn stores_scram_credentials() {
+    let auth = SqliteAuth::new("sqlite::memory:").await.unwrap();
+    assert!(auth.get_scram_credentials("user").await.unwrap().is_none());
+    auth.add_user("user", "pass").await.unwrap();
+    let first = auth.get_scram_credentials("user").await.unwrap().unwrap();
+    assert_eq!(first.iterations, DEFAULT_ITERATIONS);
+    auth.update_password("user", "other").await.unwrap();
+    let second = auth.get_scram_credentials("user").await.unwrap().unwrap();
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn sasl_plain_with_initial_response_over_tls() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect(
+            "AUTHINFO SASL PLAIN AHVzZXIAcGFzcw==",
+            "281 authentication accepted",
+        )
+        .expect("AUTHINFO SASL PLAIN", "502 permission denied")
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sasl_plain_challenge_and_bad_password() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO SASL PLAIN", "383 =")
+        .expect("AHVzZXIAd3Jvbmc=", "481 Authentication rejected")
+        .expect("AUTHINFO SASL PLAIN", "383 =")
+        .expect("*", "481 Authentication rejected")
+        .expect("AUTHINFO SASL PLAIN", "383 =")
+        .expect("not base64!", "504 Base64 encoding error")
+        .expect("AUTHINFO SASL PLAIN", "383 =")
+        .expect("AHVzZXIAcGFzcw==", "281 authentication accepted")
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sasl_response_longer_than_limit_is_rejected() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO SASL PLAIN", "383 =")
+        .expect(&"A".repeat(20_000), "481 Authentication rejected")
+        // The rest of the long line was dropped, so the session is in step
+        .expect(
+            "AUTHINFO SASL PLAIN AHVzZXIAcGFzcw==",
+            "281 authentication accepted",
+        )
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sasl_plain_requires_tls_unless_allowed() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect(
+            "AUTHINFO SASL PLAIN AHVzZXIAcGFzcw==",
+            "483 Secure connection required",
+        )
+        .expect("AUTHINFO SASL CRAM-MD5", "503 Mechanism not recognized")
+        .expect("AUTHINFO SASL", "501 not enough arguments")
+        .expect("QUIT", "205 closing connection")
+        .run(storage.clone(), auth.clone())
+        .await;
+
+    let mut cfg = utils::create_minimal_config();
+    cfg.allow_sasl_plain_insecure = true;
+    ClientMock::new()
+        .expect(
+            "AUTHINFO SASL PLAIN AHVzZXIAcGFzcw==",
+            "281 authentication accepted",
+        )
+        .expect("QUIT", "205 closing connection")
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sasl_scram_sha_256_exchange() {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pencil").await.unwrap();
+    let (addr, handle) = utils::setup_server(storage, auth).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+
+    let client_first_bare = "n=user,r=fyko+d2lbbFgONRv9qkxdawL";
+    let initial = STANDARD.encode(format!("n,,{client_first_bare}"));
+    writer
+        .write_all(format!("AUTHINFO SASL SCRAM-SHA-256 {initial}\r\n").as_bytes())
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    let challenge = line
+        .trim_end()
+        .strip_prefix("383 ")
+        .unwrap_or_else(|| panic!("unexpected response: {line}"));
+    let server_first = String::from_utf8(STANDARD.decode(challenge).unwrap()).unwrap();
+
+    let mut nonce = "";
+    let mut salt = Vec::new();
+    let mut iterations = 0u32;
+    for attr in server_first.split(',') {
+        if let Some(v) = attr.strip_prefix("r=") {
+            nonce = v;
+        } else if let Some(v) = attr.strip_prefix("s=") {
+            salt = STANDARD.decode(v).unwrap();
+        } else if let Some(v) = attr.strip_prefix("i=") {
+            iterations = v.parse().unwrap();
+        }
+    }
+    assert!(nonce.starts_with("fyko+d2lbbFgONRv9qkxdawL"));
+
+    // Hi(password, salt, i) computed independently of the server code.
+    salt.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac(b"pencil", &salt);
+    let mut salted = u.clone();
+    for _ in 1..iterations {
+        u = hmac(b"pencil", &u);
+        for (s, b) in salted.iter_mut().zip(&u) {
+            *s ^= b;
+        }
+    }
+    let client_key = hmac(&salted, b"Client Key");
+    let stored_key = Sha256::digest(&client_key);
+    let without_proof = format!("c=biws,r={nonce}");
+    let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+    let signature = hmac(&stored_key, auth_message.as_bytes());
+    let proof: Vec<u8> = client_key
+        .iter()
+        .zip(&signature)
+        .map(|(k, s)| k ^ s)
+        .collect();
+    let client_final = format!("{without_proof},p={}", STANDARD.encode(proof));
+    writer
+        .write_all(format!("{}\r\n", STANDARD.encode(client_final)).as_bytes())
+        .await
+        .unwrap();
+
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    let server_final = line
+        .trim_end()
+        .strip_prefix("283 ")
+        .unwrap_or_else(|| panic!("unexpected response: {line}"));
+    let server_key = hmac(&salted, b"Server Key");
+    let expected = format!(
+        "v={}",
+        STANDARD.encode(hmac(&server_key, auth_message.as_bytes()))
+    );
+    assert_eq!(STANDARD.decode(server_final).unwrap(), expected.as_bytes());
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "205 closing connection\r\n");
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn sasl_scram_rejects_unknown_user() {
+    let (storage, auth) = utils::setup().await;
+    ClientMock::new()
+        .expect(
+            // base64 of "n,,n=nobody,r=abcdef"
+            "AUTHINFO SASL SCRAM-SHA-256 biwsbj1ub2JvZHkscj1hYmNkZWY=",
+            "481 Authentication rejected",
+        )
+        .expect("QUIT", "205 closing connection")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn sasl_external_ignores_unmapped_common_name() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("feeder", "pass").await.unwrap();
+    auth.add_admin_without_key("feeder").await.unwrap();
+    // The certificate's CN names an admin, but no [[client_cert]] rule maps it
+    let (client_cert, client_key) = utils::generate_client_cert("feeder");
+    let (addr, server_cert, handle) = utils::setup_tls_server_with_client_ca(
+        storage,
+        auth,
+        utils::create_minimal_config(),
+        client_cert.clone(),
+    )
+    .await;
+    let (reader, writer) =
+        utils::connect_tls_with_client_cert(addr, server_cert, Some((client_cert, client_key)))
+            .await;
+    let mut caps = Vec::new();
+    for line in utils::capabilities_lines() {
+        match line.as_str() {
+            "SASL SCRAM-SHA-256" => caps.push("SASL PLAIN SCRAM-SHA-256".to_string()),
+            "MODE-READER" => caps.extend([line, "POST".to_string()]),
+            _ => caps.push(line),
+        }
+    }
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", caps)
+        // base64 of "feeder"
+        .expect(
+            "AUTHINFO SASL EXTERNAL ZmVlZGVy",
+            "481 Authentication rejected",
+        )
+        .expect("AUTHINFO SASL EXTERNAL", "481 Authentication rejected")
+        .expect("XBACKEND", "480 authentication required")
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn sasl_external_without_certificate_is_rejected() {
+    let (storage, auth) = utils::setup().await;
+    let (client_cert, _) = utils::generate_client_cert("feeder");
+    let (addr, server_cert, handle) = utils::setup_tls_server_with_client_ca(
+        storage,
+        auth,
+        utils::create_minimal_config(),
+        client_cert,
+    )
+    .await;
+    let (reader, writer) = utils::connect_tls_with_client_cert(addr, server_cert, None).await;
+    ClientMock::new()
+        .expect("AUTHINFO SASL EXTERNAL =", "481 Authentication rejected")
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn mapped_client_certificate_logs_in_without_authinfo() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    let (client_cert, client_key) = utils::generate_client_cert("feeder");
+    let mut cfg = utils::create_minimal_config();
+    cfg.client_certs = vec![renews::config::ClientCertRule {
+        subject: Some("CN=feeder,O=Renews Tests".into()),
+        fingerprint: None,
+        username: "feeder".into(),
+    }];
+    let (addr, server_cert, handle) =
+        utils::setup_tls_server_with_client_ca(storage, auth, cfg, client_cert.clone()).await;
+    let (reader, writer) =
+        utils::connect_tls_with_client_cert(addr, server_cert, Some((client_cert, client_key)))
+            .await;
+    let caps: Vec<String> = utils::capabilities_lines()
+        .into_iter()
+        .filter(|l| !l.starts_with("AUTHINFO") && !l.starts_with("SASL"))
+        .flat_map(|l| {
+            if l == "MODE-READER" {
+                vec![l, "POST".to_string()]
+            } else {
+                vec![l]
+            }
+        })
+        .collect();
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", caps)
+        .expect("AUTHINFO SASL EXTERNAL =", "502 permission denied")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(concat!(
+                "Message-ID: <cert@test>\r\n",
+                "Newsgroups: misc\r\n",
+                "From: feeder@example.com\r\n",
+                "Subject: certificate login\r\n",
+                "\r\n",
+                "Body\r\n",
+                ".",
+            )),
+            vec!["240 article received"],
+        )
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn unmapped_client_certificate_still_needs_authinfo() {
+    let (storage, auth) = utils::setup().await;
+    let (client_cert, client_key) = utils::generate_client_cert("stranger");
+    let mut cfg = utils::create_minimal_config();
+    cfg.client_certs = vec![renews::config::ClientCertRule {
+        subject: Some("CN=feeder,O=Renews Tests".into()),
+        fingerprint: None,
+        username: "feeder".into(),
+    }];
+    let (addr, server_cert, handle) =
+        utils::setup_tls_server_with_client_ca(storage, auth, cfg, client_cert.clone()).await;
+    let (reader, writer) =
+        utils::connect_tls_with_client_cert(addr, server_cert, Some((client_cert, client_key)))
+            .await;
+    ClientMock::new()
+        .expect("POST", "480 authentication required")
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap();
+}
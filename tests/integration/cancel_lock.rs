@@ -1,6 +1,8 @@
 use base64::{Engine as _, engine::general_purpose::STANDARD};
+use renews::auth::DynAuth;
 use renews::parse_message;
-use sha2::{Digest, Sha256};
+use renews::storage::DynStorage;
+use sha2::{Digest, Sha256, Sha512};
 
 use crate::utils::{self, ClientMock};
 
@@ -38,3 +40,223 @@ async fn cancel_key_allows_cancel() {
             .is_none()
     );
 }
+
+#[tokio::test]
+async fn cancelled_article_is_refused_when_offered_again() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) =
+        parse_message("Message-ID: <gone@test>\r\nNewsgroups: misc.test\r\n\r\nBody").unwrap();
+    storage.store_article(&msg).await.unwrap();
+    storage.delete_article_by_id("<gone@test>").await.unwrap();
+    assert!(!storage.article_exists("<gone@test>").await.unwrap());
+
+    ClientMock::new()
+        .expect("IHAVE <gone@test>", "435 article not wanted")
+        .expect("MODE STREAM", "203 Streaming permitted")
+        .expect("CHECK <gone@test>", "438 <gone@test>")
+        .run(storage.clone(), auth)
+        .await;
+    assert!(!storage.article_exists("<gone@test>").await.unwrap());
+}
+
+fn locked_original(key: &str) -> String {
+    let lock_hash = Sha256::digest(STANDARD.encode(key).as_bytes());
+    let lock_b64 = STANDARD.encode(lock_hash);
+    format!(
+        "Message-ID: <a@test>\r\nNewsgroups: misc.test\r\nCancel-Lock: sha256:{lock_b64}\r\n\r\nBody"
+    )
+}
+
+#[tokio::test]
+async fn supersedes_with_cancel_key_replaces_article() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(&locked_original("secret")).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    let key_b64 = STANDARD.encode("secret");
+    let replacement = format!(
+        "Message-ID: <b@test>\r\nFrom: a@test\r\nSubject: update\r\nNewsgroups: misc.test\r\nSupersedes: <a@test>\r\nCancel-Key: sha256:{key_b64}\r\n\r\nNew body\r\n.\r\n"
+    );
+    ClientMock::new()
+        .expect("IHAVE <b@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(replacement.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth)
+        .await;
+    assert!(
+        storage
+            .get_article_by_id("<a@test>")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn supersedes_with_wrong_key_keeps_original() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(&locked_original("secret")).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    let key_b64 = STANDARD.encode("guess");
+    let replacement = format!(
+        "Message-ID: <b@test>\r\nFrom: a@test\r\nSubject: update\r\nNewsgroups: misc.test\r\nSupersedes: <a@test>\r\nCancel-Key: sha256:{key_b64}\r\n\r\nNew body\r\n.\r\n"
+    );
+    ClientMock::new()
+        .expect("IHAVE <b@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(replacement.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth)
+        .await;
+    assert!(storage.article_exists("<a@test>").await.unwrap());
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn supersedes_without_cancel_key_keeps_original() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(&locked_original("secret")).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    let replacement = "Message-ID: <b@test>\r\nFrom: a@test\r\nSubject: update\r\nNewsgroups: misc.test\r\nSupersedes: <a@test>\r\n\r\nNew body\r\n.\r\n";
+    ClientMock::new()
+        .expect("IHAVE <b@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(replacement.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth)
+        .await;
+    assert!(storage.article_exists("<a@test>").await.unwrap());
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+async fn offer_replacement(storage: DynStorage, auth: DynAuth, from: &str) {
+    let replacement = format!(
+        "Message-ID: <b@test>\r\nFrom: {from}\r\nSubject: update\r\nNewsgroups: misc.test\r\nSupersedes: <a@test>\r\n\r\nNew body\r\n.\r\n"
+    );
+    ClientMock::new()
+        .expect("IHAVE <b@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(replacement.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn supersedes_from_same_poster_replaces_unlocked_original() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(
+        "Message-ID: <a@test>\r\nFrom: Alice <Alice@Example.org>\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    offer_replacement(storage.clone(), auth, "alice@example.org (Alice)").await;
+    assert!(!storage.article_exists("<a@test>").await.unwrap());
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn supersedes_from_other_poster_keeps_original() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (_, msg) = parse_message(
+        "Message-ID: <a@test>\r\nFrom: alice@example.org\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    offer_replacement(storage.clone(), auth, "mallory@example.org").await;
+    assert!(storage.article_exists("<a@test>").await.unwrap());
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn supersedes_unknown_article_stores_replacement() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+
+    offer_replacement(storage.clone(), auth, "alice@example.org").await;
+    assert!(storage.article_exists("<b@test>").await.unwrap());
+}
+
+fn lock_for<D: Digest>(key: &str) -> String {
+    STANDARD.encode(D::digest(STANDARD.encode(key).as_bytes()))
+}
+
+/// Store `<a@test>` with `locks` as its Cancel-Lock header, send a cancel
+/// carrying `cancel_key` and report whether the original was removed.
+async fn cancel_with(locks: &str, cancel_key: &str) -> bool {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let orig = format!(
+        "Message-ID: <a@test>\r\nNewsgroups: misc.test\r\nCancel-Lock: {locks}\r\n\r\nBody"
+    );
+    let (_, msg) = parse_message(&orig).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    let cancel = format!(
+        "Message-ID: <c@test>\r\nNewsgroups: misc.test\r\nControl: cancel <a@test>\r\nCancel-Key: {cancel_key}\r\n\r\n.\r\n"
+    );
+    ClientMock::new()
+        .expect("IHAVE <c@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(cancel.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth)
+        .await;
+    storage
+        .get_article_by_id("<a@test>")
+        .await
+        .unwrap()
+        .is_none()
+}
+
+#[tokio::test]
+async fn cancel_key_sha1_and_sha512() {
+    let key = STANDARD.encode("secret");
+    let sha1 = lock_for::<sha1::Sha1>("secret");
+    assert!(cancel_with(&format!("sha1:{sha1}"), &format!("sha1:{key}")).await);
+    let sha512 = lock_for::<Sha512>("secret");
+    assert!(cancel_with(&format!("sha512:{sha512}"), &format!("sha512:{key}")).await);
+    // The key must be presented under the lock's algorithm
+    assert!(!cancel_with(&format!("sha512:{sha512}"), &format!("sha256:{key}")).await);
+}
+
+#[tokio::test]
+async fn cancel_key_matches_any_of_several_locks() {
+    let poster = lock_for::<Sha256>("poster");
+    let moderator = lock_for::<Sha512>("moderator");
+    let locks = format!("bogus:xyz sha256:{poster} sha512:{moderator}");
+    let key = STANDARD.encode("moderator");
+    assert!(cancel_with(&locks, &format!("sha512:{key}")).await);
+    assert!(!cancel_with(&locks, &format!("sha512:{}", STANDARD.encode("intruder"))).await);
+}
+
+#[tokio::test]
+async fn unknown_cancel_key_scheme_is_ignored() {
+    let key = STANDARD.encode("secret");
+    let lock = lock_for::<Sha256>("secret");
+    assert!(!cancel_with(&format!("sha256:{lock}"), &format!("md5:{key}")).await);
+    assert!(
+        cancel_with(
+            &format!("sha256:{lock}"),
+            &format!("md5:{key} sha256:{key}")
+        )
+        .await
+    );
+}
@@ -0,0 +1,113 @@
+use renews::client::{GroupStatus, NntpClient};
+use renews::parse_message;
+
+use crate::utils;
+
+async fn serve() -> (std::net::SocketAddr, tokio::task::JoinHandle<()>) {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for id in ["one", "two"] {
+        let text = format!(
+            "Message-ID: <{id}@test>\r\nFrom: a@test\r\nSubject: {id}\r\nNewsgroups: misc.test\r\n\r\nBody of {id}\r\n"
+        );
+        let (_, msg) = parse_message(&text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    auth.add_user("reader", "secret").await.unwrap();
+    utils::setup_server(storage, auth).await
+}
+
+#[tokio::test]
+async fn reads_groups_overview_and_articles() {
+    let (addr, _server) = serve().await;
+    let mut client = NntpClient::connect("127.0.0.1", addr.port()).await.unwrap();
+    client.mode_reader().await.unwrap();
+    client.authenticate("reader", "secret").await.unwrap();
+
+    assert_eq!(client.group("misc.missing").await.unwrap(), None);
+    assert_eq!(
+        client.group("misc.test").await.unwrap(),
+        Some(GroupStatus {
+            count: 2,
+            low: 1,
+            high: 2
+        })
+    );
+
+    let overview = client.over(1, 2).await.unwrap();
+    let ids: Vec<_> = overview
+        .iter()
+        .map(|l| (l.number, l.message_id.as_str()))
+        .collect();
+    assert_eq!(ids, vec![(1, "<one@test>"), (2, "<two@test>")]);
+    assert_eq!(overview[1].subject, "two");
+    assert!(client.over(5, 9).await.unwrap().is_empty());
+
+    assert_eq!(
+        client.stat("2").await.unwrap(),
+        Some((2, "<two@test>".to_string()))
+    );
+    assert_eq!(client.stat("<missing@test>").await.unwrap(), None);
+
+    let article = client.article("<one@test>").await.unwrap().unwrap();
+    assert_eq!(article.body, b"Body of one\r\n");
+    assert!(client.article("<missing@test>").await.unwrap().is_none());
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn refused_credentials_are_an_error() {
+    let (addr, _server) = serve().await;
+    let mut client = NntpClient::connect("127.0.0.1", addr.port()).await.unwrap();
+    assert!(client.authenticate("reader", "wrong").await.is_err());
+}
+
+#[tokio::test]
+async fn stream_offers_pipelines_check_and_takethis() {
+    use renews::client::Offer;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let peer = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"200 ready\r\n").await.unwrap();
+        // No reply until every CHECK of the batch has arrived
+        let mut checks = Vec::new();
+        for _ in 0..3 {
+            checks.push(lines.next_line().await.unwrap().unwrap());
+        }
+        assert_eq!(
+            checks,
+            ["CHECK <a@test>", "CHECK <b@test>", "CHECK <c@test>"]
+        );
+        writer
+            .write_all(b"238 <a@test>\r\n438 <b@test>\r\n431 <c@test>\r\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            lines.next_line().await.unwrap().unwrap(),
+            "TAKETHIS <a@test>"
+        );
+        while lines.next_line().await.unwrap().unwrap() != "." {}
+        writer.write_all(b"239 <a@test>\r\n").await.unwrap();
+    });
+
+    let article = |id: &str| {
+        let text = format!("Message-ID: {id}\r\nNewsgroups: misc.test\r\n\r\nBody\r\n");
+        (id.to_string(), parse_message(&text).unwrap().1)
+    };
+    let mut client = NntpClient::connect("127.0.0.1", port).await.unwrap();
+    let offers = client
+        .stream_offers(&[
+            article("<a@test>"),
+            article("<b@test>"),
+            article("<c@test>"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(offers, [Offer::Accepted, Offer::Refused, Offer::Deferred]);
+    peer.await.unwrap();
+}
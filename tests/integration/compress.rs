@@ -0,0 +1,205 @@
+use crate::utils::{self, ClientMock};
+use renews::parse_message;
+
+async fn setup_with_article() -> (
+    std::sync::Arc<dyn renews::storage::Storage>,
+    std::sync::Arc<dyn renews::auth::AuthProvider>,
+) {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    let (_, msg) =
+        parse_message("Message-ID: <1@test>\r\nSubject: T\r\nNewsgroups: misc\r\n\r\nBody")
+            .unwrap();
+    storage.store_article(&msg).await.unwrap();
+    (storage, auth)
+}
+
+fn head_lines() -> Vec<&'static str> {
+    vec![
+        "221 1 <1@test> article headers follow",
+        "Message-ID: <1@test>",
+        "Subject: T",
+        "Newsgroups: misc",
+        ".",
+    ]
+}
+
+#[tokio::test]
+async fn compress_deflate_over_tcp() {
+    let (storage, auth) = setup_with_article().await;
+    ClientMock::new()
+        .expect_compress()
+        .expect("GROUP misc", "211 1 1 1 misc")
+        .expect_multi("HEAD 1", head_lines())
+        .expect_multi(
+            "LIST",
+            vec!["215 list of newsgroups follows", "misc 1 1 n", "."],
+        )
+        .expect("QUIT", "205 closing connection")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn compress_deflate_over_tls() {
+    let (storage, auth) = setup_with_article().await;
+    ClientMock::new()
+        .expect_compress()
+        .expect("GROUP misc", "211 1 1 1 misc")
+        .expect_multi("HEAD 1", head_lines())
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn compress_twice_is_rejected() {
+    let (storage, auth) = utils::setup().await;
+    ClientMock::new()
+        .expect_compress()
+        .expect("COMPRESS DEFLATE", "502 Compression already active")
+        .expect("QUIT", "205 closing connection")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn compress_rejects_unknown_algorithm_and_missing_argument() {
+    let (storage, auth) = utils::setup().await;
+    ClientMock::new()
+        .expect("COMPRESS GZIP", "503 feature not supported")
+        .expect("COMPRESS", "501 Syntax error")
+        .expect_compress()
+        .expect("QUIT", "205 closing connection")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn capabilities_stop_advertising_compress_once_active() {
+    let (storage, auth) = utils::setup().await;
+    let active: Vec<String> = utils::capabilities_lines()
+        .into_iter()
+        .filter(|l| l != "COMPRESS DEFLATE")
+        .collect();
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", utils::capabilities_lines())
+        .expect_compress()
+        .expect_multi("CAPABILITIES", active)
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn post_after_compress_over_tls() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let article = concat!(
+        "Message-ID: <compressed-post@test>\r\n",
+        "Newsgroups: misc\r\n",
+        "From: user@example.com\r\n",
+        "Subject: test\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    );
+    ClientMock::new()
+        .expect_compress()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(utils::request_lines(article), vec!["240 article received"])
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage.clone(), auth)
+        .await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(
+        storage
+            .get_article_by_id("<compressed-post@test>")
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn ihave_after_compress() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    let article = concat!(
+        "Path: peer!not-for-mail\r\n",
+        "From: user@example.com\r\n",
+        "Newsgroups: misc\r\n",
+        "Subject: compressed transfer\r\n",
+        "Date: 6 Oct 1998 04:38:40 -0500\r\n",
+        "Message-ID: <compressed-ihave@test>\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    );
+    ClientMock::new()
+        .expect_compress()
+        .expect(
+            "IHAVE <compressed-ihave@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(article),
+            vec!["235 Article transferred OK"],
+        )
+        .expect("IHAVE <compressed-ihave@test>", "435 article not wanted")
+        .run(storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn compressed_session_is_raw_deflate_with_sync_flushes() {
+    use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    let (storage, auth) = utils::setup().await;
+    let (addr, handle) = utils::setup_server(storage, auth).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    writer.write_all(b"COMPRESS DEFLATE\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "206 Compression active\r\n");
+
+    // Compress the request independently of the server's stream layer.
+    let mut deflate = Compress::new(Compression::default(), false);
+    let mut request = Vec::with_capacity(128);
+    deflate
+        .compress_vec(b"DATE\r\n", &mut request, FlushCompress::Sync)
+        .unwrap();
+    writer.write_all(&request).await.unwrap();
+
+    // The response must be terminated by a sync flush (an empty stored
+    // block), otherwise it would sit in the server's compressor.
+    let mut compressed = Vec::new();
+    let mut buf = [0u8; 256];
+    while !compressed.ends_with(&[0, 0, 0xff, 0xff]) {
+        let n = reader.read(&mut buf).await.unwrap();
+        assert!(n > 0, "connection closed before the response was flushed");
+        compressed.extend_from_slice(&buf[..n]);
+    }
+    let mut inflate = Decompress::new(false);
+    let mut response = Vec::with_capacity(256);
+    inflate
+        .decompress_vec(&compressed, &mut response, FlushDecompress::Sync)
+        .unwrap();
+    let response = String::from_utf8(response).unwrap();
+    assert!(
+        response.starts_with("111 ") && response.ends_with("\r\n"),
+        "{response}"
+    );
+
+    drop(writer);
+    handle.await.unwrap();
+}
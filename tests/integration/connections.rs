@@ -0,0 +1,174 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use renews::config::Config;
+use renews::connlimit::{self, ConnectionLimiter};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+use crate::utils;
+
+/// Accept connections the way the listeners do, admitting them through
+/// `limiter`.
+async fn serve(limiter: ConnectionLimiter) -> std::net::SocketAddr {
+    let (storage, auth) = utils::setup().await;
+    let cfg = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (socket, peer) = listener.accept().await.unwrap();
+            let Some(permit) = limiter.try_admit(peer.ip()) else {
+                tokio::spawn(connlimit::refuse(socket));
+                continue;
+            };
+            let (storage, auth, cfg, queue) =
+                (storage.clone(), auth.clone(), cfg.clone(), queue.clone());
+            tokio::spawn(async move {
+                let _permit = permit;
+                let _ = renews::handle_client(socket, storage, auth, cfg, false, queue).await;
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn connections_over_the_limit_are_refused() {
+    let addr = serve(ConnectionLimiter::new(Some(2), None)).await;
+
+    let mut open = Vec::new();
+    for _ in 0..2 {
+        let (mut reader, writer) = utils::connect(addr).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("20"), "{line}");
+        open.push((reader, writer));
+    }
+
+    let (mut reader, _writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "400 too many connections\r\n");
+    line.clear();
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 0);
+
+    // Closing one frees its slot
+    drop(open.pop());
+    for _ in 0..50 {
+        let (mut reader, _writer) = utils::connect(addr).await;
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        if line.starts_with("20") {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("slot was not released: {line}");
+}
+
+#[test]
+fn per_address_limit_counts_each_address() {
+    let limiter = ConnectionLimiter::new(None, Some(1));
+    let a: IpAddr = "192.0.2.1".parse().unwrap();
+    let b: IpAddr = "192.0.2.2".parse().unwrap();
+
+    let first = limiter.try_admit(a).expect("first connection from a");
+    assert!(limiter.try_admit(a).is_none());
+    let _other = limiter.try_admit(b).expect("b has a slot of its own");
+    assert_eq!(limiter.open_from(a), 1);
+
+    drop(first);
+    assert_eq!(limiter.open_from(a), 0);
+    assert!(limiter.try_admit(a).is_some());
+}
+
+/// Accept connections the way the plain listener does under `cfg`'s
+/// `[ip_access]`, passing the client's address on to the session.
+async fn serve_with_access(cfg: Config) -> std::net::SocketAddr {
+    let (storage, auth) = utils::setup().await;
+    let cfg = Arc::new(RwLock::new(cfg));
+    let queue = utils::create_test_queue();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (socket, peer) = listener.accept().await.unwrap();
+            if !cfg.read().await.ip_access.connection_allowed(peer.ip()) {
+                tokio::spawn(connlimit::deny(socket));
+                continue;
+            }
+            let (storage, auth, cfg, queue) =
+                (storage.clone(), auth.clone(), cfg.clone(), queue.clone());
+            tokio::spawn(async move {
+                let _ = renews::handle_client_with_cert(
+                    socket,
+                    storage,
+                    auth,
+                    cfg,
+                    false,
+                    None,
+                    Some(peer.ip()),
+                    queue,
+                )
+                .await;
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn denied_addresses_get_502_and_are_closed() {
+    let mut cfg = utils::create_minimal_config();
+    cfg.ip_access = toml::from_str("deny = [\"127.0.0.0/8\"]").unwrap();
+    let addr = serve_with_access(cfg).await;
+
+    let (mut reader, _writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "502 permission denied\r\n");
+    line.clear();
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn only_allowed_addresses_may_connect() {
+    let mut cfg = utils::create_minimal_config();
+    cfg.ip_access = toml::from_str("allow = [\"192.0.2.0/24\", \"::1\"]").unwrap();
+    let addr = serve_with_access(cfg.clone()).await;
+    let (mut reader, _writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("502"), "{line}");
+
+    cfg.ip_access = toml::from_str("allow = [\"127.0.0.1\"]").unwrap();
+    let addr = serve_with_access(cfg).await;
+    let (mut reader, _writer) = utils::connect(addr).await;
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("20"), "{line}");
+}
+
+#[tokio::test]
+async fn addresses_outside_the_posting_list_cannot_post() {
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.ip_access = toml::from_str("posting_allow = [\"192.0.2.0/24\"]").unwrap();
+    let addr = serve_with_access(cfg).await;
+
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("201"), "{line}");
+
+    writer.write_all(b"POST\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "440 posting not permitted\r\n");
+
+    writer.write_all(b"IHAVE <one@test>\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("502"), "{line}");
+}
@@ -1,5 +1,5 @@
 use futures_util::StreamExt;
-use renews::control::canonical_text;
+use renews::control::{canonical_text, newsgroups_file_description};
 use renews::parse_message;
 
 use crate::utils::{self, ClientMock, build_sig};
@@ -145,3 +145,245 @@ async fn admin_cancel_ignores_lock() {
             .is_none()
     );
 }
+
+#[tokio::test]
+async fn control_newgroup_sets_description() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("admin@example.org", "x").await.unwrap();
+    auth.add_admin("admin@example.org", ADMIN_PUB)
+        .await
+        .unwrap();
+
+    let body = "misc.described is an unmoderated group.\n\nFor your newsgroups file:\nmisc.described\tDiscussion of descriptions.\n";
+    let article = build_control_article("newgroup misc.described", body);
+    ClientMock::new()
+        .expect("IHAVE <ctrl@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(article.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth.clone())
+        .await;
+    assert_eq!(
+        storage
+            .get_group_description("misc.described")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("Discussion of descriptions.")
+    );
+}
+
+#[test]
+fn newsgroups_file_section_parsing() {
+    let body = "For your newsgroups file:\r\ncomp.a   First group\r\ncomp.b\tSecond (Moderated)\r\n\r\nsig";
+    assert_eq!(
+        newsgroups_file_description(body, "comp.b").as_deref(),
+        Some("Second (Moderated)")
+    );
+    assert_eq!(
+        newsgroups_file_description(body, "comp.a").as_deref(),
+        Some("First group")
+    );
+    assert_eq!(newsgroups_file_description(body, "comp.c"), None);
+    assert_eq!(newsgroups_file_description("no section", "comp.a"), None);
+}
+
+fn pinned_config(fingerprint: &str) -> renews::config::Config {
+    let mut cfg = utils::create_minimal_config();
+    cfg.control_keys = vec![renews::config::ControlKeyRule {
+        hierarchy: "test.*".into(),
+        fingerprints: vec![fingerprint.into()],
+    }];
+    cfg
+}
+
+async fn group_exists_after(article: &str, cfg: renews::config::Config) -> bool {
+    let storage = deliver_from_key_holder(article, cfg).await;
+    storage.group_exists("test.group").await.unwrap()
+}
+
+async fn deliver_from_key_holder(
+    article: &str,
+    cfg: renews::config::Config,
+) -> renews::storage::DynStorage {
+    let (storage, auth) = utils::setup().await;
+    // The sender holds the hierarchy key but is not a local admin
+    auth.add_user_with_key("admin@example.org", "x", Some(ADMIN_PUB))
+        .await
+        .unwrap();
+    ClientMock::new()
+        .expect("IHAVE <ctrl@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(article.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    storage
+}
+
+#[tokio::test]
+async fn newgroup_signed_with_hierarchy_key_is_applied() {
+    let fingerprint = renews::control::key_fingerprint(ADMIN_PUB).unwrap();
+    // Fingerprints may be written in the usual spaced, lowercase form
+    let spaced = fingerprint
+        .as_bytes()
+        .chunks(4)
+        .map(|c| std::str::from_utf8(c).unwrap().to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let article = build_control_article("newgroup test.group", "test group body\n");
+    assert!(group_exists_after(&article, pinned_config(&spaced)).await);
+}
+
+#[tokio::test]
+async fn newgroup_signed_with_untrusted_key_is_ignored() {
+    let article = build_control_article("newgroup test.group", "test group body\n");
+    let cfg = pinned_config("0000 1111 2222 3333 4444 5555 6666 7777 8888 9999");
+    assert!(!group_exists_after(&article, cfg).await);
+}
+
+#[tokio::test]
+async fn unsigned_newgroup_for_pinned_hierarchy_is_ignored() {
+    let fingerprint = renews::control::key_fingerprint(ADMIN_PUB).unwrap();
+    let article = "From: admin@example.org\r\nSubject: cmsg newgroup test.group\r\nControl: newgroup test.group\r\nMessage-ID: <ctrl@test>\r\nNewsgroups: test.group\r\n\r\nbody\r\n.\r\n";
+    assert!(!group_exists_after(article, pinned_config(&fingerprint)).await);
+}
+
+#[tokio::test]
+async fn tampered_newgroup_for_pinned_hierarchy_is_ignored() {
+    let fingerprint = renews::control::key_fingerprint(ADMIN_PUB).unwrap();
+    let article = build_control_article("newgroup test.group", "test group body\n")
+        .replace("test group body", "edited group body");
+    assert!(!group_exists_after(&article, pinned_config(&fingerprint)).await);
+}
+
+#[test]
+fn checkgroups_body_parsing() {
+    let body = "comp.lang\tProgramming languages.\n\ncomp.mod   Moderated talk. (Moderated)\nnot-a-group\n";
+    let groups = renews::control::parse_checkgroups(body);
+    let names: Vec<_> = groups.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(names, ["comp.lang", "comp.mod"]);
+    assert_eq!(groups[0].description, "Programming languages.");
+    assert!(!groups[0].moderated);
+    assert_eq!(groups[1].description, "Moderated talk. (Moderated)");
+    assert!(groups[1].moderated);
+}
+
+const CHECKGROUPS_BODY: &str =
+    "comp.lang\tProgramming languages.\ncomp.new\tNew things. (Moderated)\n";
+
+async fn run_checkgroups(cfg: renews::config::Config) -> renews::storage::DynStorage {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("admin@example.org", "x").await.unwrap();
+    auth.add_admin("admin@example.org", ADMIN_PUB)
+        .await
+        .unwrap();
+    for group in ["comp.lang", "comp.old", "misc.keep"] {
+        storage.add_group(group, false).await.unwrap();
+    }
+    storage
+        .set_group_description("comp.lang", "Old description")
+        .await
+        .unwrap();
+
+    let article = build_control_article("checkgroups comp #7", CHECKGROUPS_BODY);
+    ClientMock::new()
+        .expect("IHAVE <ctrl@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(article.trim_end_matches("\r\n")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    storage
+}
+
+#[tokio::test]
+async fn checkgroups_adds_and_updates_listed_groups() {
+    let storage = run_checkgroups(utils::create_minimal_config()).await;
+    assert!(storage.group_exists("comp.new").await.unwrap());
+    assert!(storage.is_group_moderated("comp.new").await.unwrap());
+    assert_eq!(
+        storage
+            .get_group_description("comp.lang")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("Programming languages.")
+    );
+    // Unlisted groups are kept unless removal is enabled
+    assert!(storage.group_exists("comp.old").await.unwrap());
+}
+
+#[tokio::test]
+async fn checkgroups_removes_unlisted_groups_in_scope() {
+    let mut cfg = utils::create_minimal_config();
+    cfg.checkgroups.remove_unlisted = true;
+    let storage = run_checkgroups(cfg).await;
+    assert!(!storage.group_exists("comp.old").await.unwrap());
+    assert!(storage.group_exists("misc.keep").await.unwrap());
+    assert!(storage.group_exists("comp.lang").await.unwrap());
+}
+
+#[tokio::test]
+async fn checkgroups_dry_run_changes_nothing() {
+    let mut cfg = utils::create_minimal_config();
+    cfg.checkgroups.remove_unlisted = true;
+    cfg.checkgroups.dry_run = true;
+    let storage = run_checkgroups(cfg).await;
+    assert!(!storage.group_exists("comp.new").await.unwrap());
+    assert!(storage.group_exists("comp.old").await.unwrap());
+    assert_eq!(
+        storage
+            .get_group_description("comp.lang")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("Old description")
+    );
+}
+
+#[tokio::test]
+async fn unsigned_newgroup_without_pinned_keys_is_dropped_quietly() {
+    let article = "From: admin@example.org\r\nSubject: cmsg newgroup test.group\r\nControl: newgroup test.group\r\nMessage-ID: <ctrl@test>\r\nNewsgroups: test.group\r\n\r\nbody\r\n.\r\n";
+    assert!(!group_exists_after(article, utils::create_minimal_config()).await);
+}
+
+fn foo_pinned_config() -> renews::config::Config {
+    let fingerprint = renews::control::key_fingerprint(ADMIN_PUB).unwrap();
+    let mut cfg = pinned_config(&fingerprint);
+    cfg.control_keys[0].hierarchy = "foo.*".into();
+    cfg
+}
+
+#[tokio::test]
+async fn hierarchy_key_cannot_checkgroups_another_hierarchy() {
+    let body = "foo.a\tFoo talk.\nbar.b\tBar talk.\n";
+    // Listing both hierarchies without a scope
+    let article = build_control_article("checkgroups", body);
+    let storage = deliver_from_key_holder(&article, foo_pinned_config()).await;
+    assert!(!storage.group_exists("bar.b").await.unwrap());
+    assert!(!storage.group_exists("foo.a").await.unwrap());
+
+    // Naming both hierarchies in the scope
+    let article = build_control_article("checkgroups foo bar", body);
+    let storage = deliver_from_key_holder(&article, foo_pinned_config()).await;
+    assert!(!storage.group_exists("bar.b").await.unwrap());
+    assert!(!storage.group_exists("foo.a").await.unwrap());
+
+    // Naming only the other hierarchy
+    let article = build_control_article("checkgroups bar", body);
+    let storage = deliver_from_key_holder(&article, foo_pinned_config()).await;
+    assert!(!storage.group_exists("bar.b").await.unwrap());
+}
+
+#[tokio::test]
+async fn hierarchy_key_checkgroups_its_own_hierarchy_only() {
+    let body = "foo.a\tFoo talk.\nbar.b\tBar talk.\n";
+    let article = build_control_article("checkgroups foo", body);
+    let storage = deliver_from_key_holder(&article, foo_pinned_config()).await;
+    assert!(storage.group_exists("foo.a").await.unwrap());
+    assert!(!storage.group_exists("bar.b").await.unwrap());
+}
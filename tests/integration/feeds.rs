@@ -0,0 +1,234 @@
+use renews::config::PeerRule;
+use renews::feed::Feeds;
+use renews::parse_message;
+use renews::storage::DynStorage;
+use std::time::Duration;
+
+use crate::utils;
+
+fn push_peer(port: u16) -> PeerRule {
+    toml::from_str(&format!(
+        "sitename = \"peer-b\"\nhost = \"127.0.0.1\"\nport = {port}\ntls = false\npatterns = [\"misc.*\"]"
+    ))
+    .unwrap()
+}
+
+async fn accept(storage: &DynStorage, feeds: &Feeds, text: &str) {
+    let (_, msg) = parse_message(text).unwrap();
+    storage.store_article(&msg).await.unwrap();
+    feeds.article_accepted(&msg).await.unwrap();
+}
+
+async fn wait_for(storage: &DynStorage, id: &str) -> bool {
+    for _ in 0..50 {
+        if storage.article_exists(id).await.unwrap() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn push_feed_sends_matching_articles_with_our_path() {
+    let (local, _) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+        storage.add_group("alt.test", false).await.unwrap();
+    }
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth).await;
+
+    let feeds = Feeds::new(&[push_peer(addr.port())], local.clone(), "site-a");
+    accept(
+        &local,
+        &feeds,
+        "Message-ID: <fed@test>\r\nFrom: a@test\r\nSubject: fed\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .await;
+    accept(
+        &local,
+        &feeds,
+        "Message-ID: <local@test>\r\nFrom: a@test\r\nSubject: local\r\nNewsgroups: alt.test\r\n\r\nBody",
+    )
+    .await;
+    accept(
+        &local,
+        &feeds,
+        "Message-ID: <looped@test>\r\nPath: peer-b!origin\r\nFrom: a@test\r\nSubject: loop\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .await;
+    assert_eq!(
+        local.peek_feed("peer-b", 10).await.unwrap(),
+        vec!["<fed@test>".to_string()]
+    );
+
+    let _tasks = feeds.start();
+    assert!(wait_for(&remote, "<fed@test>").await);
+    let fed = remote
+        .get_article_by_id("<fed@test>")
+        .await
+        .unwrap()
+        .unwrap();
+    let path = fed
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Path"))
+        .map(|(_, v)| v.clone());
+    // The receiving server puts its own name in front of ours
+    assert_eq!(path.as_deref(), Some("localhost!site-a"));
+    assert!(!remote.article_exists("<local@test>").await.unwrap());
+    assert!(!remote.article_exists("<looped@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn queued_articles_survive_a_restart() {
+    let (local, _) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+    }
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth).await;
+
+    // Queued by a feed that never ran
+    let before = Feeds::new(&[push_peer(addr.port())], local.clone(), "site-a");
+    accept(
+        &local,
+        &before,
+        "Message-ID: <later@test>\r\nFrom: a@test\r\nSubject: later\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .await;
+    drop(before);
+
+    let after = Feeds::new(&[push_peer(addr.port())], local.clone(), "site-a");
+    let _tasks = after.start();
+    assert!(wait_for(&remote, "<later@test>").await);
+    for _ in 0..50 {
+        if local.peek_feed("peer-b", 10).await.unwrap().is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("delivered article is still queued");
+}
+
+#[tokio::test]
+async fn peers_without_host_are_not_pushed() {
+    let (local, _) = utils::setup().await;
+    let rule: PeerRule = toml::from_str("sitename = \"peer-c\"\npatterns = [\"*\"]").unwrap();
+    let feeds = Feeds::new(&[rule], local.clone(), "site-a");
+    assert!(feeds.is_empty());
+}
+
+#[tokio::test]
+async fn later_articles_reuse_the_open_connection() {
+    let (local, _) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+    }
+    // The test server accepts a single connection
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth).await;
+
+    let feeds = Feeds::new(&[push_peer(addr.port())], local.clone(), "site-a");
+    let _tasks = feeds.start();
+    accept(
+        &local,
+        &feeds,
+        "Message-ID: <first@test>\r\nFrom: a@test\r\nSubject: first\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .await;
+    assert!(wait_for(&remote, "<first@test>").await);
+
+    accept(
+        &local,
+        &feeds,
+        "Message-ID: <second@test>\r\nFrom: a@test\r\nSubject: second\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .await;
+    assert!(wait_for(&remote, "<second@test>").await);
+}
+
+#[tokio::test]
+async fn a_backlog_larger_than_the_window_is_streamed() {
+    let (local, _) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+    }
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth).await;
+
+    let feeds = Feeds::new(&[push_peer(addr.port())], local.clone(), "site-a");
+    for n in 1..=40 {
+        accept(
+            &local,
+            &feeds,
+            &format!(
+                "Message-ID: <bulk{n}@test>\r\nFrom: a@test\r\nSubject: bulk\r\nNewsgroups: misc.test\r\n\r\nBody"
+            ),
+        )
+        .await;
+    }
+    // Already on the peer, so refused and still dequeued
+    let (_, copy) = parse_message(
+        "Message-ID: <bulk7@test>\r\nFrom: a@test\r\nSubject: bulk\r\nNewsgroups: misc.test\r\n\r\nBody",
+    )
+    .unwrap();
+    remote.store_article(&copy).await.unwrap();
+
+    let _tasks = feeds.start();
+    assert!(wait_for(&remote, "<bulk40@test>").await);
+    for n in 1..=40 {
+        assert!(
+            remote
+                .article_exists(&format!("<bulk{n}@test>"))
+                .await
+                .unwrap(),
+            "bulk{n}"
+        );
+    }
+    for _ in 0..50 {
+        if local.peek_feed("peer-b", 50).await.unwrap().is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("streamed articles are still queued");
+}
+
+#[tokio::test]
+async fn backlog_of_an_unreachable_peer_drops_the_oldest() {
+    let (local, _) = utils::setup().await;
+    local.add_group("misc.test", false).await.unwrap();
+
+    // Bind and drop a listener so nothing answers on the port
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    let mut rule = push_peer(port);
+    rule.max_backlog = Some(2);
+
+    let feeds = Feeds::new(&[rule], local.clone(), "site-a");
+    for n in 1..=3 {
+        accept(
+            &local,
+            &feeds,
+            &format!(
+                "Message-ID: <backlog{n}@test>\r\nFrom: a@test\r\nSubject: backlog\r\nNewsgroups: misc.test\r\n\r\nBody"
+            ),
+        )
+        .await;
+    }
+    assert_eq!(local.peek_feed("peer-b", 10).await.unwrap().len(), 3);
+
+    let _tasks = feeds.start();
+    for _ in 0..50 {
+        let queued = local.peek_feed("peer-b", 10).await.unwrap();
+        if queued.len() == 2 {
+            assert_eq!(queued, vec!["<backlog2@test>", "<backlog3@test>"]);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("backlog was not trimmed");
+}
@@ -24,7 +24,7 @@ async fn test_article_commands_invalid_message_id() {
     storage.add_group("test.group", false).await.unwrap();
 
     ClientMock::new()
-        .expect("GROUP test.group", "211 0 0 0 test.group")
+        .expect("GROUP test.group", "211 0 1 0 test.group")
         .expect("ARTICLE <nonexistent@test>", "430 no such article")
         .expect("HEAD <nonexistent@test>", "430 no such article")
         .expect("BODY <nonexistent@test>", "430 no such article")
@@ -40,7 +40,7 @@ async fn test_article_commands_invalid_article_number() {
     storage.add_group("test.group", false).await.unwrap();
 
     ClientMock::new()
-        .expect("GROUP test.group", "211 0 0 0 test.group")
+        .expect("GROUP test.group", "211 0 1 0 test.group")
         .expect("ARTICLE 999", "423 no such article number in this group")
         .expect("HEAD 999", "423 no such article number in this group")
         .expect("BODY 999", "423 no such article number in this group")
@@ -115,11 +115,8 @@ async fn test_listgroup_command_nonexistent_group() {
     let (storage, auth) = setup().await;
 
     ClientMock::new()
-        // LISTGROUP creates empty listing for nonexistent group
-        .expect_multi(
-            "LISTGROUP nonexistent.group",
-            vec!["211 article numbers follow", "."],
-        )
+        // LISTGROUP rejects a group that does not exist
+        .expect("LISTGROUP nonexistent.group", "411 no such newsgroup")
         .expect("QUIT", "205 closing connection")
         .run(storage, auth)
         .await;
@@ -221,8 +218,8 @@ async fn test_over_command_invalid_range() {
     storage.add_group("test.group", false).await.unwrap();
 
     ClientMock::new()
-        .expect("GROUP test.group", "211 0 0 0 test.group")
-        .expect("OVER invalid-range", "423 no articles in that range")
+        .expect("GROUP test.group", "211 0 1 0 test.group")
+        .expect("OVER invalid-range", "501 Syntax error")
         .expect("OVER 999-1000", "423 no such article number in this group")
         .expect("QUIT", "205 closing connection")
         .run(storage, auth)
@@ -24,7 +24,7 @@ idle_timeout_secs = 2
     let mut stream = TcpStream::connect(addr).await.unwrap();
 
     // Read the greeting
-    let (reader, mut writer) = stream.split();
+    let (reader, _writer) = stream.split();
     let mut reader = tokio::io::BufReader::new(reader);
     let mut line = String::new();
     reader.read_line(&mut line).await.unwrap();
@@ -33,26 +33,17 @@ idle_timeout_secs = 2
     // Wait longer than the timeout period without sending anything
     sleep(Duration::from_secs(3)).await;
 
-    // Try to send a command - this should fail because the connection was closed
-    let result = timeout(Duration::from_secs(1), writer.write_all(b"HELP\r\n")).await;
-
-    match result {
-        Ok(Ok(_)) => {
-            // If write succeeded, try to read response - should fail due to closed connection
-            line.clear();
-            let read_result = timeout(Duration::from_secs(1), reader.read_line(&mut line)).await;
-            assert!(
-                read_result.is_err() || read_result.unwrap().unwrap() == 0,
-                "Connection should be closed after timeout"
-            );
-        }
-        Ok(Err(_)) => {
-            // Write failed as expected - connection was closed
-        }
-        Err(_) => {
-            // Write timed out - also indicates connection issues
-        }
-    }
+    // The server says why before closing the connection
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "400 timeout\r\n");
+    line.clear();
+    let read_result = timeout(Duration::from_secs(1), reader.read_line(&mut line)).await;
+    assert_eq!(
+        read_result.expect("connection left open").unwrap(),
+        0,
+        "Connection should be closed after timeout"
+    );
 }
 
 #[tokio::test]
@@ -107,3 +98,64 @@ idle_timeout_secs = 3
     reader.read_line(&mut line).await.unwrap();
     assert!(line.contains("205")); // Closing connection - this confirms connection was alive
 }
+
+#[tokio::test]
+async fn listener_override_replaces_idle_timeout() {
+    let (storage, auth) = utils::setup().await;
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+idle_timeout_secs = 600
+plain_idle_timeout_secs = 1
+tls_idle_timeout_secs = 900
+"#,
+    )
+    .unwrap();
+
+    let (addr, _, _handle) = utils::start_server(storage, auth, cfg, false).await;
+    let (mut reader, _writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+
+    line.clear();
+    timeout(Duration::from_secs(5), reader.read_line(&mut line))
+        .await
+        .expect("plain listener override not applied")
+        .unwrap();
+    assert_eq!(line, "400 timeout\r\n");
+}
+
+#[tokio::test]
+async fn stalled_post_times_out_between_lines() {
+    let (storage, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.article_line_timeout_secs = 1;
+
+    let (addr, _, _handle) = utils::start_server(storage.clone(), auth, cfg, false).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    for command in ["AUTHINFO USER user", "AUTHINFO PASS pass", "POST"] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+    }
+    assert!(line.starts_with("340"), "{line}");
+
+    // Start the article and then stop sending; the idle timeout is far off
+    writer
+        .write_all(b"Message-ID: <stalled@test>\r\nNewsgroups: misc.test\r\n")
+        .await
+        .unwrap();
+    line.clear();
+    timeout(Duration::from_secs(5), reader.read_line(&mut line))
+        .await
+        .expect("stalled POST was not cut off")
+        .unwrap();
+    assert_eq!(line, "400 timeout\r\n");
+    assert!(!storage.article_exists("<stalled@test>").await.unwrap());
+}
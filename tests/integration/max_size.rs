@@ -1,5 +1,6 @@
 use renews::config::Config;
 use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 
 use crate::utils::{self, ClientMock};
@@ -72,3 +73,223 @@ max_article_bytes = "1K"
             .is_none()
     );
 }
+
+#[tokio::test]
+async fn ihave_truncates_body_over_threshold() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+[[group_settings]]
+pattern = "misc.*"
+body_truncate_bytes = 4
+"#,
+    )
+    .unwrap();
+    ClientMock::new()
+        .expect("IHAVE <trunc@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(
+            "Message-ID: <trunc@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: big\r\n\r\n0123456789\r\n.",
+            "235 Article transferred OK",
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    let article = storage
+        .get_article_by_id("<trunc@test>")
+        .await
+        .unwrap()
+        .expect("article stored");
+    assert_eq!(article.body, b"0123");
+    let marker = article
+        .headers
+        .iter()
+        .find(|(k, _)| k == renews::storage::common::TRUNCATED_HEADER)
+        .map(|(_, v)| v.as_str());
+    assert_eq!(marker, Some("12"));
+    assert_eq!(
+        storage.get_message_size("<trunc@test>").await.unwrap(),
+        Some(12)
+    );
+}
+
+/// A server with a 1K limit on every group, and a reader and writer on a
+/// connection to it past the greeting.
+async fn limited_session() -> (
+    renews::storage::DynStorage,
+    tokio::io::BufReader<tokio::net::tcp::OwnedReadHalf>,
+    tokio::net::tcp::OwnedWriteHalf,
+) {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+[[group_settings]]
+pattern = "*"
+max_article_bytes = "1K"
+"#,
+    )
+    .unwrap();
+    let (addr, _) =
+        utils::setup_server_with_cfg(storage.clone(), auth, Arc::new(RwLock::new(cfg))).await;
+    let (mut reader, writer) = utils::connect(addr).await;
+    let mut greeting = String::new();
+    reader.read_line(&mut greeting).await.unwrap();
+    (storage, reader, writer)
+}
+
+#[tokio::test]
+async fn oversized_transfer_is_refused_while_it_streams() {
+    let (storage, mut reader, mut writer) = limited_session().await;
+    let mut line = String::new();
+    writer.write_all(b"IHAVE <huge@test>\r\n").await.unwrap();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("335"), "{line}");
+
+    // The refusal comes once the terminating dot arrives, however much was
+    // sent before it; 8M of body goes by without being kept
+    writer
+        .write_all(b"Message-ID: <huge@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: huge\r\n\r\n")
+        .await
+        .unwrap();
+    let chunk = format!("{}\r\n", "A".repeat(1022)).repeat(1024);
+    for _ in 0..8 {
+        writer.write_all(chunk.as_bytes()).await.unwrap();
+    }
+    writer.write_all(b".\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "437 article rejected\r\n");
+
+    // The session is still in step with the client
+    writer.write_all(b"DATE\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("111 "), "{line}");
+    assert!(!storage.article_exists("<huge@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn oversized_eight_bit_transfer_is_refused_by_size() {
+    let (storage, mut reader, mut writer) = limited_session().await;
+    let mut line = String::new();
+    writer.write_all(b"IHAVE <latin1@test>\r\n").await.unwrap();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("335"), "{line}");
+
+    // Octets that are not UTF-8 count toward the limit like any others
+    writer
+        .write_all(b"Message-ID: <latin1@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: latin1\r\n\r\n")
+        .await
+        .unwrap();
+    let mut body = [0xffu8; 2048];
+    body[2046..].copy_from_slice(b"\r\n");
+    writer.write_all(&body).await.unwrap();
+    writer.write_all(b".\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "437 article rejected\r\n");
+
+    writer.write_all(b"DATE\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("111 "), "{line}");
+    assert!(!storage.article_exists("<latin1@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn oversized_line_is_cut_off_and_discarded() {
+    let (_, mut reader, mut writer) = limited_session().await;
+    let mut line = String::new();
+    writer.write_all(b"TAKETHIS <long@test>\r\n").await.unwrap();
+    writer
+        .write_all(b"Message-ID: <long@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: long\r\n\r\n")
+        .await
+        .unwrap();
+    // One line of 4M with no line break until the end
+    let piece = ".".repeat(64 * 1024);
+    for _ in 0..64 {
+        writer.write_all(piece.as_bytes()).await.unwrap();
+    }
+    writer.write_all(b"\r\n.\r\n").await.unwrap();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "439 <long@test>\r\n");
+
+    writer.write_all(b"DATE\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("111 "), "{line}");
+}
+
+#[tokio::test]
+async fn oversized_post_is_refused_with_the_limit() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.group_settings = toml::from_str::<Config>(
+        r#"
+addr = ":119"
+[[group_settings]]
+pattern = "misc.*"
+max_article_bytes = 100
+"#,
+    )
+    .unwrap()
+    .group_settings;
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            &format!(
+                "Message-ID: <post@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: big\r\n\r\n{}\r\n.",
+                "B".repeat(200)
+            ),
+            "441 article larger than 100 bytes",
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn global_limit_applies_to_groups_without_a_rule() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    storage.add_group("alt.binaries.test", false).await.unwrap();
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+max_article_bytes = 200
+[[group_settings]]
+pattern = "alt.binaries.*"
+max_article_bytes = "1K"
+"#,
+    )
+    .unwrap();
+    let body = "C".repeat(300);
+    ClientMock::new()
+        .expect("IHAVE <global@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(
+            &format!(
+                "Message-ID: <global@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: big\r\n\r\n{body}\r\n."
+            ),
+            "437 article rejected",
+        )
+        .expect("IHAVE <override@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(
+            &format!(
+                "Message-ID: <override@test>\r\nNewsgroups: alt.binaries.test\r\nFrom: a@test\r\nSubject: big\r\n\r\n{body}\r\n."
+            ),
+            "235 Article transferred OK",
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    assert!(!storage.article_exists("<global@test>").await.unwrap());
+    assert!(storage.article_exists("<override@test>").await.unwrap());
+}
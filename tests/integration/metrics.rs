@@ -0,0 +1,198 @@
+use renews::config::PeerRule;
+use renews::feed::Feeds;
+use renews::filters::FilterChain;
+use renews::metrics;
+use renews::parse_message;
+use renews::storage::DynStorage;
+use renews::storage::timed::TimedStorage;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::utils::{self, ClientMock};
+
+/// Value of the sample `series` in a rendering, zero when absent.
+fn sample(text: &str, series: &str) -> u64 {
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map_or(0, |value| value.parse().unwrap())
+}
+
+/// Like [`sample`] for series that are not whole numbers.
+fn sample_f64(text: &str, series: &str) -> f64 {
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map_or(0.0, |value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn render_reports_queue_depth_and_capacity() {
+    let queue = renews::queue::ArticleQueue::new(3);
+    let (_, message) = parse_message("Message-ID: <q@test>\r\n\r\nBody").unwrap();
+    queue
+        .submit(renews::queue::QueuedArticle {
+            message,
+            size: 4,
+            is_control: false,
+            already_validated: true,
+            ack: None,
+        })
+        .await
+        .unwrap();
+
+    let text = metrics::global().render(&queue);
+    assert!(text.contains("# TYPE renews_queue_depth gauge\nrenews_queue_depth 1\n"));
+    assert_eq!(sample(&text, "renews_queue_capacity"), 3);
+    // Rendering only reads the channel
+    assert_eq!(queue.len(), 1);
+}
+
+#[tokio::test]
+async fn counters_follow_commands_filters_and_storage() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let queue = utils::create_test_queue();
+    let before = metrics::global().render(&queue);
+
+    let cfg = utils::create_minimal_config();
+    let (_, bad) = parse_message("Message-ID: <bad@test>\r\n\r\nBody").unwrap();
+    assert!(
+        FilterChain::default()
+            .validate(&storage, &auth, &cfg, &bad, 4)
+            .await
+            .is_err()
+    );
+    let article = "Message-ID: <m@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody\r\n.";
+    ClientMock::new()
+        .expect("IHAVE <m@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(article),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth)
+        .await;
+
+    let after = metrics::global().render(&queue);
+    let grew = |series: &str| sample(&after, series) > sample(&before, series);
+    assert!(grew("renews_commands_total{command=\"IHAVE\"}"));
+    assert!(grew(
+        "renews_filter_rejections_total{filter=\"HeaderFilter\"}"
+    ));
+    assert!(grew("renews_articles_stored_total"));
+}
+
+#[tokio::test]
+async fn sessions_move_connection_rejection_storage_and_queue_metrics() {
+    let (storage, auth) = utils::setup().await;
+    let storage: DynStorage = Arc::new(TimedStorage::new(storage));
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue_with_workers(storage.clone(), auth.clone(), cfg).await;
+    let before = metrics::global().render(&queue);
+
+    let article = "Message-ID: <timed@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody\r\n.";
+    ClientMock::new()
+        .expect(
+            "IHAVE <timed@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(article),
+            vec!["235 Article transferred OK"],
+        )
+        .run(storage.clone(), auth.clone())
+        .await;
+    ClientMock::new()
+        .expect("IHAVE <timed@test>", "435 article not wanted")
+        .run(storage.clone(), auth)
+        .await;
+
+    let (_, message) = parse_message(
+        "Message-ID: <worker@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody",
+    )
+    .unwrap();
+    let (ack, done) = flume::bounded(1);
+    queue
+        .submit(renews::queue::QueuedArticle {
+            message: message.clone(),
+            size: 4,
+            is_control: false,
+            already_validated: false,
+            ack: Some(ack),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        done.recv_async().await.unwrap(),
+        renews::queue::Outcome::Stored
+    );
+
+    let peer: PeerRule = toml::from_str(
+        "sitename = \"metrics-peer\"\nhost = \"127.0.0.1\"\nport = 1\npatterns = [\"misc.*\"]",
+    )
+    .unwrap();
+    let feeds = Feeds::new(&[peer], storage.clone(), "test");
+    feeds.article_accepted(&message).await.unwrap();
+
+    let after = metrics::global().render(&queue);
+    let grew = |series: &str| sample_f64(&after, series) > sample_f64(&before, series);
+    assert!(
+        sample(&after, "renews_connections_total")
+            >= sample(&before, "renews_connections_total") + 2
+    );
+    assert!(grew("renews_articles_rejected_total{reason=\"duplicate\"}"));
+    assert!(grew(
+        "renews_storage_operation_seconds_count{operation=\"store_article\"}"
+    ));
+    assert!(grew("renews_queue_worker_busy_seconds_total"));
+    assert_eq!(
+        sample(&after, "renews_feed_backlog{peer=\"metrics-peer\"}"),
+        1
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn http_endpoint_serves_text_format() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(metrics::serve(listener, utils::create_test_queue()));
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(response.contains("# TYPE renews_connections_active gauge"));
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 404"));
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn http_endpoint_refuses_oversized_requests() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(metrics::serve(listener, utils::create_test_queue()));
+
+    // A header line that reaches the 8K cap without ending is refused
+    // rather than read on; exactly the cap, so nothing is left unread
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut request = b"GET /metrics HTTP/1.1\r\nX-Padding: ".to_vec();
+    request.resize(8192, b'a');
+    stream.write_all(&request).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 431"), "{response}");
+}
@@ -70,7 +70,7 @@ async fn post_requires_approval_for_moderated_group() {
         .expect("AUTHINFO USER user", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP mod.test", "211 0 0 0 mod.test")
+        .expect("GROUP mod.test", "211 0 1 0 mod.test")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -110,7 +110,7 @@ async fn post_with_approval_succeeds() {
         .expect("AUTHINFO USER user", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP mod.test", "211 0 0 0 mod.test")
+        .expect("GROUP mod.test", "211 0 1 0 mod.test")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -151,7 +151,7 @@ async fn cross_post_different_moderators() {
         .expect("AUTHINFO USER poster", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP mod.one", "211 0 0 0 mod.one")
+        .expect("GROUP mod.one", "211 0 1 0 mod.one")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -180,3 +180,464 @@ async fn cross_post_different_moderators() {
     }
     assert_eq!(mod_two_nums, vec![1]);
 }
+
+fn unapproved_crosspost() -> &'static str {
+    concat!(
+        "Message-ID: <xp@test>\r\n",
+        "Newsgroups: mod.test,free.test\r\n",
+        "From: user@example.com\r\n",
+        "Subject: t\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    )
+}
+
+#[tokio::test]
+async fn unapproved_crosspost_held_under_hold_all() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_crosspost(), "441 posting failed")
+        .run_tls(storage.clone(), auth)
+        .await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    assert!(
+        storage
+            .get_article_by_id("<xp@test>")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn unapproved_crosspost_split_goes_live_in_unmoderated_group() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let (relay, sink) = smtp_sink().await;
+    let mut cfg = relay_config(&relay);
+    cfg.moderated_crosspost_policy = renews::config::ModeratedCrosspostPolicy::Split;
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_crosspost(), "240 article received")
+        .run_with_cfg_tls(cfg, storage.clone(), auth)
+        .await;
+
+    // The moderated group's copy goes to its moderator
+    let (rcpt, mail) = sink.await.unwrap();
+    assert_eq!(rcpt, "<mod-test@moderators.example.org>");
+    assert!(mail.contains("Newsgroups: mod.test\n"), "{mail}");
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    let mut free_nums = Vec::new();
+    let mut stream = storage.list_article_numbers("free.test");
+    while let Some(result) = stream.next().await {
+        free_nums.push(result.unwrap());
+    }
+    assert_eq!(free_nums, vec![1]);
+
+    let mut mod_nums = Vec::new();
+    let mut stream = storage.list_article_numbers("mod.test");
+    while let Some(result) = stream.next().await {
+        mod_nums.push(result.unwrap());
+    }
+    assert!(mod_nums.is_empty());
+}
+
+#[tokio::test]
+async fn split_without_a_moderator_address_refuses_the_post() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    // No relay, so the moderated copy could go nowhere
+    let cfg = toml::from_str("addr=\":119\"\nmoderated_crosspost_policy=\"split\"").unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_crosspost(), "441 posting failed")
+        .run_with_cfg_tls(cfg, storage.clone(), auth)
+        .await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(!storage.article_exists("<xp@test>").await.unwrap());
+}
+
+/// Accept one SMTP session on a local port, returning the port's address
+/// and a handle yielding the envelope recipient and the message lines.
+async fn smtp_sink() -> (String, tokio::task::JoinHandle<(String, String)>) {
+    let (addr, sessions) = smtp_sink_for(1).await;
+    let handle = tokio::spawn(async move { sessions.await.unwrap().remove(0) });
+    (addr, handle)
+}
+
+/// Like [`smtp_sink`] for `count` sessions in turn, yielding one recipient
+/// and message per session.
+async fn smtp_sink_for(count: usize) -> (String, tokio::task::JoinHandle<Vec<(String, String)>>) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = tokio::spawn(async move {
+        let mut mails = Vec::new();
+        for _ in 0..count {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 sink ready\r\n").await.unwrap();
+            let (mut rcpt, mut data, mut in_data) = (String::new(), String::new(), false);
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        write.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                if let Some(to) = line.strip_prefix("RCPT TO:") {
+                    rcpt = to.to_string();
+                }
+                let reply = match line.as_str() {
+                    "DATA" => {
+                        in_data = true;
+                        "354 go ahead\r\n"
+                    }
+                    "QUIT" => "221 bye\r\n",
+                    _ => "250 ok\r\n",
+                };
+                write.write_all(reply.as_bytes()).await.unwrap();
+                if line == "QUIT" {
+                    break;
+                }
+            }
+            mails.push((rcpt, data));
+        }
+        mails
+    });
+    (addr, handle)
+}
+
+fn relay_config(relay: &str) -> renews::config::Config {
+    toml::from_str(&format!(
+        "addr=\":119\"\nsmtp_relay=\"{relay}\"\nmoderators=\"%s@moderators.example.org\""
+    ))
+    .unwrap()
+}
+
+fn unapproved_post() -> &'static str {
+    concat!(
+        "Message-ID: <held@test>\r\n",
+        "Newsgroups: mod.test\r\n",
+        "From: user@example.com\r\n",
+        "Subject: please approve\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    )
+}
+
+#[tokio::test]
+async fn unapproved_post_is_mailed_to_moderator_template() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let (relay, sink) = smtp_sink().await;
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_post(), "240 article received")
+        .run_with_cfg_tls(relay_config(&relay), storage.clone(), auth)
+        .await;
+
+    let (rcpt, mail) = sink.await.unwrap();
+    assert_eq!(rcpt, "<mod-test@moderators.example.org>");
+    assert!(mail.contains("To: mod-test@moderators.example.org"));
+    assert!(mail.contains("Subject: please approve"));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(
+        storage
+            .get_article_by_id("<held@test>")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn split_sends_each_moderated_group_its_own_copy() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.one", true).await.unwrap();
+    storage.add_group("mod.two", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let (relay, sink) = smtp_sink_for(2).await;
+    let mut cfg = relay_config(&relay);
+    cfg.moderated_crosspost_policy = renews::config::ModeratedCrosspostPolicy::Split;
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            &unapproved_post().replace(
+                "Newsgroups: mod.test",
+                "Newsgroups: mod.one,mod.two,free.test",
+            ),
+            "240 article received",
+        )
+        .run_with_cfg_tls(cfg, storage.clone(), auth)
+        .await;
+
+    let mails = sink.await.unwrap();
+    assert_eq!(mails[0].0, "<mod-one@moderators.example.org>");
+    assert!(
+        mails[0].1.contains("Newsgroups: mod.one\n"),
+        "{}",
+        mails[0].1
+    );
+    assert_eq!(mails[1].0, "<mod-two@moderators.example.org>");
+    assert!(
+        mails[1].1.contains("Newsgroups: mod.two\n"),
+        "{}",
+        mails[1].1
+    );
+
+    // The unmoderated group gets its copy right away
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let stored = storage
+        .get_article_by_id("<held@test>")
+        .await
+        .unwrap()
+        .expect("free.test copy stored");
+    let newsgroups = stored
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Newsgroups"))
+        .map(|(_, v)| v.as_str());
+    assert_eq!(newsgroups, Some("free.test"));
+}
+
+#[tokio::test]
+async fn hold_all_mails_a_crosspost_to_two_moderated_groups_once() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.one", true).await.unwrap();
+    storage.add_group("mod.two", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let (relay, sink) = smtp_sink().await;
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            &unapproved_post().replace(
+                "Newsgroups: mod.test",
+                "Newsgroups: mod.one,mod.two,free.test",
+            ),
+            "240 article received",
+        )
+        .run_with_cfg_tls(relay_config(&relay), storage.clone(), auth)
+        .await;
+
+    // The whole article waits on the first moderator
+    let (rcpt, mail) = sink.await.unwrap();
+    assert_eq!(rcpt, "<mod-one@moderators.example.org>");
+    assert!(mail.contains("Newsgroups: mod.one,mod.two,free.test"));
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(!storage.article_exists("<held@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn stored_moderator_address_overrides_template() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    storage
+        .set_group_moderator("mod.test", "editor@example.org")
+        .await
+        .unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let (relay, sink) = smtp_sink().await;
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_post(), "240 article received")
+        .run_with_cfg_tls(relay_config(&relay), storage.clone(), auth)
+        .await;
+
+    let (rcpt, _) = sink.await.unwrap();
+    assert_eq!(rcpt, "<editor@example.org>");
+}
+
+#[tokio::test]
+async fn logged_in_moderator_may_approve_without_signature() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    auth.add_moderator("user", "mod.*").await.unwrap();
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            concat!(
+                "Message-ID: <ok@test>\r\n",
+                "Newsgroups: mod.test\r\n",
+                "From: user@example.com\r\n",
+                "Subject: t\r\n",
+                "Approved: user@example.com\r\n",
+                "\r\n",
+                "Body\r\n",
+                ".",
+            ),
+            "240 article received",
+        )
+        .run_tls(storage.clone(), auth)
+        .await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(
+        storage
+            .get_article_by_id("<ok@test>")
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn approval_from_non_moderator_is_refused() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    auth.add_moderator("user", "other.*").await.unwrap();
+    // An Approved header is never mailed on, so the relay is not used
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            concat!(
+                "Message-ID: <forged@test>\r\n",
+                "Newsgroups: mod.test\r\n",
+                "From: user@example.com\r\n",
+                "Subject: t\r\n",
+                "Approved: user@example.com\r\n",
+                "\r\n",
+                "Body\r\n",
+                ".",
+            ),
+            "441 posting failed",
+        )
+        .run_with_cfg_tls(relay_config("127.0.0.1:9"), storage.clone(), auth)
+        .await;
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    assert!(
+        storage
+            .get_article_by_id("<forged@test>")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn unreachable_relay_refuses_the_post() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    // Nothing listens on a port whose listener was just dropped
+    let relay = {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(unapproved_post(), "441 posting failed")
+        .run_with_cfg_tls(relay_config(&relay), storage.clone(), auth)
+        .await;
+
+    assert!(
+        storage
+            .get_article_by_id("<held@test>")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn unapproved_crosspost_is_refused_from_peers() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("mod.test", true).await.unwrap();
+    storage.add_group("free.test", false).await.unwrap();
+    // Approval is needed when any target is moderated, wherever it is listed
+    let article = concat!(
+        "Path: peer\r\n",
+        "Message-ID: <xp@test>\r\n",
+        "Newsgroups: free.test,mod.test\r\n",
+        "From: user@example.com\r\n",
+        "Subject: t\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    );
+    ClientMock::new()
+        .expect("IHAVE <xp@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(utils::request_lines(article), vec!["437 article rejected"])
+        .run(storage.clone(), auth)
+        .await;
+
+    assert!(!storage.article_exists("<xp@test>").await.unwrap());
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::RwLock;
+
+use crate::utils::{self, ClientMock};
+
+const ARTICLE: &str = "Message-ID: <feed@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: feed\r\n\r\nBody\r\n.";
+
+#[tokio::test]
+async fn mode_reader_refuses_transit_commands() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    ClientMock::new()
+        .expect("MODE READER", "201 Posting prohibited")
+        .expect("IHAVE <feed@test>", "502 Command unavailable in this mode")
+        .expect("CHECK <feed@test>", "502 Command unavailable in this mode")
+        .expect("MODE STREAM", "502 Command unavailable in this mode")
+        .expect("GROUP misc.test", "211 0 1 0 misc.test")
+        .run(storage.clone(), auth)
+        .await;
+    assert!(!storage.article_exists("<feed@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn reader_listener_refuses_feeds_when_transit_listener_is_set() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg = toml::from_str("addr=\":119\"\ntransit_addr=\":434\"").unwrap();
+    let capabilities: Vec<String> = utils::capabilities_lines()
+        .into_iter()
+        .filter(|l| !["MODE-READER", "IHAVE", "STREAMING"].contains(&l.as_str()))
+        .collect();
+    ClientMock::new()
+        .expect_multi("CAPABILITIES", capabilities)
+        .expect("IHAVE <feed@test>", "502 Command unavailable in this mode")
+        .expect("GROUP misc.test", "211 0 1 0 misc.test")
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn transit_listener_only_accepts_feeds() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue =
+        utils::create_test_queue_with_workers(storage.clone(), auth.clone(), cfg.clone()).await;
+    let (client, server) = tokio::io::duplex(4096);
+    let handle = tokio::spawn(renews::handle_transit_client(
+        server,
+        storage.clone(),
+        auth,
+        cfg,
+        None,
+        queue,
+    ));
+    let (read, mut writer) = tokio::io::split(client);
+    let mut reader = BufReader::new(read);
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("201 "), "{line}");
+
+    for cmd in ["GROUP misc.test", "ARTICLE <feed@test>", "OVER", "POST"] {
+        writer
+            .write_all(format!("{cmd}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "502 Command unavailable in this mode\r\n", "{cmd}");
+    }
+
+    writer.write_all(b"MODE READER\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "502 Command unavailable in this mode\r\n");
+
+    writer.write_all(b"CAPABILITIES\r\n").await.unwrap();
+    let mut capabilities = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let cap = line.trim_end_matches(['\r', '\n']).to_string();
+        if cap == "." {
+            break;
+        }
+        capabilities.push(cap);
+    }
+    assert!(capabilities.iter().any(|c| c == "IHAVE"));
+    assert!(capabilities.iter().any(|c| c == "STREAMING"));
+    assert!(!capabilities.iter().any(|c| c == "READER" || c == "POST"));
+
+    writer.write_all(b"IHAVE <feed@test>\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("335 "), "{line}");
+    writer
+        .write_all(format!("{ARTICLE}\r\n").as_bytes())
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("235 "), "{line}");
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    drop(writer);
+    handle.await.unwrap().unwrap();
+    for _ in 0..50 {
+        if storage.article_exists("<feed@test>").await.unwrap() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("fed article was not stored");
+}
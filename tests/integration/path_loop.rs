@@ -0,0 +1,185 @@
+use renews::storage::DynStorage;
+
+use crate::utils::{self, ClientMock};
+
+fn path_of(storage_article: &renews::Message) -> Option<String> {
+    storage_article
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Path"))
+        .map(|(_, v)| v.clone())
+}
+
+async fn stored_path(storage: &DynStorage, id: &str) -> Option<String> {
+    for _ in 0..50 {
+        if let Some(article) = storage.get_article_by_id(id).await.unwrap() {
+            return path_of(&article);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("{id} was not stored");
+}
+
+fn article(id: &str, path: &str) -> String {
+    format!(
+        "Path: {path}\r\nMessage-ID: {id}\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: loop\r\n\r\nBody\r\n."
+    )
+}
+
+#[tokio::test]
+async fn ihave_refuses_articles_that_already_passed_through_us() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg = utils::create_minimal_config();
+    ClientMock::new()
+        .expect("IHAVE <loop@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(&article("<loop@test>", "peer!test!origin")),
+            vec!["437 article rejected"],
+        )
+        .expect(
+            "IHAVE <fresh@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<fresh@test>", "peer!origin")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert!(!storage.article_exists("<loop@test>").await.unwrap());
+    assert_eq!(
+        stored_path(&storage, "<fresh@test>").await.as_deref(),
+        Some("test!peer!origin")
+    );
+}
+
+#[tokio::test]
+async fn takethis_refuses_articles_that_already_passed_through_us() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let cfg = utils::create_minimal_config();
+    let mut lines = vec!["TAKETHIS <loop@test>".to_string()];
+    lines.extend(utils::request_lines(&article("<loop@test>", "test")));
+    ClientMock::new()
+        .expect_request_multi(lines, vec!["439 <loop@test>"])
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    assert!(!storage.article_exists("<loop@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn posted_articles_get_a_path_of_just_our_name() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let cfg = utils::create_insecure_posting_config();
+    // A client-supplied Path is replaced, even one naming this site
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<posted@test>", "forged!test")),
+            vec!["240 article received"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    assert_eq!(
+        stored_path(&storage, "<posted@test>").await.as_deref(),
+        Some("test")
+    );
+}
+
+async fn stored_header(storage: &DynStorage, id: &str, name: &str) -> Option<String> {
+    stored_path(storage, id).await;
+    let article = storage.get_article_by_id(id).await.unwrap().unwrap();
+    article
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+}
+
+#[tokio::test]
+async fn posts_get_path_host_and_a_date() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.path_host = Some("news.test".into());
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<dated@test>", "client")),
+            vec!["240 article received"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert_eq!(
+        stored_path(&storage, "<dated@test>").await.as_deref(),
+        Some("news.test")
+    );
+    let date = stored_header(&storage, "<dated@test>", "Date")
+        .await
+        .expect("Date was added");
+    assert!(
+        chrono::DateTime::parse_from_rfc2822(&date).is_ok(),
+        "{date}"
+    );
+}
+
+#[tokio::test]
+async fn transit_articles_keep_their_date_and_extend_the_path() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.path_host = Some("news.test".into());
+    ClientMock::new()
+        .expect(
+            "IHAVE <undated@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<undated@test>", "peer!origin")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert_eq!(
+        stored_path(&storage, "<undated@test>").await.as_deref(),
+        Some("news.test!peer!origin")
+    );
+    assert_eq!(
+        stored_header(&storage, "<undated@test>", "Date").await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn offers_naming_path_host_are_refused() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.path_host = Some("news.test".into());
+    ClientMock::new()
+        .expect("IHAVE <loop@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(&article("<loop@test>", "peer!news.test!origin")),
+            vec!["437 article rejected"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    assert!(!storage.article_exists("<loop@test>").await.unwrap());
+}
@@ -127,9 +127,9 @@ async fn peer_transfer_helper(schedule: &str) {
     auth.add_user("user", "pass").await.unwrap();
 
     let cfg_a: renews::config::Config = toml::from_str("addr=\":119\"\nsite_name='A'").unwrap();
-    let cfg_b: renews::config::Config = toml::from_str("addr=\":119\"").unwrap();
+    let cfg_b: renews::config::Config = toml::from_str("addr=\":119\"\nsite_name='B'").unwrap();
     let (addr_b, cert_b, handle_b) =
-        common::start_server(storage_b.clone(), auth.clone(), cfg_b.clone(), true).await;
+        common::start_server(storage_b.clone(), auth.clone(), cfg_b, true).await;
     let ca_file = NamedTempFile::new().unwrap();
     if let Some((_, pem)) = &cert_b {
         fs::write(ca_file.path(), pem).unwrap();
@@ -166,7 +166,7 @@ async fn peer_transfer_helper(schedule: &str) {
 
     let db = PeerDb::new("sqlite::memory:").await.unwrap();
     let peer_name = format!("localhost:{}", addr_b.port());
-    db.sync_config(&[peer_name.clone()]).await.unwrap();
+    db.sync_config(std::slice::from_ref(&peer_name)).await.unwrap();
     let peer = PeerConfig {
         sitename: peer_name.clone(),
         patterns: vec!["*".into()],
@@ -192,29 +192,22 @@ async fn peer_transfer_helper(schedule: &str) {
     tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
     handle_b.await.unwrap();
 
-    let (check_addr, check_cert, check_handle) =
-        common::start_server(storage_b.clone(), auth.clone(), cfg_b.clone(), true).await;
-    let check_cert = check_cert.unwrap().0;
-    ClientMock::new()
-        .expect_multi(
-            "ARTICLE <1@test>",
-            vec![
-                "220 0 <1@test> article follows",
-                "Message-ID: <1@test>",
-                "Newsgroups: misc.test",
-                "From: a@test",
-                "Subject: hello",
-                "Date: Wed, 05 Oct 2022 00:00:00 GMT",
-                "Path: A",
-                "",
-                "body",
-                ".",
-            ],
-        )
-        .run_tls_at(check_addr, check_cert)
-        .await;
-
-    check_handle.await.unwrap();
+    // The article arrives as posted on A, stamped by both sites
+    let article = storage_b
+        .get_article_by_id("<1@test>")
+        .await
+        .unwrap()
+        .unwrap();
+    let header = |name: &str| {
+        article
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    };
+    assert_eq!(header("Subject").as_deref(), Some("hello"));
+    assert_eq!(header("Path").as_deref(), Some("B!A"));
+    assert!(header("Injection-Info").unwrap().starts_with('A'));
 }
 
 #[tokio::test]
@@ -0,0 +1,483 @@
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+use crate::utils::{self, ClientMock};
+
+fn read_only_config() -> renews::config::Config {
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.posting_enabled = false;
+    cfg
+}
+
+#[tokio::test]
+async fn read_only_server_greets_with_201_and_hides_post() {
+    let (storage, auth) = utils::setup().await;
+    let cfg = Arc::new(RwLock::new(read_only_config()));
+    let (addr, handle) = utils::setup_server_with_cfg(storage, auth, cfg).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("201 "), "{line}");
+
+    writer.write_all(b"CAPABILITIES\r\n").await.unwrap();
+    let mut capabilities = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let cap = line.trim_end_matches(['\r', '\n']).to_string();
+        if cap == "." {
+            break;
+        }
+        capabilities.push(cap);
+    }
+    assert!(!capabilities.iter().any(|c| c == "POST"));
+    assert!(capabilities.iter().any(|c| c == "IHAVE"));
+
+    writer.write_all(b"MODE READER\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "201 Posting prohibited\r\n");
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    drop(writer);
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn read_only_server_refuses_post_but_accepts_ihave() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let article = "Message-ID: <transit@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: transit\r\n\r\nBody\r\n.";
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("POST", "440 posting not permitted")
+        .expect(
+            "IHAVE <transit@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(article),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(read_only_config(), storage.clone(), auth)
+        .await;
+    assert!(storage.article_exists("<transit@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn posting_can_be_reenabled_on_reload() {
+    let (storage, auth) = utils::setup().await;
+    let cfg = Arc::new(RwLock::new(read_only_config()));
+    let (addr, handle) = utils::setup_server_with_cfg(storage, auth, cfg.clone()).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+
+    writer.write_all(b"POST\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "440 posting not permitted\r\n");
+
+    cfg.write()
+        .await
+        .update_runtime(utils::create_insecure_posting_config());
+
+    // Posting is back, so the next refusal is for the missing login
+    writer.write_all(b"POST\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "480 authentication required\r\n");
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    drop(writer);
+    handle.await.unwrap();
+}
+
+/// Serve one connection with the client's address known, as the real
+/// listeners do, and POST `article` on it. Returns the reply to the article.
+async fn post_with_peer_ip(
+    storage: renews::storage::DynStorage,
+    cfg: renews::config::Config,
+    article: &str,
+) -> String {
+    let (_, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    let cfg = Arc::new(RwLock::new(cfg));
+    let queue =
+        utils::create_test_queue_with_workers(storage.clone(), auth.clone(), cfg.clone()).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (sock, peer) = listener.accept().await.unwrap();
+        renews::handle_client_with_cert(
+            sock,
+            storage,
+            auth,
+            cfg,
+            false,
+            None,
+            Some(peer.ip()),
+            queue,
+        )
+        .await
+        .unwrap();
+    });
+
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    for command in ["AUTHINFO USER user", "AUTHINFO PASS pass", "POST"] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+    }
+    assert!(line.starts_with("340"), "{line}");
+    writer
+        .write_all(format!("{article}\r\n.\r\n").as_bytes())
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    drop(writer);
+    server.await.unwrap();
+    line
+}
+
+async fn injection_info(storage: &renews::storage::DynStorage, id: &str) -> (String, String) {
+    for _ in 0..50 {
+        if let Some(article) = storage.get_article_by_id(id).await.unwrap() {
+            let header = |name: &str| {
+                article
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| v.clone())
+                    .unwrap()
+            };
+            return (header("Injection-Date"), header("Injection-Info"));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("{id} was not stored");
+}
+
+#[tokio::test]
+async fn posted_articles_get_injection_headers_with_a_hashed_host() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let article = "Message-ID: <injected@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: injected\r\n\r\nBody";
+    let reply = post_with_peer_ip(
+        storage.clone(),
+        utils::create_insecure_posting_config(),
+        article,
+    )
+    .await;
+    assert!(reply.starts_with("240"), "{reply}");
+
+    let (date, info) = injection_info(&storage, "<injected@test>").await;
+    assert!(
+        chrono::DateTime::parse_from_rfc2822(&date).is_ok(),
+        "{date}"
+    );
+    assert!(info.starts_with("test; posting-host=\""), "{info}");
+    assert!(info.contains("logging-data=\""), "{info}");
+    assert!(!info.contains("127.0.0.1"), "{info}");
+    assert!(!info.contains("\"user\""), "{info}");
+}
+
+#[tokio::test]
+async fn plain_posting_host_shows_the_client_address() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.injection.posting_host = renews::config::PostingHost::Plain;
+    let article = "Message-ID: <plain@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: plain\r\n\r\nBody";
+    let reply = post_with_peer_ip(storage.clone(), cfg, article).await;
+    assert!(reply.starts_with("240"), "{reply}");
+
+    let (_, info) = injection_info(&storage, "<plain@test>").await;
+    assert_eq!(
+        info,
+        "test; posting-host=\"127.0.0.1\"; logging-data=\"user\""
+    );
+}
+
+#[tokio::test]
+async fn client_supplied_injection_headers_are_refused() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    for (id, header) in [
+        (
+            "<forged-date@test>",
+            "Injection-Date: Wed, 05 Oct 2022 00:00:00 GMT",
+        ),
+        ("<forged-info@test>", "Injection-Info: elsewhere.example"),
+    ] {
+        let article = format!(
+            "Message-ID: {id}\r\n{header}\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: forged\r\n\r\nBody"
+        );
+        let reply = post_with_peer_ip(
+            storage.clone(),
+            utils::create_insecure_posting_config(),
+            &article,
+        )
+        .await;
+        let name = header.split(':').next().unwrap();
+        assert_eq!(reply, format!("441 {name} header not allowed in posts\r\n"));
+        assert!(!storage.article_exists(id).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn malformed_headers_are_named_in_the_reply() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let article = "Message-ID: <no-at-sign>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: bad id\r\n\r\nBody";
+    let reply = post_with_peer_ip(
+        storage.clone(),
+        utils::create_insecure_posting_config(),
+        article,
+    )
+    .await;
+    assert_eq!(reply, "441 malformed Message-ID <no-at-sign>\r\n");
+}
+
+#[tokio::test]
+async fn strict_distributions_refuse_unknown_keywords() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.distributions = toml::from_str("strict = true\n[[keywords]]\nname = \"local\"\n").unwrap();
+    let post = |id: &str, distribution: &str| {
+        format!(
+            "Message-ID: {id}\r\nDistribution: {distribution}\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: dist\r\n\r\nBody"
+        )
+    };
+
+    let reply = post_with_peer_ip(
+        storage.clone(),
+        cfg.clone(),
+        &post("<d1@test>", "local, mars"),
+    )
+    .await;
+    assert_eq!(reply, "441 unknown distribution mars\r\n");
+    let reply = post_with_peer_ip(storage.clone(), cfg.clone(), &post("<d2@test>", "LOCAL")).await;
+    assert!(reply.starts_with("240"), "{reply}");
+
+    // Without strict any keyword is taken
+    cfg.distributions.strict = false;
+    let reply = post_with_peer_ip(storage.clone(), cfg, &post("<d3@test>", "mars")).await;
+    assert!(reply.starts_with("240"), "{reply}");
+}
+
+#[tokio::test]
+async fn random_message_ids_are_named_in_the_reply_and_stored() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.message_id_generation = renews::config::MessageIdGeneration::Random;
+    let article = "Newsgroups: misc.test\r\nFrom: a@test\r\nSubject: no id\r\n\r\nBody";
+    let reply = post_with_peer_ip(storage.clone(), cfg, article).await;
+
+    let id = reply
+        .trim_end()
+        .strip_prefix("240 article received ")
+        .unwrap_or_else(|| panic!("{reply}"));
+    assert!(id.starts_with('<') && id.ends_with("@test>"), "{id}");
+    for _ in 0..50 {
+        if storage.article_exists(id).await.unwrap() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("{id} was not stored");
+}
+
+#[tokio::test]
+async fn posts_without_a_message_id_can_be_refused() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.message_id_generation = renews::config::MessageIdGeneration::Refuse;
+    let article = "Newsgroups: misc.test\r\nFrom: a@test\r\nSubject: no id\r\n\r\nBody";
+    let reply = post_with_peer_ip(storage, cfg, article).await;
+    assert_eq!(reply, "441 posting failed\r\n");
+}
+
+#[tokio::test]
+async fn crossposts_over_the_limit_are_refused_with_the_limit() {
+    let (storage, _) = utils::setup().await;
+    for group in ["misc.a", "misc.b", "misc.c"] {
+        storage.add_group(group, false).await.unwrap();
+    }
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.max_crosspost_groups = Some(2);
+    let article = "Message-ID: <wide@test>\r\nNewsgroups: misc.a,misc.b,misc.c\r\nFrom: a@test\r\nSubject: wide\r\n\r\nBody";
+    let reply = post_with_peer_ip(storage.clone(), cfg, article).await;
+    assert_eq!(reply, "441 crossposted to 3 groups; the limit is 2\r\n");
+    assert!(!storage.article_exists("<wide@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn copies_of_one_body_past_the_limit_are_refused() {
+    let (storage, _) = utils::setup().await;
+    for group in ["misc.a", "misc.b"] {
+        storage.add_group(group, false).await.unwrap();
+    }
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.spam.max_duplicates = Some(2);
+    // A crosspost is one copy however many groups it reaches
+    for (id, groups) in [
+        ("<flood1@test>", "misc.a,misc.b"),
+        ("<flood2@test>", "misc.a"),
+    ] {
+        let article = format!(
+            "Message-ID: {id}\r\nNewsgroups: {groups}\r\nFrom: a@test\r\nSubject: flood\r\n\r\nSame old text"
+        );
+        let reply = post_with_peer_ip(storage.clone(), cfg.clone(), &article).await;
+        assert!(reply.starts_with("240"), "{reply}");
+    }
+    let article = "Message-ID: <flood3@test>\r\nNewsgroups: misc.b\r\nFrom: a@test\r\nSubject: flood\r\n\r\nSame  old\r\ntext";
+    let reply = post_with_peer_ip(storage.clone(), cfg.clone(), article).await;
+    assert_eq!(
+        reply,
+        "441 the same text was already posted 2 times in the last 60 minutes\r\n"
+    );
+    assert!(!storage.article_exists("<flood3@test>").await.unwrap());
+
+    let article = "Message-ID: <fresh@test>\r\nNewsgroups: misc.b\r\nFrom: a@test\r\nSubject: new\r\n\r\nSomething else";
+    let reply = post_with_peer_ip(storage, cfg, article).await;
+    assert!(reply.starts_with("240"), "{reply}");
+}
+
+#[tokio::test]
+async fn binary_bodies_are_stored_byte_for_byte() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("alt.binaries.test", false).await.unwrap();
+    let cfg = utils::create_insecure_posting_config();
+    let article = "Message-ID: <yenc@test>\r\nNewsgroups: alt.binaries.test\r\nFrom: a@test\r\nSubject: a.bin\r\n\r\n=ybegin line=128 size=5 name=a.bin\r\n\0\u{1}\u{ff}\u{e9}*\r\n=yend size=5";
+    let reply = post_with_peer_ip(storage.clone(), cfg, article).await;
+    assert!(reply.starts_with("240"), "{reply}");
+
+    // The server reads the article up to the line ending before the dot
+    let sent = format!("{article}\r\n");
+    let (_, sent) = renews::parse_message(&sent).unwrap();
+    for _ in 0..50 {
+        if let Some(stored) = storage.get_article_by_id("<yenc@test>").await.unwrap() {
+            assert_eq!(stored.body, sent.body);
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("<yenc@test> was not stored");
+}
+
+#[tokio::test]
+async fn eight_bit_body_is_served_back_unchanged() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("alt.binaries.test", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let cfg = Arc::new(RwLock::new(utils::create_insecure_posting_config()));
+    let (addr, handle) = utils::setup_server_with_cfg(storage.clone(), auth, cfg).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).await.unwrap();
+    for command in ["AUTHINFO USER user", "AUTHINFO PASS pass", "POST"] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_until(b'\n', &mut line).await.unwrap();
+    }
+    assert!(line.starts_with(b"340"));
+    // A literal 0xFF octet, which is never valid UTF-8
+    let body = b"raw \xff octet\r\n";
+    writer
+        .write_all(b"Message-ID: <ff@test>\r\nNewsgroups: alt.binaries.test\r\nFrom: a@test\r\nSubject: ff\r\n\r\n")
+        .await
+        .unwrap();
+    writer.write_all(body).await.unwrap();
+    writer.write_all(b".\r\n").await.unwrap();
+    line.clear();
+    reader.read_until(b'\n', &mut line).await.unwrap();
+    assert!(
+        line.starts_with(b"240"),
+        "{}",
+        String::from_utf8_lossy(&line)
+    );
+
+    let mut stored = None;
+    for _ in 0..50 {
+        stored = storage.get_article_by_id("<ff@test>").await.unwrap();
+        if stored.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(stored.expect("<ff@test> was not stored").body, body);
+
+    writer.write_all(b"BODY <ff@test>\r\n").await.unwrap();
+    line.clear();
+    reader.read_until(b'\n', &mut line).await.unwrap();
+    assert!(
+        line.starts_with(b"222"),
+        "{}",
+        String::from_utf8_lossy(&line)
+    );
+    let mut served = Vec::new();
+    loop {
+        line.clear();
+        reader.read_until(b'\n', &mut line).await.unwrap();
+        if line == b".\r\n" {
+            break;
+        }
+        served.extend_from_slice(&line);
+    }
+    assert_eq!(served, body);
+
+    writer.write_all(b"QUIT\r\n").await.unwrap();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn image_attachments_are_refused_in_text_only_groups() {
+    let (storage, _) = utils::setup().await;
+    for group in ["comp.text", "misc.pictures"] {
+        storage.add_group(group, false).await.unwrap();
+    }
+    let mut cfg = utils::create_insecure_posting_config();
+    cfg.group_settings = toml::from_str::<renews::config::Config>(
+        "addr = \":119\"\n[[group_settings]]\npattern = \"comp.*\"\nno_binaries = true\n",
+    )
+    .unwrap()
+    .group_settings;
+    let article = |id: &str, group: &str| {
+        format!(
+            "Message-ID: {id}\r\nNewsgroups: {group}\r\nFrom: a@test\r\nSubject: photo\r\nMIME-Version: 1.0\r\nContent-Type: multipart/mixed; boundary=\"b1\"\r\n\r\n--b1\r\nContent-Type: text/plain\r\n\r\nMy cat.\r\n--b1\r\nContent-Type: image/jpeg\r\nContent-Transfer-Encoding: base64\r\n\r\n/9j/4AAQSkZJRgABAQ==\r\n--b1--"
+        )
+    };
+
+    let reply = post_with_peer_ip(
+        storage.clone(),
+        cfg.clone(),
+        &article("<cat1@test>", "comp.text"),
+    )
+    .await;
+    assert_eq!(
+        reply,
+        "441 base64 encoded image/jpeg part not allowed in comp.text\r\n"
+    );
+
+    let reply = post_with_peer_ip(storage, cfg, &article("<cat2@test>", "misc.pictures")).await;
+    assert!(reply.starts_with("240"), "{reply}");
+}
@@ -0,0 +1,171 @@
+use renews::config::RateLimitConfig;
+
+use crate::utils::{self, ClientMock};
+
+fn article(id: &str) -> String {
+    format!(
+        "Message-ID: <{id}@test>\r\nNewsgroups: misc\r\nFrom: user@example.com\r\nSubject: {id}\r\n\r\nBody\r\n."
+    )
+}
+
+#[tokio::test]
+async fn post_is_limited_per_user() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.rate_limit = RateLimitConfig {
+        articles_per_minute: Some(1),
+        bytes_per_minute: None,
+        posts_per_hour: None,
+    };
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(&article("one"), "240 article received")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect(
+            &article("two"),
+            "441 posting rate exceeded; retry after 60 seconds",
+        )
+        .run_with_cfg_tls(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn ihave_and_takethis_ask_peers_to_retry_later() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("peer", "pass").await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.rate_limit = RateLimitConfig {
+        articles_per_minute: None,
+        bytes_per_minute: Some(10),
+        posts_per_hour: None,
+    };
+    ClientMock::new()
+        .expect("AUTHINFO USER peer", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("IHAVE <one@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(&article("one"), "235 Article transferred OK")
+        .expect(
+            "IHAVE <two@test>",
+            "436 transfer not possible; try again later",
+        )
+        .expect("CHECK <two@test>", "431 <two@test>")
+        .expect(
+            &format!("TAKETHIS <two@test>\r\n{}", article("two")),
+            "431 <two@test>",
+        )
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn unlimited_without_configured_rates() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let mut mock = ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted");
+    for i in 0..5 {
+        mock = mock
+            .expect(
+                "POST",
+                "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+            )
+            .expect(&article(&format!("free{i}")), "240 article received");
+    }
+    mock.run_with_cfg_tls(utils::create_minimal_config(), storage, auth)
+        .await;
+}
+
+#[tokio::test]
+async fn post_beyond_hourly_limit_is_refused_with_retry_hint() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.rate_limit.posts_per_hour = Some(3);
+    let mut mock = ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted");
+    for i in 0..3 {
+        mock = mock
+            .expect(
+                "POST",
+                "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+            )
+            .expect(&article(&format!("hourly{i}")), "240 article received");
+    }
+    // The fourth waits for a third of an hour's worth of tokens
+    mock.expect(
+        "POST",
+        "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+    )
+    .expect(
+        &article("hourly3"),
+        "441 posting rate exceeded; retry after 1200 seconds",
+    )
+    .run_with_cfg_tls(cfg, storage, auth)
+    .await;
+}
+
+#[tokio::test]
+async fn refused_posts_do_not_use_up_the_limit() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.rate_limit.posts_per_hour = Some(1);
+    let mut mock = ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted");
+    for i in 0..2 {
+        mock = mock
+            .expect(
+                "POST",
+                "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+            )
+            .expect(
+                &article(&format!("refused{i}")).replace("Newsgroups: misc", "Newsgroups: nowhere"),
+                "441 posting failed",
+            );
+    }
+    mock.expect(
+        "POST",
+        "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+    )
+    .expect(&article("accepted"), "240 article received")
+    .run_with_cfg_tls(cfg, storage, auth)
+    .await;
+}
+
+#[tokio::test]
+async fn configured_peers_are_not_limited() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("peer", "pass").await.unwrap();
+    let mut cfg = utils::create_minimal_config();
+    cfg.rate_limit.bytes_per_minute = Some(10);
+    cfg.peers
+        .push(toml::from_str("sitename = \"peer\"\npatterns = [\"*\"]").unwrap());
+    ClientMock::new()
+        .expect("AUTHINFO USER peer", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("IHAVE <one@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(&article("one"), "235 Article transferred OK")
+        .expect("IHAVE <two@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(&article("two"), "235 Article transferred OK")
+        .expect("CHECK <three@test>", "238 <three@test>")
+        .run_with_cfg(cfg, storage, auth)
+        .await;
+}
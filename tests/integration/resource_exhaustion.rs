@@ -20,11 +20,12 @@ async fn test_queue_capacity_exhaustion() {
                 ("Subject".to_string(), "Test 1".to_string()),
                 ("Message-ID".to_string(), "<test1@example.com>".to_string()),
             ],
-            body: "Test body 1".to_string(),
+            body: "Test body 1".into(),
         },
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     let article2 = QueuedArticle {
@@ -34,11 +35,12 @@ async fn test_queue_capacity_exhaustion() {
                 ("Subject".to_string(), "Test 2".to_string()),
                 ("Message-ID".to_string(), "<test2@example.com>".to_string()),
             ],
-            body: "Test body 2".to_string(),
+            body: "Test body 2".into(),
         },
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     let article3 = QueuedArticle {
@@ -48,11 +50,12 @@ async fn test_queue_capacity_exhaustion() {
                 ("Subject".to_string(), "Test 3".to_string()),
                 ("Message-ID".to_string(), "<test3@example.com>".to_string()),
             ],
-            body: "Test body 3".to_string(),
+            body: "Test body 3".into(),
         },
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     // Fill the queue to capacity
@@ -95,11 +98,12 @@ async fn test_small_capacity_queue_exhaustion() {
                 ("Subject".to_string(), "Test 1".to_string()),
                 ("Message-ID".to_string(), "<test1@example.com>".to_string()),
             ],
-            body: "Test body 1".to_string(),
+            body: "Test body 1".into(),
         },
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     let article2 = QueuedArticle {
@@ -109,11 +113,12 @@ async fn test_small_capacity_queue_exhaustion() {
                 ("Subject".to_string(), "Test 2".to_string()),
                 ("Message-ID".to_string(), "<test2@example.com>".to_string()),
             ],
-            body: "Test body 2".to_string(),
+            body: "Test body 2".into(),
         },
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     // First article should succeed
@@ -224,11 +229,12 @@ async fn test_concurrent_queue_access() {
                         ("Subject".to_string(), format!("Test {i}")),
                         ("Message-ID".to_string(), format!("<test{i}@example.com>")),
                     ],
-                    body: format!("Test body {i}"),
+                    body: format!("Test body {i}").into_bytes(),
                 },
                 size: 100,
                 is_control: false,
                 already_validated: false,
+                ack: None,
             };
 
             queue_clone.submit(article).await
@@ -61,3 +61,110 @@ retention_days = 10
             .is_none()
     );
 }
+
+#[tokio::test]
+async fn cleanup_uses_sooner_of_retention_and_expires() {
+    use chrono::Duration as ChronoDuration;
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+[[group_settings]]
+group = "short"
+retention = "1s"
+[[group_settings]]
+group = "long"
+retention = "2w"
+"#,
+    )
+    .unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    storage.add_group("short", false).await.unwrap();
+    storage.add_group("long", false).await.unwrap();
+    let tomorrow = (chrono::Utc::now() + ChronoDuration::days(1)).to_rfc2822();
+    let text =
+        format!("Message-ID: <short@test>\r\nNewsgroups: short\r\nExpires: {tomorrow}\r\n\r\nB");
+    let (_, msg) = parse_message(&text).unwrap();
+    storage.store_article(&msg).await.unwrap();
+    let text =
+        format!("Message-ID: <long@test>\r\nNewsgroups: long\r\nExpires: {tomorrow}\r\n\r\nB");
+    let (_, msg) = parse_message(&text).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    sleep(StdDuration::from_secs(2)).await;
+    cleanup_expired_articles(&*storage, &cfg).await.unwrap();
+
+    // Retention elapsed before the Expires date
+    assert!(!storage.article_exists("<short@test>").await.unwrap());
+    // Neither deadline has passed yet
+    assert!(storage.article_exists("<long@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn cleanup_keeps_newest_articles_and_raises_low_mark() {
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+[[group_settings]]
+pattern = "misc*"
+max_articles = 5
+[[group_settings]]
+group = "misc"
+max_articles = 2
+"#,
+    )
+    .unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    storage.add_group("misc", false).await.unwrap();
+    storage.add_group("other", false).await.unwrap();
+    for i in 1..=4 {
+        let text = format!("Message-ID: <{i}@test>\r\nNewsgroups: misc,other\r\n\r\nB");
+        let (_, msg) = parse_message(&text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+
+    cleanup_expired_articles(&*storage, &cfg).await.unwrap();
+
+    // The stricter of the two matching limits applies
+    assert_eq!(storage.group_counts("misc").await.unwrap(), (2, 3, 4));
+    assert_eq!(storage.group_counts("other").await.unwrap(), (4, 1, 4));
+    assert!(
+        storage
+            .get_article_by_number("misc", 2)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    // Still crossposted to an unlimited group, so the message survives
+    assert!(storage.article_exists("<1@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn cleanup_with_tiny_window_empties_group() {
+    let cfg: Config = toml::from_str(
+        r#"
+addr = ":119"
+retention_interval = "1s"
+[[group_settings]]
+group = "misc"
+retention = "1s"
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        cfg.retention_interval_or_default(),
+        StdDuration::from_secs(1)
+    );
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    storage.add_group("misc", false).await.unwrap();
+    let (_, msg) = parse_message("Message-ID: <old@test>\r\nNewsgroups: misc\r\n\r\nB").unwrap();
+    storage.store_article(&msg).await.unwrap();
+    sleep(StdDuration::from_secs(2)).await;
+    let (_, msg) = parse_message("Message-ID: <new@test>\r\nNewsgroups: misc\r\n\r\nB").unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    cleanup_expired_articles(&*storage, &cfg).await.unwrap();
+
+    assert!(!storage.article_exists("<old@test>").await.unwrap());
+    assert!(storage.article_exists("<new@test>").await.unwrap());
+    assert_eq!(storage.group_counts("misc").await.unwrap(), (1, 2, 2));
+}
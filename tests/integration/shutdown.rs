@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use renews::queue::ArticleQueue;
+use renews::shutdown::shut_down;
+use renews::storage::DynStorage;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+use crate::utils;
+
+/// Serve one connection through `queue`, returning the address to connect
+/// to and the session task.
+async fn serve(
+    storage: DynStorage,
+    cfg: renews::config::Config,
+) -> (
+    std::net::SocketAddr,
+    ArticleQueue,
+    tokio::task::JoinHandle<()>,
+) {
+    let (_, auth) = utils::setup().await;
+    auth.add_user("user", "pass").await.unwrap();
+    let cfg = Arc::new(RwLock::new(cfg));
+    let queue =
+        utils::create_test_queue_with_workers(storage.clone(), auth.clone(), cfg.clone()).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let session_queue = queue.clone();
+    let session = tokio::spawn(async move {
+        let (sock, _) = listener.accept().await.unwrap();
+        renews::handle_client(sock, storage, auth, cfg, false, session_queue)
+            .await
+            .unwrap();
+    });
+    (addr, queue, session)
+}
+
+#[tokio::test]
+async fn idle_sessions_are_told_the_service_is_discontinued() {
+    let (storage, _) = utils::setup().await;
+    let (addr, queue, session) = serve(storage, utils::create_minimal_config()).await;
+    let (mut reader, _writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+
+    shut_down(&queue, Duration::from_secs(5), Duration::from_secs(5)).await;
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "400 service discontinued\r\n");
+    session.await.unwrap();
+    assert_eq!(queue.shutdown().active_sessions(), 0);
+}
+
+#[tokio::test]
+async fn article_posted_just_before_shutdown_is_stored() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (addr, queue, session) =
+        serve(storage.clone(), utils::create_insecure_posting_config()).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    for command in ["AUTHINFO USER user", "AUTHINFO PASS pass", "POST"] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+    }
+    assert!(line.starts_with("340"), "{line}");
+    writer
+        .write_all(
+            b"Message-ID: <last@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody\r\n.\r\n",
+        )
+        .await
+        .unwrap();
+
+    // Shut down without waiting for the reply
+    shut_down(&queue, Duration::from_secs(5), Duration::from_secs(5)).await;
+    assert!(
+        storage
+            .get_article_by_id("<last@test>")
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("240"), "{line}");
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "400 service discontinued\r\n");
+    session.await.unwrap();
+}
+
+#[tokio::test]
+async fn closed_queue_refuses_submissions() {
+    let queue = utils::create_test_queue();
+    queue.close_and_drain().await;
+    assert!(queue.is_closed());
+    let (_, message) = renews::parse_message("Message-ID: <late@test>\r\n\r\nBody").unwrap();
+    let article = renews::queue::QueuedArticle {
+        message,
+        size: 4,
+        is_control: false,
+        already_validated: true,
+        ack: None,
+    };
+    assert!(queue.try_submit(article.clone()).is_err());
+    assert!(queue.submit(article).await.is_err());
+    assert!(queue.is_empty());
+}
+
+#[tokio::test]
+async fn submissions_after_shutdown_starts_get_400() {
+    let (storage, _) = utils::setup().await;
+    storage.add_group("misc.test", false).await.unwrap();
+    let (addr, queue, session) =
+        serve(storage.clone(), utils::create_insecure_posting_config()).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    for command in ["AUTHINFO USER user", "AUTHINFO PASS pass", "POST"] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+    }
+    assert!(line.starts_with("340"), "{line}");
+
+    // The article in progress is still taken, the next POST is not
+    queue.shutdown().trigger();
+    writer
+        .write_all(
+            b"Message-ID: <during@test>\r\nNewsgroups: misc.test\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody\r\n.\r\nPOST\r\n",
+        )
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("240"), "{line}");
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "400 service discontinued\r\n");
+    session.await.unwrap();
+}
+
+#[tokio::test]
+async fn queued_articles_are_abandoned_after_the_drain_timeout() {
+    // No workers, so the queued article is never finished
+    let queue = utils::create_test_queue();
+    let (_, message) = renews::parse_message("Message-ID: <stuck@test>\r\n\r\nBody").unwrap();
+    queue
+        .try_submit(renews::queue::QueuedArticle {
+            message,
+            size: 4,
+            is_control: false,
+            already_validated: true,
+            ack: None,
+        })
+        .unwrap();
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        shut_down(&queue, Duration::from_secs(1), Duration::from_millis(50)),
+    )
+    .await
+    .expect("shutdown waits no longer than the drain timeout");
+    assert!(queue.is_closed());
+    assert_eq!(queue.in_flight(), 1);
+}
@@ -1,7 +1,8 @@
 use futures_util::StreamExt;
 use renews::{
+    config::MirrorFailurePolicy,
     parse_message,
-    storage::{Storage, sqlite::SqliteStorage},
+    storage::{Direction, HistoryStatus, Storage, mirror::MirrorStorage, sqlite::SqliteStorage},
 };
 
 #[tokio::test]
@@ -15,7 +16,7 @@ async fn store_and_retrieve_article() {
         .await
         .unwrap()
         .expect("article by number");
-    assert_eq!(fetched.body, "Body");
+    assert_eq!(fetched.body, b"Body");
     let fetched_id = storage
         .get_article_by_id("<1@test>")
         .await
@@ -69,8 +70,8 @@ async fn numbering_is_per_group() {
         .await
         .unwrap()
         .unwrap();
-    assert_eq!(g1_msg1.body, "A");
-    assert_eq!(g2_msg1.body, "A");
+    assert_eq!(g1_msg1.body, b"A");
+    assert_eq!(g2_msg1.body, b"A");
 
     // Verify msg2 is at position 2 in g1
     let g1_msg2 = storage
@@ -78,7 +79,7 @@ async fn numbering_is_per_group() {
         .await
         .unwrap()
         .unwrap();
-    assert_eq!(g1_msg2.body, "B");
+    assert_eq!(g1_msg2.body, b"B");
 }
 
 #[tokio::test]
@@ -183,9 +184,9 @@ async fn store_article_in_multiple_groups() {
         .unwrap()
         .expect("article in group3");
 
-    assert_eq!(article1.body, "Body");
-    assert_eq!(article2.body, "Body");
-    assert_eq!(article3.body, "Body");
+    assert_eq!(article1.body, b"Body");
+    assert_eq!(article2.body, b"Body");
+    assert_eq!(article3.body, b"Body");
 
     // Verify they're the same message by checking Message-ID
     let msg_id1 = article1
@@ -257,7 +258,7 @@ async fn store_article_multiple_groups_comma_separated() {
             .await
             .unwrap()
             .unwrap_or_else(|| panic!("article in {group}"));
-        assert_eq!(article.body, "Body content");
+        assert_eq!(article.body, b"Body content");
 
         // Verify the Message-ID is consistent
         let msg_id = article
@@ -280,3 +281,521 @@ async fn store_article_multiple_groups_comma_separated() {
         assert_eq!(newsgroups, "group1,group2,group3");
     }
 }
+
+#[tokio::test]
+async fn overview_range_and_delete() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    let (_, m1) = parse_message(
+        "Message-ID: <1@test>\r\nNewsgroups: g1\r\nSubject: One\r\nFrom: a@test\r\n\r\nA",
+    )
+    .unwrap();
+    let (_, m2) = parse_message(
+        "Message-ID: <2@test>\r\nNewsgroups: g1\r\nSubject: Two\r\nReferences: <1@test>\r\n\r\nB\r\nC",
+    )
+    .unwrap();
+    storage.store_article(&m1).await.unwrap();
+    storage.store_article(&m2).await.unwrap();
+
+    let lines = storage.get_overview("g1", 1..=u64::MAX).await.unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].number, 1);
+    assert_eq!(lines[0].subject, "One");
+    assert_eq!(lines[0].from, "a@test");
+    assert_eq!(lines[1].message_id, "<2@test>");
+    assert_eq!(lines[1].references, "<1@test>");
+    assert_eq!(lines[1].lines, 2);
+
+    storage.delete_article_by_id("<1@test>").await.unwrap();
+    let lines = storage.get_overview("g1", 1..=2).await.unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].number, 2);
+}
+
+#[tokio::test]
+async fn reports_backend_matching_uri_scheme() {
+    let uri = "sqlite::memory:";
+    let storage = renews::storage::open(uri).await.unwrap();
+    let scheme = uri.split(':').next().unwrap();
+    assert_eq!(storage.backend_name(), scheme);
+    assert!(storage.schema_version().await.unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn group_counts_report_count_and_watermarks() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    storage.add_group("g1", false).await.unwrap();
+    assert_eq!(storage.group_counts("g1").await.unwrap(), (0, 1, 0));
+
+    for id in 1..=3 {
+        let text = format!("Message-ID: <{id}@test>\r\nNewsgroups: g1\r\n\r\nBody");
+        let (_, msg) = parse_message(&text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<1@test>").await.unwrap();
+    assert_eq!(storage.group_counts("g1").await.unwrap(), (2, 2, 3));
+}
+
+#[tokio::test]
+async fn history_remembers_stored_cancelled_and_expired_ids() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    storage.add_group("g1", false).await.unwrap();
+    assert_eq!(storage.history_status("<1@test>").await.unwrap(), None);
+
+    let (_, kept) = parse_message("Message-ID: <1@test>\r\nNewsgroups: g1\r\n\r\nBody").unwrap();
+    let (_, expiring) = parse_message(
+        "Message-ID: <2@test>\r\nNewsgroups: g1\r\nExpires: Thu, 01 Jan 2015 00:00:00 +0000\r\n\r\nBody",
+    )
+    .unwrap();
+    storage.store_article(&kept).await.unwrap();
+    storage.store_article(&expiring).await.unwrap();
+    assert_eq!(
+        storage.history_status("<1@test>").await.unwrap(),
+        Some(HistoryStatus::Stored)
+    );
+
+    storage.delete_article_by_id("<1@test>").await.unwrap();
+    storage.purge_expired(chrono::Utc::now()).await.unwrap();
+    assert_eq!(
+        storage.history_status("<1@test>").await.unwrap(),
+        Some(HistoryStatus::Cancelled)
+    );
+    assert_eq!(
+        storage.history_status("<2@test>").await.unwrap(),
+        Some(HistoryStatus::Expired)
+    );
+    assert!(storage.message_seen("<1@test>").await.unwrap());
+    assert!(!storage.message_seen("<3@test>").await.unwrap());
+
+    storage
+        .purge_history_before(chrono::Utc::now() - chrono::Duration::days(1))
+        .await
+        .unwrap();
+    assert!(storage.message_seen("<2@test>").await.unwrap());
+    storage
+        .purge_history_before(chrono::Utc::now() + chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(!storage.message_seen("<1@test>").await.unwrap());
+    assert!(!storage.message_seen("<2@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn group_descriptions_stream_filtered_by_wildmat() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    storage.add_group("alt.test", false).await.unwrap();
+    storage.add_group("comp.lang.rust", false).await.unwrap();
+    storage.add_group("comp.lang.c", false).await.unwrap();
+    storage
+        .set_group_description("comp.lang.rust", "The Rust language")
+        .await
+        .unwrap();
+
+    let mut all = Vec::new();
+    let mut stream = storage.list_group_descriptions(None);
+    while let Some(result) = stream.next().await {
+        all.push(result.unwrap());
+    }
+    assert_eq!(
+        all,
+        vec![
+            ("alt.test".to_string(), String::new()),
+            ("comp.lang.c".to_string(), String::new()),
+            (
+                "comp.lang.rust".to_string(),
+                "The Rust language".to_string()
+            ),
+        ]
+    );
+
+    let mut matched = Vec::new();
+    let mut stream = storage.list_group_descriptions(Some("comp.*,!comp.lang.c"));
+    while let Some(result) = stream.next().await {
+        matched.push(result.unwrap().0);
+    }
+    assert_eq!(matched, vec!["comp.lang.rust".to_string()]);
+}
+
+#[tokio::test]
+async fn article_exists_without_loading_message() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    storage.add_group("g1", false).await.unwrap();
+    let (_, msg) = parse_message("Message-ID: <e@test>\r\nNewsgroups: g1\r\n\r\nBody").unwrap();
+    assert!(!storage.article_exists("<e@test>").await.unwrap());
+    storage.store_article(&msg).await.unwrap();
+    assert!(storage.article_exists("<e@test>").await.unwrap());
+    storage.delete_article_by_id("<e@test>").await.unwrap();
+    assert!(!storage.article_exists("<e@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn purge_expired_uses_stored_expires_header() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    storage.add_group("g1", false).await.unwrap();
+    let now = chrono::Utc::now();
+    let past = (now - chrono::Duration::hours(1)).to_rfc2822();
+    let future = (now + chrono::Duration::days(1)).to_rfc2822();
+    for (id, expires) in [("<past@test>", Some(past)), ("<future@test>", Some(future))]
+        .into_iter()
+        .chain([("<none@test>", None)])
+    {
+        let expires = expires
+            .map(|e| format!("Expires: {e}\r\n"))
+            .unwrap_or_default();
+        let text = format!("Message-ID: {id}\r\nNewsgroups: g1\r\n{expires}\r\nBody");
+        let (_, msg) = parse_message(&text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+
+    storage.purge_expired(now).await.unwrap();
+
+    assert!(!storage.article_exists("<past@test>").await.unwrap());
+    assert!(storage.article_exists("<future@test>").await.unwrap());
+    assert!(storage.article_exists("<none@test>").await.unwrap());
+    let numbers: Vec<u64> = storage
+        .get_overview_range("g1", 1, 3)
+        .map(|line| line.unwrap().number)
+        .collect()
+        .await;
+    assert_eq!(numbers, vec![2, 3]);
+}
+
+async fn broken_secondary() -> (tempfile::NamedTempFile, std::sync::Arc<dyn Storage>) {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let uri = format!("sqlite://{}", file.path().display());
+    let storage = SqliteStorage::new(&uri).await.unwrap();
+    // Drop a table behind the backend's back so every article write fails
+    let pool = sqlx::SqlitePool::connect(&uri).await.unwrap();
+    sqlx::query("DROP TABLE messages")
+        .execute(&pool)
+        .await
+        .unwrap();
+    (file, std::sync::Arc::new(storage))
+}
+
+#[tokio::test]
+async fn mirror_writes_to_both_backends() {
+    let primary: std::sync::Arc<dyn Storage> =
+        std::sync::Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let secondary: std::sync::Arc<dyn Storage> =
+        std::sync::Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let mirror = MirrorStorage::new(
+        primary.clone(),
+        secondary.clone(),
+        MirrorFailurePolicy::Warn,
+    );
+
+    mirror.add_group("g1", false).await.unwrap();
+    let (_, msg) = parse_message("Message-ID: <m@test>\r\nNewsgroups: g1\r\n\r\nBody").unwrap();
+    mirror.store_article(&msg).await.unwrap();
+
+    assert!(primary.article_exists("<m@test>").await.unwrap());
+    assert!(secondary.article_exists("<m@test>").await.unwrap());
+    assert!(secondary.group_exists("g1").await.unwrap());
+}
+
+#[tokio::test]
+async fn mirror_secondary_failure_follows_policy() {
+    let (_file, secondary) = broken_secondary().await;
+    let primary: std::sync::Arc<dyn Storage> =
+        std::sync::Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let (_, msg) = parse_message("Message-ID: <w@test>\r\nNewsgroups: g1\r\n\r\nBody").unwrap();
+
+    let best_effort = MirrorStorage::new(
+        primary.clone(),
+        secondary.clone(),
+        MirrorFailurePolicy::Warn,
+    );
+    best_effort.store_article(&msg).await.unwrap();
+    assert!(primary.article_exists("<w@test>").await.unwrap());
+
+    let strict = MirrorStorage::new(primary.clone(), secondary, MirrorFailurePolicy::Fail);
+    let (_, msg) = parse_message("Message-ID: <f@test>\r\nNewsgroups: g1\r\n\r\nBody").unwrap();
+    assert!(strict.store_article(&msg).await.is_err());
+    // The primary write still happened before the secondary failed
+    assert!(primary.article_exists("<f@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn header_range_uses_overview_and_falls_back_to_headers() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    let articles = [
+        "Message-ID: <1@test>\r\nNewsgroups: g1\r\nSubject: First\r\nX-Tag: one\r\n\r\nA",
+        "Message-ID: <2@test>\r\nNewsgroups: g1\r\n\r\nB\r\nC",
+        "Message-ID: <3@test>\r\nNewsgroups: g1\r\nSubject: Third\r\nX-Tag: three\r\n\r\nD",
+    ];
+    for text in articles {
+        let (_, msg) = parse_message(text).unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+
+    let subjects = storage
+        .get_header_range("g1", "subject", 1..=3)
+        .await
+        .unwrap();
+    assert_eq!(
+        subjects,
+        vec![
+            (1, "First".to_string()),
+            (2, String::new()),
+            (3, "Third".to_string()),
+        ]
+    );
+
+    let tags = storage
+        .get_header_range("g1", "X-Tag", 2..=3)
+        .await
+        .unwrap();
+    assert_eq!(tags, vec![(2, String::new()), (3, "three".to_string())]);
+
+    let lines = storage
+        .get_header_range("g1", ":lines", 2..=2)
+        .await
+        .unwrap();
+    assert_eq!(lines, vec![(2, "2".to_string())]);
+
+    assert!(
+        storage
+            .get_header_range("g1", "Subject", 4..=9)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn next_article_number_skips_gaps() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    for i in 1..=4 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{i}@test>\r\nNewsgroups: g1\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<2@test>").await.unwrap();
+    storage.delete_article_by_id("<3@test>").await.unwrap();
+
+    let next = |n| storage.next_article_number("g1", n, Direction::Next);
+    assert_eq!(next(1).await.unwrap(), Some(4));
+    assert_eq!(next(4).await.unwrap(), None);
+    let prev = |n| storage.next_article_number("g1", n, Direction::Previous);
+    assert_eq!(prev(4).await.unwrap(), Some(1));
+    assert_eq!(prev(1).await.unwrap(), None);
+    assert_eq!(
+        storage
+            .next_article_number("missing", 0, Direction::Next)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn article_numbers_in_range_are_bounded_by_the_query() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    for i in 1..=5 {
+        let (_, msg) = parse_message(&format!(
+            "Message-ID: <{i}@test>\r\nNewsgroups: g1\r\n\r\nBody"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    storage.delete_article_by_id("<3@test>").await.unwrap();
+
+    let range = |start, end| {
+        storage
+            .list_article_numbers_in_range("g1", start, end)
+            .map(Result::unwrap)
+            .collect::<Vec<u64>>()
+    };
+    assert_eq!(range(2, 4).await, vec![2, 4]);
+    assert_eq!(range(4, u64::MAX).await, vec![4, 5]);
+    assert_eq!(range(6, 9).await, Vec::<u64>::new());
+    assert_eq!(range(4, 2).await, Vec::<u64>::new());
+}
+
+#[tokio::test]
+async fn file_uri_opens_database_in_wal_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("news.db");
+    let storage = SqliteStorage::new(&format!("file:{}", path.display()))
+        .await
+        .expect("init");
+    let (_, msg) = parse_message("Message-ID: <w@test>\r\nNewsgroups: g1\r\n\r\nA").unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    assert!(path.exists());
+    assert!(dir.path().join("news.db-wal").exists());
+    assert!(storage.article_exists("<w@test>").await.unwrap());
+}
+
+#[tokio::test]
+async fn concurrent_stores_get_distinct_numbers() {
+    let dir = tempfile::tempdir().unwrap();
+    let uri = format!("sqlite://{}", dir.path().join("news.db").display());
+    let storage = std::sync::Arc::new(SqliteStorage::new(&uri).await.expect("init"));
+
+    let mut tasks = Vec::new();
+    for i in 0..20 {
+        let storage = storage.clone();
+        tasks.push(tokio::spawn(async move {
+            let text = format!("Message-ID: <c{i}@test>\r\nNewsgroups: g1\r\n\r\nA");
+            let (_, msg) = parse_message(&text).unwrap();
+            storage.store_article(&msg).await.unwrap();
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut numbers = Vec::new();
+    let mut stream = storage.list_article_numbers("g1");
+    while let Some(result) = stream.next().await {
+        numbers.push(result.unwrap());
+    }
+    numbers.sort_unstable();
+    assert_eq!(numbers, (1..=20).collect::<Vec<u64>>());
+}
+
+/// Store 100 articles into one group from 8 tasks at once and check that
+/// they were numbered 1 to 100 without gaps or duplicates.
+async fn assert_concurrent_numbering(storage: std::sync::Arc<dyn Storage>) {
+    storage.add_group("busy.test", false).await.unwrap();
+    let mut tasks = Vec::new();
+    for task in 0..8 {
+        let storage = storage.clone();
+        tasks.push(tokio::spawn(async move {
+            for n in (task..100).step_by(8) {
+                let text = format!("Message-ID: <{n}@busy>\r\nNewsgroups: busy.test\r\n\r\nBody");
+                let (_, msg) = parse_message(&text).unwrap();
+                storage.store_article(&msg).await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut numbers: Vec<u64> = storage
+        .list_article_numbers("busy.test")
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    numbers.sort_unstable();
+    assert_eq!(numbers, (1..=100).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn concurrent_sqlite_stores_get_distinct_numbers() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(&format!("sqlite://{}", file.path().display()))
+        .await
+        .unwrap();
+    assert_concurrent_numbering(std::sync::Arc::new(storage)).await;
+}
+
+/// Runs against the database named by `RENEWS_TEST_POSTGRES_URL`, if set.
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn concurrent_postgres_stores_get_distinct_numbers() {
+    let Ok(url) = std::env::var("RENEWS_TEST_POSTGRES_URL") else {
+        return;
+    };
+    let storage = renews::storage::postgres::PostgresStorage::new(&url)
+        .await
+        .unwrap();
+    assert_concurrent_numbering(std::sync::Arc::new(storage)).await;
+}
+
+/// A body with NUL bytes, 8-bit characters and a yEnc line, which must come
+/// back from `storage` exactly as stored.
+async fn assert_binary_body_round_trip(storage: std::sync::Arc<dyn Storage>) {
+    let body =
+        "=ybegin line=128 size=6 name=a.bin\r\n\0\u{1}\u{ff}\u{e9}\u{7f}*\r\n=yend size=6\r\n";
+    let text = format!(
+        "Message-ID: <bin@test>\r\nNewsgroups: alt.binaries.test\r\nSubject: \u{e9}t\u{e9} \0\r\n\r\n{body}"
+    );
+    let (_, msg) = parse_message(&text).unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    let by_id = storage
+        .get_article_by_id("<bin@test>")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(by_id.body, msg.body);
+    assert_eq!(by_id.headers, msg.headers);
+    let by_number = storage
+        .get_article_by_number("alt.binaries.test", 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(by_number.body, msg.body);
+    let ids = vec!["<bin@test>".to_string()];
+    let batch: Vec<_> = storage.get_articles_by_ids(&ids).collect().await;
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].as_ref().unwrap().1.body, msg.body);
+}
+
+#[tokio::test]
+async fn sqlite_bodies_are_binary_safe() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    assert_binary_body_round_trip(std::sync::Arc::new(storage)).await;
+}
+
+/// Runs against the database named by `RENEWS_TEST_POSTGRES_URL`, if set.
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn postgres_bodies_are_binary_safe() {
+    let Ok(url) = std::env::var("RENEWS_TEST_POSTGRES_URL") else {
+        return;
+    };
+    let storage = renews::storage::postgres::PostgresStorage::new(&url)
+        .await
+        .unwrap();
+    assert_binary_body_round_trip(std::sync::Arc::new(storage)).await;
+}
+
+#[tokio::test]
+async fn batch_store_numbers_crossposts_in_order() {
+    let storage = SqliteStorage::new("sqlite::memory:").await.expect("init");
+    let (_, earlier) = parse_message("Message-ID: <0@test>\r\nNewsgroups: g1\r\n\r\nA").unwrap();
+    storage.store_article(&earlier).await.unwrap();
+
+    let batch: Vec<_> = [
+        "Message-ID: <1@test>\r\nNewsgroups: g1\r\nSubject: One\r\n\r\nA",
+        "Message-ID: <2@test>\r\nNewsgroups: g2, g1\r\nSubject: Two\r\n\r\nB\r\nC",
+        "Message-ID: <3@test>\r\nNewsgroups: g2\r\nSubject: Three\r\n\r\nD",
+    ]
+    .into_iter()
+    .map(|text| parse_message(text).unwrap().1)
+    .collect();
+    storage.store_articles(&batch).await.unwrap();
+
+    let ids = |group: &'static str| {
+        storage
+            .list_article_ids(group)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids("g1").await, vec!["<0@test>", "<1@test>", "<2@test>"]);
+    assert_eq!(ids("g2").await, vec!["<2@test>", "<3@test>"]);
+
+    let lines = storage.get_overview("g1", 1..=u64::MAX).await.unwrap();
+    assert_eq!(
+        lines.iter().map(|l| l.number).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(lines[2].subject, "Two");
+    assert_eq!(lines[2].lines, 2);
+    let lines = storage.get_overview("g2", 1..=u64::MAX).await.unwrap();
+    assert_eq!(lines[0].message_id, "<2@test>");
+    assert_eq!(lines[1].number, 2);
+    assert!(
+        storage
+            .get_article_by_number("g2", 2)
+            .await
+            .unwrap()
+            .is_some_and(|a| a.body == b"D")
+    );
+}
@@ -0,0 +1,127 @@
+use renews::config::SubscriptionRule;
+use renews::parse_message;
+use renews::storage::DynStorage;
+use renews::subscriptions;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::utils;
+
+fn subscription(port: u16) -> SubscriptionRule {
+    toml::from_str(&format!(
+        "host = \"127.0.0.1\"\nport = {port}\ntls = false\npatterns = [\"misc.*\"]"
+    ))
+    .unwrap()
+}
+
+async fn post(storage: &DynStorage, id: &str, group: &str) {
+    let text = format!(
+        "Message-ID: {id}\r\nFrom: a@test\r\nSubject: upstream\r\nNewsgroups: {group}\r\n\r\nBody"
+    );
+    let (_, msg) = parse_message(&text).unwrap();
+    storage.store_article(&msg).await.unwrap();
+}
+
+#[tokio::test]
+async fn fetches_wanted_groups_and_resumes_after_the_mark() {
+    let (local, local_auth) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+        storage.add_group("alt.test", false).await.unwrap();
+    }
+    post(&remote, "<one@upstream>", "misc.test").await;
+    post(&remote, "<two@upstream>", "misc.test").await;
+    post(&remote, "<other@upstream>", "alt.test").await;
+
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue_with_workers(local.clone(), local_auth, config).await;
+
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth.clone()).await;
+    let sub = subscription(addr.port());
+    assert_eq!(
+        subscriptions::sync_once(&sub, &local, &queue)
+            .await
+            .unwrap(),
+        2
+    );
+    assert!(local.article_exists("<one@upstream>").await.unwrap());
+    assert!(local.article_exists("<two@upstream>").await.unwrap());
+    assert!(!local.article_exists("<other@upstream>").await.unwrap());
+    assert_eq!(
+        local
+            .subscription_mark(&sub.server(), "misc.test")
+            .await
+            .unwrap(),
+        Some(2)
+    );
+
+    // A later fetch only picks up what arrived since. The test server takes
+    // one connection, so carry the mark over to the new port.
+    post(&remote, "<three@upstream>", "misc.test").await;
+    let (addr, _server) = utils::setup_server(remote.clone(), remote_auth).await;
+    let sub = subscription(addr.port());
+    local
+        .set_subscription_mark(&sub.server(), "misc.test", 2)
+        .await
+        .unwrap();
+    assert_eq!(
+        subscriptions::sync_once(&sub, &local, &queue)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(local.article_exists("<three@upstream>").await.unwrap());
+    assert_eq!(
+        local
+            .subscription_mark(&sub.server(), "misc.test")
+            .await
+            .unwrap(),
+        Some(3)
+    );
+}
+
+#[tokio::test]
+async fn already_seen_articles_are_not_fetched_again() {
+    let (local, local_auth) = utils::setup().await;
+    let (remote, remote_auth) = utils::setup().await;
+    for storage in [&local, &remote] {
+        storage.add_group("misc.test", false).await.unwrap();
+    }
+    post(&remote, "<dup@upstream>", "misc.test").await;
+    post(&local, "<dup@upstream>", "misc.test").await;
+
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue_with_workers(local.clone(), local_auth, config).await;
+    let (addr, _server) = utils::setup_server(remote, remote_auth).await;
+    let sub = subscription(addr.port());
+    assert_eq!(
+        subscriptions::sync_once(&sub, &local, &queue)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        local
+            .subscription_mark(&sub.server(), "misc.test")
+            .await
+            .unwrap(),
+        Some(1)
+    );
+}
+
+#[tokio::test]
+async fn unreachable_subscriptions_are_reported() {
+    let (local, local_auth) = utils::setup().await;
+    local.add_group("misc.test", false).await.unwrap();
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue_with_workers(local.clone(), local_auth, config).await;
+
+    // Bind and drop a listener so nothing answers on the port
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let result = subscriptions::sync_all(&[subscription(port)], &local, &queue).await;
+    assert!(result.is_err());
+}
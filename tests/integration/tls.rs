@@ -24,7 +24,7 @@ async fn tls_post_requires_auth() {
     storage.add_group("misc", false).await.unwrap();
     ClientMock::new()
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP misc", "211 0 0 0 misc")
+        .expect("GROUP misc", "211 0 1 0 misc")
         .expect("POST", "480 authentication required")
         .run_tls(storage.clone(), auth)
         .await;
@@ -55,7 +55,7 @@ async fn tls_authinfo_and_post() {
         .expect("AUTHINFO USER user", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP misc", "211 0 0 0 misc")
+        .expect("GROUP misc", "211 0 1 0 misc")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -97,7 +97,7 @@ async fn post_without_msgid_generates_one() {
         .expect("AUTHINFO USER user", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP misc", "211 0 0 0 misc")
+        .expect("GROUP misc", "211 0 1 0 misc")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -139,7 +139,7 @@ async fn post_without_date_adds_header() {
         .expect("AUTHINFO USER user", "381 password required")
         .expect("AUTHINFO PASS pass", "281 authentication accepted")
         .expect("MODE READER", "200 Posting allowed")
-        .expect("GROUP misc", "211 0 0 0 misc")
+        .expect("GROUP misc", "211 0 1 0 misc")
         .expect(
             "POST",
             "340 send article to be posted. End with <CR-LF>.<CR-LF>",
@@ -169,3 +169,50 @@ async fn post_without_date_adds_header() {
     assert!(date.is_some());
     chrono::DateTime::parse_from_rfc2822(&date.unwrap()).unwrap();
 }
+
+#[tokio::test]
+async fn fresh_group_reports_empty_watermarks_and_numbers_from_one() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc", false).await.unwrap();
+    auth.add_user("user", "pass").await.unwrap();
+    let article = concat!(
+        "Message-ID: <first@test>\r\n",
+        "Newsgroups: misc\r\n",
+        "From: user@example.com\r\n",
+        "Subject: first\r\n",
+        "\r\n",
+        "Body\r\n",
+        ".",
+    );
+    ClientMock::new()
+        .expect("AUTHINFO USER user", "381 password required")
+        .expect("AUTHINFO PASS pass", "281 authentication accepted")
+        .expect("GROUP misc", "211 0 1 0 misc")
+        .expect(
+            "POST",
+            "340 send article to be posted. End with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(article.trim_end_matches("\r\n")),
+            vec!["240 article received"],
+        )
+        .expect("QUIT", "205 closing connection")
+        .run_tls(storage.clone(), auth)
+        .await;
+
+    // Wait for queue processing
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    let stored = storage
+        .get_article_by_number("misc", 1)
+        .await
+        .unwrap()
+        .expect("first article numbered 1");
+    assert!(
+        stored
+            .headers
+            .iter()
+            .any(|(k, v)| k == "Message-ID" && v == "<first@test>")
+    );
+    assert_eq!(storage.group_counts("misc").await.unwrap(), (1, 1, 1));
+}
@@ -0,0 +1,183 @@
+use renews::storage::DynStorage;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::RwLock;
+
+use crate::utils::{self, ClientMock};
+
+async fn stored_xref(storage: &DynStorage, id: &str) -> Option<String> {
+    for _ in 0..50 {
+        if let Some(article) = storage.get_article_by_id(id).await.unwrap() {
+            return article
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("Xref"))
+                .map(|(_, v)| v.clone());
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("{id} was not stored");
+}
+
+fn article(id: &str, newsgroups: &str, extra: &str) -> String {
+    format!(
+        "Path: peer\r\nMessage-ID: {id}\r\nNewsgroups: {newsgroups}\r\nFrom: a@test\r\nSubject: xref\r\n{extra}\r\nBody\r\n."
+    )
+}
+
+#[tokio::test]
+async fn crossposts_list_each_group_in_newsgroups_order() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.a", false).await.unwrap();
+    storage.add_group("misc.b", false).await.unwrap();
+    let cfg = utils::create_minimal_config();
+    ClientMock::new()
+        .expect(
+            "IHAVE <first@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<first@test>", "misc.a", "")),
+            vec!["235 Article transferred OK"],
+        )
+        .expect(
+            "IHAVE <cross@test>",
+            "335 Send it; end with <CR-LF>.<CR-LF>",
+        )
+        .expect_request_multi(
+            utils::request_lines(&article("<cross@test>", "misc.b,misc.a", "")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert_eq!(
+        stored_xref(&storage, "<cross@test>").await.as_deref(),
+        Some("test misc.b:1 misc.a:2")
+    );
+}
+
+#[tokio::test]
+async fn xref_from_a_peer_is_replaced_with_our_own() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.a", false).await.unwrap();
+    let cfg = utils::create_minimal_config();
+    ClientMock::new()
+        .expect("IHAVE <peer@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(&article(
+                "<peer@test>",
+                "misc.a",
+                "Xref: elsewhere misc.a:99\r\n",
+            )),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+
+    assert_eq!(
+        stored_xref(&storage, "<peer@test>").await.as_deref(),
+        Some("test misc.a:1")
+    );
+}
+
+#[tokio::test]
+async fn groups_that_do_not_exist_are_left_out() {
+    let (storage, _auth) = utils::setup().await;
+    storage.add_group("misc.a", false).await.unwrap();
+    let (_, msg) = renews::parse_message(
+        "Message-ID: <gone@test>\r\nNewsgroups: misc.none, misc.a\r\nFrom: a@test\r\nSubject: xref\r\n\r\nBody\r\n",
+    )
+    .unwrap();
+    storage.store_article(&msg).await.unwrap();
+
+    assert_eq!(
+        storage.record_xref("<gone@test>", "host").await.unwrap(),
+        Some("host misc.a:1".to_string())
+    );
+    assert_eq!(
+        stored_xref(&storage, "<gone@test>").await.as_deref(),
+        Some("host misc.a:1")
+    );
+    assert_eq!(
+        storage.record_xref("<unknown@test>", "host").await.unwrap(),
+        None
+    );
+}
+
+/// The Xref line of the reply to `command`, a HEAD or ARTICLE.
+async fn served_xref(storage: &DynStorage, command: &str) -> Option<String> {
+    let (_, auth) = utils::setup().await;
+    let cfg = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let (addr, server) = utils::setup_server_with_cfg(storage.clone(), auth, cfg).await;
+    let (mut reader, mut writer) = utils::connect(addr).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    writer
+        .write_all(format!("{command}\r\nQUIT\r\n").as_bytes())
+        .await
+        .unwrap();
+
+    let mut xref = None;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let line = line.trim_end();
+        if line == "." {
+            break;
+        }
+        assert!(!line.starts_with('4'), "{line}");
+        if let Some(value) = line.strip_prefix("Xref: ") {
+            xref = Some(value.to_string());
+        }
+    }
+    drop(writer);
+    server.await.unwrap();
+    xref
+}
+
+#[tokio::test]
+async fn crossposts_are_served_with_their_numbers_in_each_group() {
+    let (storage, auth) = utils::setup().await;
+    storage.add_group("misc.a", false).await.unwrap();
+    storage.add_group("misc.b", false).await.unwrap();
+    let cfg = utils::create_minimal_config();
+    for id in ["<a1@test>", "<a2@test>"] {
+        let (_, msg) = renews::parse_message(&format!(
+            "Message-ID: {id}\r\nNewsgroups: misc.a\r\nFrom: a@test\r\nSubject: xref\r\n\r\nBody\r\n"
+        ))
+        .unwrap();
+        storage.store_article(&msg).await.unwrap();
+    }
+    ClientMock::new()
+        .expect("IHAVE <both@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect_request_multi(
+            utils::request_lines(&article("<both@test>", "misc.a,misc.b", "")),
+            vec!["235 Article transferred OK"],
+        )
+        .run_with_cfg(cfg, storage.clone(), auth)
+        .await;
+    stored_xref(&storage, "<both@test>").await;
+
+    assert_eq!(
+        storage
+            .get_article_numbers_by_id("<both@test>")
+            .await
+            .unwrap(),
+        vec![("misc.a".to_string(), 3), ("misc.b".to_string(), 1)]
+    );
+    for command in ["HEAD <both@test>", "ARTICLE <both@test>"] {
+        assert_eq!(
+            served_xref(&storage, command).await.as_deref(),
+            Some("test misc.a:3 misc.b:1"),
+            "{command}"
+        );
+    }
+    assert!(
+        storage
+            .get_article_numbers_by_id("<unknown@test>")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -18,7 +18,7 @@ async fn test_pgp_discovery_with_missing_key() {
             ("From".to_string(), "test@example.com".to_string()),
             ("Subject".to_string(), "Test message".to_string()),
         ],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     // Try verification with a non-existent user (will attempt discovery)
@@ -97,7 +97,7 @@ async fn test_canonical_text_generation() {
                 "Mon, 1 Jan 2024 12:00:00 +0000".to_string()
             ),
         ],
-        body: "Test message body".to_string(),
+        body: "Test message body".into(),
     };
 
     let canonical = canonical_text(&msg, "From,Subject");
@@ -147,7 +147,7 @@ async fn test_verify_pgp_with_discovery_fallback() {
             ("From".to_string(), "test@example.com".to_string()),
             ("Subject".to_string(), "Test".to_string()),
         ],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     // Test with user that has no stored key - should attempt discovery
@@ -4,7 +4,7 @@ mod utils;
 
 use renews::{
     auth::sqlite::SqliteAuth,
-    queue::{ArticleQueue, QueuedArticle, WorkerPool},
+    queue::{ArticleQueue, QueueFull, QueuedArticle, WorkerPool},
     storage::{Storage, sqlite::SqliteStorage},
 };
 use std::sync::Arc;
@@ -48,6 +48,7 @@ async fn test_queue_functionality() {
         size: 100,
         is_control: false,
         already_validated: false,
+        ack: None,
     };
 
     queue.submit(queued_article).await.unwrap();
@@ -61,7 +62,7 @@ async fn test_queue_functionality() {
         .await
         .unwrap();
     assert!(stored.is_some());
-    assert_eq!(stored.unwrap().body, "Test body");
+    assert_eq!(stored.unwrap().body, b"Test body");
 }
 
 #[tokio::test]
@@ -89,3 +90,182 @@ async fn test_queue_with_server() {
 
     handle.abort();
 }
+
+#[tokio::test]
+async fn test_queue_reports_full() {
+    let queue = ArticleQueue::new(1);
+    assert!(!queue.is_full());
+    let message = renews::parse_message("Message-ID: <full@example.com>\r\n\r\nBody")
+        .unwrap()
+        .1;
+    queue
+        .submit(QueuedArticle {
+            message,
+            size: 4,
+            is_control: false,
+            already_validated: true,
+            ack: None,
+        })
+        .await
+        .unwrap();
+    assert!(queue.is_full());
+}
+
+#[tokio::test]
+async fn test_queue_len_tracks_submits_and_receives() {
+    let queue = ArticleQueue::new(3);
+    assert_eq!(queue.capacity(), Some(3));
+    assert!(queue.is_empty());
+
+    for id in 1..=2 {
+        let message = renews::parse_message(&format!("Message-ID: <{id}@example.com>\r\n\r\nBody"))
+            .unwrap()
+            .1;
+        queue
+            .submit(QueuedArticle {
+                message,
+                size: 4,
+                is_control: false,
+                already_validated: true,
+                ack: None,
+            })
+            .await
+            .unwrap();
+    }
+    assert_eq!(queue.len(), 2);
+    assert!(!queue.is_full());
+
+    let receiver = queue.receiver();
+    receiver.recv_async().await.unwrap();
+    assert_eq!(queue.len(), 1);
+    receiver.recv_async().await.unwrap();
+    assert!(queue.is_empty());
+}
+
+#[tokio::test]
+async fn test_try_submit_fails_without_waiting_when_full() {
+    let queue = ArticleQueue::new(1);
+    let article = || QueuedArticle {
+        message: renews::parse_message("Message-ID: <try@example.com>\r\n\r\nBody")
+            .unwrap()
+            .1,
+        size: 4,
+        is_control: false,
+        already_validated: true,
+        ack: None,
+    };
+    assert!(queue.try_submit(article()).is_ok());
+    assert_eq!(queue.try_submit(article()), Err(QueueFull));
+    assert_eq!(queue.len(), 1);
+}
+
+#[tokio::test]
+async fn test_queue_skips_duplicate_message_ids() {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let auth = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    storage.add_group("test.group", false).await.unwrap();
+
+    let queue = ArticleQueue::new(10);
+    let config = Arc::new(RwLock::new(toml::from_str("addr=\":119\"").unwrap()));
+    let worker_pool = WorkerPool::new(queue.clone(), storage.clone(), auth, config, 1);
+    let _worker_handles = worker_pool.start().await;
+
+    let message = renews::parse_message(
+        "Message-ID: <dup@example.com>\r\nFrom: test@example.com\r\nSubject: Test\r\nNewsgroups: test.group\r\n\r\nTest body"
+    ).unwrap().1;
+    for _ in 0..2 {
+        queue
+            .submit(QueuedArticle {
+                message: message.clone(),
+                size: 100,
+                is_control: false,
+                already_validated: true,
+                ack: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    assert!(storage.article_exists("<dup@example.com>").await.unwrap());
+    assert_eq!(storage.group_counts("test.group").await.unwrap(), (1, 1, 1));
+}
+
+#[tokio::test]
+async fn test_history_is_shared_across_connections() {
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let auth: renews::auth::DynAuth = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    storage.add_group("test.group", false).await.unwrap();
+    let queue = ArticleQueue::new(10).with_history(100);
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+
+    let connect_client = || {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(renews::handle_client(
+            server,
+            storage.clone(),
+            auth.clone(),
+            config.clone(),
+            false,
+            queue.clone(),
+        ));
+        (tokio::io::split(client), handle)
+    };
+
+    let ((reader, writer), handle) = connect_client();
+    utils::ClientMock::new()
+        .expect("IHAVE <hist@test>", "335 Send it; end with <CR-LF>.<CR-LF>")
+        .expect(
+            "Message-ID: <hist@test>\r\nNewsgroups: test.group\r\nFrom: a@test\r\nSubject: s\r\n\r\nBody\r\n.",
+            "235 Article transferred OK",
+        )
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap().unwrap();
+
+    // Once removed from storage only the history still knows the article
+    storage.delete_article_by_id("<hist@test>").await.unwrap();
+    assert_eq!(queue.history().unwrap().len(), 1);
+
+    let ((reader, writer), handle) = connect_client();
+    utils::ClientMock::new()
+        .expect("IHAVE <hist@test>", "435 article not wanted")
+        .expect("CHECK <hist@test>", "438 <hist@test>")
+        .expect("CHECK <other@test>", "238 <other@test>")
+        .expect("QUIT", "205 closing connection")
+        .drive(reader, writer)
+        .await;
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_control_messages_are_processed_before_queued_articles() {
+    let queue = ArticleQueue::new(10);
+    let article = |id: &str, is_control: bool| QueuedArticle {
+        message: renews::parse_message(&format!("Message-ID: <{id}@example.com>\r\n\r\nBody"))
+            .unwrap()
+            .1,
+        size: 4,
+        is_control,
+        already_validated: true,
+        ack: None,
+    };
+    for n in 0..10 {
+        queue.submit(article(&n.to_string(), false)).await.unwrap();
+    }
+    assert!(queue.is_full());
+    // A full ordinary lane does not keep control messages out
+    queue.try_submit(article("cancel", true)).unwrap();
+    assert_eq!(queue.len(), 11);
+
+    let mut order = Vec::new();
+    while !queue.is_empty() {
+        let next = queue.recv().await.unwrap();
+        order.push(renews::storage::common::extract_message_id(&next.message).unwrap());
+    }
+    let mut expected = vec!["<cancel@example.com>".to_string()];
+    expected.extend((0..10).map(|n| format!("<{n}@example.com>")));
+    assert_eq!(order, expected);
+}
@@ -2,11 +2,16 @@
 
 mod utils;
 
+use anyhow::Result;
 use renews::{
+    Message,
     auth::{AuthProvider, sqlite::SqliteAuth},
-    config::Config,
-    queue::{ArticleQueue, WorkerPool},
-    storage::{Storage, sqlite::SqliteStorage},
+    config::{Config, PostDurability, QueueFullPolicy},
+    queue::{ArticleQueue, Outcome, QueuedArticle, WorkerPool},
+    storage::{
+        ArticleStream, Direction, HeaderStream, HistoryStatus, OverviewStream, Storage,
+        StringPairStream, StringStream, StringTimestampStream, U64Stream, sqlite::SqliteStorage,
+    },
 };
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -26,23 +31,69 @@ async fn setup_queue_enabled_server() -> (std::net::SocketAddr, Arc<dyn Storage>
     let config = Config {
         addr: "127.0.0.1:0".to_string(),
         site_name: "test".to_string(),
+        path_host: None,
+        admin_address: None,
         db_path: "sqlite::memory:".to_string(),
         auth_db_path: "sqlite::memory:".to_string(),
         peer_db_path: "sqlite::memory:".to_string(),
+        mirror_db_path: None,
+        mirror_failure_policy: renews::config::MirrorFailurePolicy::Warn,
         peer_sync_schedule: "0 0 * * * *".to_string(),
         idle_timeout_secs: 600,
+        max_connections: None,
+        max_connections_per_ip: None,
+        plain_idle_timeout_secs: None,
+        tls_idle_timeout_secs: None,
+        transit_idle_timeout_secs: None,
+        article_line_timeout_secs: 60,
+        shutdown_grace_secs: 30,
+        drain_timeout_secs: 60,
         peers: vec![],
+        subscriptions: vec![],
         tls_addr: Some("127.0.0.1:0".to_string()),
         tls_cert: None,
         tls_key: None,
+        tls_client_ca: None,
         ws_addr: None,
+        transit_addr: None,
+        metrics_addr: None,
         article_queue_capacity: 100,
         article_worker_count: 2,
+        article_batch_size: 32,
+        dead_letter_dir: None,
+        history_cache_size: 1000,
+        overview_extra_headers: Vec::new(),
         runtime_threads: 1,
         group_settings: vec![],
+        retention_interval: None,
+        history_retention: None,
         filters: vec![],
+        client_certs: vec![],
+        access: renews::config::AccessRules::default(),
+        list_unreadable_groups: false,
         pgp_key_servers: renews::config::default_pgp_key_servers(),
+        control_keys: vec![],
+        checkgroups: renews::config::CheckgroupsConfig::default(),
+        posting_enabled: true,
+        plain_posting_enabled: None,
+        tls_posting_enabled: None,
         allow_posting_insecure_connections: false,
+        allow_sasl_plain_insecure: false,
+        moderated_crosspost_policy: renews::config::ModeratedCrosspostPolicy::HoldAll,
+        header_rules: Vec::new(),
+        max_article_bytes: None,
+        max_crosspost_groups: None,
+        require_followup_to_above: None,
+        moderators: None,
+        smtp_relay: None,
+        post_durability: renews::config::PostDurability::AckOnQueue,
+        message_id_generation: renews::config::MessageIdGeneration::Hash,
+        rate_limit: renews::config::RateLimitConfig::default(),
+        backpressure: renews::config::BackpressureConfig::default(),
+        injection: renews::config::InjectionConfig::default(),
+        ip_access: renews::config::IpAccessConfig::default(),
+        spam: renews::config::SpamConfig::default(),
+        distributions: renews::config::DistributionsConfig::default(),
     };
 
     // Since we can't easily test with TLS in this setup, we'll create a simplified server
@@ -145,7 +196,7 @@ async fn test_full_queue_integration() {
     let article = stored.unwrap();
     assert!(
         article
-            .body
+            .body_text()
             .contains("This is a test article submitted via queue")
     );
 
@@ -197,3 +248,682 @@ async fn test_queue_validation_failure() {
 
     writer.write_all(b"QUIT\r\n").await.unwrap();
 }
+
+/// Client side of a POST awaiting its response; the writer keeps the
+/// connection open.
+struct PendingPost {
+    lines: tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    _writer: tokio::net::tcp::OwnedWriteHalf,
+}
+
+/// Start a server whose queue has no workers yet, authenticate, and POST an
+/// article. Returns the pending response reader and what is needed to start
+/// workers later.
+async fn post_without_workers(
+    durability: PostDurability,
+) -> (
+    PendingPost,
+    Arc<dyn Storage>,
+    Arc<dyn AuthProvider>,
+    ArticleQueue,
+    Arc<RwLock<Config>>,
+) {
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let auth: Arc<dyn AuthProvider> = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    auth.add_user("testuser", "password").await.unwrap();
+    storage.add_group("test.group", false).await.unwrap();
+
+    let mut config = utils::create_minimal_config();
+    config.post_durability = durability;
+    let config = Arc::new(RwLock::new(config));
+    let queue = ArticleQueue::new(10);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (s, a, c, q) = (storage.clone(), auth.clone(), config.clone(), queue.clone());
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let _ = renews::handle_client(socket, s, a, c, true, q).await;
+        }
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(read_half).lines();
+    lines.next_line().await.unwrap();
+    for (cmd, code) in [
+        ("AUTHINFO USER testuser\r\n", "381"),
+        ("AUTHINFO PASS password\r\n", "281"),
+        ("POST\r\n", "340"),
+    ] {
+        writer.write_all(cmd.as_bytes()).await.unwrap();
+        assert!(lines.next_line().await.unwrap().unwrap().starts_with(code));
+    }
+    let article = "From: test@example.com\r\nSubject: Durable\r\nNewsgroups: test.group\r\nMessage-ID: <durable@example.com>\r\n\r\nBody\r\n.\r\n";
+    writer.write_all(article.as_bytes()).await.unwrap();
+    (
+        PendingPost {
+            lines,
+            _writer: writer,
+        },
+        storage,
+        auth,
+        queue,
+        config,
+    )
+}
+
+#[tokio::test]
+async fn test_ack_on_queue_replies_before_store() {
+    let (mut post, storage, _auth, _queue, _config) =
+        post_without_workers(PostDurability::AckOnQueue).await;
+
+    let line = tokio::time::timeout(std::time::Duration::from_secs(1), post.lines.next_line())
+        .await
+        .expect("240 sent without waiting for a worker")
+        .unwrap()
+        .unwrap();
+    assert!(line.starts_with("240"));
+    assert!(
+        !storage
+            .article_exists("<durable@example.com>")
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_ack_on_store_waits_for_worker() {
+    let (mut post, storage, auth, queue, config) =
+        post_without_workers(PostDurability::AckOnStore).await;
+
+    // Nothing is acknowledged while no worker has stored the article
+    assert!(
+        tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            post.lines.next_line()
+        )
+        .await
+        .is_err()
+    );
+
+    let worker_pool = WorkerPool::new(queue, storage.clone(), auth, config, 1);
+    let _worker_handles = worker_pool.start().await;
+
+    let line = tokio::time::timeout(std::time::Duration::from_secs(5), post.lines.next_line())
+        .await
+        .expect("240 after the worker stored the article")
+        .unwrap()
+        .unwrap();
+    assert!(line.starts_with("240"));
+    assert!(
+        storage
+            .article_exists("<durable@example.com>")
+            .await
+            .unwrap()
+    );
+}
+
+/// Connect to a server whose single-slot queue lanes are already taken, with
+/// no worker to drain them, and return the first reply to each of `commands`.
+async fn replies_with_full_queue(
+    policy: QueueFullPolicy,
+    wait_timeout_ms: Option<u64>,
+    commands: &[&str],
+) -> Vec<Option<String>> {
+    let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    let auth: Arc<dyn AuthProvider> = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    auth.add_user("testuser", "password").await.unwrap();
+    storage.add_group("test.group", false).await.unwrap();
+
+    let mut config = utils::create_minimal_config();
+    config.backpressure.post = policy;
+    config.backpressure.transit = policy;
+    config.backpressure.wait_timeout_ms = wait_timeout_ms;
+    let config = Arc::new(RwLock::new(config));
+    let queue = ArticleQueue::new(1);
+    for is_control in [false, true] {
+        let (_, filler) =
+            renews::parse_message("Message-ID: <filler@example.com>\r\n\r\nBody").unwrap();
+        queue
+            .try_submit(QueuedArticle {
+                message: filler,
+                size: 4,
+                is_control,
+                already_validated: true,
+                ack: None,
+            })
+            .unwrap();
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (s, a, c, q) = (storage, auth, config, queue);
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let _ = renews::handle_client(socket, s, a, c, true, q).await;
+        }
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(read_half).lines();
+    lines.next_line().await.unwrap();
+    let mut replies = Vec::new();
+    for cmd in commands {
+        writer.write_all(cmd.as_bytes()).await.unwrap();
+        let reply =
+            tokio::time::timeout(std::time::Duration::from_millis(300), lines.next_line()).await;
+        replies.push(reply.ok().and_then(|line| line.unwrap()));
+    }
+    replies
+}
+
+const POST_ON_FULL_QUEUE: [&str; 4] = [
+    "AUTHINFO USER testuser\r\n",
+    "AUTHINFO PASS password\r\n",
+    "POST\r\n",
+    "From: test@example.com\r\nSubject: Busy\r\nNewsgroups: test.group\r\nMessage-ID: <busy@example.com>\r\n\r\nBody\r\n.\r\n",
+];
+
+#[tokio::test]
+async fn test_post_to_full_queue_is_refused_by_default() {
+    let replies = replies_with_full_queue(QueueFullPolicy::Reject, None, &POST_ON_FULL_QUEUE).await;
+    let last = replies.last().unwrap().as_deref().unwrap();
+    assert!(last.starts_with("441"), "{last}");
+    assert!(last.contains("try again later"), "{last}");
+}
+
+#[tokio::test]
+async fn test_post_to_full_queue_waits_when_configured() {
+    let replies = replies_with_full_queue(QueueFullPolicy::Wait, None, &POST_ON_FULL_QUEUE).await;
+    assert_eq!(replies.last().unwrap(), &None);
+}
+
+#[tokio::test]
+async fn test_post_waiting_on_full_queue_gives_up_after_timeout() {
+    let replies =
+        replies_with_full_queue(QueueFullPolicy::Wait, Some(50), &POST_ON_FULL_QUEUE).await;
+    let last = replies.last().unwrap().as_deref().unwrap();
+    assert!(last.starts_with("441"), "{last}");
+}
+
+#[tokio::test]
+async fn test_takethis_waiting_on_full_queue_gives_up_after_timeout() {
+    let takethis = "TAKETHIS <ctl@example.com>\r\nFrom: test@example.com\r\nSubject: cmsg cancel <x@example.com>\r\nControl: cancel <x@example.com>\r\nNewsgroups: test.group\r\nMessage-ID: <ctl@example.com>\r\n\r\nBody\r\n.\r\n";
+    let replies = replies_with_full_queue(QueueFullPolicy::Wait, Some(50), &[takethis]).await;
+    assert_eq!(replies[0].as_deref(), Some("431 <ctl@example.com>"));
+}
+
+#[tokio::test]
+async fn test_ihave_with_full_queue_asks_to_retry() {
+    let replies = replies_with_full_queue(
+        QueueFullPolicy::Reject,
+        None,
+        &["IHAVE <busy@example.com>\r\n"],
+    )
+    .await;
+    assert!(replies[0].as_deref().unwrap().starts_with("436"));
+}
+
+#[tokio::test]
+async fn test_failed_article_is_dead_lettered_with_its_flags() {
+    let (storage, auth) = utils::setup().await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = utils::create_minimal_config();
+    config.dead_letter_dir = Some(dir.path().display().to_string());
+    let queue =
+        utils::create_test_queue_with_workers(storage, auth, Arc::new(RwLock::new(config))).await;
+
+    // Nothing carries the group, so the worker's filters refuse it
+    let text = "Message-ID: <dead@test>\r\nFrom: a@test\r\nSubject: lost\r\nNewsgroups: no.such.group\r\n\r\nBody\r\n";
+    let (_, message) = renews::parse_message(text).unwrap();
+    let (ack, done) = flume::bounded(1);
+    queue
+        .submit(QueuedArticle {
+            message: message.clone(),
+            size: text.len() as u64,
+            is_control: false,
+            already_validated: false,
+            ack: Some(ack),
+        })
+        .await
+        .unwrap();
+    assert_eq!(done.recv_async().await.unwrap(), Outcome::Refused);
+
+    let mut entries: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    entries.sort();
+    assert_eq!(entries.len(), 2);
+    let article_path = entries
+        .iter()
+        .find(|p| p.extension().is_some_and(|e| e == "msg"))
+        .unwrap();
+
+    let (replayed, meta) = renews::dead_letter::read(article_path).await.unwrap();
+    assert_eq!(replayed.message, message);
+    assert_eq!(replayed.size, text.len() as u64);
+    assert!(!replayed.is_control);
+    assert!(!replayed.already_validated);
+    assert_eq!(meta.message_id.as_deref(), Some("<dead@test>"));
+    assert!(!meta.reason.is_empty());
+}
+
+/// Storage that fails or panics when storing two particular Message-IDs and
+/// otherwise delegates to `inner`. The size of every batch it is asked to
+/// store is recorded in `batches`.
+struct FaultyStorage {
+    inner: Arc<dyn Storage>,
+    batches: std::sync::Mutex<Vec<usize>>,
+}
+
+#[async_trait::async_trait]
+impl Storage for FaultyStorage {
+    async fn store_article(&self, article: &Message) -> Result<()> {
+        let id = renews::storage::common::extract_message_id(article).unwrap_or_default();
+        match id.as_str() {
+            "<error@example.com>" => Err(anyhow::anyhow!("disk full")),
+            "<panic@example.com>" => panic!("corrupt index"),
+            _ => self.inner.store_article(article).await,
+        }
+    }
+
+    async fn store_articles(&self, articles: &[Message]) -> Result<()> {
+        self.batches.lock().unwrap().push(articles.len());
+        for article in articles {
+            self.store_article(article).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_article_by_number(&self, group: &str, number: u64) -> Result<Option<Message>> {
+        self.inner.get_article_by_number(group, number).await
+    }
+
+    async fn get_article_by_id(&self, message_id: &str) -> Result<Option<Message>> {
+        self.inner.get_article_by_id(message_id).await
+    }
+
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        self.inner.article_exists(message_id).await
+    }
+
+    fn get_articles_by_ids<'a>(&'a self, message_ids: &'a [String]) -> ArticleStream<'a> {
+        self.inner.get_articles_by_ids(message_ids)
+    }
+
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        self.inner.get_overview_range(group, start, end)
+    }
+
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        self.inner.get_header_for_range(group, header, start, end)
+    }
+
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
+        self.inner
+            .add_group_with_creator(group, moderated, created_by)
+            .await
+    }
+
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_creator(group).await
+    }
+
+    async fn set_group_moderated(&self, group: &str, moderated: bool) -> Result<()> {
+        self.inner.set_group_moderated(group, moderated).await
+    }
+
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        self.inner.set_group_description(group, description).await
+    }
+
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_description(group).await
+    }
+
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        self.inner.set_group_moderator(group, address).await
+    }
+
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_moderator(group).await
+    }
+
+    async fn remove_group(&self, group: &str) -> Result<()> {
+        self.inner.remove_group(group).await
+    }
+
+    async fn remove_groups_by_pattern(&self, pattern: &str) -> Result<()> {
+        self.inner.remove_groups_by_pattern(pattern).await
+    }
+
+    fn list_groups(&self) -> StringStream<'_> {
+        self.inner.list_groups()
+    }
+
+    fn list_groups_since(&self, since: chrono::DateTime<chrono::Utc>) -> StringStream<'_> {
+        self.inner.list_groups_since(since)
+    }
+
+    fn list_groups_with_times(&self) -> StringTimestampStream<'_> {
+        self.inner.list_groups_with_times()
+    }
+
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        self.inner.list_group_descriptions(pattern)
+    }
+
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        self.inner.group_counts(group).await
+    }
+
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        self.inner
+            .next_article_number(group, current, direction)
+            .await
+    }
+
+    fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
+        self.inner.list_article_numbers(group)
+    }
+
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        self.inner.list_article_numbers_in_range(group, start, end)
+    }
+
+    fn list_article_ids(&self, group: &str) -> StringStream<'_> {
+        self.inner.list_article_ids(group)
+    }
+
+    fn list_article_ids_since(
+        &self,
+        group: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> StringStream<'_> {
+        self.inner.list_article_ids_since(group, since)
+    }
+
+    async fn purge_group_before(
+        &self,
+        group: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        self.inner.purge_group_before(group, before).await
+    }
+
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        self.inner.purge_group_keep_latest(group, keep).await
+    }
+
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_expired(now).await
+    }
+
+    async fn purge_orphan_messages(&self) -> Result<()> {
+        self.inner.purge_orphan_messages().await
+    }
+
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        self.inner.history_status(message_id).await
+    }
+
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_history_before(before).await
+    }
+
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.inner.enqueue_feed(peer, message_id).await
+    }
+
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        self.inner.peek_feed(peer, limit).await
+    }
+
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.inner.dequeue_feed(peer, message_id).await
+    }
+
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        self.inner.trim_feed(peer, keep).await
+    }
+
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        self.inner.feed_len(peer).await
+    }
+
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        self.inner.subscription_mark(server, group).await
+    }
+
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        self.inner
+            .set_subscription_mark(server, group, number)
+            .await
+    }
+
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        self.inner.recent_body_times(hash, since).await
+    }
+
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.record_body(hash, at).await
+    }
+
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_bodies_before(before).await
+    }
+
+    async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
+        self.inner.get_message_size(message_id).await
+    }
+
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        self.inner.record_xref(message_id, host).await
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        self.inner.get_article_numbers_by_id(message_id).await
+    }
+
+    async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        self.inner.delete_article_by_id(message_id).await
+    }
+
+    async fn is_group_moderated(&self, group: &str) -> Result<bool> {
+        self.inner.is_group_moderated(group).await
+    }
+
+    async fn group_exists(&self, group: &str) -> Result<bool> {
+        self.inner.group_exists(group).await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        self.inner.schema_version().await
+    }
+}
+
+#[tokio::test]
+async fn test_worker_keeps_going_after_storage_errors_and_panics() {
+    let inner: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    inner.add_group("test.group", false).await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(FaultyStorage {
+        inner: inner.clone(),
+        batches: Default::default(),
+    });
+    let auth: Arc<dyn AuthProvider> = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = ArticleQueue::new(10);
+    // A single worker, so a failure that killed it would stall the rest
+    let _workers = WorkerPool::new(queue.clone(), storage, auth, config, 1)
+        .start()
+        .await;
+
+    let mut outcomes = Vec::new();
+    for id in ["error", "panic", "fine"] {
+        let text = format!(
+            "Message-ID: <{id}@example.com>\r\nFrom: a@test\r\nSubject: {id}\r\nNewsgroups: test.group\r\n\r\nBody\r\n"
+        );
+        let (_, message) = renews::parse_message(&text).unwrap();
+        let (ack, done) = flume::bounded(1);
+        queue
+            .submit(QueuedArticle {
+                message,
+                size: text.len() as u64,
+                is_control: false,
+                already_validated: true,
+                ack: Some(ack),
+            })
+            .await
+            .unwrap();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(5), done.recv_async())
+            .await
+            .expect("worker answered")
+            .unwrap();
+        outcomes.push(outcome);
+    }
+
+    assert_eq!(
+        outcomes,
+        vec![Outcome::Failed, Outcome::Failed, Outcome::Stored]
+    );
+    assert!(inner.article_exists("<fine@example.com>").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_worker_batches_waiting_articles_and_isolates_failures() {
+    let inner: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    inner.add_group("test.group", false).await.unwrap();
+    let storage = Arc::new(FaultyStorage {
+        inner: inner.clone(),
+        batches: Default::default(),
+    });
+    let auth: Arc<dyn AuthProvider> = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = ArticleQueue::new(10);
+
+    // Queue everything before a worker starts so it finds them waiting
+    let mut acks = Vec::new();
+    for id in ["first", "error", "second"] {
+        let text = format!(
+            "Message-ID: <{id}@example.com>\r\nFrom: a@test\r\nSubject: {id}\r\nNewsgroups: test.group\r\n\r\nBody\r\n"
+        );
+        let (_, message) = renews::parse_message(&text).unwrap();
+        let (ack, done) = flume::bounded(1);
+        queue
+            .submit(QueuedArticle {
+                message,
+                size: text.len() as u64,
+                is_control: false,
+                already_validated: true,
+                ack: Some(ack),
+            })
+            .await
+            .unwrap();
+        acks.push(done);
+    }
+    let _workers = WorkerPool::new(queue.clone(), storage.clone(), auth, config, 1)
+        .start()
+        .await;
+
+    let mut outcomes = Vec::new();
+    for done in acks {
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(5), done.recv_async())
+            .await
+            .expect("worker answered")
+            .unwrap();
+        outcomes.push(outcome);
+    }
+
+    // One batch was tried; after it failed the articles were stored one at
+    // a time and only the faulty one was refused
+    assert_eq!(*storage.batches.lock().unwrap(), vec![3]);
+    assert_eq!(
+        outcomes,
+        vec![Outcome::Stored, Outcome::Failed, Outcome::Stored]
+    );
+    assert_eq!(
+        inner
+            .get_article_by_number("test.group", 2)
+            .await
+            .unwrap()
+            .and_then(|a| renews::storage::common::extract_message_id(&a)),
+        Some("<second@example.com>".to_string())
+    );
+}
+
+#[tokio::test]
+async fn subscription_mark_stops_before_an_article_that_failed() {
+    let (remote, remote_auth) = utils::setup().await;
+    remote.add_group("test.group", false).await.unwrap();
+    // Stored upstream as is; the second lacks a From the local filters want
+    for (id, from) in [
+        ("first", "From: a@test\r\n"),
+        ("refused", ""),
+        ("error", "From: a@test\r\n"),
+        ("second", "From: a@test\r\n"),
+    ] {
+        let text = format!(
+            "Message-ID: <{id}@example.com>\r\n{from}Subject: {id}\r\nNewsgroups: test.group\r\n\r\nBody\r\n"
+        );
+        let (_, message) = renews::parse_message(&text).unwrap();
+        remote.store_article(&message).await.unwrap();
+    }
+
+    let inner: Arc<dyn Storage> = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+    inner.add_group("test.group", false).await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(FaultyStorage {
+        inner: inner.clone(),
+        batches: Default::default(),
+    });
+    let auth: Arc<dyn AuthProvider> = Arc::new(SqliteAuth::new("sqlite::memory:").await.unwrap());
+    let config = Arc::new(RwLock::new(utils::create_minimal_config()));
+    let queue = utils::create_test_queue_with_workers(storage.clone(), auth, config).await;
+
+    let (addr, _server) = utils::setup_server(remote, remote_auth).await;
+    let sub: renews::config::SubscriptionRule = toml::from_str(&format!(
+        "host = \"127.0.0.1\"\nport = {}\ntls = false\npatterns = [\"test.*\"]",
+        addr.port()
+    ))
+    .unwrap();
+    let fetched = renews::subscriptions::sync_once(&sub, &storage, &queue)
+        .await
+        .unwrap();
+
+    // The refused article is passed over for good, the failed one is not
+    assert_eq!(fetched, 2);
+    assert!(inner.article_exists("<second@example.com>").await.unwrap());
+    assert_eq!(
+        storage
+            .subscription_mark(&sub.server(), "test.group")
+            .await
+            .unwrap(),
+        Some(2)
+    );
+}
@@ -1,11 +1,19 @@
+#[path = "unit/cert.rs"]
+mod cert;
 #[path = "unit/config.rs"]
 mod config;
 #[path = "unit/config_failures.rs"]
 mod config_failures;
+#[path = "unit/datetime.rs"]
+mod datetime;
 #[path = "unit/filters.rs"]
 mod filters;
 #[path = "unit/parse_failures.rs"]
 mod parse_failures;
+#[path = "unit/ratelimit.rs"]
+mod ratelimit;
+#[path = "unit/scram.rs"]
+mod scram;
 #[path = "unit/storage_common.rs"]
 mod storage_common;
 #[path = "unit/wildmat.rs"]
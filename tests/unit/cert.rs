@@ -0,0 +1,35 @@
+use renews::auth::ClientCertificate;
+use sha2::{Digest, Sha256};
+
+#[test]
+fn parses_subject_and_fingerprint() {
+    let mut params = rcgen::CertificateParams::new(vec!["feeder.example".to_string()]).unwrap();
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CountryName, "CA");
+    params
+        .distinguished_name
+        .push(rcgen::DnType::OrganizationName, "Example, Inc");
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "feeder");
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key).unwrap();
+    let der = cert.der().to_vec();
+
+    let parsed = ClientCertificate::from_der(&der).unwrap();
+    assert_eq!(parsed.common_name.as_deref(), Some("feeder"));
+    assert_eq!(parsed.subject, "CN=feeder,O=Example\\, Inc,C=CA");
+    let expected: String = Sha256::digest(&der)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    assert_eq!(parsed.fingerprint, expected);
+}
+
+#[test]
+fn rejects_truncated_der() {
+    assert!(ClientCertificate::from_der(&[0x30, 0x82, 0x01]).is_err());
+    assert!(ClientCertificate::from_der(&[]).is_err());
+}
@@ -24,6 +24,35 @@ max_article_bytes = "1K"
     assert_eq!(cfg.max_size_for_group("foo.bar"), Some(20480));
 }
 
+#[test]
+fn retention_duration_overrides_days() {
+    let toml = r#"addr = ":119"
+[[group_settings]]
+group = "misc"
+retention_days = 30
+retention = "36h"
+[[group_settings]]
+pattern = "alt.*"
+retention = 600
+[[group_settings]]
+pattern = "*"
+retention = "2w"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert_eq!(cfg.retention_for_group("misc").unwrap().num_hours(), 36);
+    assert_eq!(
+        cfg.retention_for_group("alt.test").unwrap().num_seconds(),
+        600
+    );
+    assert_eq!(cfg.retention_for_group("comp.lang").unwrap().num_days(), 14);
+    assert!(
+        toml::from_str::<Config>(
+            "addr = \":119\"\n[[group_settings]]\ngroup = \"x\"\nretention = \"soon\""
+        )
+        .is_err()
+    );
+}
+
 #[test]
 fn runtime_update_preserves_immutable_fields() {
     let initial = r#"addr = ":119"
@@ -269,3 +298,234 @@ runtime_threads = 8
     // Runtime threads should be updated (runtime-adjustable)
     assert_eq!(cfg.runtime_threads, 8);
 }
+
+#[test]
+fn client_certificates_map_to_users() {
+    use renews::auth::ClientCertificate;
+
+    let toml = r#"addr = ":119"
+[[client_cert]]
+subject = "CN=feeder,O=Example"
+username = "feeder"
+[[client_cert]]
+fingerprint = "AB:CD:EF"
+username = "hub"
+[[client_cert]]
+subject = "CN=pinned"
+fingerprint = "0123"
+username = "pinned"
+[[client_cert]]
+username = "nobody"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    let cert = |subject: &str, fingerprint: &str| ClientCertificate {
+        subject: subject.into(),
+        common_name: None,
+        fingerprint: fingerprint.into(),
+    };
+    assert_eq!(
+        cfg.user_for_certificate(&cert("CN=feeder,O=Example", "ff")),
+        Some("feeder".into())
+    );
+    assert_eq!(
+        cfg.user_for_certificate(&cert("CN=other", "abcdef")),
+        Some("hub".into())
+    );
+    assert_eq!(
+        cfg.user_for_certificate(&cert("CN=pinned", "0123")),
+        Some("pinned".into())
+    );
+    assert_eq!(cfg.user_for_certificate(&cert("CN=pinned", "4567")), None);
+    assert_eq!(cfg.user_for_certificate(&cert("CN=unknown", "99")), None);
+}
+
+#[test]
+fn posting_enabled_per_listener() {
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    assert!(cfg.posting_enabled_for(false));
+    assert!(cfg.posting_enabled_for(true));
+
+    let cfg: Config =
+        toml::from_str("addr = \":119\"\nposting_enabled = false\ntls_posting_enabled = true\n")
+            .unwrap();
+    assert!(!cfg.posting_enabled_for(false));
+    assert!(cfg.posting_enabled_for(true));
+
+    let cfg: Config = toml::from_str("addr = \":119\"\nplain_posting_enabled = false\n").unwrap();
+    assert!(!cfg.posting_enabled_for(false));
+    assert!(cfg.posting_enabled_for(true));
+}
+
+#[test]
+fn ip_access_matches_ipv4_and_ipv6_networks() {
+    let toml = r#"addr = ":119"
+[ip_access]
+allow = ["192.0.2.0/24", "2001:db8::/32", "198.51.100.7"]
+deny = ["192.0.2.128/25"]
+posting_allow = ["192.0.2.0/28"]
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    let access = &cfg.ip_access;
+    let ip = |s: &str| s.parse::<std::net::IpAddr>().unwrap();
+
+    assert!(access.connection_allowed(ip("192.0.2.1")));
+    assert!(access.connection_allowed(ip("2001:db8::1")));
+    assert!(access.connection_allowed(ip("198.51.100.7")));
+    // Denied even though the allow list covers it
+    assert!(!access.connection_allowed(ip("192.0.2.200")));
+    assert!(!access.connection_allowed(ip("198.51.100.8")));
+    assert!(!access.connection_allowed(ip("2001:db9::1")));
+    // IPv4 clients seen through an IPv6 socket match the IPv4 ranges
+    assert!(access.connection_allowed(ip("::ffff:192.0.2.1")));
+
+    assert!(access.posting_allowed(ip("192.0.2.15")));
+    assert!(!access.posting_allowed(ip("192.0.2.16")));
+}
+
+#[test]
+fn ip_access_defaults_to_allowing_everyone() {
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    let ip = "203.0.113.9".parse().unwrap();
+    assert!(cfg.ip_access.connection_allowed(ip));
+    assert!(cfg.ip_access.posting_allowed(ip));
+}
+
+#[test]
+fn ip_access_rejects_invalid_networks() {
+    let toml = r#"addr = ":119"
+[ip_access]
+deny = ["192.0.2.0/33"]
+"#;
+    let Err(err) = toml::from_str::<Config>(toml) else {
+        panic!("an invalid prefix length must be refused");
+    };
+    assert!(err.to_string().contains("192.0.2.0/33"), "{err}");
+}
+
+#[test]
+fn no_binaries_follows_the_most_specific_rule() {
+    let toml = r#"addr = ":119"
+[[group_settings]]
+pattern = "comp.*"
+no_binaries = true
+
+[[group_settings]]
+pattern = "comp.sources.*"
+no_binaries = false
+
+[[group_settings]]
+group = "misc.text"
+no_binaries = true
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert!(cfg.no_binaries_for_group("comp.lang.rust"));
+    assert!(!cfg.no_binaries_for_group("comp.sources.unix"));
+    assert!(!cfg.no_binaries_for_group("comp.binaries.ibm.pc"));
+    assert!(cfg.no_binaries_for_group("misc.text"));
+    assert!(!cfg.no_binaries_for_group("misc.test"));
+}
+
+#[test]
+fn global_size_limit_applies_where_no_rule_does() {
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    assert_eq!(cfg.size_limit_for_group("misc.test"), Some(1024 * 1024));
+
+    let toml = r#"addr = ":119"
+max_article_bytes = "64K"
+
+[[group_settings]]
+pattern = "alt.binaries.*"
+max_article_bytes = "10M"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert_eq!(cfg.size_limit_for_group("misc.test"), Some(64 * 1024));
+    assert_eq!(
+        cfg.size_limit_for_group("alt.binaries.misc"),
+        Some(10 * 1024 * 1024)
+    );
+    assert_eq!(
+        cfg.max_size_for_groups(&["misc.test", "alt.binaries.misc"]),
+        Some(64 * 1024)
+    );
+    assert_eq!(cfg.largest_size_limit(), Some(10 * 1024 * 1024));
+
+    let cfg: Config = toml::from_str("addr = \":119\"\nmax_article_bytes = 0").unwrap();
+    assert_eq!(cfg.size_limit_for_group("misc.test"), None);
+    assert_eq!(cfg.largest_size_limit(), None);
+
+    // With the global limit lifted the rules still bound the headers
+    let toml = r#"addr = ":119"
+max_article_bytes = 0
+
+[[group_settings]]
+pattern = "alt.binaries.*"
+max_article_bytes = "10M"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert_eq!(cfg.size_limit_for_group("misc.test"), None);
+    assert_eq!(cfg.largest_size_limit(), Some(10 * 1024 * 1024));
+
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    assert_eq!(cfg.size_limit_for_group("misc.test"), Some(1024 * 1024));
+}
+
+#[test]
+fn distributions_table_is_parsed() {
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    assert!(!cfg.distributions.strict);
+    assert!(cfg.distributions.keywords.is_empty());
+
+    let toml = r#"addr = ":119"
+[distributions]
+strict = true
+
+[[distributions.keywords]]
+name = "local"
+description = "Local to this site"
+
+[[distributions.patterns]]
+weight = 10
+groups = "local.*"
+distribution = "local"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert!(cfg.distributions.strict);
+    assert!(cfg.distributions.is_known("LOCAL"));
+    assert!(!cfg.distributions.is_known("world"));
+    assert_eq!(
+        cfg.distributions.keywords[0].description,
+        "Local to this site"
+    );
+    assert_eq!(cfg.distributions.patterns[0].weight, 10);
+    assert_eq!(cfg.distributions.patterns[0].groups, "local.*");
+}
+
+#[test]
+fn users_without_post_rights_anywhere_are_read_only() {
+    let cfg: Config = toml::from_str("addr = \":119\"").unwrap();
+    assert!(cfg.access.can_post_somewhere(None));
+
+    let toml = r#"addr = ":119"
+[[access]]
+groups = "*"
+post = ["alice"]
+
+[[access]]
+groups = "local.*"
+post = ["bob"]
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert!(cfg.access.can_post_somewhere(Some("alice")));
+    assert!(cfg.access.can_post_somewhere(Some("bob")));
+    assert!(!cfg.access.can_post_somewhere(Some("carol")));
+    assert!(!cfg.access.can_post_somewhere(None));
+
+    // Groups no catch-all rule covers stay open
+    let toml = r#"addr = ":119"
+[[access]]
+groups = "private.*"
+post = ["alice"]
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert!(cfg.access.can_post_somewhere(Some("carol")));
+}
@@ -0,0 +1,59 @@
+//! Tests for the NEWGROUPS and NEWNEWS date and time arguments
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use renews::parse::parse_datetime_at;
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 6, 15).unwrap()
+}
+
+#[test]
+fn four_digit_year_with_gmt() {
+    let parsed = parse_datetime_at("20240101", "123456", true, today()).unwrap();
+    assert_eq!(
+        parsed,
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 34, 56).unwrap()
+    );
+}
+
+#[test]
+fn two_digit_year_in_current_century() {
+    let parsed = parse_datetime_at("240101", "000000", true, today()).unwrap();
+    assert_eq!(parsed, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+}
+
+#[test]
+fn two_digit_year_in_future_falls_back_a_century() {
+    let parsed = parse_datetime_at("991231", "000000", true, today()).unwrap();
+    assert_eq!(parsed, Utc.with_ymd_and_hms(1999, 12, 31, 0, 0, 0).unwrap());
+    let parsed = parse_datetime_at("240616", "000000", true, today()).unwrap();
+    assert_eq!(parsed, Utc.with_ymd_and_hms(1924, 6, 16, 0, 0, 0).unwrap());
+}
+
+#[test]
+fn local_time_without_gmt() {
+    let parsed = parse_datetime_at("20240101", "000000", false, today()).unwrap();
+    let expected = chrono::Local
+        .with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+        .unwrap()
+        .with_timezone(&Utc);
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn malformed_dates_and_times_are_rejected() {
+    for (date, time) in [
+        ("2024011", "000000"),
+        ("2024-01-01", "000000"),
+        ("20241301", "000000"),
+        ("20240230", "000000"),
+        ("20240101", "0000"),
+        ("20240101", "246000"),
+        ("abcdef", "000000"),
+    ] {
+        assert!(
+            parse_datetime_at(date, time, true, today()).is_err(),
+            "{date} {time}"
+        );
+    }
+}
@@ -1,6 +1,24 @@
+use anyhow::Result;
+use renews::config::HeaderAction;
+use renews::filters::crosspost::{CrosspostFilter, CrosspostRejection};
+use renews::filters::duplicate_body::{DuplicateBodyFilter, RecentBodies, body_hash};
+use renews::filters::external::{
+    ExternalCommandConfig, ExternalCommandFilter, FailurePolicy, parse_score,
+};
+use renews::filters::groups::GroupExistenceFilter;
 use renews::filters::header::HeaderFilter;
+use renews::filters::header_rules::{HeaderRuleRejection, HeaderRulesFilter};
+use renews::filters::header_syntax::{HeaderSyntaxFilter, valid_message_id, valid_newsgroup_name};
+use renews::filters::history::{HistoryCache, HistoryFilter};
+use renews::filters::hook::{ExternalFilter, ExternalHookConfig, HookRejection};
+use renews::filters::mime::{MimeContent, MimeFilter, MimeRejection};
+use renews::filters::path::PathLoopFilter;
 use renews::filters::size::SizeFilter;
-use renews::filters::{ArticleFilter, FilterChain};
+use renews::filters::{ArticleFilter, FilterChain, Rejection, Submitter};
+use renews::storage::{
+    ArticleStream, Direction, HeaderStream, HistoryStatus, OverviewStream, Storage,
+    StringPairStream, StringStream, StringTimestampStream, U64Stream,
+};
 use renews::{Message, config::Config};
 use smallvec::smallvec;
 use std::sync::Arc;
@@ -18,7 +36,7 @@ async fn test_header_filter_valid() {
             ("Subject".to_string(), "Test Article".to_string()),
             ("Newsgroups".to_string(), "alt.test".to_string()),
         ],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     let result = filter.validate(&storage, &auth, &cfg, &article, 100).await;
@@ -37,7 +55,7 @@ async fn test_header_filter_missing_from() {
             ("Subject".to_string(), "Test Article".to_string()),
             ("Newsgroups".to_string(), "alt.test".to_string()),
         ],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     let result = filter.validate(&storage, &auth, &cfg, &article, 100).await;
@@ -55,12 +73,17 @@ async fn test_size_filter_within_limit() {
         group: None,
         pattern: Some("*".to_string()),
         retention_days: None,
+        retention: None,
         max_article_bytes: Some(1000),
+        body_truncate_bytes: None,
+        max_articles: None,
+        max_crosspost_groups: None,
+        no_binaries: None,
     });
 
     let article = Message {
         headers: smallvec![("Newsgroups".to_string(), "test.group".to_string())],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     let result = filter.validate(&storage, &auth, &cfg, &article, 500).await;
@@ -77,12 +100,17 @@ async fn test_size_filter_exceeds_limit() {
         group: None,
         pattern: Some("*".to_string()),
         retention_days: None,
+        retention: None,
         max_article_bytes: Some(1000),
+        body_truncate_bytes: None,
+        max_articles: None,
+        max_crosspost_groups: None,
+        no_binaries: None,
     });
 
     let article = Message {
         headers: smallvec![("Newsgroups".to_string(), "test.group".to_string())],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     let result = filter.validate(&storage, &auth, &cfg, &article, 1500).await;
@@ -93,16 +121,66 @@ async fn test_size_filter_exceeds_limit() {
     );
 }
 
+#[tokio::test]
+async fn test_size_filter_falls_back_to_global_limit() {
+    let filter = SizeFilter;
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.max_article_bytes = Some(1000);
+
+    let article = Message {
+        headers: smallvec![("Newsgroups".to_string(), "test.group".to_string())],
+        body: "Test body".into(),
+    };
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &article, 1000)
+            .await
+            .is_ok()
+    );
+    let result = filter.validate(&storage, &auth, &cfg, &article, 1001).await;
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "article too large for group test.group"
+    );
+
+    // A group rule takes precedence, even when it is more generous
+    cfg.group_settings.push(renews::config::GroupRule {
+        group: Some("test.group".to_string()),
+        pattern: None,
+        retention_days: None,
+        retention: None,
+        max_article_bytes: Some(5000),
+        body_truncate_bytes: None,
+        max_articles: None,
+        max_crosspost_groups: None,
+        no_binaries: None,
+    });
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &article, 4000)
+            .await
+            .is_ok()
+    );
+}
+
 #[tokio::test]
 async fn test_filter_chain_default() {
     let chain = FilterChain::default();
     let names = chain.filter_names();
 
-    assert_eq!(names.len(), 4);
+    assert_eq!(names.len(), 10);
     assert_eq!(names[0], "HeaderFilter");
-    assert_eq!(names[1], "SizeFilter");
-    assert_eq!(names[2], "GroupExistenceFilter");
-    assert_eq!(names[3], "ModerationFilter");
+    assert_eq!(names[1], "HeaderSyntaxFilter");
+    assert_eq!(names[2], "HeaderRulesFilter");
+    assert_eq!(names[3], "PathLoopFilter");
+    assert_eq!(names[4], "SizeFilter");
+    assert_eq!(names[5], "CrosspostFilter");
+    assert_eq!(names[6], "MimeFilter");
+    assert_eq!(names[7], "GroupExistenceFilter");
+    assert_eq!(names[8], "ModerationFilter");
+    assert_eq!(names[9], "DuplicateBodyFilter");
 }
 
 #[tokio::test]
@@ -131,7 +209,7 @@ async fn test_comprehensive_validation_compatibility() {
             ("Subject".to_string(), "Test Article".to_string()),
             ("Newsgroups".to_string(), "alt.test".to_string()),
         ],
-        body: "Test body".to_string(),
+        body: "Test body".into(),
     };
 
     let result = renews::handlers::utils::comprehensive_validate_article(
@@ -150,7 +228,187 @@ async fn test_comprehensive_validation_compatibility() {
     );
 }
 
+fn scanner(script: &str, on_error: FailurePolicy) -> ExternalCommandFilter {
+    ExternalCommandFilter::new(ExternalCommandConfig {
+        command: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+        threshold: 5.0,
+        timeout_secs: 1,
+        on_error,
+    })
+}
+
+fn scanned_article() -> Message {
+    Message {
+        headers: smallvec![
+            ("From".to_string(), "test@example.com".to_string()),
+            ("Subject".to_string(), "Buy now".to_string()),
+            ("Newsgroups".to_string(), "alt.test".to_string()),
+        ],
+        body: "Cheap watches".into(),
+    }
+}
+
+#[test]
+fn test_parse_scanner_score() {
+    assert_eq!(parse_score("7.5\n"), Some(7.5));
+    assert_eq!(parse_score("\n 2.1/5.0\n"), Some(2.1));
+    assert_eq!(parse_score("-0.3/5.0"), Some(-0.3));
+    assert_eq!(parse_score("spam"), None);
+    assert_eq!(parse_score(""), None);
+}
+
+#[tokio::test]
+async fn test_external_filter_scores_article() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    let article = scanned_article();
+
+    // spamc -c style: reads the article, prints score/threshold and exits 1 for spam
+    let spam = scanner(
+        "grep -q Cheap && { echo 9.0/5.0; exit 1; }; echo 0.0/5.0",
+        FailurePolicy::Open,
+    );
+    assert!(
+        spam.validate(&storage, &auth, &cfg, &article, 100)
+            .await
+            .is_err()
+    );
+
+    let ham = scanner(
+        "grep -q Expensive && echo 9.0 || echo 1.0",
+        FailurePolicy::Closed,
+    );
+    assert!(
+        ham.validate(&storage, &auth, &cfg, &article, 100)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_external_filter_failure_policy() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    let article = scanned_article();
+
+    for script in ["exit 2", "sleep 5; echo 0.0"] {
+        let open = scanner(script, FailurePolicy::Open);
+        assert!(
+            open.validate(&storage, &auth, &cfg, &article, 100)
+                .await
+                .is_ok()
+        );
+        let closed = scanner(script, FailurePolicy::Closed);
+        assert!(
+            closed
+                .validate(&storage, &auth, &cfg, &article, 100)
+                .await
+                .is_err()
+        );
+    }
+}
+
+#[test]
+fn test_history_cache_evicts_least_recently_used() {
+    let cache = HistoryCache::new(2);
+    cache.insert("<a@test>");
+    cache.insert("<b@test>");
+    // Touching <a> makes <b> the oldest entry
+    assert!(cache.contains("<a@test>"));
+    cache.insert("<c@test>");
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains("<a@test>"));
+    assert!(!cache.contains("<b@test>"));
+    assert!(cache.contains("<c@test>"));
+
+    let disabled = HistoryCache::new(0);
+    disabled.insert("<a@test>");
+    assert!(disabled.is_empty());
+}
+
+#[tokio::test]
+async fn test_history_filter_rejects_reoffers() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    let cache = Arc::new(HistoryCache::new(10));
+    let filter = HistoryFilter::new(cache.clone());
+
+    let article = |id: &str| Message {
+        headers: smallvec![
+            ("Message-ID".to_string(), id.to_string()),
+            ("Newsgroups".to_string(), "alt.test".to_string()),
+        ],
+        body: "Body".into(),
+    };
+
+    let fresh = article("<fresh@test>");
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &fresh, 10)
+            .await
+            .is_ok()
+    );
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &fresh, 10)
+            .await
+            .is_err()
+    );
+
+    // Articles already in storage are refused and remembered
+    storage.add_group("alt.test", false).await.unwrap();
+    let stored = article("<stored@test>");
+    storage.store_article(&stored).await.unwrap();
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &stored, 10)
+            .await
+            .is_err()
+    );
+    assert!(cache.contains("<stored@test>"));
+}
+
+#[test]
+fn test_filter_chain_with_history_runs_first() {
+    let chain = FilterChain::default().with_history(Arc::new(HistoryCache::new(10)));
+    let names = chain.filter_names();
+    assert_eq!(names.len(), 11);
+    assert_eq!(names[0], "HistoryFilter");
+}
+
 // Helper functions to create test objects
+#[tokio::test]
+async fn test_path_loop_filter_matches_whole_path_host_entries() {
+    let filter = PathLoopFilter;
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.path_host = Some("news.test".to_string());
+    let with_path = |path: &str| Message {
+        headers: smallvec![("Path".to_string(), path.to_string())],
+        body: Vec::new(),
+    };
+
+    for path in ["peer!news.test!origin", "news.test", " news.test !peer"] {
+        let result = filter
+            .validate(&storage, &auth, &cfg, &with_path(path), 0)
+            .await;
+        assert!(result.is_err(), "{path}");
+    }
+    // site_name no longer counts once path_host is set, and only whole
+    // entries match
+    for path in ["peer!test.local", "peer!news.test.example", "old-news.test"] {
+        let result = filter
+            .validate(&storage, &auth, &cfg, &with_path(path), 0)
+            .await;
+        assert!(result.is_ok(), "{path}");
+    }
+}
+
 fn create_test_config() -> Config {
     // Create a minimal config for testing by parsing a TOML string
     let toml = r#"
@@ -180,3 +438,1156 @@ async fn create_mock_auth() -> renews::auth::DynAuth {
     let auth = SqliteAuth::new(":memory:").await.unwrap();
     Arc::new(auth)
 }
+
+/// Storage that refuses to list every group and otherwise delegates to
+/// `inner`.
+struct NoGroupScanStorage {
+    inner: renews::storage::DynStorage,
+}
+
+#[async_trait::async_trait]
+impl Storage for NoGroupScanStorage {
+    async fn store_article(&self, article: &Message) -> Result<()> {
+        self.inner.store_article(article).await
+    }
+
+    async fn get_article_by_number(&self, group: &str, number: u64) -> Result<Option<Message>> {
+        self.inner.get_article_by_number(group, number).await
+    }
+
+    async fn get_article_by_id(&self, message_id: &str) -> Result<Option<Message>> {
+        self.inner.get_article_by_id(message_id).await
+    }
+
+    async fn article_exists(&self, message_id: &str) -> Result<bool> {
+        self.inner.article_exists(message_id).await
+    }
+
+    fn get_articles_by_ids<'a>(&'a self, message_ids: &'a [String]) -> ArticleStream<'a> {
+        self.inner.get_articles_by_ids(message_ids)
+    }
+
+    fn get_overview_range(&self, group: &str, start: u64, end: u64) -> OverviewStream<'_> {
+        self.inner.get_overview_range(group, start, end)
+    }
+
+    fn get_header_for_range(
+        &self,
+        group: &str,
+        header: &str,
+        start: u64,
+        end: u64,
+    ) -> HeaderStream<'_> {
+        self.inner.get_header_for_range(group, header, start, end)
+    }
+
+    async fn add_group_with_creator(
+        &self,
+        group: &str,
+        moderated: bool,
+        created_by: Option<&str>,
+    ) -> Result<()> {
+        self.inner
+            .add_group_with_creator(group, moderated, created_by)
+            .await
+    }
+
+    async fn get_group_creator(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_creator(group).await
+    }
+
+    async fn set_group_moderated(&self, group: &str, moderated: bool) -> Result<()> {
+        self.inner.set_group_moderated(group, moderated).await
+    }
+
+    async fn set_group_description(&self, group: &str, description: &str) -> Result<()> {
+        self.inner.set_group_description(group, description).await
+    }
+
+    async fn get_group_description(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_description(group).await
+    }
+
+    async fn set_group_moderator(&self, group: &str, address: &str) -> Result<()> {
+        self.inner.set_group_moderator(group, address).await
+    }
+
+    async fn get_group_moderator(&self, group: &str) -> Result<Option<String>> {
+        self.inner.get_group_moderator(group).await
+    }
+
+    async fn remove_group(&self, group: &str) -> Result<()> {
+        self.inner.remove_group(group).await
+    }
+
+    async fn remove_groups_by_pattern(&self, pattern: &str) -> Result<()> {
+        self.inner.remove_groups_by_pattern(pattern).await
+    }
+
+    fn list_groups(&self) -> StringStream<'_> {
+        panic!("list_groups scans every group");
+    }
+
+    fn list_groups_since(&self, since: chrono::DateTime<chrono::Utc>) -> StringStream<'_> {
+        self.inner.list_groups_since(since)
+    }
+
+    fn list_groups_with_times(&self) -> StringTimestampStream<'_> {
+        self.inner.list_groups_with_times()
+    }
+
+    fn list_group_descriptions(&self, pattern: Option<&str>) -> StringPairStream<'_> {
+        self.inner.list_group_descriptions(pattern)
+    }
+
+    async fn group_counts(&self, group: &str) -> Result<(u64, u64, u64)> {
+        self.inner.group_counts(group).await
+    }
+
+    async fn next_article_number(
+        &self,
+        group: &str,
+        current: u64,
+        direction: Direction,
+    ) -> Result<Option<u64>> {
+        self.inner
+            .next_article_number(group, current, direction)
+            .await
+    }
+
+    fn list_article_numbers(&self, group: &str) -> U64Stream<'_> {
+        self.inner.list_article_numbers(group)
+    }
+
+    fn list_article_numbers_in_range(&self, group: &str, start: u64, end: u64) -> U64Stream<'_> {
+        self.inner.list_article_numbers_in_range(group, start, end)
+    }
+
+    fn list_article_ids(&self, group: &str) -> StringStream<'_> {
+        self.inner.list_article_ids(group)
+    }
+
+    fn list_article_ids_since(
+        &self,
+        group: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> StringStream<'_> {
+        self.inner.list_article_ids_since(group, since)
+    }
+
+    async fn purge_group_before(
+        &self,
+        group: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        self.inner.purge_group_before(group, before).await
+    }
+
+    async fn purge_group_keep_latest(&self, group: &str, keep: u64) -> Result<()> {
+        self.inner.purge_group_keep_latest(group, keep).await
+    }
+
+    async fn purge_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_expired(now).await
+    }
+
+    async fn purge_orphan_messages(&self) -> Result<()> {
+        self.inner.purge_orphan_messages().await
+    }
+
+    async fn history_status(&self, message_id: &str) -> Result<Option<HistoryStatus>> {
+        self.inner.history_status(message_id).await
+    }
+
+    async fn purge_history_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_history_before(before).await
+    }
+
+    async fn enqueue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.inner.enqueue_feed(peer, message_id).await
+    }
+
+    async fn peek_feed(&self, peer: &str, limit: usize) -> Result<Vec<String>> {
+        self.inner.peek_feed(peer, limit).await
+    }
+
+    async fn dequeue_feed(&self, peer: &str, message_id: &str) -> Result<()> {
+        self.inner.dequeue_feed(peer, message_id).await
+    }
+
+    async fn trim_feed(&self, peer: &str, keep: usize) -> Result<u64> {
+        self.inner.trim_feed(peer, keep).await
+    }
+
+    async fn feed_len(&self, peer: &str) -> Result<u64> {
+        self.inner.feed_len(peer).await
+    }
+
+    async fn subscription_mark(&self, server: &str, group: &str) -> Result<Option<u64>> {
+        self.inner.subscription_mark(server, group).await
+    }
+
+    async fn set_subscription_mark(&self, server: &str, group: &str, number: u64) -> Result<()> {
+        self.inner
+            .set_subscription_mark(server, group, number)
+            .await
+    }
+
+    async fn recent_body_times(
+        &self,
+        hash: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<chrono::DateTime<chrono::Utc>>> {
+        self.inner.recent_body_times(hash, since).await
+    }
+
+    async fn record_body(&self, hash: &str, at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.record_body(hash, at).await
+    }
+
+    async fn purge_bodies_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.inner.purge_bodies_before(before).await
+    }
+
+    async fn get_message_size(&self, message_id: &str) -> Result<Option<u64>> {
+        self.inner.get_message_size(message_id).await
+    }
+
+    async fn record_xref(&self, message_id: &str, host: &str) -> Result<Option<String>> {
+        self.inner.record_xref(message_id, host).await
+    }
+
+    async fn get_article_numbers_by_id(&self, message_id: &str) -> Result<Vec<(String, u64)>> {
+        self.inner.get_article_numbers_by_id(message_id).await
+    }
+
+    async fn delete_article_by_id(&self, message_id: &str) -> Result<()> {
+        self.inner.delete_article_by_id(message_id).await
+    }
+
+    async fn is_group_moderated(&self, group: &str) -> Result<bool> {
+        self.inner.is_group_moderated(group).await
+    }
+
+    async fn group_exists(&self, group: &str) -> Result<bool> {
+        self.inner.group_exists(group).await
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    async fn schema_version(&self) -> Result<u32> {
+        self.inner.schema_version().await
+    }
+}
+
+#[tokio::test]
+async fn test_group_existence_filter_looks_up_each_group() {
+    let inner = create_mock_storage().await;
+    inner.add_group("alt.test", false).await.unwrap();
+    let storage: renews::storage::DynStorage = Arc::new(NoGroupScanStorage { inner });
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    let article = |groups: &str| Message {
+        headers: smallvec![("Newsgroups".to_string(), groups.to_string())],
+        body: "Body".into(),
+    };
+
+    GroupExistenceFilter
+        .validate(&storage, &auth, &cfg, &article("alt.test"), 0)
+        .await
+        .unwrap();
+    let err = GroupExistenceFilter
+        .validate(&storage, &auth, &cfg, &article("alt.test,alt.missing"), 0)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "group does not exist: alt.missing");
+}
+
+fn crosspost(newsgroups: &str, extra: &[(&str, &str)]) -> Message {
+    let mut article = Message {
+        headers: smallvec![("Newsgroups".to_string(), newsgroups.to_string())],
+        body: "Test body".into(),
+    };
+    for (name, value) in extra {
+        article
+            .headers
+            .push(((*name).to_string(), (*value).to_string()));
+    }
+    article
+}
+
+#[tokio::test]
+async fn test_crosspost_filter_allows_exactly_the_limit() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.max_crosspost_groups = Some(2);
+
+    let at_limit = crosspost("a.one,a.two", &[]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &at_limit, 100)
+            .await
+            .is_ok()
+    );
+
+    let over = crosspost("a.one, a.two, a.three", &[]);
+    let err = CrosspostFilter
+        .validate(&storage, &auth, &cfg, &over, 100)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Rejection>(),
+        Some(&Rejection::Crosspost(CrosspostRejection::TooManyGroups {
+            groups: 3,
+            limit: 2
+        }))
+    );
+}
+
+#[tokio::test]
+async fn test_crosspost_filter_counts_distinct_trimmed_groups() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.max_crosspost_groups = Some(2);
+
+    let article = crosspost("a.one, a.two,a.one ,, a.two", &[]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &article, 100)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_crosspost_filter_applies_smallest_group_limit() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.max_crosspost_groups = Some(5);
+    cfg.group_settings.push(renews::config::GroupRule {
+        group: None,
+        pattern: Some("strict.*".to_string()),
+        retention_days: None,
+        retention: None,
+        max_article_bytes: None,
+        body_truncate_bytes: None,
+        max_articles: None,
+        max_crosspost_groups: Some(1),
+        no_binaries: None,
+    });
+
+    let elsewhere = crosspost("a.one,a.two,a.three", &[]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &elsewhere, 100)
+            .await
+            .is_ok()
+    );
+    let alone = crosspost("strict.one", &[]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &alone, 100)
+            .await
+            .is_ok()
+    );
+    let strict = crosspost("a.one,strict.one", &[]);
+    let err = CrosspostFilter
+        .validate(&storage, &auth, &cfg, &strict, 100)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "crossposted to 2 groups; the limit is 1");
+}
+
+#[tokio::test]
+async fn test_crosspost_filter_exempts_control_messages() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.max_crosspost_groups = Some(1);
+    cfg.require_followup_to_above = Some(1);
+
+    let cancel = crosspost("a.one,a.two,a.three", &[("Control", "cancel <x@test>")]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &cancel, 100)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_crosspost_filter_requires_followup_to_above_threshold() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let mut cfg = create_test_config();
+    cfg.require_followup_to_above = Some(2);
+
+    let at_threshold = crosspost("a.one,a.two", &[]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &at_threshold, 100)
+            .await
+            .is_ok()
+    );
+
+    let above = crosspost("a.one,a.two,a.three", &[]);
+    let err = CrosspostFilter
+        .validate(&storage, &auth, &cfg, &above, 100)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Rejection>(),
+        Some(&Rejection::Crosspost(
+            CrosspostRejection::FollowupToRequired {
+                groups: 3,
+                threshold: 2
+            }
+        ))
+    );
+
+    let scattered = crosspost("a.one,a.two,a.three", &[("Followup-To", "a.one,a.two")]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &scattered, 100)
+            .await
+            .is_err()
+    );
+
+    let directed = crosspost("a.one,a.two,a.three", &[("Followup-To", " a.two ")]);
+    assert!(
+        CrosspostFilter
+            .validate(&storage, &auth, &cfg, &directed, 100)
+            .await
+            .is_ok()
+    );
+}
+
+/// The test configuration with `rules`, a run of `[[header_rules]]` tables.
+fn config_with_header_rules(rules: &str) -> Config {
+    let toml = format!(
+        r#"
+addr = ":119"
+db_path = "sqlite:///:memory:"
+auth_db_path = "sqlite:///:memory:"
+peer_db_path = "sqlite:///:memory:"
+site_name = "test.local"
+{rules}
+"#
+    );
+    toml::from_str(&toml).unwrap()
+}
+
+async fn header_rules_verdict(cfg: &Config, headers: &[(&str, &str)]) -> Result<()> {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let article = crosspost("alt.test", headers);
+    HeaderRulesFilter
+        .validate(&storage, &auth, cfg, &article, 100)
+        .await
+}
+
+#[tokio::test]
+async fn test_header_rules_reject_matching_header_in_any_case() {
+    let cfg = config_with_header_rules(
+        r#"
+[[header_rules]]
+name = "no-spamware"
+header = "x-newsreader"
+pattern = "SpamBlaster*"
+"#,
+    );
+
+    let err = header_rules_verdict(&cfg, &[("X-Newsreader", "spamblaster 2.0")])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Rejection>(),
+        Some(&Rejection::HeaderRule(HeaderRuleRejection {
+            rule: "no-spamware".to_string(),
+            action: HeaderAction::Reject,
+        }))
+    );
+    assert!(err.to_string().contains("no-spamware"), "{err}");
+
+    assert!(
+        header_rules_verdict(&cfg, &[("X-Newsreader", "tin 2.6")])
+            .await
+            .is_ok()
+    );
+    assert!(header_rules_verdict(&cfg, &[]).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_header_rules_require_refuses_missing_header() {
+    let cfg = config_with_header_rules(
+        r#"
+[[header_rules]]
+header = "Organization"
+pattern = "*"
+action = "require"
+"#,
+    );
+
+    let err = header_rules_verdict(&cfg, &[]).await.unwrap_err();
+    let Some(Rejection::HeaderRule(rejection)) = err.downcast_ref::<Rejection>() else {
+        panic!("not a header rule rejection: {err}");
+    };
+    assert_eq!(rejection.rule, "Organization: *");
+    assert_eq!(rejection.action, HeaderAction::Require);
+
+    assert!(
+        header_rules_verdict(&cfg, &[("organization", "Example")])
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_header_rules_first_broken_rule_decides() {
+    let cfg = config_with_header_rules(
+        r#"
+[[header_rules]]
+name = "first"
+header = "Subject"
+pattern = "*money*"
+
+[[header_rules]]
+name = "second"
+header = "Subject"
+pattern = "*"
+action = "require"
+"#,
+    );
+
+    let err = header_rules_verdict(&cfg, &[("Subject", "make money fast")])
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err.downcast_ref(), Some(Rejection::HeaderRule(r)) if r.rule == "first"),
+        "{err}"
+    );
+
+    let err = header_rules_verdict(&cfg, &[]).await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref(), Some(Rejection::HeaderRule(r)) if r.rule == "second"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn test_header_rules_wildmat_escapes_and_regex_characters() {
+    // `\*` is a literal star and `.` and `+` carry no regex meaning
+    let cfg = config_with_header_rules(
+        r#"
+[[header_rules]]
+header = "Subject"
+pattern = 'a.b+\*'
+"#,
+    );
+    assert!(
+        header_rules_verdict(&cfg, &[("Subject", "a.b+*")])
+            .await
+            .is_err()
+    );
+    for subject in ["axb+*", "a.bb*", "a.b+x"] {
+        assert!(
+            header_rules_verdict(&cfg, &[("Subject", subject)])
+                .await
+                .is_ok(),
+            "{subject}"
+        );
+    }
+
+    let cfg = config_with_header_rules(
+        r#"
+[[header_rules]]
+header = "Subject"
+pattern = '^\[ad\]'
+regex = true
+"#,
+    );
+    assert!(
+        header_rules_verdict(&cfg, &[("Subject", "[ad] buy now")])
+            .await
+            .is_err()
+    );
+    assert!(
+        header_rules_verdict(&cfg, &[("Subject", "[AD] buy now")])
+            .await
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_header_rules_with_invalid_regex_fail_to_load() {
+    let toml = r#"
+addr = ":119"
+db_path = "sqlite:///:memory:"
+auth_db_path = "sqlite:///:memory:"
+peer_db_path = "sqlite:///:memory:"
+site_name = "test.local"
+
+[[header_rules]]
+header = "Subject"
+pattern = "(unclosed"
+regex = true
+"#;
+    let Err(err) = toml::from_str::<Config>(toml) else {
+        panic!("an invalid regex must be refused");
+    };
+    assert!(err.to_string().contains("(unclosed"), "{err}");
+}
+
+fn hook(command: Option<&str>, url: Option<String>, on_timeout: FailurePolicy) -> ExternalFilter {
+    ExternalFilter::new(ExternalHookConfig {
+        command: command.map(|_| "sh".to_string()),
+        args: command
+            .map(|script| vec!["-c".to_string(), script.to_string()])
+            .unwrap_or_default(),
+        url,
+        timeout_secs: 1,
+        on_timeout,
+        max_output: 64,
+    })
+    .unwrap()
+}
+
+/// The external checker's refusal in `err`, if that is what it is.
+fn hook_rejection(err: &anyhow::Error) -> Option<&HookRejection> {
+    match err.downcast_ref() {
+        Some(Rejection::Hook(rejection)) => Some(rejection),
+        _ => None,
+    }
+}
+
+async fn hook_verdict(filter: &dyn ArticleFilter) -> Result<()> {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    filter
+        .validate(&storage, &auth, &cfg, &scanned_article(), 100)
+        .await
+}
+
+fn submitter() -> Submitter {
+    Submitter {
+        user: Some("alice".to_string()),
+        ip: Some("192.0.2.1".parse().unwrap()),
+    }
+}
+
+#[tokio::test]
+async fn test_external_hook_command_gets_article_and_metadata() {
+    let script = r#"grep -q '^Subject: Buy now' \
+        && [ "$RENEWS_ARTICLE_SIZE" = 100 ] \
+        && [ "$RENEWS_USER" = alice ] \
+        && [ "$RENEWS_CLIENT_IP" = 192.0.2.1 ]"#;
+    let filter = hook(Some(script), None, FailurePolicy::Closed);
+    let specialised = filter.for_submitter(&submitter()).unwrap();
+    assert!(hook_verdict(specialised.as_ref()).await.is_ok());
+
+    // Without a known submitter the variables are not set
+    assert!(hook_verdict(&filter).await.is_err());
+}
+
+#[tokio::test]
+async fn test_external_hook_command_output_is_the_reason() {
+    let filter = hook(
+        Some("cat >/dev/null; echo 'too many links'; exit 1"),
+        None,
+        FailurePolicy::Open,
+    );
+    let err = hook_verdict(&filter).await.unwrap_err();
+    let rejection = hook_rejection(&err).unwrap();
+    assert_eq!(rejection.reason, "too many links");
+    assert_eq!(
+        rejection.to_string(),
+        "rejected by external filter: too many links"
+    );
+}
+
+#[tokio::test]
+async fn test_external_hook_command_output_is_capped() {
+    let filter = hook(
+        Some("head -c 200000 /dev/zero | tr '\\0' x; exit 2"),
+        None,
+        FailurePolicy::Open,
+    );
+    let err = hook_verdict(&filter).await.unwrap_err();
+    let rejection = hook_rejection(&err).unwrap();
+    assert_eq!(rejection.reason, "x".repeat(64));
+}
+
+#[tokio::test]
+async fn test_external_hook_timeout_follows_policy() {
+    let open = hook(Some("sleep 5"), None, FailurePolicy::Open);
+    assert!(hook_verdict(&open).await.is_ok());
+
+    let closed = hook(Some("sleep 5"), None, FailurePolicy::Closed);
+    let err = hook_verdict(&closed).await.unwrap_err();
+    assert!(hook_rejection(&err).is_none());
+}
+
+/// Serve one webhook call with `response`, handing back the request.
+async fn webhook(response: &'static str) -> (String, tokio::sync::oneshot::Receiver<String>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/check", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // Read until the whole body named by Content-Length has arrived
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("Content-Length: "))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break;
+                }
+            }
+        }
+        socket.write_all(response.as_bytes()).await.unwrap();
+        let _ = socket.shutdown().await;
+        let _ = tx.send(String::from_utf8_lossy(&request).into_owned());
+    });
+    (url, rx)
+}
+
+#[tokio::test]
+async fn test_external_hook_webhook_accepts_on_200() {
+    let (url, request) =
+        webhook("HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+    let filter = hook(None, Some(url), FailurePolicy::Closed);
+    let specialised = filter.for_submitter(&submitter()).unwrap();
+    assert!(hook_verdict(specialised.as_ref()).await.is_ok());
+
+    let request = request.await.unwrap();
+    assert!(request.starts_with("POST /check HTTP/1.1\r\n"), "{request}");
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    let document: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(document["size"], 100);
+    assert_eq!(document["user"], "alice");
+    assert_eq!(document["client_ip"], "192.0.2.1");
+    assert_eq!(document["headers"][1][0], "Subject");
+    assert_eq!(document["headers"][1][1], "Buy now");
+    assert_eq!(document["body"], "Q2hlYXAgd2F0Y2hlcw==");
+}
+
+#[tokio::test]
+async fn test_external_hook_webhook_rejects_with_body_as_reason() {
+    let (url, _request) =
+        webhook("HTTP/1.1 403 Forbidden\r\nContent-Length: 14\r\n\r\nspam detected\n").await;
+    let filter = hook(None, Some(url), FailurePolicy::Open);
+    let err = hook_verdict(&filter).await.unwrap_err();
+    assert_eq!(hook_rejection(&err).unwrap().reason, "spam detected");
+}
+
+#[tokio::test]
+async fn test_external_hook_webhook_reason_is_capped() {
+    let (url, _request) = webhook(
+        "HTTP/1.1 403 Forbidden\r\nContent-Length: 100\r\n\r\n\
+         xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx",
+    )
+    .await;
+    let filter = hook(None, Some(url), FailurePolicy::Open);
+    let err = hook_verdict(&filter).await.unwrap_err();
+    assert_eq!(hook_rejection(&err).unwrap().reason, "x".repeat(64));
+}
+
+#[tokio::test]
+async fn test_external_hook_webhook_without_content_length_follows_policy() {
+    let response =
+        "HTTP/1.1 403 Forbidden\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nspam\r\n0\r\n\r\n";
+    let (url, _request) = webhook(response).await;
+    let open = hook(None, Some(url), FailurePolicy::Open);
+    assert!(hook_verdict(&open).await.is_ok());
+
+    let (url, _request) = webhook(response).await;
+    let closed = hook(None, Some(url), FailurePolicy::Closed);
+    let err = hook_verdict(&closed).await.unwrap_err();
+    assert!(hook_rejection(&err).is_none());
+}
+
+#[tokio::test]
+async fn test_external_hook_unreachable_webhook_follows_policy() {
+    // Bound and dropped, so nothing listens there
+    let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let url = format!("http://{addr}/check");
+    let open = hook(None, Some(url.clone()), FailurePolicy::Open);
+    assert!(hook_verdict(&open).await.is_ok());
+    let closed = hook(None, Some(url), FailurePolicy::Closed);
+    assert!(hook_verdict(&closed).await.is_err());
+}
+
+fn spam_config(max_duplicates: u32) -> Config {
+    config_with_header_rules(&format!(
+        "[spam]\nmax_duplicates = {max_duplicates}\nduplicate_window = 60\n"
+    ))
+}
+
+fn copy(id: &str, body: &str) -> Message {
+    Message {
+        headers: smallvec![
+            ("Message-ID".to_string(), id.to_string()),
+            ("From".to_string(), "flood@example.com".to_string()),
+            ("Subject".to_string(), "Buy now".to_string()),
+            ("Newsgroups".to_string(), "alt.test,misc.test".to_string()),
+        ],
+        body: body.into(),
+    }
+}
+
+#[test]
+fn test_body_hash_ignores_quotes_and_whitespace() {
+    let plain = body_hash("Buy cheap  watches\nnow!\n").unwrap();
+    assert_eq!(
+        body_hash("> you wrote\r\n  Buy cheap watches\r\n\r\nnow!  \r\n").unwrap(),
+        plain
+    );
+    assert_ne!(body_hash("Buy cheap clocks now!").unwrap(), plain);
+    assert!(body_hash("> only a quote\n \n").is_none());
+}
+
+#[tokio::test]
+async fn test_duplicate_body_filter_rejects_copies_over_the_limit() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = spam_config(2);
+    let filter = DuplicateBodyFilter::default();
+
+    for id in ["<one@test>", "<two@test>"] {
+        let article = copy(id, "Buy cheap watches now!");
+        assert!(
+            filter
+                .validate(&storage, &auth, &cfg, &article, 100)
+                .await
+                .is_ok()
+        );
+    }
+    let third = copy("<three@test>", "Buy  cheap watches\nnow!");
+    let err = filter
+        .validate(&storage, &auth, &cfg, &third, 100)
+        .await
+        .unwrap_err();
+    let Some(Rejection::DuplicateBody(rejection)) = err.downcast_ref::<Rejection>() else {
+        panic!("not a duplicate body rejection: {err}");
+    };
+    assert_eq!(rejection.copies, 2);
+    assert_eq!(rejection.window_minutes, 60);
+
+    let other = copy("<four@test>", "Something else entirely");
+    assert!(
+        filter
+            .validate(&storage, &auth, &cfg, &other, 100)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_duplicate_body_filter_shares_recent_bodies() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = spam_config(1);
+    let recent = Arc::new(RecentBodies::new(10));
+
+    let first = DuplicateBodyFilter::with_recent(recent.clone());
+    let article = copy("<one@test>", "Buy cheap watches now!");
+    assert!(
+        first
+            .validate(&storage, &auth, &cfg, &article, 100)
+            .await
+            .is_ok()
+    );
+    assert_eq!(recent.len(), 1);
+
+    // A filter on another connection sees the same sightings
+    let second = DuplicateBodyFilter::with_recent(recent);
+    let again = copy("<two@test>", "Buy cheap watches now!");
+    assert!(
+        second
+            .validate(&storage, &auth, &cfg, &again, 100)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_duplicate_body_filter_is_off_by_default() {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    let cfg = create_test_config();
+    let filter = DuplicateBodyFilter::default();
+
+    for id in ["<one@test>", "<two@test>", "<three@test>"] {
+        let article = copy(id, "Buy cheap watches now!");
+        assert!(
+            filter
+                .validate(&storage, &auth, &cfg, &article, 100)
+                .await
+                .is_ok()
+        );
+    }
+}
+
+#[test]
+fn test_recent_bodies_drops_stalest_when_full() {
+    let recent = RecentBodies::new(2);
+    let now = chrono::Utc::now();
+    let since = now - chrono::Duration::minutes(60);
+    recent.put("old", vec![now - chrono::Duration::minutes(90)], since);
+    recent.put("a", vec![now - chrono::Duration::minutes(5)], since);
+    recent.put("b", vec![now], since);
+    assert_eq!(recent.len(), 2);
+    assert!(recent.get("old").is_none());
+    recent.put("c", vec![now], since);
+    assert!(recent.get("a").is_none());
+    assert!(recent.get("c").is_some());
+}
+
+fn text_only_config() -> Config {
+    config_with_header_rules(
+        r#"
+[[group_settings]]
+pattern = "comp.*"
+no_binaries = true
+"#,
+    )
+}
+
+fn mime_article(groups: &str, headers: &[(&str, &str)], body: &str) -> Message {
+    let mut article = crosspost(groups, headers);
+    article.body = body.into();
+    article
+}
+
+/// The MIME filter's refusal in `err`.
+fn mime_rejection(err: &anyhow::Error) -> &MimeRejection {
+    match err.downcast_ref() {
+        Some(Rejection::Mime(rejection)) => rejection,
+        _ => panic!("not a MIME rejection: {err}"),
+    }
+}
+
+async fn mime_verdict(article: &Message) -> Result<()> {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    MimeFilter
+        .validate(&storage, &auth, &text_only_config(), article, 100)
+        .await
+}
+
+const MULTIPART_WITH_IMAGE: &str = "--sep\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n--sep\r\nContent-Type: image/png; name=\"a.png\"\r\nContent-Transfer-Encoding: base64\r\n\r\niVBORw0KGgo=\r\n--sep--\r\n";
+
+#[tokio::test]
+async fn test_mime_filter_refuses_image_part_in_text_group() {
+    let headers = [("Content-Type", "multipart/mixed; boundary=\"sep\"")];
+    let article = mime_article("comp.lang.rust", &headers, MULTIPART_WITH_IMAGE);
+    let err = mime_verdict(&article).await.unwrap_err();
+    let rejection = mime_rejection(&err);
+    assert_eq!(rejection.group, "comp.lang.rust");
+    assert_eq!(
+        rejection.content,
+        MimeContent::Base64("image/png".to_string())
+    );
+    assert_eq!(
+        rejection.to_string(),
+        "base64 encoded image/png part not allowed in comp.lang.rust"
+    );
+
+    // Only groups marked no_binaries are checked
+    let elsewhere = mime_article("misc.test", &headers, MULTIPART_WITH_IMAGE);
+    assert!(mime_verdict(&elsewhere).await.is_ok());
+    let binaries = mime_article("comp.binaries.misc", &headers, MULTIPART_WITH_IMAGE);
+    assert!(mime_verdict(&binaries).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_mime_filter_checks_media_types_and_yenc() {
+    let plain = mime_article("comp.lang.rust", &[], "Just text.\r\n");
+    assert!(mime_verdict(&plain).await.is_ok());
+
+    let signed = mime_article(
+        "comp.lang.rust",
+        &[("Content-Type", "multipart/signed; boundary=s")],
+        "--s\r\nContent-Type: text/plain\r\n\r\nHi\r\n--s\r\nContent-Type: application/pgp-signature\r\n\r\n-----BEGIN PGP SIGNATURE-----\r\n--s--\r\n",
+    );
+    assert!(mime_verdict(&signed).await.is_ok());
+
+    let zip = mime_article(
+        "comp.lang.rust",
+        &[("Content-Type", "application/zip")],
+        "PK\r\n",
+    );
+    let err = mime_verdict(&zip).await.unwrap_err();
+    assert_eq!(
+        mime_rejection(&err).content,
+        MimeContent::MediaType("application/zip".to_string())
+    );
+
+    let yenc = mime_article(
+        "comp.lang.rust",
+        &[],
+        "Here it is\r\n=ybegin line=128 size=3 name=a.bin\r\nabc\r\n=yend size=3\r\n",
+    );
+    let err = mime_verdict(&yenc).await.unwrap_err();
+    assert_eq!(mime_rejection(&err).content, MimeContent::Yenc);
+}
+
+fn well_formed(replace: &str, value: &str) -> Message {
+    let mut article = crosspost(
+        "comp.lang.rust",
+        &[
+            ("Message-ID", "<syntax@test>"),
+            ("Date", "Wed, 05 Oct 2022 00:00:00 GMT"),
+            ("From", "Poster <poster@example.com>"),
+            ("Subject", "syntax"),
+        ],
+    );
+    for (name, v) in article.headers.iter_mut() {
+        if name.as_str() == replace {
+            *v = value.to_string();
+        }
+    }
+    article
+}
+
+async fn syntax_verdict(article: &Message) -> Result<()> {
+    let storage = create_mock_storage().await;
+    let auth = create_mock_auth().await;
+    HeaderSyntaxFilter
+        .validate(&storage, &auth, &create_test_config(), article, 100)
+        .await
+}
+
+#[tokio::test]
+async fn test_header_syntax_filter_accepts_well_formed_headers() {
+    assert!(syntax_verdict(&well_formed("", "")).await.is_ok());
+    for from in [
+        "poster@example.com",
+        "poster@example.com (Poster)",
+        "\"P. Oster\" <p@example.com>",
+    ] {
+        assert!(
+            syntax_verdict(&well_formed("From", from)).await.is_ok(),
+            "{from}"
+        );
+    }
+    let dated = well_formed("Date", "6 Oct 1998 04:38:40 -0500");
+    assert!(syntax_verdict(&dated).await.is_ok());
+    let encoded = well_formed("Subject", "=?UTF-8?Q?Gr=C3=BC=C3=9Fe?=");
+    assert!(syntax_verdict(&encoded).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_header_syntax_filter_refuses_malformed_headers() {
+    let cases = [
+        (
+            "Message-ID",
+            "syntax@test",
+            "malformed Message-ID syntax@test",
+        ),
+        (
+            "Message-ID",
+            "<syntax.test>",
+            "malformed Message-ID <syntax.test>",
+        ),
+        (
+            "Message-ID",
+            "<syn tax@test>",
+            "malformed Message-ID <syn tax@test>",
+        ),
+        ("Message-ID", "<@test>", "malformed Message-ID <@test>"),
+        ("Date", "yesterday", "unparseable Date \"yesterday\""),
+        (
+            "Date",
+            "2022-10-05T00:00:00Z",
+            "unparseable Date \"2022-10-05T00:00:00Z\"",
+        ),
+        ("From", "Poster", "no valid address in From \"Poster\""),
+        (
+            "From",
+            "Poster <poster>",
+            "no valid address in From \"Poster <poster>\"",
+        ),
+        ("From", "poster@", "no valid address in From \"poster@\""),
+        (
+            "Newsgroups",
+            "Comp.Lang.Rust",
+            "invalid newsgroup name \"Comp.Lang.Rust\"",
+        ),
+        (
+            "Newsgroups",
+            "comp..rust",
+            "invalid newsgroup name \"comp..rust\"",
+        ),
+        (
+            "Newsgroups",
+            "misc.test,alt.2600",
+            "invalid newsgroup name \"alt.2600\"",
+        ),
+        ("Newsgroups", " ", "empty Newsgroups header"),
+        (
+            "Subject",
+            "Grüße",
+            "8-bit or control characters in Subject header",
+        ),
+        (
+            "Subject",
+            "bell\u{7}",
+            "8-bit or control characters in Subject header",
+        ),
+    ];
+    for (header, value, reason) in cases {
+        let err = syntax_verdict(&well_formed(header, value))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(Rejection::HeaderSyntax(_))),
+            "{value}"
+        );
+        assert_eq!(err.to_string(), reason);
+    }
+}
+
+#[test]
+fn test_newsgroup_and_message_id_grammar() {
+    for name in ["comp.graphics.x3d", "alt.c++", "misc.test-group", "a.b_c"] {
+        assert!(valid_newsgroup_name(name), "{name}");
+    }
+    for name in [
+        "",
+        ".misc",
+        "misc.",
+        "misc._private",
+        "misc.te st",
+        "misc.123",
+        "comp.graphics.3d",
+    ] {
+        assert!(!valid_newsgroup_name(name), "{name}");
+    }
+    assert!(valid_message_id("<a.b$c@host.example>"));
+    assert!(!valid_message_id("<a<b@host>"));
+    assert!(!valid_message_id(&format!("<{}@host>", "x".repeat(250))));
+}
@@ -0,0 +1,131 @@
+use renews::config::RateLimitConfig;
+use renews::ratelimit::{RateLimitKey, RateLimitStats, RateLimiter};
+use std::time::{Duration, Instant};
+
+fn limits(articles: Option<u32>, bytes: Option<u64>) -> RateLimitConfig {
+    RateLimitConfig {
+        articles_per_minute: articles,
+        bytes_per_minute: bytes,
+        posts_per_hour: None,
+    }
+}
+
+#[test]
+fn article_bucket_refills_over_time() {
+    let limiter = RateLimiter::new();
+    let limits = limits(Some(2), None);
+    let key = [RateLimitKey::User("alice".into())];
+    let start = Instant::now();
+    assert!(limiter.check_at(&key, 100, &limits, start));
+    assert!(limiter.check_at(&key, 100, &limits, start));
+    assert!(!limiter.check_at(&key, 100, &limits, start));
+    // one article's worth of tokens comes back every 30 seconds
+    assert!(limiter.check_at(&key, 100, &limits, start + Duration::from_secs(30)));
+    assert!(!limiter.check_at(&key, 100, &limits, start + Duration::from_secs(31)));
+    assert_eq!(
+        limiter.stats(),
+        RateLimitStats {
+            tracked: 1,
+            admitted: 3,
+            throttled: 2,
+        }
+    );
+}
+
+#[test]
+fn every_key_must_have_room() {
+    let limiter = RateLimiter::new();
+    let limits = limits(Some(1), None);
+    let ip = RateLimitKey::Ip("192.0.2.1".parse().unwrap());
+    let alice = [RateLimitKey::User("alice".into()), ip.clone()];
+    let bob = [RateLimitKey::User("bob".into()), ip.clone()];
+    let carol = [RateLimitKey::User("carol".into())];
+    let now = Instant::now();
+    assert!(limiter.check_at(&alice, 1, &limits, now));
+    // bob shares alice's address, which is now out of tokens
+    assert!(!limiter.check_at(&bob, 1, &limits, now));
+    assert!(limiter.check_at(&carol, 1, &limits, now));
+    assert!(limiter.is_throttled(&[ip], &limits));
+}
+
+#[test]
+fn oversized_article_leaves_byte_bucket_in_debt() {
+    let limiter = RateLimiter::new();
+    let limits = limits(None, Some(1000));
+    let key = [RateLimitKey::User("alice".into())];
+    let start = Instant::now();
+    assert!(limiter.check_at(&key, 1500, &limits, start));
+    assert!(!limiter.check_at(&key, 1, &limits, start + Duration::from_secs(30)));
+    assert!(limiter.check_at(&key, 1, &limits, start + Duration::from_secs(31)));
+}
+
+#[test]
+fn disabled_limits_admit_everything() {
+    let limiter = RateLimiter::new();
+    let key = [RateLimitKey::User("alice".into())];
+    for _ in 0..100 {
+        assert!(limiter.check(&key, 1 << 20, &RateLimitConfig::default()));
+    }
+    assert!(!limiter.is_throttled(&key, &RateLimitConfig::default()));
+    assert_eq!(limiter.stats().tracked, 0);
+}
+
+#[test]
+fn hourly_bucket_outlasts_idle_expiry() {
+    let limiter = RateLimiter::new();
+    let limits = RateLimitConfig {
+        posts_per_hour: Some(2),
+        ..RateLimitConfig::default()
+    };
+    let key = [RateLimitKey::User("alice".into())];
+    let start = Instant::now();
+    assert!(limiter.check_at(&key, 1, &limits, start));
+    assert!(limiter.check_at(&key, 1, &limits, start));
+    assert!(!limiter.check_at(&key, 1, &limits, start));
+    // Quiet for longer than the per-minute expiry, but only half a post back
+    let later = start + Duration::from_secs(900);
+    assert!(!limiter.check_at(&key, 1, &limits, later));
+    let wait = limiter.retry_after_at(&key, &limits, later);
+    assert_eq!(wait.as_secs_f64().round(), 900.0);
+    assert!(limiter.check_at(&key, 1, &limits, start + Duration::from_secs(1800)));
+}
+
+#[test]
+fn retry_after_is_zero_with_room() {
+    let limiter = RateLimiter::new();
+    let limits = limits(Some(1), None);
+    let key = [RateLimitKey::User("alice".into())];
+    let start = Instant::now();
+    assert_eq!(limiter.retry_after_at(&key, &limits, start), Duration::ZERO);
+    assert!(limiter.check_at(&key, 1, &limits, start));
+    let wait = limiter.retry_after_at(&key, &limits, start + Duration::from_secs(15));
+    assert_eq!(wait.as_secs_f64().round(), 45.0);
+}
+
+#[test]
+fn idle_buckets_are_swept() {
+    let limiter = RateLimiter::new();
+    let limits = limits(Some(5), None);
+    let alice = [RateLimitKey::User("alice".into())];
+    let bob = [RateLimitKey::User("bob".into())];
+    let start = Instant::now();
+    assert!(limiter.check_at(&alice, 1, &limits, start));
+    assert!(limiter.check_at(&bob, 1, &limits, start + Duration::from_secs(200)));
+    assert_eq!(limiter.stats().tracked, 2);
+    assert!(limiter.check_at(&bob, 1, &limits, start + Duration::from_secs(700)));
+    assert_eq!(limiter.stats().tracked, 1);
+}
+
+#[test]
+fn unset_limits_are_not_charged() {
+    let limiter = RateLimiter::new();
+    let key = [RateLimitKey::User("alice".into())];
+    let start = Instant::now();
+    for _ in 0..5 {
+        assert!(limiter.check_at(&key, 1, &limits(None, Some(1000)), start));
+    }
+    // An article limit turned on later starts from an empty bucket, not
+    // from the articles sent while it was unset
+    let later = start + Duration::from_secs(60);
+    assert!(limiter.check_at(&key, 1, &limits(Some(1), Some(1000)), later));
+}
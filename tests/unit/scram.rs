@@ -0,0 +1,75 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use renews::auth::scram::{ClientFirst, ScramCredentials};
+
+/// The SCRAM-SHA-256 example exchange from RFC 7677 section 3.
+fn rfc7677_credentials() -> ScramCredentials {
+    let salt = STANDARD.decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap();
+    ScramCredentials::with_salt("pencil", salt, 4096)
+}
+
+#[test]
+fn rfc7677_exchange() {
+    let first = ClientFirst::parse("n,,n=user,r=rOprNGfwEbeRWgbNEkqO").unwrap();
+    assert_eq!(first.username, "user");
+    assert_eq!(first.authzid, None);
+
+    let (server, server_first) =
+        first.respond_with_nonce(rfc7677_credentials(), "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0");
+    assert_eq!(
+        server_first,
+        "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"
+    );
+
+    let server_final = server
+        .finish(
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+             p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+        )
+        .unwrap();
+    assert_eq!(
+        server_final,
+        "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="
+    );
+}
+
+#[test]
+fn wrong_proof_is_rejected() {
+    let first = ClientFirst::parse("n,,n=user,r=rOprNGfwEbeRWgbNEkqO").unwrap();
+    let (server, _) =
+        first.respond_with_nonce(rfc7677_credentials(), "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0");
+    let bad_proof = STANDARD.encode([0u8; 32]);
+    assert!(
+        server
+            .finish(&format!(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p={bad_proof}"
+            ))
+            .is_err()
+    );
+}
+
+#[test]
+fn nonce_must_extend_client_nonce() {
+    let first = ClientFirst::parse("n,,n=user,r=abc").unwrap();
+    let (server, _) = first.respond_with_nonce(rfc7677_credentials(), "xyz");
+    let proof = STANDARD.encode([0u8; 32]);
+    assert!(server.finish(&format!("c=biws,r=abc,p={proof}")).is_err());
+}
+
+#[test]
+fn client_first_parsing() {
+    let first = ClientFirst::parse("y,a=us=2Cer,n=us=3Der,r=nonce").unwrap();
+    assert_eq!(first.username, "us=er");
+    assert_eq!(first.authzid.as_deref(), Some("us,er"));
+    assert!(ClientFirst::parse("p=tls-unique,,n=user,r=nonce").is_err());
+    assert!(ClientFirst::parse("n,,n=user").is_err());
+    assert!(ClientFirst::parse("n,,r=nonce").is_err());
+    assert!(ClientFirst::parse("n,,n=bad=2Xname,r=nonce").is_err());
+}
+
+#[test]
+fn credentials_round_trip() {
+    let creds = ScramCredentials::derive("secret");
+    let parsed: ScramCredentials = creds.to_string().parse().unwrap();
+    assert_eq!(parsed, creds);
+    assert!("4096:c2FsdA==:AAAA".parse::<ScramCredentials>().is_err());
+}
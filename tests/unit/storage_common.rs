@@ -1,6 +1,9 @@
 use renews::Message;
-use renews::storage::common::{Headers, extract_message_id};
+use renews::storage::common::{
+    Headers, batch_groups, batch_rows, extract_message_id, path_contains, stamp_path,
+};
 use smallvec::smallvec;
+use std::collections::HashMap;
 
 #[test]
 fn test_extract_message_id_present() {
@@ -70,3 +73,75 @@ fn test_headers_serialization() {
 
     assert_eq!(headers.0, deserialized.0);
 }
+
+#[test]
+fn test_stamp_path_prepends_or_adds() {
+    let mut article = Message {
+        headers: smallvec![("Path".into(), "peer!origin".into())],
+        body: Vec::new(),
+    };
+    stamp_path(&mut article, "site");
+    assert_eq!(article.headers[0].1, "site!peer!origin");
+    // Stamping twice does not repeat the name
+    stamp_path(&mut article, "site");
+    assert_eq!(article.headers[0].1, "site!peer!origin");
+
+    let mut local = Message {
+        headers: smallvec![("Subject".into(), "local".into())],
+        body: Vec::new(),
+    };
+    stamp_path(&mut local, "site");
+    assert_eq!(local.headers[1], ("Path".into(), "site".into()));
+}
+
+#[test]
+fn test_path_contains_matches_whole_entries() {
+    let article = Message {
+        headers: smallvec![("Path".into(), "hub.example!site!not-for-mail".into())],
+        body: Vec::new(),
+    };
+    assert!(path_contains(&article, "site"));
+    assert!(!path_contains(&article, "hub"));
+    assert!(!path_contains(&article, "other"));
+}
+
+#[test]
+fn test_batch_rows_continue_numbering_per_group() {
+    let articles: Vec<Message> = ["<a@x>", "<b@x>", "<a@x>"]
+        .into_iter()
+        .zip(["g1, g2", "g2", "g1"])
+        .map(|(id, groups)| Message {
+            headers: smallvec![
+                ("Message-ID".into(), id.into()),
+                ("Newsgroups".into(), groups.into()),
+            ],
+            body: "Body".into(),
+        })
+        .collect();
+    assert_eq!(
+        batch_groups(&articles).into_iter().collect::<Vec<_>>(),
+        vec!["g1", "g2"]
+    );
+
+    let mut next = HashMap::from([("g1".to_string(), 5)]);
+    let (messages, groups) = batch_rows(&articles, &mut next).unwrap();
+
+    // A Message-ID repeated in the batch is stored once but numbered again
+    assert_eq!(messages.len(), 2);
+    let numbered: Vec<_> = groups
+        .iter()
+        .map(|g| (g.group.as_str(), g.number, g.message_id.as_str()))
+        .collect();
+    assert_eq!(
+        numbered,
+        vec![
+            ("g1", 5, "<a@x>"),
+            ("g2", 1, "<a@x>"),
+            ("g2", 2, "<b@x>"),
+            ("g1", 6, "<a@x>"),
+        ]
+    );
+    assert!(groups[0].overview.starts_with("5\t"));
+    assert_eq!(next["g1"], 7);
+    assert_eq!(next["g2"], 3);
+}
@@ -3,9 +3,9 @@
 use rcgen::{CertifiedKey, generate_simple_self_signed};
 use renews::auth::AuthProvider;
 use renews::config::Config;
-use renews::handle_client;
 use renews::queue::ArticleQueue;
 use renews::storage::Storage;
+use renews::{handle_client, handle_client_with_cert};
 use std::sync::Arc;
 use tokio::io::BufReader;
 use tokio::io::{self, ReadHalf, WriteHalf};
@@ -46,12 +46,16 @@ pub fn capabilities_lines() -> Vec<String> {
         "VERSION 2".into(),
         format!("IMPLEMENTATION Renews {}", env!("CARGO_PKG_VERSION")),
         "READER".into(),
+        "MODE-READER".into(),
         "NEWNEWS".into(),
         "IHAVE".into(),
         "STREAMING".into(),
+        "COMPRESS DEFLATE".into(),
         "OVER MSGID".into(),
         "HDR".into(),
-        "LIST ACTIVE NEWSGROUPS ACTIVE.TIMES OVERVIEW.FMT HEADERS".into(),
+        "LIST ACTIVE NEWSGROUPS ACTIVE.TIMES COUNTS OVERVIEW.FMT HEADERS".into(),
+        "AUTHINFO USER SASL".into(),
+        "SASL SCRAM-SHA-256".into(),
         ".".into(),
     ]
 }
@@ -224,6 +228,90 @@ pub async fn setup_tls_server_with_cert(
     (addr, handle)
 }
 
+/// Generate a self-signed client certificate whose subject is `CN=<common_name>,O=Renews Tests`.
+pub fn generate_client_cert(common_name: &str) -> (rustls::Certificate, rustls::PrivateKey) {
+    let mut params = rcgen::CertificateParams::new(vec![common_name.to_string()]).unwrap();
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::OrganizationName, "Renews Tests");
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, common_name);
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key).unwrap();
+    (
+        rustls::Certificate(cert.der().to_vec()),
+        rustls::PrivateKey(key.serialize_der()),
+    )
+}
+
+/// Start a TLS server that accepts client certificates signed by `client_ca`
+/// and passes the presented certificate on to the session.
+pub async fn setup_tls_server_with_client_ca(
+    storage: Arc<dyn Storage>,
+    auth: Arc<dyn AuthProvider>,
+    cfg: Config,
+    client_ca: rustls::Certificate,
+) -> (
+    std::net::SocketAddr,
+    rustls::Certificate,
+    tokio::task::JoinHandle<()>,
+) {
+    let (cert, key, _) = generate_self_signed_cert();
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&client_ca).unwrap();
+    let tls_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(
+            rustls::server::AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+        )
+        .with_single_cert(vec![cert.clone()], key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(tls_config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let cfg = Arc::new(RwLock::new(cfg));
+    let queue = create_test_queue();
+
+    let handle = tokio::spawn(async move {
+        let (sock, _) = listener.accept().await.unwrap();
+        let stream = acceptor.accept(sock).await.unwrap();
+        let client_cert =
+            renews::auth::ClientCertificate::from_peer(stream.get_ref().1.peer_certificates());
+        handle_client_with_cert(stream, storage, auth, cfg, true, client_cert, None, queue)
+            .await
+            .unwrap();
+    });
+    (addr, cert, handle)
+}
+
+/// Connect over TLS, optionally presenting a client certificate.
+pub async fn connect_tls_with_client_cert(
+    addr: std::net::SocketAddr,
+    server_cert: rustls::Certificate,
+    client: Option<(rustls::Certificate, rustls::PrivateKey)>,
+) -> (
+    BufReader<ReadHalf<tokio_rustls::client::TlsStream<TcpStream>>>,
+    WriteHalf<tokio_rustls::client::TlsStream<TcpStream>>,
+) {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&server_cert).unwrap();
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+    let config = match client {
+        Some((cert, key)) => builder.with_client_auth_cert(vec![cert], key).unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+    let connector = TlsConnector::from(Arc::new(config));
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let server_name = rustls::ServerName::try_from("localhost").unwrap();
+    let tls_stream = connector.connect(server_name, stream).await.unwrap();
+    let (r, w) = io::split(tls_stream);
+    (BufReader::new(r), w)
+}
+
 pub async fn setup_tls_server(
     storage: Arc<dyn Storage>,
     auth: Arc<dyn AuthProvider>,
@@ -412,6 +500,7 @@ impl ClientMock {
 /// Builder to mock a client connection using `tokio_test::io`.
 pub struct ClientMock {
     steps: Vec<(Vec<String>, Vec<String>)>,
+    compress_after: Option<usize>,
 }
 
 impl Default for ClientMock {
@@ -422,7 +511,10 @@ impl Default for ClientMock {
 
 impl ClientMock {
     pub fn new() -> Self {
-        Self { steps: Vec::new() }
+        Self {
+            steps: Vec::new(),
+            compress_after: None,
+        }
     }
 
     /// Expect a command with a single-line response.
@@ -461,27 +553,45 @@ impl ClientMock {
         self
     }
 
-    pub async fn drive<R, W>(self, mut reader: R, mut writer: W)
+    /// Negotiate `COMPRESS DEFLATE`; every later step runs compressed.
+    pub fn expect_compress(mut self) -> Self {
+        self.steps.push((
+            vec!["COMPRESS DEFLATE".to_string()],
+            vec!["206 Compression active".to_string()],
+        ));
+        self.compress_after = Some(self.steps.len() - 1);
+        self
+    }
+
+    pub async fn drive<R, W>(self, reader: R, writer: W)
     where
-        R: tokio::io::AsyncBufRead + Unpin,
+        R: tokio::io::AsyncRead + Unpin,
         W: tokio::io::AsyncWrite + Unpin,
     {
+        use renews::compress::{DeflateWriter, InflateReader};
         use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 
+        let mut reader = BufReader::new(InflateReader::new(reader));
+        let mut writer = DeflateWriter::new(writer);
         let mut line = String::new();
         reader.read_line(&mut line).await.unwrap();
-        for (cmds, resps) in self.steps {
+        for (i, (cmds, resps)) in self.steps.into_iter().enumerate() {
             for cmd in cmds {
                 writer
                     .write_all(format!("{cmd}\r\n").as_bytes())
                     .await
                     .unwrap();
             }
+            writer.flush().await.unwrap();
             for resp in resps {
                 line.clear();
                 reader.read_line(&mut line).await.unwrap();
                 assert_eq!(line.trim_end_matches(['\r', '\n']), resp);
             }
+            if self.compress_after == Some(i) {
+                reader.get_mut().start_compression();
+                writer.start_compression();
+            }
         }
         let _ = writer.shutdown().await;
     }
@@ -549,22 +659,68 @@ pub fn create_minimal_config() -> Config {
     Config {
         addr: "127.0.0.1:0".to_string(),
         site_name: "test".to_string(),
+        path_host: None,
+        admin_address: None,
         db_path: "sqlite::memory:".to_string(),
         auth_db_path: "sqlite::memory:".to_string(),
         peer_db_path: "sqlite::memory:".to_string(),
+        mirror_db_path: None,
+        mirror_failure_policy: renews::config::MirrorFailurePolicy::Warn,
         peer_sync_schedule: "0 0 * * * *".to_string(),
         idle_timeout_secs: 600,
+        max_connections: None,
+        max_connections_per_ip: None,
+        plain_idle_timeout_secs: None,
+        tls_idle_timeout_secs: None,
+        transit_idle_timeout_secs: None,
+        article_line_timeout_secs: 60,
+        shutdown_grace_secs: 30,
+        drain_timeout_secs: 60,
         peers: vec![],
+        subscriptions: vec![],
         tls_addr: None,
         tls_cert: None,
         tls_key: None,
+        tls_client_ca: None,
         ws_addr: None,
+        transit_addr: None,
+        metrics_addr: None,
         article_queue_capacity: 10,
         article_worker_count: 2,
+        article_batch_size: 32,
+        dead_letter_dir: None,
+        history_cache_size: 1000,
+        overview_extra_headers: Vec::new(),
         group_settings: vec![],
+        retention_interval: None,
+        history_retention: None,
         filters: vec![],
+        client_certs: vec![],
+        access: renews::config::AccessRules::default(),
+        list_unreadable_groups: false,
         pgp_key_servers: renews::config::default_pgp_key_servers(),
+        control_keys: vec![],
+        checkgroups: renews::config::CheckgroupsConfig::default(),
+        posting_enabled: true,
+        plain_posting_enabled: None,
+        tls_posting_enabled: None,
         allow_posting_insecure_connections: false,
+        allow_sasl_plain_insecure: false,
+        moderated_crosspost_policy: renews::config::ModeratedCrosspostPolicy::HoldAll,
+        header_rules: Vec::new(),
+        max_article_bytes: None,
+        max_crosspost_groups: None,
+        require_followup_to_above: None,
+        moderators: None,
+        smtp_relay: None,
+        post_durability: renews::config::PostDurability::AckOnQueue,
+        message_id_generation: renews::config::MessageIdGeneration::Hash,
+        rate_limit: renews::config::RateLimitConfig::default(),
+        backpressure: renews::config::BackpressureConfig::default(),
+        injection: renews::config::InjectionConfig::default(),
+        ip_access: renews::config::IpAccessConfig::default(),
+        spam: renews::config::SpamConfig::default(),
+        distributions: renews::config::DistributionsConfig::default(),
         runtime_threads: 4,
     }
 }
@@ -25,7 +25,7 @@ fn create_test_article(subject: &str, from: &str, message_id: &str, group: &str)
             ("Message-ID".to_string(), message_id.to_string()),
             ("Newsgroups".to_string(), group.to_string()),
         ],
-        body: "This is a test article body.\nWith multiple lines.".to_string(),
+        body: "This is a test article body.\nWith multiple lines.".into(),
     }
 }
 